@@ -0,0 +1,704 @@
+#[cfg(feature = "coreaudio")]
+use std::{mem, ptr, slice};
+#[cfg(feature = "coreaudio")]
+use std::os::raw::c_void;
+
+#[cfg(feature = "coreaudio")]
+use audiotoolbox_sys::*;
+#[cfg(feature = "coreaudio")]
+use core_foundation::base::OSStatus;
+
+use channel_map::{ChannelLayout, ChannelMixer};
+use convert::*;
+use frame_reader::FrameReader;
+use resample::{Quality, Resampler};
+use stream_format::*;
+
+/// Supplies input packets on demand, like an `AudioConverterComplexInputDataProc`.
+pub trait InputDataProc {
+    /// Fills `buffers`, one per buffer of the input format, with up to `packets` packets and
+    /// returns how many were supplied. Returning zero signals the end of the input.
+    fn input_data(&mut self, packets: usize, buffers: &mut [Vec<u8>]) -> Result<usize, String>;
+}
+
+impl<F> InputDataProc for F
+    where F: FnMut(usize, &mut [Vec<u8>]) -> Result<usize, String>
+{
+    fn input_data(&mut self, packets: usize, buffers: &mut [Vec<u8>]) -> Result<usize, String> {
+        self(packets, buffers)
+    }
+}
+
+/// Supplies the frames of a `FrameReader` in the reader's own format.
+pub struct FrameReaderInput<R> {
+    reader: R,
+    encoder: Encoder,
+    samples: Vec<f32>,
+}
+
+impl<R: FrameReader> FrameReaderInput<R> {
+    pub fn new(reader: R) -> FrameReaderInput<R> {
+        let format = reader.format();
+        FrameReaderInput {
+            reader,
+            encoder: Encoder::new(format).with_clipping(Clipping::Preserve),
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn format(&self) -> StreamFormat {
+        *self.encoder.format()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: FrameReader> InputDataProc for FrameReaderInput<R> {
+    fn input_data(&mut self, packets: usize, buffers: &mut [Vec<u8>]) -> Result<usize, String> {
+        let channels = self.encoder.format().channels as usize;
+        self.samples.resize(packets * channels, 0.0);
+        let frames = self.reader.read_frames(&mut self.samples)?;
+        self.encoder.encode(&self.samples[..frames * channels], buffers)
+    }
+}
+
+/// Presents a reader's frames at the rate and channel count of a converter's output.
+pub struct AudioConverterReader<R> {
+    converter: AudioConverter,
+    input: FrameReaderInput<R>,
+    output: Vec<Vec<u8>>,
+}
+
+impl<R: FrameReader> AudioConverterReader<R> {
+    /// `converter` must take the reader's format and produce interleaved native f32.
+    pub fn new(reader: R, converter: AudioConverter) -> Result<AudioConverterReader<R>, String> {
+        if converter.input_format() != reader.format() {
+            return Err("converter input does not match the reader".to_owned());
+        }
+        let output = converter.output_format();
+        if output != StreamFormat::new(output.sample_rate, output.channels, SampleFormat::F32) {
+            return Err("converter output must be interleaved native f32".to_owned());
+        }
+        Ok(AudioConverterReader {
+               converter,
+               input: FrameReaderInput::new(reader),
+               output: vec![Vec::new()],
+           })
+    }
+
+    pub fn converter_mut(&mut self) -> &mut AudioConverter {
+        &mut self.converter
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input.into_inner()
+    }
+}
+
+impl<R: FrameReader> FrameReader for AudioConverterReader<R> {
+    fn format(&self) -> StreamFormat {
+        self.converter.output_format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        let channels = self.converter.output_format().channels;
+        if channels == self.converter.input_format().channels {
+            self.input.reader.channel_layout()
+        } else {
+            ChannelLayout::default_for(channels)
+        }
+    }
+
+    fn length(&self) -> Option<u64> {
+        let (input, output) = (self.converter.input_format(), self.converter.output_format());
+        self.input
+            .reader
+            .length()
+            .map(|frames| (frames as f64 * output.sample_rate / input.sample_rate).ceil() as u64)
+    }
+
+    /// Seeks the reader to the input frame at the same time and drops the converter's
+    /// buffered audio.
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let (input, output) = (self.converter.input_format(), self.converter.output_format());
+        let input_frame = (frame as f64 * input.sample_rate / output.sample_rate).round();
+        self.input.reader.seek(input_frame as u64)?;
+        self.converter.reset()
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let format = self.converter.output_format();
+        let frames = buffer.len() / format.channels as usize;
+        let produced = self.converter
+            .fill_complex_buffer(&mut self.input, frames, &mut self.output)?;
+        let bytes = produced * format.bytes_per_frame();
+        let samples = decode_f32(&format, &[&self.output[0][..bytes]])?;
+        buffer[..samples.len()].copy_from_slice(&samples);
+        Ok(produced)
+    }
+}
+
+/// Converts linear PCM between any two `StreamFormat`s, changing sample format, rate and
+/// channel count as needed.
+///
+/// Output is pulled with `fill_complex_buffer`, which asks an `InputDataProc` for as many
+/// input packets as it needs, mirroring `AudioConverterFillComplexBuffer`. For linear PCM a
+/// packet is one frame.
+pub struct AudioConverter {
+    backend: Backend,
+}
+
+enum Backend {
+    Native(NativeConverter),
+    #[cfg(feature = "coreaudio")]
+    CoreAudio(CoreAudioConverter),
+}
+
+impl AudioConverter {
+    /// A converter implemented in Rust, available on every platform.
+    pub fn new(input: StreamFormat, output: StreamFormat) -> Result<AudioConverter, String> {
+        Ok(AudioConverter { backend: Backend::Native(NativeConverter::new(input, output)?) })
+    }
+
+    /// A converter backed by the framework's `AudioConverter`.
+    #[cfg(feature = "coreaudio")]
+    pub fn core_audio(input: StreamFormat, output: StreamFormat) -> Result<AudioConverter, String> {
+        Ok(AudioConverter { backend: Backend::CoreAudio(CoreAudioConverter::new(input, output)?) })
+    }
+
+    pub fn input_format(&self) -> StreamFormat {
+        match self.backend {
+            Backend::Native(ref native) => native.input,
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref core_audio) => core_audio.input,
+        }
+    }
+
+    pub fn output_format(&self) -> StreamFormat {
+        match self.backend {
+            Backend::Native(ref native) => native.output,
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref core_audio) => core_audio.output,
+        }
+    }
+
+    pub fn maximum_output_packet_size(&self) -> Result<usize, String> {
+        match self.backend {
+            Backend::Native(ref native) => Ok(native.output.bytes_per_frame()),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref core_audio) => core_audio.maximum_output_packet_size(),
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(ref mut native) => native.set_quality(quality),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref mut core_audio) => core_audio.set_quality(quality),
+        }
+    }
+
+    /// Only supported by the native backend.
+    pub fn set_dither(&mut self, dither: Dither) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(ref mut native) => {
+                native.dither = dither;
+                native.rebuild_encoders();
+                Ok(())
+            }
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(_) => {
+                Err("dither is not supported by the CoreAudio backend".to_owned())
+            }
+        }
+    }
+
+    /// Only supported by the native backend.
+    pub fn set_clipping(&mut self, clipping: Clipping) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(ref mut native) => {
+                native.clipping = clipping;
+                native.rebuild_encoders();
+                Ok(())
+            }
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(_) => {
+                Err("clipping is not supported by the CoreAudio backend".to_owned())
+            }
+        }
+    }
+
+    /// Replaces the default channel mapping, which follows `ChannelLayout::default_for`.
+    /// Only supported by the native backend.
+    pub fn set_channel_mixer(&mut self, mixer: ChannelMixer) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(ref mut native) => native.set_mixer(mixer),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(_) => {
+                Err("channel mixing is not supported by the CoreAudio backend".to_owned())
+            }
+        }
+    }
+
+    pub fn set_channel_layouts(&mut self,
+                               from: &ChannelLayout,
+                               to: &ChannelLayout)
+                               -> Result<(), String> {
+        self.set_channel_mixer(ChannelMixer::between(from, to)?)
+    }
+
+    /// The cookie an encoder produces for the start of its output, to be stored with the
+    /// file. Linear PCM has none.
+    pub fn get_magic_cookie(&self) -> Result<Option<Vec<u8>>, String> {
+        match self.backend {
+            Backend::Native(_) => Ok(None),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref core_audio) => core_audio.get_magic_cookie(),
+        }
+    }
+
+    /// Hands a decoder the cookie stored with its input.
+    pub fn set_magic_cookie(&mut self, cookie: Vec<u8>) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(_) if cookie.is_empty() => Ok(()),
+            Backend::Native(_) => Err("linear PCM does not take a magic cookie".to_owned()),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref mut core_audio) => core_audio.set_magic_cookie(cookie),
+        }
+    }
+
+    /// Produces up to `packets` output packets into `output`, one buffer per buffer of the
+    /// output format, pulling input from `input` as needed. Output buffers are overwritten.
+    /// Returns the number of packets produced, which is zero once the input has ended and
+    /// every buffered packet has been returned.
+    pub fn fill_complex_buffer<I>(&mut self,
+                                  input: &mut I,
+                                  packets: usize,
+                                  output: &mut [Vec<u8>])
+                                  -> Result<usize, String>
+        where I: InputDataProc
+    {
+        if output.len() != self.output_format().buffer_count() {
+            return Err(format!("expected {} output buffers, got {}",
+                               self.output_format().buffer_count(),
+                               output.len()));
+        }
+        match self.backend {
+            Backend::Native(ref mut native) => native.fill(input, packets, output),
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref mut core_audio) => core_audio.fill(input, packets, output),
+        }
+    }
+
+    /// Drops buffered audio so the converter can start a new stream.
+    pub fn reset(&mut self) -> Result<(), String> {
+        match self.backend {
+            Backend::Native(ref mut native) => {
+                native.reset();
+                Ok(())
+            }
+            #[cfg(feature = "coreaudio")]
+            Backend::CoreAudio(ref mut core_audio) => core_audio.reset(),
+        }
+    }
+}
+
+struct NativeConverter {
+    input: StreamFormat,
+    output: StreamFormat,
+    quality: Quality,
+    dither: Dither,
+    clipping: Clipping,
+    /// Used instead of the float pipeline when only the sample layout changes.
+    direct: Option<Converter>,
+    mixer: Option<ChannelMixer>,
+    resampler: Option<Resampler>,
+    encoder: Encoder,
+    input_buffers: Vec<Vec<u8>>,
+    mixed: Vec<f32>,
+    pending: Vec<f32>,
+    finished: bool,
+}
+
+impl NativeConverter {
+    fn new(input: StreamFormat, output: StreamFormat) -> Result<NativeConverter, String> {
+        if input.channels == 0 || output.channels == 0 {
+            return Err("formats must have at least one channel".to_owned());
+        }
+        let mixer = if input.channels == output.channels {
+            None
+        } else {
+            let layout = |channels: u32| {
+                ChannelLayout::default_for(channels)
+                    .ok_or_else(|| format!("no default layout for {} channels", channels))
+            };
+            Some(ChannelMixer::between(&layout(input.channels)?, &layout(output.channels)?)?)
+        };
+        let mut converter = NativeConverter {
+            input,
+            output,
+            quality: Quality::Medium,
+            dither: Dither::None,
+            clipping: Clipping::Clamp,
+            direct: None,
+            mixer,
+            resampler: None,
+            encoder: Encoder::new(output),
+            input_buffers: vec![Vec::new(); input.buffer_count()],
+            mixed: Vec::new(),
+            pending: Vec::new(),
+            finished: false,
+        };
+        converter.set_quality(Quality::Medium)?;
+        converter.rebuild_encoders();
+        Ok(converter)
+    }
+
+    fn set_quality(&mut self, quality: Quality) -> Result<(), String> {
+        self.quality = quality;
+        self.resampler = if self.input.sample_rate == self.output.sample_rate {
+            None
+        } else {
+            Some(Resampler::new(self.output.channels as usize,
+                                self.input.sample_rate,
+                                self.output.sample_rate,
+                                quality)?)
+        };
+        self.rebuild_encoders();
+        Ok(())
+    }
+
+    fn set_mixer(&mut self, mixer: ChannelMixer) -> Result<(), String> {
+        if mixer.input_channels() != self.input.channels as usize ||
+           mixer.output_channels() != self.output.channels as usize {
+            return Err(format!("mixer maps {} channels to {}, converter maps {} to {}",
+                               mixer.input_channels(),
+                               mixer.output_channels(),
+                               self.input.channels,
+                               self.output.channels));
+        }
+        self.mixer = Some(mixer);
+        self.rebuild_encoders();
+        Ok(())
+    }
+
+    fn rebuild_encoders(&mut self) {
+        self.encoder = Encoder::new(self.output)
+            .with_dither(self.dither)
+            .with_clipping(self.clipping);
+        self.direct = if self.mixer.is_none() && self.resampler.is_none() {
+            Converter::new(self.input, self.output)
+                .ok()
+                .map(|c| c.with_dither(self.dither).with_clipping(self.clipping))
+        } else {
+            None
+        };
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.finished = false;
+        if let Some(ref mut resampler) = self.resampler {
+            resampler.reset();
+        }
+    }
+
+    /// Asks for `packets` input packets and returns the buffers trimmed to what arrived.
+    fn pull<I: InputDataProc>(&mut self, input: &mut I, packets: usize) -> Result<usize, String> {
+        let supplied = input.input_data(packets, &mut self.input_buffers)?;
+        let bytes = supplied * self.input.bytes_per_frame();
+        for buffer in self.input_buffers.iter_mut() {
+            if buffer.len() < bytes {
+                return Err(format!("input supplied {} packets but only {} bytes",
+                                   supplied,
+                                   buffer.len()));
+            }
+            buffer.truncate(bytes);
+        }
+        Ok(supplied)
+    }
+
+    fn fill<I: InputDataProc>(&mut self,
+                              input: &mut I,
+                              packets: usize,
+                              output: &mut [Vec<u8>])
+                              -> Result<usize, String> {
+        if self.direct.is_some() {
+            if self.pull(input, packets)? == 0 {
+                for buffer in output.iter_mut() {
+                    buffer.clear();
+                }
+                return Ok(0);
+            }
+            let buffers: Vec<&[u8]> = self.input_buffers.iter().map(|b| &b[..]).collect();
+            return self.direct.as_mut().unwrap().convert(&buffers, output);
+        }
+
+        let channels = self.output.channels as usize;
+        while self.pending.len() < packets * channels && !self.finished {
+            let missing = packets - self.pending.len() / channels;
+            let request = (missing as f64 * self.input.sample_rate / self.output.sample_rate)
+                .ceil()
+                .max(1.0) as usize;
+            if self.pull(input, request)? == 0 {
+                if let Some(ref mut resampler) = self.resampler {
+                    resampler.flush(&mut self.pending);
+                }
+                self.finished = true;
+                break;
+            }
+
+            let samples = {
+                let buffers: Vec<&[u8]> = self.input_buffers.iter().map(|b| &b[..]).collect();
+                decode_f32(&self.input, &buffers)?
+            };
+            let mixed = match self.mixer {
+                Some(ref mixer) => {
+                    self.mixed.clear();
+                    mixer.process(&samples, &mut self.mixed);
+                    &self.mixed
+                }
+                None => &samples,
+            };
+            match self.resampler {
+                Some(ref mut resampler) => resampler.process(mixed, &mut self.pending),
+                None => self.pending.extend_from_slice(mixed),
+            }
+        }
+
+        let frames = packets.min(self.pending.len() / channels);
+        self.encoder.encode(&self.pending[..frames * channels], output)?;
+        self.pending.drain(..frames * channels);
+        Ok(frames)
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+const INPUT_PROC_FAILED: OSStatus = 0x696e7072;
+
+#[cfg(feature = "coreaudio")]
+type PacketDescription = AudioStreamPacketDescription;
+
+#[cfg(feature = "coreaudio")]
+struct InputContext<'a, I: 'a> {
+    input: &'a mut I,
+    format: StreamFormat,
+    buffers: &'a mut Vec<Vec<u8>>,
+    error: Option<String>,
+}
+
+#[cfg(feature = "coreaudio")]
+unsafe extern "C" fn complex_input_data_proc<I>(_converter: AudioConverterRef,
+                                                io_packets: *mut u32,
+                                                io_data: *mut AudioBufferList,
+                                                _descriptions: *mut *mut PacketDescription,
+                                                user_data: *mut c_void)
+                                                -> OSStatus
+    where I: InputDataProc
+{
+    let context = &mut *(user_data as *mut InputContext<I>);
+    let supplied = match context.input.input_data(*io_packets as usize, &mut context.buffers[..]) {
+        Ok(supplied) => supplied,
+        Err(error) => {
+            context.error = Some(error);
+            *io_packets = 0;
+            return INPUT_PROC_FAILED;
+        }
+    };
+    let bytes = supplied * context.format.bytes_per_frame();
+    let channels_per_buffer = context.format.channels / context.format.buffer_count() as u32;
+    let buffers = slice::from_raw_parts_mut((*io_data).mBuffers.as_mut_ptr(),
+                                            (*io_data).mNumberBuffers as usize);
+    for (buffer, data) in buffers.iter_mut().zip(context.buffers.iter_mut()) {
+        if data.len() < bytes {
+            context.error = Some(format!("input supplied {} packets but only {} bytes",
+                                         supplied,
+                                         data.len()));
+            *io_packets = 0;
+            return INPUT_PROC_FAILED;
+        }
+        buffer.mNumberChannels = channels_per_buffer;
+        buffer.mDataByteSize = bytes as u32;
+        buffer.mData = data.as_mut_ptr() as *mut c_void;
+    }
+    *io_packets = supplied as u32;
+    0
+}
+
+#[cfg(feature = "coreaudio")]
+struct CoreAudioConverter {
+    converter: AudioConverterRef,
+    input: StreamFormat,
+    output: StreamFormat,
+    input_buffers: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "coreaudio")]
+impl Drop for CoreAudioConverter {
+    fn drop(&mut self) {
+        unsafe {
+            AudioConverterDispose(self.converter);
+        }
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl CoreAudioConverter {
+    fn new(input: StreamFormat, output: StreamFormat) -> Result<CoreAudioConverter, String> {
+        let (input_asbd, output_asbd) = (input.to_asbd(), output.to_asbd());
+        let mut converter: AudioConverterRef = ptr::null_mut();
+        let error = unsafe { AudioConverterNew(&input_asbd, &output_asbd, &mut converter) };
+        if error != 0 {
+            return Err(format!("AudioConverterNew failed: {}", error));
+        }
+        Ok(CoreAudioConverter {
+               converter: converter,
+               input: input,
+               output: output,
+               input_buffers: vec![Vec::new(); input.buffer_count()],
+           })
+    }
+
+    fn maximum_output_packet_size(&self) -> Result<usize, String> {
+        let mut size = mem::size_of::<u32>() as u32;
+        let mut value: u32 = 0;
+        let error = unsafe {
+            AudioConverterGetProperty(self.converter,
+                                      kAudioConverterPropertyMaximumOutputPacketSize as u32,
+                                      &mut size,
+                                      &mut value as *mut _ as *mut c_void)
+        };
+        if error != 0 {
+            Err(format!("could not get maximum output packet size: {}", error))
+        } else {
+            Ok(value as usize)
+        }
+    }
+
+    fn set_quality(&mut self, quality: Quality) -> Result<(), String> {
+        let value: u32 = match quality {
+            Quality::Fast => kAudioConverterQuality_Low as u32,
+            Quality::Medium => kAudioConverterQuality_High as u32,
+            Quality::Best => kAudioConverterQuality_Max as u32,
+        };
+        let error = unsafe {
+            AudioConverterSetProperty(self.converter,
+                                      kAudioConverterSampleRateConverterQuality as u32,
+                                      mem::size_of::<u32>() as u32,
+                                      &value as *const _ as *const c_void)
+        };
+        if error != 0 {
+            Err(format!("could not set converter quality: {}", error))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_magic_cookie(&self) -> Result<Option<Vec<u8>>, String> {
+        let mut size: u32 = 0;
+        let error = unsafe {
+            AudioConverterGetPropertyInfo(self.converter,
+                                          kAudioConverterCompressionMagicCookie as u32,
+                                          &mut size,
+                                          ptr::null_mut())
+        };
+        if error != 0 {
+            return Err(format!("could not get magic cookie size: {}", error));
+        }
+        if size == 0 {
+            return Ok(None);
+        }
+        let mut cookie = vec![0u8; size as usize];
+        let error = unsafe {
+            AudioConverterGetProperty(self.converter,
+                                      kAudioConverterCompressionMagicCookie as u32,
+                                      &mut size,
+                                      cookie.as_mut_ptr() as *mut c_void)
+        };
+        if error != 0 {
+            return Err(format!("could not get magic cookie: {}", error));
+        }
+        cookie.truncate(size as usize);
+        Ok(Some(cookie))
+    }
+
+    fn set_magic_cookie(&mut self, cookie: Vec<u8>) -> Result<(), String> {
+        let error = unsafe {
+            AudioConverterSetProperty(self.converter,
+                                      kAudioConverterDecompressionMagicCookie as u32,
+                                      cookie.len() as u32,
+                                      cookie.as_ptr() as *const c_void)
+        };
+        if error != 0 {
+            Err(format!("could not set magic cookie: {}", error))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), String> {
+        let error = unsafe { AudioConverterReset(self.converter) };
+        if error != 0 {
+            Err(format!("AudioConverterReset failed: {}", error))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn fill<I: InputDataProc>(&mut self,
+                              input: &mut I,
+                              packets: usize,
+                              output: &mut [Vec<u8>])
+                              -> Result<usize, String> {
+        let bytes_per_packet = self.output.bytes_per_frame();
+        let channels_per_buffer = self.output.channels / self.output.buffer_count() as u32;
+        for buffer in output.iter_mut() {
+            buffer.resize(packets * bytes_per_packet, 0);
+        }
+
+        // AudioBufferList is declared with one buffer; planar formats need one per channel.
+        let list_size = mem::size_of::<AudioBufferList>() +
+                        (output.len() - 1) * mem::size_of::<AudioBuffer>();
+        let mut storage = vec![0u64; (list_size + 7) / 8];
+        let list = storage.as_mut_ptr() as *mut AudioBufferList;
+        unsafe {
+            (*list).mNumberBuffers = output.len() as u32;
+            let buffers = slice::from_raw_parts_mut((*list).mBuffers.as_mut_ptr(), output.len());
+            for (buffer, data) in buffers.iter_mut().zip(output.iter_mut()) {
+                *buffer = AudioBuffer {
+                    mNumberChannels: channels_per_buffer,
+                    mDataByteSize: data.len() as u32,
+                    mData: data.as_mut_ptr() as *mut c_void,
+                };
+            }
+        }
+
+        let mut context = InputContext {
+            input: input,
+            format: self.input,
+            buffers: &mut self.input_buffers,
+            error: None,
+        };
+        let mut io_packets = packets as u32;
+        let error = unsafe {
+            AudioConverterFillComplexBuffer(self.converter,
+                                            Some(complex_input_data_proc::<I>),
+                                            &mut context as *mut _ as *mut c_void,
+                                            &mut io_packets,
+                                            list,
+                                            ptr::null_mut())
+        };
+        if let Some(message) = context.error {
+            return Err(message);
+        }
+        if error != 0 {
+            return Err(format!("AudioConverterFillComplexBuffer failed: {}", error));
+        }
+        for buffer in output.iter_mut() {
+            buffer.truncate(io_packets as usize * bytes_per_packet);
+        }
+        Ok(io_packets as usize)
+    }
+}
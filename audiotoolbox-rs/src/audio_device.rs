@@ -0,0 +1,605 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "coreaudio")]
+use std::collections::VecDeque;
+#[cfg(feature = "coreaudio")]
+use std::os::raw::c_void;
+#[cfg(feature = "coreaudio")]
+use std::ptr;
+#[cfg(feature = "coreaudio")]
+use std::slice;
+#[cfg(feature = "coreaudio")]
+use std::sync::{Arc, Condvar, Mutex};
+
+#[cfg(feature = "coreaudio")]
+use audiotoolbox_sys::*;
+#[cfg(feature = "coreaudio")]
+use core_foundation::base::OSStatus;
+
+#[cfg(feature = "coreaudio")]
+use audio_hardware_base::AudioDevice;
+#[cfg(feature = "coreaudio")]
+use audio_queue::{AudioQueue, Buffer};
+use frame_reader::FrameReader;
+use frame_writer::FrameWriter;
+use stream_format::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+/// An audio device as its backend describes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub name: String,
+    /// Identifies the device among the backend's devices, even when names repeat.
+    pub uid: String,
+    pub input_channels: u32,
+    pub output_channels: u32,
+    pub sample_rate: f64,
+    pub default_input: bool,
+    pub default_output: bool,
+}
+
+impl DeviceInfo {
+    pub fn channels(&self, direction: Direction) -> u32 {
+        match direction {
+            Direction::Input => self.input_channels,
+            Direction::Output => self.output_channels,
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} [{}]: {} in, {} out, {} Hz",
+               self.name,
+               self.uid,
+               self.input_channels,
+               self.output_channels,
+               self.sample_rate)?;
+        if self.default_input {
+            write!(f, ", default input")?;
+        }
+        if self.default_output {
+            write!(f, ", default output")?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays and records interleaved f32 frames through a set of devices.
+///
+/// An output stream's `write_frames` blocks until there is room to queue the frames, and
+/// `finish` waits until they have been played; dropping the stream stops playback at once.
+/// An input stream's `read_frames` blocks until audio arrives and never reports the end of
+/// the stream.
+pub trait AudioBackend {
+    fn name(&self) -> &'static str;
+
+    fn devices(&self) -> Result<Vec<DeviceInfo>, String>;
+
+    /// Opens the device with `uid` for playback, or the default output device when `None`.
+    fn open_output(&self,
+                   uid: Option<&str>,
+                   format: StreamFormat)
+                   -> Result<Box<dyn FrameWriter>, String>;
+
+    /// Opens the device with `uid` for recording, or the default input device when `None`.
+    fn open_input(&self,
+                  uid: Option<&str>,
+                  format: StreamFormat)
+                  -> Result<Box<dyn FrameReader>, String>;
+}
+
+/// Names accepted by `backend`, the default first.
+pub fn backend_names() -> &'static [&'static str] {
+    #[cfg(feature = "coreaudio")]
+    {
+        &["coreaudio", "null"]
+    }
+    #[cfg(not(feature = "coreaudio"))]
+    {
+        &["null"]
+    }
+}
+
+pub fn backend(name: &str) -> Result<Box<dyn AudioBackend>, String> {
+    match name {
+        "null" => Ok(Box::new(NullBackend::new())),
+        #[cfg(feature = "coreaudio")]
+        "coreaudio" => Ok(Box::new(CoreAudioBackend)),
+        #[cfg(not(feature = "coreaudio"))]
+        "coreaudio" => Err("the coreaudio backend requires the coreaudio feature".to_owned()),
+        _ => Err(format!("unknown backend '{}'", name)),
+    }
+}
+
+/// Finds the device with channels in `direction` whose UID or name is `query`, or failing
+/// that the only one whose name contains `query`, ignoring case.
+pub fn find_device(devices: &[DeviceInfo],
+                   query: &str,
+                   direction: Direction)
+                   -> Result<DeviceInfo, String> {
+    let candidates: Vec<&DeviceInfo> =
+        devices.iter().filter(|d| d.channels(direction) > 0).collect();
+    if let Some(device) = candidates.iter().find(|d| d.uid == query) {
+        return Ok((*device).clone());
+    }
+    if let Some(device) = candidates.iter().find(|d| d.name == query) {
+        return Ok((*device).clone());
+    }
+    let lower = query.to_lowercase();
+    let matches: Vec<&&DeviceInfo> =
+        candidates.iter().filter(|d| d.name.to_lowercase().contains(&lower)).collect();
+    let kind = match direction {
+        Direction::Input => "input",
+        Direction::Output => "output",
+    };
+    match matches.len() {
+        0 => Err(format!("no {} device matches '{}'", kind, query)),
+        1 => Ok((*matches[0]).clone()),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|d| d.name.as_str()).collect();
+            Err(format!("'{}' matches several {} devices: {}", query, kind, names.join(", ")))
+        }
+    }
+}
+
+const NULL_UID: &str = "null";
+
+/// A device that discards what is played and records silence, for testing without audio
+/// hardware. Streams keep real time unless `realtime` is off, in which case they run as
+/// fast as they are called.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NullBackend {
+    pub realtime: bool,
+}
+
+impl NullBackend {
+    pub fn new() -> NullBackend {
+        NullBackend { realtime: true }
+    }
+
+    fn check_uid(&self, uid: Option<&str>) -> Result<(), String> {
+        match uid {
+            Some(uid) if uid != NULL_UID => Err(format!("no device with UID '{}'", uid)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for NullBackend {
+    fn default() -> NullBackend {
+        NullBackend::new()
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn name(&self) -> &'static str {
+        "null"
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceInfo>, String> {
+        Ok(vec![DeviceInfo {
+                    name: "Null Device".to_owned(),
+                    uid: NULL_UID.to_owned(),
+                    input_channels: 2,
+                    output_channels: 2,
+                    sample_rate: 48000.0,
+                    default_input: true,
+                    default_output: true,
+                }])
+    }
+
+    fn open_output(&self,
+                   uid: Option<&str>,
+                   format: StreamFormat)
+                   -> Result<Box<dyn FrameWriter>, String> {
+        self.check_uid(uid)?;
+        let format = StreamFormat::new(format.sample_rate, format.channels, SampleFormat::F32);
+        Ok(Box::new(NullStream {
+                        format,
+                        clock: Clock::new(format.sample_rate, self.realtime),
+                    }))
+    }
+
+    fn open_input(&self,
+                  uid: Option<&str>,
+                  format: StreamFormat)
+                  -> Result<Box<dyn FrameReader>, String> {
+        self.check_uid(uid)?;
+        let format = StreamFormat::new(format.sample_rate, format.channels, SampleFormat::F32);
+        Ok(Box::new(NullStream {
+                        format,
+                        clock: Clock::new(format.sample_rate, self.realtime),
+                    }))
+    }
+}
+
+/// Holds a stream back to the rate a device would consume or produce its frames.
+struct Clock {
+    sample_rate: f64,
+    realtime: bool,
+    start: Option<Instant>,
+    frames: u64,
+}
+
+impl Clock {
+    fn new(sample_rate: f64, realtime: bool) -> Clock {
+        Clock {
+            sample_rate,
+            realtime,
+            start: None,
+            frames: 0,
+        }
+    }
+
+    fn advance(&mut self, frames: usize) {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        self.frames += frames as u64;
+        if !self.realtime {
+            return;
+        }
+        let nanos = (self.frames as f64 / self.sample_rate * 1e9) as u64;
+        let due = start + Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+struct NullStream {
+    format: StreamFormat,
+    clock: Clock,
+}
+
+impl FrameWriter for NullStream {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        self.clock.advance(frames.len() / self.format.channels as usize);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl FrameReader for NullStream {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let frames = buffer.len() / self.format.channels as usize;
+        for sample in buffer.iter_mut() {
+            *sample = 0.0;
+        }
+        self.clock.advance(frames);
+        Ok(frames)
+    }
+}
+
+/// Length of each audio queue buffer.
+#[cfg(feature = "coreaudio")]
+const QUEUE_BUFFER_SECONDS: f64 = 0.05;
+#[cfg(feature = "coreaudio")]
+const QUEUE_BUFFER_COUNT: usize = 3;
+/// Audio recorded but not yet read beyond this is dropped, oldest first.
+#[cfg(feature = "coreaudio")]
+const INPUT_BACKLOG_SECONDS: f64 = 10.0;
+/// How long a stream waits for the queue before deciding the device has stopped.
+#[cfg(feature = "coreaudio")]
+const QUEUE_TIMEOUT_SECONDS: u64 = 2;
+
+/// Devices of the CoreAudio HAL, played and recorded through audio queues.
+#[cfg(feature = "coreaudio")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CoreAudioBackend;
+
+#[cfg(feature = "coreaudio")]
+impl CoreAudioBackend {
+    fn open_queue(&self,
+                  uid: Option<&str>,
+                  format: StreamFormat,
+                  direction: Direction)
+                  -> Result<QueueStream, String> {
+        let format = StreamFormat::new(format.sample_rate, format.channels, SampleFormat::F32);
+        let mut asbd = format.to_asbd();
+        let capacity = match direction {
+            Direction::Input => (INPUT_BACKLOG_SECONDS * format.sample_rate) as usize,
+            Direction::Output => {
+                (QUEUE_BUFFER_SECONDS * format.sample_rate) as usize * QUEUE_BUFFER_COUNT
+            }
+        } * format.channels as usize;
+        let shared = Box::new(Arc::new(QueueShared {
+                                           state: Mutex::new(QueueState {
+                                                                 samples: VecDeque::new(),
+                                                                 capacity: capacity,
+                                                                 running: true,
+                                                             }),
+                                           changed: Condvar::new(),
+                                       }));
+        let user_data = Box::into_raw(shared);
+        let queue = match direction {
+            Direction::Input => {
+                AudioQueue::new_input(Some(queue_input_callback),
+                                      user_data as *mut c_void,
+                                      &mut asbd)
+            }
+            Direction::Output => {
+                AudioQueue::new_output(Some(queue_output_callback),
+                                       user_data as *mut c_void,
+                                       &asbd)
+            }
+        };
+        // From here on dropping the stream disposes of the queue and frees `user_data`.
+        let mut stream = QueueStream {
+            queue: None,
+            shared: unsafe { (*user_data).clone() },
+            user_data: user_data,
+            format: format,
+            direction: direction,
+        };
+        let mut queue =
+            queue.map_err(|status| format!("unable to create audio queue: {}", status))?;
+        if let Some(uid) = uid {
+            queue.set_current_device(uid)
+                .map_err(|status| format!("unable to use device '{}': {}", uid, status))?;
+        }
+        let frames = (QUEUE_BUFFER_SECONDS * format.sample_rate).ceil() as u32;
+        for _ in 0..QUEUE_BUFFER_COUNT {
+            let mut buffer = Buffer::new(&mut queue, frames * format.bytes_per_frame() as u32)
+                .map_err(|status| format!("unable to allocate queue buffer: {}", status))?;
+            match direction {
+                Direction::Input => {
+                    queue.enqueue_buffer(&mut buffer)
+                        .map_err(|status| format!("unable to enqueue buffer: {}", status))?
+                }
+                // Primes the queue with silence.
+                Direction::Output => unsafe {
+                    queue_output_callback(user_data as *mut c_void,
+                                          queue.as_ref(),
+                                          buffer.as_ref())
+                },
+            }
+        }
+        queue.start().map_err(|status| format!("unable to start audio queue: {}", status))?;
+        stream.queue = Some(queue);
+        Ok(stream)
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl AudioBackend for CoreAudioBackend {
+    fn name(&self) -> &'static str {
+        "coreaudio"
+    }
+
+    fn devices(&self) -> Result<Vec<DeviceInfo>, String> {
+        let error = |status: OSStatus| format!("unable to query audio devices: {}", status);
+        let default_input = AudioDevice::default_input().ok();
+        let default_output = AudioDevice::default_output().ok();
+        let mut devices = Vec::new();
+        for device in AudioDevice::all().map_err(&error)? {
+            devices.push(DeviceInfo {
+                             name: device.get_name().map_err(&error)?,
+                             uid: device.get_uid().map_err(&error)?,
+                             input_channels: device.get_channel_count(Direction::Input)
+                                 .map_err(&error)?,
+                             output_channels: device.get_channel_count(Direction::Output)
+                                 .map_err(&error)?,
+                             sample_rate: device.get_sample_rate().map_err(&error)?,
+                             default_input: default_input == Some(device),
+                             default_output: default_output == Some(device),
+                         });
+        }
+        Ok(devices)
+    }
+
+    fn open_output(&self,
+                   uid: Option<&str>,
+                   format: StreamFormat)
+                   -> Result<Box<dyn FrameWriter>, String> {
+        Ok(Box::new(self.open_queue(uid, format, Direction::Output)?))
+    }
+
+    fn open_input(&self,
+                  uid: Option<&str>,
+                  format: StreamFormat)
+                  -> Result<Box<dyn FrameReader>, String> {
+        Ok(Box::new(self.open_queue(uid, format, Direction::Input)?))
+    }
+}
+
+/// Interleaved samples passed between a stream and its queue's callbacks.
+#[cfg(feature = "coreaudio")]
+struct QueueState {
+    samples: VecDeque<f32>,
+    capacity: usize,
+    running: bool,
+}
+
+#[cfg(feature = "coreaudio")]
+struct QueueShared {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+/// Fills the buffer from the queued samples, padding with silence when they run out.
+#[cfg(feature = "coreaudio")]
+unsafe extern "C" fn queue_output_callback(user_data: *mut c_void,
+                                           queue: AudioQueueRef,
+                                           buffer: AudioQueueBufferRef) {
+    let shared = &*(user_data as *const Arc<QueueShared>);
+    let mut state = match shared.state.lock() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+    if !state.running {
+        return;
+    }
+    let length = (*buffer).mAudioDataBytesCapacity as usize / 4;
+    let data = slice::from_raw_parts_mut((*buffer).mAudioData as *mut f32, length);
+    let available = length.min(state.samples.len());
+    for (out, sample) in data.iter_mut().zip(state.samples.drain(..available)) {
+        *out = sample;
+    }
+    for out in data[available..].iter_mut() {
+        *out = 0.0;
+    }
+    (*buffer).mAudioDataByteSize = (length * 4) as u32;
+    AudioQueueEnqueueBuffer(queue, buffer, 0, ptr::null());
+    shared.changed.notify_all();
+}
+
+/// Appends the recorded samples and hands the buffer back to the queue.
+#[cfg(feature = "coreaudio")]
+unsafe extern "C" fn queue_input_callback(user_data: *mut c_void,
+                                          queue: AudioQueueRef,
+                                          buffer: AudioQueueBufferRef,
+                                          _start_time: *const AudioTimeStamp,
+                                          _packet_count: u32,
+                                          _packets: *const AudioStreamPacketDescription) {
+    let shared = &*(user_data as *const Arc<QueueShared>);
+    let mut state = match shared.state.lock() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+    if !state.running {
+        return;
+    }
+    let length = (*buffer).mAudioDataByteSize as usize / 4;
+    let data = slice::from_raw_parts((*buffer).mAudioData as *const f32, length);
+    state.samples.extend(data.iter().cloned());
+    if state.samples.len() > state.capacity {
+        let excess = state.samples.len() - state.capacity;
+        state.samples.drain(..excess);
+    }
+    AudioQueueEnqueueBuffer(queue, buffer, 0, ptr::null());
+    shared.changed.notify_all();
+}
+
+#[cfg(feature = "coreaudio")]
+struct QueueStream {
+    queue: Option<AudioQueue>,
+    shared: Arc<QueueShared>,
+    /// The `Box<Arc<QueueShared>>` the callbacks are given.
+    user_data: *mut Arc<QueueShared>,
+    format: StreamFormat,
+    direction: Direction,
+}
+
+#[cfg(feature = "coreaudio")]
+impl QueueStream {
+    fn stop(&mut self) {
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.running = false;
+        }
+        if let Some(mut queue) = self.queue.take() {
+            let _ = queue.stop(true);
+        }
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl Drop for QueueStream {
+    fn drop(&mut self) {
+        self.stop();
+        unsafe {
+            drop(Box::from_raw(self.user_data));
+        }
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl FrameWriter for QueueStream {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        let timeout = Duration::from_secs(QUEUE_TIMEOUT_SECONDS);
+        let mut state = self.shared.state.lock().map_err(|e| e.to_string())?;
+        while state.running && state.samples.len() >= state.capacity {
+            let (next, wait) = self.shared
+                .changed
+                .wait_timeout(state, timeout)
+                .map_err(|e| e.to_string())?;
+            state = next;
+            if wait.timed_out() && state.samples.len() >= state.capacity {
+                return Err("the output device stopped playing".to_owned());
+            }
+        }
+        if !state.running {
+            return Err("the output stream has been finished".to_owned());
+        }
+        state.samples.extend(frames.iter().cloned());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if self.direction != Direction::Output || self.queue.is_none() {
+            return Ok(());
+        }
+        {
+            let timeout = Duration::from_secs(QUEUE_TIMEOUT_SECONDS);
+            let mut state = self.shared.state.lock().map_err(|e| e.to_string())?;
+            while !state.samples.is_empty() {
+                let (next, wait) = self.shared
+                    .changed
+                    .wait_timeout(state, timeout)
+                    .map_err(|e| e.to_string())?;
+                state = next;
+                if wait.timed_out() && !state.samples.is_empty() {
+                    return Err("the output device stopped playing".to_owned());
+                }
+            }
+        }
+        // The last samples are in buffers the queue has yet to play.
+        let seconds = QUEUE_BUFFER_SECONDS * QUEUE_BUFFER_COUNT as f64;
+        thread::sleep(Duration::from_millis((seconds * 1000.0).ceil() as u64));
+        self.stop();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl FrameReader for QueueStream {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.format.channels as usize;
+        let timeout = Duration::from_secs(QUEUE_TIMEOUT_SECONDS);
+        let mut state = self.shared.state.lock().map_err(|e| e.to_string())?;
+        while state.samples.len() < channels {
+            if !state.running {
+                return Err("the input stream has been stopped".to_owned());
+            }
+            let (next, wait) = self.shared
+                .changed
+                .wait_timeout(state, timeout)
+                .map_err(|e| e.to_string())?;
+            state = next;
+            if wait.timed_out() && state.samples.len() < channels {
+                return Err("the input device stopped recording".to_owned());
+            }
+        }
+        let frames = buffer.len().min(state.samples.len()) / channels;
+        for (out, sample) in buffer.iter_mut().zip(state.samples.drain(..frames * channels)) {
+            *out = sample;
+        }
+        Ok(frames)
+    }
+}
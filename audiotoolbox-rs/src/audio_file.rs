@@ -7,6 +7,8 @@ use core_foundation_sys::base::*;
 use core_foundation::base::TCFType;
 use core_foundation::url::CFURL;
 
+pub use file_type::AudioFileTypeId;
+
 
 pub struct AudioFile(AudioFileID);
 
@@ -23,6 +25,11 @@ pub enum AudioFileProperty {
     FileFormat(AudioFileTypeId),
     MagicCookie(Vec<u8>),
     MaximumPacketSize(u32),
+    AudioDataByteCount(u64),
+    AudioDataPacketCount(u64),
+    DataOffset(i64),
+    /// The bytes of an `AudioChannelLayout`.
+    ChannelLayout(Vec<u8>),
 }
 
 #[repr(u32)]
@@ -32,54 +39,10 @@ pub enum AudioFilePropertyId {
     DataFormat = 1684434292,
     MagicCookie = 1835493731,
     MaximumPacketSize = 1886616165,
-}
-
-#[repr(u32)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-#[allow(non_camel_case_types)]
-pub enum AudioFileTypeId {
-    AIFF = 1095321158,
-    AIFC = 1095321155,
-    WAVE = 1463899717,
-    SoundDesigner2 = 1399075430,
-    Next = 1315264596,
-    MP3 = 1297106739,
-    MP2 = 1297106738,
-    MP1 = 1297106737,
-    AC3 = 1633889587,
-    AAC_ADTS = 1633973363,
-    MPEG4 = 1836069990,
-    M4A = 1832149350,
-    M4B = 1832149606,
-    CAF = 1667327590,
-    _3GP = 862417008,
-    _3GP2 = 862416946,
-    AMR = 1634562662,
-}
-
-impl AudioFileTypeId {
-    pub fn from_u32(v: u32) -> Result<AudioFileTypeId, ()> {
-        match v {
-            1095321158 => Ok(AudioFileTypeId::AIFF),
-            1095321155 => Ok(AudioFileTypeId::AIFC),
-            1463899717 => Ok(AudioFileTypeId::WAVE),
-            1399075430 => Ok(AudioFileTypeId::SoundDesigner2),
-            1315264596 => Ok(AudioFileTypeId::Next),
-            1297106739 => Ok(AudioFileTypeId::MP3),
-            1297106738 => Ok(AudioFileTypeId::MP2),
-            1297106737 => Ok(AudioFileTypeId::MP1),
-            1633889587 => Ok(AudioFileTypeId::AC3),
-            1633973363 => Ok(AudioFileTypeId::AAC_ADTS),
-            1836069990 => Ok(AudioFileTypeId::MPEG4),
-            1832149350 => Ok(AudioFileTypeId::M4A),
-            1832149606 => Ok(AudioFileTypeId::M4B),
-            1667327590 => Ok(AudioFileTypeId::CAF),
-            862417008 => Ok(AudioFileTypeId::_3GP),
-            862416946 => Ok(AudioFileTypeId::_3GP2),
-            1634562662 => Ok(AudioFileTypeId::AMR),
-            _ => Err(()),
-        }
-    }
+    AudioDataByteCount = 1650683508,
+    AudioDataPacketCount = 1885564532,
+    DataOffset = 1685022310,
+    ChannelLayout = 1668112752,
 }
 
 impl AudioFile {
@@ -156,6 +119,19 @@ impl AudioFile {
                 let max_packet_size: *const u32 = mem::transmute(data.as_ptr());
                 Ok(AudioFileProperty::MaximumPacketSize(*max_packet_size))
             },
+            AudioFilePropertyId::AudioDataByteCount => unsafe {
+                let byte_count = ptr::read_unaligned(data.as_ptr() as *const u64);
+                Ok(AudioFileProperty::AudioDataByteCount(byte_count))
+            },
+            AudioFilePropertyId::AudioDataPacketCount => unsafe {
+                let packet_count = ptr::read_unaligned(data.as_ptr() as *const u64);
+                Ok(AudioFileProperty::AudioDataPacketCount(packet_count))
+            },
+            AudioFilePropertyId::DataOffset => unsafe {
+                let offset = ptr::read_unaligned(data.as_ptr() as *const i64);
+                Ok(AudioFileProperty::DataOffset(offset))
+            },
+            AudioFilePropertyId::ChannelLayout => Ok(AudioFileProperty::ChannelLayout(data)),
         }
     }
 
@@ -1,44 +1,168 @@
 #![macro_use]
 
-use core_foundation::base::OSStatus;
+use core_foundation::base::{OSStatus, TCFType};
+use core_foundation::string::CFString;
+use core_foundation_sys::string::CFStringRef;
 
+use audio_device::Direction;
 use audiotoolbox_sys::*;
 use std::os::raw::c_void;
 use std::ptr;
 use std::mem;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct AudioDevice(AudioDeviceID);
 
+fn property_address(selector: u32, scope: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        mSelector: selector,
+        mScope: scope,
+        mElement: kAudioObjectPropertyElementMaster as u32,
+    }
+}
+
+fn default_device(selector: u32) -> Result<AudioDevice, OSStatus> {
+    let mut device_id: AudioDeviceID = 0;
+    let mut device_id_size = mem::size_of::<AudioDeviceID>() as u32;
+    let property_address = property_address(selector, kAudioObjectPropertyScopeGlobal as u32);
+    let error = unsafe {
+        AudioObjectGetPropertyData(kAudioObjectSystemObject as u32,
+                                   &property_address,
+                                   0,
+                                   ptr::null(),
+                                   &mut device_id_size,
+                                   &mut device_id as *mut _ as *mut c_void)
+    };
+    if error != 0 {
+        Err(error)
+    } else {
+        Ok(AudioDevice(device_id))
+    }
+}
+
 impl AudioDevice {
-    pub fn default_input() -> Result<AudioDevice, OSStatus> {
-        let mut device_id: AudioDeviceID = 0;
-        let mut device_id_size = mem::size_of::<AudioDeviceID>() as u32;
-        let property_address = AudioObjectPropertyAddress {
-            mSelector: kAudioHardwarePropertyDefaultInputDevice as u32,
-            mScope: kAudioObjectPropertyScopeGlobal as u32,
-            mElement: kAudioObjectPropertyElementMaster as u32,
+    /// Every device the system knows of.
+    pub fn all() -> Result<Vec<AudioDevice>, OSStatus> {
+        let property_address = property_address(kAudioHardwarePropertyDevices as u32,
+                                                kAudioObjectPropertyScopeGlobal as u32);
+        let mut prop_size: u32 = 0;
+        let mut error = unsafe {
+            AudioObjectGetPropertyDataSize(kAudioObjectSystemObject as u32,
+                                           &property_address,
+                                           0,
+                                           ptr::null(),
+                                           &mut prop_size)
         };
+        if error != 0 {
+            return Err(error);
+        }
+        let mut ids: Vec<AudioDeviceID> =
+            vec![0; prop_size as usize / mem::size_of::<AudioDeviceID>()];
+        error = unsafe {
+            AudioObjectGetPropertyData(kAudioObjectSystemObject as u32,
+                                       &property_address,
+                                       0,
+                                       ptr::null(),
+                                       &mut prop_size,
+                                       ids.as_mut_ptr() as *mut c_void)
+        };
+        if error != 0 {
+            return Err(error);
+        }
+        ids.truncate(prop_size as usize / mem::size_of::<AudioDeviceID>());
+        Ok(ids.into_iter().map(AudioDevice).collect())
+    }
+
+    pub fn default_input() -> Result<AudioDevice, OSStatus> {
+        default_device(kAudioHardwarePropertyDefaultInputDevice as u32)
+    }
+
+    pub fn default_output() -> Result<AudioDevice, OSStatus> {
+        default_device(kAudioHardwarePropertyDefaultOutputDevice as u32)
+    }
+
+    pub fn id(&self) -> AudioDeviceID {
+        self.0
+    }
 
+    fn get_string(&self, selector: u32) -> Result<String, OSStatus> {
+        let property_address = property_address(selector, kAudioObjectPropertyScopeGlobal as u32);
+        let mut prop_size = mem::size_of::<CFStringRef>() as u32;
+        let mut value: CFStringRef = ptr::null();
         let error = unsafe {
-            AudioObjectGetPropertyData(kAudioObjectSystemObject as u32,
+            AudioObjectGetPropertyData(self.0 as u32,
                                        &property_address,
                                        0,
                                        ptr::null(),
-                                       &mut device_id_size,
-                                       &mut device_id as *mut _ as *mut c_void)
+                                       &mut prop_size,
+                                       &mut value as *mut _ as *mut c_void)
         };
         if error != 0 {
             Err(error)
+        } else if value.is_null() {
+            Ok(String::new())
         } else {
-            Ok(AudioDevice(device_id))
+            Ok(unsafe { CFString::wrap_under_create_rule(value) }.to_string())
         }
     }
 
-    pub fn get_sample_rate(&self) -> Result<f64, OSStatus> {
-        let property_address = AudioObjectPropertyAddress {
-            mElement: kAudioObjectPropertyElementMaster as u32,
-            mScope: kAudioObjectPropertyScopeGlobal as u32,
-            mSelector: kAudioDevicePropertyNominalSampleRate as u32,
+    pub fn get_name(&self) -> Result<String, OSStatus> {
+        self.get_string(kAudioObjectPropertyName as u32)
+    }
+
+    /// The identifier that stays the same for a device across reboots.
+    pub fn get_uid(&self) -> Result<String, OSStatus> {
+        self.get_string(kAudioDevicePropertyDeviceUID as u32)
+    }
+
+    /// The total number of channels across the device's streams in `direction`.
+    pub fn get_channel_count(&self, direction: Direction) -> Result<u32, OSStatus> {
+        let scope = match direction {
+            Direction::Input => kAudioObjectPropertyScopeInput as u32,
+            Direction::Output => kAudioObjectPropertyScopeOutput as u32,
+        };
+        let property_address =
+            property_address(kAudioDevicePropertyStreamConfiguration as u32, scope);
+        let mut prop_size: u32 = 0;
+        let mut error = unsafe {
+            AudioObjectGetPropertyDataSize(self.0 as u32,
+                                           &property_address,
+                                           0,
+                                           ptr::null(),
+                                           &mut prop_size)
         };
+        if error != 0 {
+            return Err(error);
+        }
+        if (prop_size as usize) < mem::size_of::<u32>() {
+            return Ok(0);
+        }
+        // A u64 buffer keeps the list's pointers aligned.
+        let mut data: Vec<u64> = vec![0; (prop_size as usize + 7) / 8];
+        error = unsafe {
+            AudioObjectGetPropertyData(self.0 as u32,
+                                       &property_address,
+                                       0,
+                                       ptr::null(),
+                                       &mut prop_size,
+                                       data.as_mut_ptr() as *mut c_void)
+        };
+        if error != 0 {
+            return Err(error);
+        }
+        let channels = unsafe {
+            let list = data.as_ptr() as *const AudioBufferList;
+            let buffers = (*list).mBuffers.as_ptr();
+            (0..(*list).mNumberBuffers as isize)
+                .map(|i| (*buffers.offset(i)).mNumberChannels)
+                .sum()
+        };
+        Ok(channels)
+    }
+
+    pub fn get_sample_rate(&self) -> Result<f64, OSStatus> {
+        let property_address = property_address(kAudioDevicePropertyNominalSampleRate as u32,
+                                                kAudioObjectPropertyScopeGlobal as u32);
         let mut prop_size = mem::size_of::<f64>() as u32;
         let mut out_sample_rate: f64 = 0.0;
         let error = unsafe {
@@ -1,11 +1,14 @@
 #![macro_use]
 
-use core_foundation::base::OSStatus;
+use core_foundation::base::{OSStatus, TCFType};
+use core_foundation::string::CFString;
+use core_foundation_sys::string::CFStringRef;
 
 use audiotoolbox_sys::*;
 use std::os::raw::c_void;
 use std::ptr;
 use audio_file::*;
+use meter::LevelMeterState;
 use std::mem;
 pub struct AudioQueue(pub AudioQueueRef);
 pub struct Buffer(AudioQueueBufferRef);
@@ -180,6 +183,72 @@ impl AudioQueue {
         if status == 0 { Ok(()) } else { Err(status) }
     }
 
+    /// Plays or records through the device with `uid` instead of the system default.
+    pub fn set_current_device(&mut self, uid: &str) -> Result<(), OSStatus> {
+        let uid = CFString::new(uid);
+        let mut uid_ref: CFStringRef = uid.as_concrete_TypeRef();
+        let status = unsafe {
+            AudioQueueSetProperty(self.0,
+                                  kAudioQueueProperty_CurrentDevice as u32,
+                                  &mut uid_ref as *mut _ as *mut c_void,
+                                  mem::size_of::<CFStringRef>() as u32)
+        };
+        if status == 0 { Ok(()) } else { Err(status) }
+    }
+
+    /// Turns on the level meters that `current_level_meter` reads.
+    pub fn set_level_metering(&mut self, enabled: bool) -> Result<(), OSStatus> {
+        let mut enabled = enabled as u32;
+        let status = unsafe {
+            AudioQueueSetProperty(self.0,
+                                  kAudioQueueProperty_EnableLevelMetering as u32,
+                                  &mut enabled as *mut _ as *mut c_void,
+                                  mem::size_of::<u32>() as u32)
+        };
+        if status == 0 { Ok(()) } else { Err(status) }
+    }
+
+    /// Linear levels per channel, as `meter::MeterReadings` gives them for other backends.
+    pub fn current_level_meter(&self) -> Result<Vec<LevelMeterState>, OSStatus> {
+        self.get_level_meter(kAudioQueueProperty_CurrentLevelMeter as u32)
+    }
+
+    /// Levels per channel in dB.
+    pub fn current_level_meter_db(&self) -> Result<Vec<LevelMeterState>, OSStatus> {
+        self.get_level_meter(kAudioQueueProperty_CurrentLevelMeterDB as u32)
+    }
+
+    fn get_level_meter(&self, property: u32) -> Result<Vec<LevelMeterState>, OSStatus> {
+        let mut size: u32 = 0;
+        let status = unsafe { AudioQueueGetPropertySize(self.0, property, &mut size) };
+        if status != 0 {
+            return Err(status);
+        }
+        let count = size as usize / mem::size_of::<AudioQueueLevelMeterState>();
+        let mut states: Vec<AudioQueueLevelMeterState> = Vec::with_capacity(count);
+        let status = unsafe {
+            AudioQueueGetProperty(self.0,
+                                  property,
+                                  states.as_mut_ptr() as *mut c_void,
+                                  &mut size)
+        };
+        if status != 0 {
+            return Err(status);
+        }
+        let filled = size as usize / mem::size_of::<AudioQueueLevelMeterState>();
+        unsafe {
+            states.set_len(filled.min(count));
+        }
+        Ok(states.iter()
+               .map(|state| {
+                        LevelMeterState {
+                            average_power: state.mAveragePower,
+                            peak_power: state.mPeakPower,
+                        }
+                    })
+               .collect())
+    }
+
     pub fn copy_cookie_to_queue(&mut self, file: &mut AudioFile) -> Result<(), OSStatus> {
         match file.get_property(AudioFilePropertyId::MagicCookie)? {
             AudioFileProperty::MagicCookie(cookie) => self.set_magic_cookie(cookie),
@@ -0,0 +1,495 @@
+extern crate audiotoolbox;
+extern crate clap;
+
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::process;
+
+use audiotoolbox::audio_converter::{AudioConverter, AudioConverterReader};
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::convert::Dither;
+use audiotoolbox::dsp::time_pitch::{Preset, TimePitch, TimePitchReader};
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::{self, FrameReader, NativeFileReader, RangeReader};
+use audiotoolbox::frame_writer::{self, FrameWriter, NativeFileWriter};
+use audiotoolbox::loudness;
+use audiotoolbox::normalize::{Limiter, NormalizingReader, Target};
+use audiotoolbox::resample::Quality;
+use audiotoolbox::silence::{self, SilenceDetector};
+use audiotoolbox::stream_format::*;
+use clap::{App, Arg, ArgMatches};
+
+mod common {
+    pub mod args;
+    pub mod data_format;
+}
+
+use common::args::parse;
+use common::data_format::{data_format, DataFormat};
+
+const BUFFER_FRAMES: usize = 4096;
+/// The highest true peak EBU R 128 allows in delivered programmes.
+const DEFAULT_TRUE_PEAK_LIMIT: f64 = -1.0;
+const DEFAULT_SILENCE_THRESHOLD: f64 = -60.0;
+
+/// Reports how much of the input has been converted on stderr.
+struct Progress {
+    total: Option<u64>,
+    done: u64,
+    percent: Option<u64>,
+    quiet: bool,
+}
+
+impl Progress {
+    fn advance(&mut self, frames: usize) {
+        self.done += frames as u64;
+        if self.quiet {
+            return;
+        }
+        if let Some(total) = self.total {
+            let percent = (self.done * 100).checked_div(total).map_or(100, |p| p.min(100));
+            if self.percent != Some(percent) {
+                self.percent = Some(percent);
+                eprint!("\rconverting: {:3}%", percent);
+                let _ = io::stderr().flush();
+            }
+        }
+    }
+
+    fn finish(&self, output: &str, format: &StreamFormat) {
+        if self.quiet {
+            return;
+        }
+        match self.percent {
+            Some(100) => eprintln!(),
+            Some(_) => eprintln!("\rconverting: 100%"),
+            None => {}
+        }
+        eprintln!("wrote {} frames ({:.3} sec) to {}",
+                  self.done,
+                  self.done as f64 / format.sample_rate,
+                  output);
+    }
+}
+
+/// Opens the input, converts it to the requested rate and channel count and changes its
+/// speed and pitch, returning the sample format of native files, which keep it unless told
+/// otherwise.
+fn open_input(matches: &ArgMatches,
+              input: &str)
+              -> Result<(Box<dyn FrameReader>, Option<SampleFormat>), String> {
+    let (mut reader, source_format): (Box<dyn FrameReader>, Option<SampleFormat>) =
+        match NativeFileReader::open(input) {
+            Ok(reader) => {
+                let sample_format = reader.file_format().sample_format;
+                (Box::new(reader), Some(sample_format))
+            }
+            Err(_) => (frame_reader::open(input)?, None),
+        };
+    let quality = match matches.value_of("quality").unwrap_or("medium") {
+        "fast" => Quality::Fast,
+        "medium" => Quality::Medium,
+        _ => Quality::Best,
+    };
+
+    let source = reader.format();
+    let sample_rate = parse(matches, "sample-rate")?.unwrap_or(source.sample_rate);
+    let channels = parse(matches, "channels")?.unwrap_or(source.channels);
+    if sample_rate <= 0.0 || channels == 0 {
+        return Err("sample rate and channel count must be positive".to_owned());
+    }
+    if sample_rate != source.sample_rate || channels != source.channels {
+        let mut converter = AudioConverter::new(source,
+                                                StreamFormat::new(sample_rate,
+                                                                  channels,
+                                                                  SampleFormat::F32))?;
+        converter.set_quality(quality)?;
+        if channels != source.channels {
+            if let (Some(from), Some(to)) = (reader.channel_layout(),
+                                             ChannelLayout::default_for(channels)) {
+                converter.set_channel_layouts(&from, &to)?;
+            }
+        }
+        reader = Box::new(AudioConverterReader::new(reader, converter)?);
+    }
+
+    let speed = parse(matches, "speed")?.unwrap_or(1.0);
+    let pitch = parse(matches, "pitch")?.unwrap_or(0.0);
+    if speed != 1.0 || pitch != 0.0 {
+        let preset = match matches.value_of("stretch-preset").unwrap_or("music") {
+            "speech" => Preset::Speech,
+            _ => Preset::Music,
+        };
+        let time_pitch = TimePitch::new(sample_rate, channels as usize, speed, pitch, preset)?;
+        reader = Box::new(TimePitchReader::new(reader, time_pitch)?);
+    }
+    Ok((reader, source_format))
+}
+
+/// Measures `reader`, which holds `name`, and returns the gain in dB that brings it to
+/// `target`.
+fn normalizing_gain<R: FrameReader>(reader: R,
+                                    target: Target,
+                                    name: &str,
+                                    quiet: bool)
+                                    -> Result<f64, String> {
+    if !quiet {
+        eprintln!("measuring {}", name);
+    }
+    let measured = loudness::measure(reader)?;
+    let gain = target.gain(&measured)
+        .ok_or_else(|| format!("{} is silent and cannot be normalised", name))?;
+    if !quiet {
+        eprintln!("loudness {} LUFS, true peak {:.1} dBTP, applying {:+.1} dB",
+                  measured.integrated.map_or("-inf".to_owned(), |l| format!("{:.1}", l)),
+                  measured.true_peak,
+                  gain);
+    }
+    Ok(gain)
+}
+
+/// `NAME-1.EXT`, `NAME-2.EXT` and so on, with as many digits as the last number needs.
+fn numbered(output: &str, number: usize, count: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let mut name = format!("{}-{:0width$}", stem, number, width = count.to_string().len());
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        name.push('.');
+        name.push_str(extension);
+    }
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// How to write each output file.
+struct OutputSettings {
+    file_type: AudioFileTypeId,
+    data_format: DataFormat,
+    dither: Dither,
+    /// dB.
+    gain: f64,
+    /// Limit true peaks to this many dBTP.
+    ceiling: Option<f64>,
+    quiet: bool,
+}
+
+fn write_file<R: FrameReader>(reader: R,
+                              output: &str,
+                              settings: &OutputSettings)
+                              -> Result<(), String> {
+    let StreamFormat { sample_rate, channels, .. } = reader.format();
+    let limiter = match settings.ceiling {
+        Some(ceiling) => Some(Limiter::new(sample_rate, channels as usize, ceiling)?),
+        None => None,
+    };
+    let mut reader = NormalizingReader::new(reader, settings.gain, limiter)?;
+
+    let file_type = settings.file_type;
+    let layout = reader.channel_layout();
+    let mut writer: Box<dyn FrameWriter> = match settings.data_format {
+        DataFormat::Pcm(sample_format) => {
+            let format = StreamFormat::new(sample_rate, channels, sample_format);
+            if NativeFileWriter::supports(file_type) {
+                Box::new(NativeFileWriter::create(output, file_type, format, layout)?
+                             .with_dither(settings.dither))
+            } else {
+                frame_writer::create(output,
+                                     file_type,
+                                     &StreamDescription::from_stream_format(&format),
+                                     layout)?
+            }
+        }
+        DataFormat::Encoded(format_id) => {
+            let description = StreamDescription {
+                sample_rate,
+                format_id,
+                format_flags: 0,
+                bytes_per_packet: 0,
+                frames_per_packet: 0,
+                bytes_per_frame: 0,
+                channels_per_frame: channels,
+                bits_per_channel: 0,
+            };
+            frame_writer::create(output, file_type, &description, layout)?
+        }
+    };
+
+    let mut progress = Progress {
+        total: reader.length(),
+        done: 0,
+        percent: None,
+        quiet: settings.quiet,
+    };
+    let mut buffer = vec![0.0; BUFFER_FRAMES * channels as usize];
+    loop {
+        let frames = reader.read_frames(&mut buffer)?;
+        if frames == 0 {
+            break;
+        }
+        writer.write_frames(&buffer[..frames * channels as usize])?;
+        progress.advance(frames);
+    }
+    writer.finish()?;
+    progress.finish(output, &writer.format());
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let input = matches.value_of("INPUT").unwrap();
+    let quiet = matches.is_present("quiet");
+    let (mut reader, source_format) = open_input(matches, input)?;
+    let StreamFormat { sample_rate, channels, .. } = reader.format();
+
+    let trim = matches.is_present("trim");
+    let split = matches.is_present("split");
+    let silence = if trim || split || matches.is_present("detect-silence") {
+        let threshold = parse(matches, "silence-threshold")?.unwrap_or(DEFAULT_SILENCE_THRESHOLD);
+        let mut detector = SilenceDetector::new(sample_rate, channels as usize, threshold)?;
+        if let Some(hold) = parse(matches, "silence-hold")? {
+            detector = detector.with_hold(hold);
+        }
+        if let Some(hysteresis) = parse(matches, "silence-hysteresis")? {
+            detector = detector.with_hysteresis(hysteresis);
+        }
+        Some(silence::detect(open_input(matches, input)?.0, detector)?)
+    } else {
+        None
+    };
+    if matches.is_present("detect-silence") {
+        for region in &silence.unwrap().regions {
+            println!("silence {:.3} - {:.3} sec (frames {}..{})",
+                     region.start as f64 / sample_rate,
+                     region.end as f64 / sample_rate,
+                     region.start,
+                     region.end);
+        }
+        return Ok(());
+    }
+
+    let output = matches.value_of("OUTPUT").unwrap();
+    let file_type = match matches.value_of("file-type") {
+        Some(name) => {
+            AudioFileTypeId::from_extension(name)
+                .ok_or_else(|| format!("unknown file type '{}'", name))?
+        }
+        None => {
+            Path::new(output)
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(AudioFileTypeId::from_extension)
+                .ok_or_else(|| "cannot tell the file type from the output name; use --file-type"
+                                   .to_owned())?
+        }
+    };
+    let data_format = match matches.value_of("data-format") {
+        Some(text) => data_format(text)?,
+        None => DataFormat::Pcm(source_format.unwrap_or(SampleFormat::I16)),
+    };
+    let dither = match matches.value_of("dither").unwrap_or("none") {
+        "rectangular" => Dither::Rectangular,
+        "triangular" => Dither::Triangular,
+        _ => Dither::None,
+    };
+
+    let target = match (parse(matches, "normalize")?, parse(matches, "normalize-peak")?) {
+        (Some(loudness), _) => Some(Target::Loudness(loudness)),
+        (None, Some(peak)) => Some(Target::Peak(peak)),
+        (None, None) => None,
+    };
+    let input_loudness: Option<f64> = parse(matches, "input-loudness")?;
+    // Loudness targets are limited to -1 dBTP unless told otherwise; a peak target is its
+    // own ceiling.
+    let ceiling = match (parse(matches, "true-peak-limit")?, target) {
+        (Some(ceiling), _) => Some(ceiling),
+        (None, Some(Target::Loudness(_))) => Some(DEFAULT_TRUE_PEAK_LIMIT),
+        (None, Some(Target::Peak(peak))) => Some(peak),
+        (None, None) => None,
+    };
+    let mut settings = OutputSettings {
+        file_type,
+        data_format,
+        dither,
+        gain: 0.0,
+        ceiling,
+        quiet,
+    };
+
+    // Each output file and the frames of the input it holds, or all of them.
+    let outputs: Vec<(String, Option<Range<u64>>)> = match silence {
+        Some(silence) => {
+            let pad: f64 = parse(matches, "silence-pad")?.unwrap_or(0.0);
+            let pad = (pad.max(0.0) * sample_rate).round() as u64;
+            let ranges = if split { silence.segments(pad) } else { vec![silence.trimmed(pad)] };
+            if ranges.iter().all(|range| range.start == range.end) {
+                return Err(format!("{} is all silence", input));
+            }
+            let count = ranges.len();
+            ranges.into_iter()
+                .enumerate()
+                .map(|(i, range)| {
+                    let name = if split {
+                        numbered(output, i + 1, count)
+                    } else {
+                        output.to_owned()
+                    };
+                    (name, Some(range))
+                })
+                .collect()
+        }
+        None => vec![(output.to_owned(), None)],
+    };
+    for (output, range) in outputs {
+        // Each file is measured on its own, so the silence trimmed from it and the other
+        // segments of a split do not change its gain.
+        settings.gain = match (target, input_loudness) {
+            (Some(Target::Loudness(target)), Some(loudness)) => target - loudness,
+            (Some(target), _) => {
+                let input_reader = open_input(matches, input)?.0;
+                match range.clone() {
+                    Some(range) => {
+                        let name = format!("frames {}..{} of {}", range.start, range.end, input);
+                        normalizing_gain(RangeReader::new(input_reader, range)?,
+                                         target,
+                                         &name,
+                                         quiet)?
+                    }
+                    None => normalizing_gain(input_reader, target, input, quiet)?,
+                }
+            }
+            (None, _) => 0.0,
+        };
+        match range {
+            Some(range) => {
+                write_file(RangeReader::new(&mut reader, range)?, &output, &settings)?
+            }
+            None => write_file(&mut reader, &output, &settings)?,
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("audiotoolbox-convert")
+        .about("Converts audio files between containers, sample formats, rates and channel \
+                counts")
+        .arg(Arg::with_name("file-type")
+                 .short("f")
+                 .long("file-type")
+                 .takes_value(true)
+                 .help("Output container, such as wav, aiff, aifc or caf, or with CoreAudio \
+                        m4a or aac. Defaults to the output file's extension"))
+        .arg(Arg::with_name("data-format")
+                 .short("d")
+                 .long("data-format")
+                 .takes_value(true)
+                 .help("i8, i16, i24, i32, f32, f64, or with CoreAudio the four character \
+                        code of a compressed format such as aac or alac. Defaults to the \
+                        input's sample format"))
+        .arg(Arg::with_name("sample-rate")
+                 .short("r")
+                 .long("sample-rate")
+                 .takes_value(true)
+                 .help("Output sample rate in Hz"))
+        .arg(Arg::with_name("channels")
+                 .short("c")
+                 .long("channels")
+                 .takes_value(true)
+                 .help("Output channel count"))
+        .arg(Arg::with_name("quality")
+                 .long("quality")
+                 .takes_value(true)
+                 .possible_values(&["fast", "medium", "best"])
+                 .help("Sample rate conversion quality"))
+        .arg(Arg::with_name("dither")
+                 .long("dither")
+                 .takes_value(true)
+                 .possible_values(&["none", "rectangular", "triangular"])
+                 .help("Dither when writing integer samples"))
+        .arg(Arg::with_name("speed")
+                 .long("speed")
+                 .takes_value(true)
+                 .help("Play this many times faster, from 0.125 to 8, keeping the pitch"))
+        .arg(Arg::with_name("pitch")
+                 .long("pitch")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .help("Shift the pitch by this many cents, up to 2400 either way, keeping the \
+                        speed"))
+        .arg(Arg::with_name("stretch-preset")
+                 .long("stretch-preset")
+                 .takes_value(true)
+                 .possible_values(&["speech", "music"])
+                 .help("How to change speed and pitch: speech with WSOLA, or music with a \
+                        phase vocoder [default: music]"))
+        .arg(Arg::with_name("normalize")
+                 .long("normalize")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .conflicts_with("normalize-peak")
+                 .help("Normalise to this integrated loudness in LUFS, such as -23, measuring \
+                        the input first, or each file --trim or --split writes. Limiting peaks \
+                        can leave the result a little quieter"))
+        .arg(Arg::with_name("normalize-peak")
+                 .long("normalize-peak")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .help("Normalise to this true peak level in dBTP, measuring the input first, \
+                        or each file --trim or --split writes"))
+        .arg(Arg::with_name("input-loudness")
+                 .long("input-loudness")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .requires("normalize")
+                 .help("The input's integrated loudness in LUFS, when already known, to \
+                        normalise without measuring it"))
+        .arg(Arg::with_name("true-peak-limit")
+                 .long("true-peak-limit")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .help("Limit true peaks to this level in dBTP with a look-ahead limiter \
+                        [default: -1 when normalising loudness, the target when normalising \
+                        peaks]"))
+        .arg(Arg::with_name("trim")
+                 .long("trim")
+                 .conflicts_with("split")
+                 .help("Cut off silence at the start and end. Fails if the input is all \
+                        silence"))
+        .arg(Arg::with_name("split")
+                 .long("split")
+                 .help("Split at each silence, writing OUTPUT-1, OUTPUT-2 and so on with the \
+                        silence left out. Fails if the input is all silence"))
+        .arg(Arg::with_name("detect-silence")
+                 .long("detect-silence")
+                 .conflicts_with_all(&["trim", "split"])
+                 .help("List the silent parts of the input and exit"))
+        .arg(Arg::with_name("silence-threshold")
+                 .long("silence-threshold")
+                 .takes_value(true)
+                 .allow_hyphen_values(true)
+                 .help("Peak level in dBFS below which audio is silent [default: -60]"))
+        .arg(Arg::with_name("silence-hold")
+                 .long("silence-hold")
+                 .takes_value(true)
+                 .help("Seconds audio must stay below the threshold to count as silence \
+                        [default: 0.5]"))
+        .arg(Arg::with_name("silence-hysteresis")
+                 .long("silence-hysteresis")
+                 .takes_value(true)
+                 .help("dB above the threshold audio must rise to end a silence [default: 3]"))
+        .arg(Arg::with_name("silence-pad")
+                 .long("silence-pad")
+                 .takes_value(true)
+                 .help("Seconds of silence to keep around the audio when trimming or \
+                        splitting [default: 0]"))
+        .arg(Arg::with_name("quiet")
+                 .short("q")
+                 .long("quiet")
+                 .help("Do not report progress"))
+        .arg(Arg::with_name("INPUT").required(true))
+        .arg(Arg::with_name("OUTPUT").required_unless("detect-silence"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("audiotoolbox-convert: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,170 @@
+extern crate audiotoolbox;
+extern crate clap;
+
+use std::process;
+
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::file_info::AudioFileInfo;
+use audiotoolbox::file_type::fourcc;
+use clap::{App, Arg};
+
+fn layout_name(layout: &ChannelLayout) -> String {
+    let channels: Vec<String> = layout.channels().iter().map(|c| format!("{:?}", c)).collect();
+    match layout.tag() {
+        Some(tag) => format!("{:?} ({})", tag, channels.join(", ")),
+        None => channels.join(", "),
+    }
+}
+
+fn print_text(path: &str, info: &AudioFileInfo) {
+    let description = &info.description;
+    println!("File:                {}", path);
+    println!("File type ID:        {}", info.file_type);
+    println!("Data format:         {}", description);
+    let flags = description.flag_names();
+    if !flags.is_empty() {
+        println!("Format flags:        {}", flags.join(" "));
+    }
+    println!("Bytes per packet:    {}", description.bytes_per_packet);
+    println!("Frames per packet:   {}", description.frames_per_packet);
+    println!("Bytes per frame:     {}", description.bytes_per_frame);
+    match info.channel_layout {
+        Some(ref layout) => println!("Channel layout:      {}", layout_name(layout)),
+        None => println!("Channel layout:      none"),
+    }
+    match info.duration() {
+        Some(duration) => println!("Estimated duration:  {:.6} sec", duration),
+        None => println!("Estimated duration:  unknown"),
+    }
+    println!("Audio bytes:         {}", info.data_size);
+    if let Some(packets) = info.packet_count {
+        println!("Audio packets:       {}", packets);
+    }
+    if let Some(frames) = info.frame_count {
+        println!("Audio frames:        {}", frames);
+    }
+    if let Some(bit_rate) = info.bit_rate() {
+        println!("Bit rate:            {:.0} bits per second", bit_rate);
+    }
+    if let Some(offset) = info.data_offset {
+        println!("Audio data offset:   {}", offset);
+    }
+    match info.magic_cookie {
+        Some(ref cookie) => println!("Magic cookie:        {} bytes", cookie.len()),
+        None => println!("Magic cookie:        none"),
+    }
+    for (key, value) in &info.metadata {
+        println!("{:<20} {}", format!("{}:", key), value);
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or("null".to_owned(), |v| v.to_string())
+}
+
+fn json(path: &str, info: &AudioFileInfo) -> String {
+    let description = &info.description;
+    let flags: Vec<String> = description.flag_names().iter().map(|f| json_string(f)).collect();
+    let layout = match info.channel_layout {
+        Some(ref layout) => {
+            let channels: Vec<String> = layout.channels()
+                .iter()
+                .map(|c| json_string(&format!("{:?}", c)))
+                .collect();
+            format!("{{\"tag\": {}, \"channels\": [{}]}}",
+                    layout.tag().map_or("null".to_owned(), |t| json_string(&format!("{:?}", t))),
+                    channels.join(", "))
+        }
+        None => "null".to_owned(),
+    };
+    let metadata: Vec<String> = info.metadata
+        .iter()
+        .map(|(key, value)| format!("{}: {}", json_string(key), json_string(value)))
+        .collect();
+    let fields = vec![("file", json_string(path)),
+                      ("file_type", json_string(&info.file_type.to_string())),
+                      ("format_id", json_string(&fourcc(description.format_id))),
+                      ("format_flags", description.format_flags.to_string()),
+                      ("format_flag_names", format!("[{}]", flags.join(", "))),
+                      ("sample_rate", description.sample_rate.to_string()),
+                      ("channels", description.channels_per_frame.to_string()),
+                      ("bits_per_channel", description.bits_per_channel.to_string()),
+                      ("bytes_per_packet", description.bytes_per_packet.to_string()),
+                      ("frames_per_packet", description.frames_per_packet.to_string()),
+                      ("bytes_per_frame", description.bytes_per_frame.to_string()),
+                      ("packet_count", json_option(info.packet_count)),
+                      ("frame_count", json_option(info.frame_count)),
+                      ("duration", json_option(info.duration())),
+                      ("bit_rate", json_option(info.bit_rate())),
+                      ("data_offset", json_option(info.data_offset)),
+                      ("data_size", info.data_size.to_string()),
+                      ("magic_cookie_size",
+                       json_option(info.magic_cookie.as_ref().map(|c| c.len()))),
+                      ("channel_layout", layout),
+                      ("metadata", format!("{{{}}}", metadata.join(", ")))];
+    let fields: Vec<String> = fields.iter()
+        .map(|(key, value)| format!("  {}: {}", json_string(key), value))
+        .collect();
+    format!("{{\n{}\n}}", fields.join(",\n"))
+}
+
+fn main() {
+    let matches = App::new("audiotoolbox-info")
+        .about("Prints the container, data format and contents of audio files")
+        .arg(Arg::with_name("json")
+                 .long("json")
+                 .help("Print a JSON object, or an array of objects for several files"))
+        .arg(Arg::with_name("FILE").required(true).multiple(true))
+        .get_matches();
+    let paths: Vec<&str> = matches.values_of("FILE").unwrap().collect();
+    let as_json = matches.is_present("json");
+
+    let mut status = 0;
+    let mut objects = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        match AudioFileInfo::open(path) {
+            Ok(info) => {
+                if as_json {
+                    objects.push(json(path, &info));
+                } else {
+                    if i > 0 {
+                        println!("----");
+                    }
+                    print_text(path, &info);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                status = 1;
+            }
+        }
+    }
+    if as_json {
+        if paths.len() == 1 {
+            if let Some(object) = objects.pop() {
+                println!("{}", object);
+            }
+        } else {
+            println!("[{}]", objects.join(",\n"));
+        }
+    }
+    process::exit(status);
+}
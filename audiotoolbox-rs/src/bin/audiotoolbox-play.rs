@@ -0,0 +1,189 @@
+extern crate audiotoolbox;
+extern crate clap;
+extern crate ctrlc;
+
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use audiotoolbox::audio_converter::{AudioConverter, AudioConverterReader};
+use audiotoolbox::audio_device::{self, AudioBackend, Direction};
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::frame_reader::{self, FrameReader};
+use audiotoolbox::stream_format::*;
+use clap::{App, Arg, ArgMatches};
+
+mod common {
+    pub mod args;
+    pub mod meter;
+}
+
+use common::args::parse;
+use common::meter::Meter;
+
+const BUFFER_FRAMES: usize = 1024;
+
+fn list_devices(backend: &dyn AudioBackend) -> Result<(), String> {
+    for device in backend.devices()? {
+        println!("{}", device);
+    }
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let backend_name = matches.value_of("backend").unwrap_or(audio_device::backend_names()[0]);
+    let backend = audio_device::backend(backend_name)?;
+    if matches.is_present("list-devices") {
+        return list_devices(&*backend);
+    }
+    let input = matches.value_of("FILE").unwrap();
+    let quiet = matches.is_present("quiet");
+
+    let mut reader = frame_reader::open(input)?;
+    let source = reader.format();
+    let sample_rate = parse(matches, "sample-rate")?.unwrap_or(source.sample_rate);
+    let channels = parse(matches, "channels")?.unwrap_or(source.channels);
+    if sample_rate <= 0.0 || channels == 0 {
+        return Err("sample rate and channel count must be positive".to_owned());
+    }
+    if sample_rate != source.sample_rate || channels != source.channels {
+        let mut converter = AudioConverter::new(source,
+                                                StreamFormat::new(sample_rate,
+                                                                  channels,
+                                                                  SampleFormat::F32))?;
+        if channels != source.channels {
+            if let (Some(from), Some(to)) = (reader.channel_layout(),
+                                             ChannelLayout::default_for(channels)) {
+                converter.set_channel_layouts(&from, &to)?;
+            }
+        }
+        reader = Box::new(AudioConverterReader::new(reader, converter)?);
+    }
+    let format = reader.format();
+
+    let start_time: f64 = parse(matches, "start")?.unwrap_or(0.0);
+    let duration: Option<f64> = parse(matches, "duration")?;
+    if start_time < 0.0 || duration.is_some_and(|duration| duration < 0.0) {
+        return Err("times must not be negative".to_owned());
+    }
+    let start = (start_time * sample_rate).round() as u64;
+    if start > 0 {
+        reader.seek(start)?;
+    }
+    let duration = duration.map(|duration| (duration * sample_rate).round() as u64);
+    let looping = matches.is_present("loop");
+
+    let device = match matches.value_of("device") {
+        Some(query) => {
+            Some(audio_device::find_device(&backend.devices()?, query, Direction::Output)?)
+        }
+        None => None,
+    };
+    let mut output = backend.open_output(device.as_ref().map(|d| d.uid.as_str()), format)?;
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))
+            .map_err(|e| format!("unable to handle Ctrl-C: {}", e))?;
+    }
+
+    let channels = channels as usize;
+    let mut buffer = vec![0.0; BUFFER_FRAMES * channels];
+    let mut meter = Meter::new(&format, quiet)?;
+    let mut position = start;
+    let mut played = 0u64;
+    while !interrupted.load(Ordering::SeqCst) {
+        let mut frames = BUFFER_FRAMES;
+        if let Some(duration) = duration {
+            if played >= duration {
+                break;
+            }
+            frames = frames.min((duration - played) as usize);
+        }
+        let frames = reader.read_frames(&mut buffer[..frames * channels])?;
+        if frames == 0 {
+            // A start past the end of the file would otherwise loop forever.
+            if looping && position > start {
+                reader.seek(start)?;
+                position = start;
+                continue;
+            }
+            break;
+        }
+        output.write_frames(&buffer[..frames * channels])?;
+        position += frames as u64;
+        played += frames as u64;
+        meter.update(&buffer[..frames * channels], position);
+    }
+    meter.clear();
+    let stopped = interrupted.load(Ordering::SeqCst);
+    if stopped {
+        // Dropping the stream cuts playback off rather than playing out what is queued.
+        drop(output);
+    } else {
+        output.finish()?;
+    }
+    if !quiet {
+        eprintln!("{} {:.3} sec of {} on {}",
+                  if stopped { "stopped after" } else { "played" },
+                  played as f64 / sample_rate,
+                  input,
+                  device.map_or("the default device".to_owned(), |d| d.name));
+    }
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("audiotoolbox-play")
+        .about("Plays an audio file through an output device")
+        .arg(Arg::with_name("backend")
+                 .long("backend")
+                 .takes_value(true)
+                 .possible_values(audio_device::backend_names())
+                 .help("Audio backend; 'null' discards the audio in real time, for testing \
+                        without hardware"))
+        .arg(Arg::with_name("list-devices")
+                 .short("l")
+                 .long("list-devices")
+                 .help("List the backend's devices and exit"))
+        .arg(Arg::with_name("device")
+                 .long("device")
+                 .takes_value(true)
+                 .help("Output device, by UID, name or part of its name [default: the system \
+                        default]"))
+        .arg(Arg::with_name("sample-rate")
+                 .short("r")
+                 .long("sample-rate")
+                 .takes_value(true)
+                 .help("Convert to this sample rate in Hz before playing"))
+        .arg(Arg::with_name("channels")
+                 .short("c")
+                 .long("channels")
+                 .takes_value(true)
+                 .help("Convert to this channel count before playing"))
+        .arg(Arg::with_name("start")
+                 .short("s")
+                 .long("start")
+                 .takes_value(true)
+                 .help("Start this many seconds into the file"))
+        .arg(Arg::with_name("duration")
+                 .short("t")
+                 .long("duration")
+                 .takes_value(true)
+                 .help("Stop after playing this many seconds"))
+        .arg(Arg::with_name("loop")
+                 .long("loop")
+                 .help("Go back to the start time at the end of the file, until the duration \
+                        is up or Ctrl-C is pressed"))
+        .arg(Arg::with_name("quiet")
+                 .short("q")
+                 .long("quiet")
+                 .help("Do not show level meters"))
+        .arg(Arg::with_name("FILE").required_unless("list-devices"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("audiotoolbox-play: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,218 @@
+extern crate audiotoolbox;
+extern crate clap;
+extern crate ctrlc;
+
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use audiotoolbox::audio_device::{self, AudioBackend, Direction};
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_writer::{self, FrameWriter, NativeFileWriter};
+use audiotoolbox::stream_format::*;
+use clap::{App, Arg, ArgMatches};
+
+mod common {
+    pub mod args;
+    pub mod data_format;
+    pub mod meter;
+}
+
+use common::args::parse;
+use common::data_format::{data_format, DataFormat};
+use common::meter::Meter;
+
+const BUFFER_FRAMES: usize = 1024;
+
+fn list_devices(backend: &dyn AudioBackend) -> Result<(), String> {
+    for device in backend.devices()? {
+        println!("{}", device);
+    }
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let backend_name = matches.value_of("backend").unwrap_or(audio_device::backend_names()[0]);
+    let backend = audio_device::backend(backend_name)?;
+    if matches.is_present("list-devices") {
+        return list_devices(&*backend);
+    }
+    let output = matches.value_of("OUTPUT").unwrap();
+    let quiet = matches.is_present("quiet");
+
+    let file_type = match matches.value_of("file-type") {
+        Some(name) => {
+            AudioFileTypeId::from_extension(name)
+                .ok_or_else(|| format!("unknown file type '{}'", name))?
+        }
+        None => {
+            Path::new(output)
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(AudioFileTypeId::from_extension)
+                .ok_or_else(|| "cannot tell the file type from the output name; use --file-type"
+                                   .to_owned())?
+        }
+    };
+    let data_format = data_format(matches.value_of("data-format").unwrap_or("i16"))?;
+
+    // The device's own rate and up to two of its channels, unless told otherwise.
+    let devices = backend.devices()?;
+    let device = match matches.value_of("device") {
+        Some(query) => Some(audio_device::find_device(&devices, query, Direction::Input)?),
+        None => None,
+    };
+    let (device_rate, device_channels) = match device.as_ref()
+              .or_else(|| devices.iter().find(|d| d.default_input)) {
+        Some(info) => (info.sample_rate, info.input_channels.min(2)),
+        None => (0.0, 0),
+    };
+    let sample_rate = match parse(matches, "sample-rate")? {
+        Some(rate) => rate,
+        None if device_rate > 0.0 => device_rate,
+        None => 44100.0,
+    };
+    let channels = match parse(matches, "channels")? {
+        Some(channels) => channels,
+        None if device_channels > 0 => device_channels,
+        None => 2,
+    };
+    if sample_rate <= 0.0 || channels == 0 {
+        return Err("sample rate and channel count must be positive".to_owned());
+    }
+    let duration: Option<f64> = parse(matches, "duration")?;
+    if duration.is_some_and(|duration| duration < 0.0) {
+        return Err("the duration must not be negative".to_owned());
+    }
+    let duration = duration.map(|duration| (duration * sample_rate).round() as u64);
+
+    // Open the device first, so a device that cannot record leaves no empty file behind.
+    let format = StreamFormat::new(sample_rate, channels, SampleFormat::F32);
+    let mut input = backend.open_input(device.as_ref().map(|d| d.uid.as_str()), format)?;
+
+    let layout = ChannelLayout::default_for(channels);
+    let mut writer: Box<dyn FrameWriter> = match data_format {
+        DataFormat::Pcm(sample_format) => {
+            let file_format = StreamFormat::new(sample_rate, channels, sample_format);
+            if NativeFileWriter::supports(file_type) {
+                Box::new(NativeFileWriter::create(output, file_type, file_format, layout)?)
+            } else {
+                frame_writer::create(output,
+                                     file_type,
+                                     &StreamDescription::from_stream_format(&file_format),
+                                     layout)?
+            }
+        }
+        DataFormat::Encoded(format_id) => {
+            let description = StreamDescription {
+                sample_rate,
+                format_id,
+                format_flags: 0,
+                bytes_per_packet: 0,
+                frames_per_packet: 0,
+                bytes_per_frame: 0,
+                channels_per_frame: channels,
+                bits_per_channel: 0,
+            };
+            frame_writer::create(output, file_type, &description, layout)?
+        }
+    };
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+    {
+        let interrupted = interrupted.clone();
+        ctrlc::set_handler(move || interrupted.store(true, Ordering::SeqCst))
+            .map_err(|e| format!("unable to handle Ctrl-C: {}", e))?;
+    }
+    if !quiet && duration.is_none() {
+        eprintln!("recording to {}, press Ctrl-C to stop", output);
+    }
+
+    let channels = channels as usize;
+    let mut buffer = vec![0.0; BUFFER_FRAMES * channels];
+    let mut meter = Meter::new(&format, quiet)?;
+    let mut recorded = 0u64;
+    while !interrupted.load(Ordering::SeqCst) {
+        let mut frames = BUFFER_FRAMES;
+        if let Some(duration) = duration {
+            if recorded >= duration {
+                break;
+            }
+            frames = frames.min((duration - recorded) as usize);
+        }
+        let frames = input.read_frames(&mut buffer[..frames * channels])?;
+        writer.write_frames(&buffer[..frames * channels])?;
+        recorded += frames as u64;
+        meter.update(&buffer[..frames * channels], recorded);
+    }
+    drop(input);
+    meter.clear();
+    writer.finish()?;
+    if !quiet {
+        eprintln!("recorded {} frames ({:.3} sec) to {}",
+                  recorded,
+                  recorded as f64 / sample_rate,
+                  output);
+    }
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("audiotoolbox-record")
+        .about("Records from an input device to an audio file until the duration is up or \
+                Ctrl-C is pressed")
+        .arg(Arg::with_name("backend")
+                 .long("backend")
+                 .takes_value(true)
+                 .possible_values(audio_device::backend_names())
+                 .help("Audio backend; 'null' records silence in real time, for testing \
+                        without hardware"))
+        .arg(Arg::with_name("list-devices")
+                 .short("l")
+                 .long("list-devices")
+                 .help("List the backend's devices and exit"))
+        .arg(Arg::with_name("device")
+                 .long("device")
+                 .takes_value(true)
+                 .help("Input device, by UID, name or part of its name [default: the system \
+                        default]"))
+        .arg(Arg::with_name("file-type")
+                 .short("f")
+                 .long("file-type")
+                 .takes_value(true)
+                 .help("Output container, such as wav, aiff, aifc or caf, or with CoreAudio \
+                        m4a or aac. Defaults to the output file's extension"))
+        .arg(Arg::with_name("data-format")
+                 .short("d")
+                 .long("data-format")
+                 .takes_value(true)
+                 .help("i8, i16, i24, i32, f32, f64, or with CoreAudio the four character \
+                        code of a compressed format such as aac or alac [default: i16]"))
+        .arg(Arg::with_name("sample-rate")
+                 .short("r")
+                 .long("sample-rate")
+                 .takes_value(true)
+                 .help("Sample rate in Hz [default: the device's]"))
+        .arg(Arg::with_name("channels")
+                 .short("c")
+                 .long("channels")
+                 .takes_value(true)
+                 .help("Channel count [default: up to two of the device's]"))
+        .arg(Arg::with_name("duration")
+                 .short("t")
+                 .long("duration")
+                 .takes_value(true)
+                 .help("Stop after recording this many seconds"))
+        .arg(Arg::with_name("quiet")
+                 .short("q")
+                 .long("quiet")
+                 .help("Do not show level meters"))
+        .arg(Arg::with_name("OUTPUT").required_unless("list-devices"))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("audiotoolbox-record: {}", e);
+        process::exit(1);
+    }
+}
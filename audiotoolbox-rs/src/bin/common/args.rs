@@ -0,0 +1,16 @@
+// Command line argument parsing shared by the tools.
+
+use std::str::FromStr;
+
+use clap::ArgMatches;
+
+pub fn parse<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, String> {
+    match matches.value_of(name) {
+        Some(text) => {
+            text.parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value '{}' for --{}", text, name))
+        }
+        None => Ok(None),
+    }
+}
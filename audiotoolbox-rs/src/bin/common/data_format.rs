@@ -0,0 +1,32 @@
+// The `--data-format` argument of the tools that write files.
+
+use audiotoolbox::file_type::fourcc_code;
+use audiotoolbox::stream_format::SampleFormat;
+
+pub enum DataFormat {
+    Pcm(SampleFormat),
+    /// A compressed format's four character code.
+    Encoded(u32),
+}
+
+pub fn data_format(text: &str) -> Result<DataFormat, String> {
+    let sample_format = match text {
+        "i8" => SampleFormat::I8,
+        "i16" => SampleFormat::I16,
+        "i24" => SampleFormat::I24,
+        "i32" => SampleFormat::I32,
+        "f32" => SampleFormat::F32,
+        "f64" => SampleFormat::F64,
+        _ => {
+            // Codes shorter than four characters, such as `aac`, are padded with spaces.
+            let bytes = text.as_bytes();
+            if bytes.is_empty() || bytes.len() > 4 {
+                return Err(format!("unknown data format '{}'", text));
+            }
+            let mut code = [b' '; 4];
+            code[..bytes.len()].copy_from_slice(bytes);
+            return Ok(DataFormat::Encoded(fourcc_code(&code)));
+        }
+    };
+    Ok(DataFormat::Pcm(sample_format))
+}
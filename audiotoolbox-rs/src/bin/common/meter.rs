@@ -0,0 +1,77 @@
+// The level meter the tools that play and record draw on stderr.
+
+use std::io::{self, Write};
+
+use audiotoolbox::meter::{Ballistics, LevelMeter};
+use audiotoolbox::stream_format::StreamFormat;
+
+/// Lowest level the meter shows.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Draws the position and a meter per channel on one line of stderr, ten times a second:
+/// a BBC peak programme meter's bar and the highest peak of the last second and a half.
+pub struct Meter {
+    meter: LevelMeter,
+    sample_rate: f64,
+    width: usize,
+    pending: usize,
+    drawn: bool,
+    quiet: bool,
+}
+
+impl Meter {
+    pub fn new(format: &StreamFormat, quiet: bool) -> Result<Meter, String> {
+        let channels = format.channels as usize;
+        Ok(Meter {
+               meter: LevelMeter::new(format.sample_rate, channels)?
+                   .with_ballistics(Ballistics::PpmType2),
+               sample_rate: format.sample_rate,
+               width: (40 / channels).clamp(4, 20),
+               pending: 0,
+               drawn: false,
+               quiet,
+           })
+    }
+
+    /// Measures interleaved `samples` that end `position` frames into the stream.
+    pub fn update(&mut self, samples: &[f32], position: u64) {
+        if self.quiet {
+            return;
+        }
+        self.meter.process(samples);
+        self.pending += samples.len() / self.meter.channels();
+        if (self.pending as f64) < self.sample_rate / 10.0 {
+            return;
+        }
+        let seconds = position as f64 / self.sample_rate;
+        let mut line = format!("\r{:02}:{:05.2}", (seconds / 60.0) as u64, seconds % 60.0);
+        for levels in self.meter.levels() {
+            let fill = ((20.0 * levels.ballistic.log10() - METER_FLOOR_DB) / -METER_FLOOR_DB *
+                        self.width as f32)
+                .round()
+                .clamp(0.0, self.width as f32) as usize;
+            let db = 20.0 * levels.peak_hold.log10();
+            let level = if db > METER_FLOOR_DB {
+                format!("{:6.1}", db)
+            } else {
+                "  -inf".to_owned()
+            };
+            line.push_str(&format!(" [{}{}]{}",
+                                   "#".repeat(fill),
+                                   " ".repeat(self.width - fill),
+                                   level));
+        }
+        eprint!("{}", line);
+        let _ = io::stderr().flush();
+        self.pending = 0;
+        self.drawn = true;
+    }
+
+    /// Ends the meter's line, if it drew one.
+    pub fn clear(&mut self) {
+        if self.drawn {
+            eprintln!();
+            self.drawn = false;
+        }
+    }
+}
@@ -0,0 +1,508 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use frame_reader::FrameReader;
+use stream_format::StreamFormat;
+
+/// Speaker positions, declared in WAV channel mask order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Channel {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftOfCenter,
+    FrontRightOfCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+}
+
+static WAV_ORDER: [Channel; 11] = [Channel::FrontLeft,
+                                   Channel::FrontRight,
+                                   Channel::FrontCenter,
+                                   Channel::LowFrequency,
+                                   Channel::BackLeft,
+                                   Channel::BackRight,
+                                   Channel::FrontLeftOfCenter,
+                                   Channel::FrontRightOfCenter,
+                                   Channel::BackCenter,
+                                   Channel::SideLeft,
+                                   Channel::SideRight];
+
+impl Channel {
+    /// The `SPEAKER_*` bit of a WAVE_FORMAT_EXTENSIBLE channel mask.
+    pub fn wav_bit(&self) -> u32 {
+        1 << (*self as u32)
+    }
+
+    /// Maps a CoreAudio `AudioChannelLabel`. The surrounds (Ls and Rs) are the back pair,
+    /// as in CoreAudio's channel bitmap where they share the WAV back speaker bits, and the
+    /// direct surrounds are the side pair.
+    pub fn from_label(label: u32) -> Result<Channel, String> {
+        match label {
+            1 => Ok(Channel::FrontLeft),
+            2 => Ok(Channel::FrontRight),
+            3 => Ok(Channel::FrontCenter),
+            4 => Ok(Channel::LowFrequency),
+            5 | 33 => Ok(Channel::BackLeft),
+            6 | 34 => Ok(Channel::BackRight),
+            7 => Ok(Channel::FrontLeftOfCenter),
+            8 => Ok(Channel::FrontRightOfCenter),
+            9 => Ok(Channel::BackCenter),
+            10 => Ok(Channel::SideLeft),
+            11 => Ok(Channel::SideRight),
+            _ => Err(format!("unsupported channel label {}", label)),
+        }
+    }
+
+    pub fn label(&self) -> u32 {
+        match *self {
+            Channel::FrontLeft => 1,
+            Channel::FrontRight => 2,
+            Channel::FrontCenter => 3,
+            Channel::LowFrequency => 4,
+            Channel::BackLeft => 5,
+            Channel::BackRight => 6,
+            Channel::FrontLeftOfCenter => 7,
+            Channel::FrontRightOfCenter => 8,
+            Channel::BackCenter => 9,
+            Channel::SideLeft => 10,
+            Channel::SideRight => 11,
+        }
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(non_camel_case_types)]
+pub enum ChannelLayoutTag {
+    UseChannelDescriptions = 0,
+    UseChannelBitmap = 65536,
+    Mono = 6553601,
+    Stereo = 6619138,
+    Quadraphonic = 7077892,
+    MPEG_5_1_A = 7929862,
+    MPEG_5_1_B = 7995398,
+    MPEG_5_1_C = 8060934,
+    MPEG_5_1_D = 8126470,
+    MPEG_7_1_A = 8257544,
+    MPEG_7_1_B = 8323080,
+    MPEG_7_1_C = 8388616,
+}
+
+impl ChannelLayoutTag {
+    pub fn from_u32(v: u32) -> Result<ChannelLayoutTag, String> {
+        match v {
+            0 => Ok(ChannelLayoutTag::UseChannelDescriptions),
+            65536 => Ok(ChannelLayoutTag::UseChannelBitmap),
+            6553601 => Ok(ChannelLayoutTag::Mono),
+            6619138 => Ok(ChannelLayoutTag::Stereo),
+            7077892 => Ok(ChannelLayoutTag::Quadraphonic),
+            7929862 => Ok(ChannelLayoutTag::MPEG_5_1_A),
+            7995398 => Ok(ChannelLayoutTag::MPEG_5_1_B),
+            8060934 => Ok(ChannelLayoutTag::MPEG_5_1_C),
+            8126470 => Ok(ChannelLayoutTag::MPEG_5_1_D),
+            8257544 => Ok(ChannelLayoutTag::MPEG_7_1_A),
+            8323080 => Ok(ChannelLayoutTag::MPEG_7_1_B),
+            8388616 => Ok(ChannelLayoutTag::MPEG_7_1_C),
+            _ => Err(format!("unsupported channel layout tag {:#x}", v)),
+        }
+    }
+
+    /// The surrounds are the back pair, except next to the rear surrounds of
+    /// `MPEG_7_1_C`, where they move to the sides.
+    fn channels(&self) -> &'static [Channel] {
+        use self::Channel::*;
+        static MONO: [Channel; 1] = [FrontCenter];
+        static STEREO: [Channel; 2] = [FrontLeft, FrontRight];
+        static QUADRAPHONIC: [Channel; 4] = [FrontLeft, FrontRight, BackLeft, BackRight];
+        static MPEG_5_1_A: [Channel; 6] =
+            [FrontLeft, FrontRight, FrontCenter, LowFrequency, BackLeft, BackRight];
+        static MPEG_5_1_B: [Channel; 6] =
+            [FrontLeft, FrontRight, BackLeft, BackRight, FrontCenter, LowFrequency];
+        static MPEG_5_1_C: [Channel; 6] =
+            [FrontLeft, FrontCenter, FrontRight, BackLeft, BackRight, LowFrequency];
+        static MPEG_5_1_D: [Channel; 6] =
+            [FrontCenter, FrontLeft, FrontRight, BackLeft, BackRight, LowFrequency];
+        static MPEG_7_1_A: [Channel; 8] = [FrontLeft,
+                                           FrontRight,
+                                           FrontCenter,
+                                           LowFrequency,
+                                           BackLeft,
+                                           BackRight,
+                                           FrontLeftOfCenter,
+                                           FrontRightOfCenter];
+        static MPEG_7_1_B: [Channel; 8] = [FrontCenter,
+                                           FrontLeftOfCenter,
+                                           FrontRightOfCenter,
+                                           FrontLeft,
+                                           FrontRight,
+                                           BackLeft,
+                                           BackRight,
+                                           LowFrequency];
+        static MPEG_7_1_C: [Channel; 8] = [FrontLeft,
+                                           FrontRight,
+                                           FrontCenter,
+                                           LowFrequency,
+                                           SideLeft,
+                                           SideRight,
+                                           BackLeft,
+                                           BackRight];
+        match *self {
+            ChannelLayoutTag::UseChannelDescriptions |
+            ChannelLayoutTag::UseChannelBitmap => &[],
+            ChannelLayoutTag::Mono => &MONO,
+            ChannelLayoutTag::Stereo => &STEREO,
+            ChannelLayoutTag::Quadraphonic => &QUADRAPHONIC,
+            ChannelLayoutTag::MPEG_5_1_A => &MPEG_5_1_A,
+            ChannelLayoutTag::MPEG_5_1_B => &MPEG_5_1_B,
+            ChannelLayoutTag::MPEG_5_1_C => &MPEG_5_1_C,
+            ChannelLayoutTag::MPEG_5_1_D => &MPEG_5_1_D,
+            ChannelLayoutTag::MPEG_7_1_A => &MPEG_7_1_A,
+            ChannelLayoutTag::MPEG_7_1_B => &MPEG_7_1_B,
+            ChannelLayoutTag::MPEG_7_1_C => &MPEG_7_1_C,
+        }
+    }
+}
+
+static LAYOUT_TAGS: [ChannelLayoutTag; 10] = [ChannelLayoutTag::Mono,
+                                              ChannelLayoutTag::Stereo,
+                                              ChannelLayoutTag::Quadraphonic,
+                                              ChannelLayoutTag::MPEG_5_1_A,
+                                              ChannelLayoutTag::MPEG_5_1_B,
+                                              ChannelLayoutTag::MPEG_5_1_C,
+                                              ChannelLayoutTag::MPEG_5_1_D,
+                                              ChannelLayoutTag::MPEG_7_1_A,
+                                              ChannelLayoutTag::MPEG_7_1_B,
+                                              ChannelLayoutTag::MPEG_7_1_C];
+
+/// The speaker position of each channel of a stream, in stream order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelLayout {
+    channels: Vec<Channel>,
+}
+
+impl ChannelLayout {
+    pub fn new(channels: Vec<Channel>) -> ChannelLayout {
+        ChannelLayout { channels }
+    }
+
+    pub fn mono() -> ChannelLayout {
+        ChannelLayout::new(vec![Channel::FrontCenter])
+    }
+
+    pub fn stereo() -> ChannelLayout {
+        ChannelLayout::new(vec![Channel::FrontLeft, Channel::FrontRight])
+    }
+
+    /// 5.1 in WAV order, as `KSAUDIO_SPEAKER_5POINT1`.
+    pub fn surround_5_1() -> ChannelLayout {
+        ChannelLayout::from_wav_mask(0x3f).unwrap()
+    }
+
+    /// 7.1 in WAV order, as `KSAUDIO_SPEAKER_7POINT1_SURROUND`.
+    pub fn surround_7_1() -> ChannelLayout {
+        ChannelLayout::from_wav_mask(0x63f).unwrap()
+    }
+
+    /// The layout assumed for a stream that does not describe its channels.
+    pub fn default_for(channels: u32) -> Option<ChannelLayout> {
+        match channels {
+            1 => Some(ChannelLayout::mono()),
+            2 => Some(ChannelLayout::stereo()),
+            4 => Some(ChannelLayout::from_wav_mask(0x33).unwrap()),
+            6 => Some(ChannelLayout::surround_5_1()),
+            8 => Some(ChannelLayout::surround_7_1()),
+            _ => None,
+        }
+    }
+
+    /// Channels of a WAVE_FORMAT_EXTENSIBLE stream, which appear in mask bit order.
+    pub fn from_wav_mask(mask: u32) -> Result<ChannelLayout, String> {
+        let known = WAV_ORDER.iter().fold(0, |acc, channel| acc | channel.wav_bit());
+        if mask & !known != 0 {
+            return Err(format!("unsupported speaker bits {:#x}", mask & !known));
+        }
+        let channels = WAV_ORDER.iter().cloned().filter(|c| mask & c.wav_bit() != 0).collect();
+        Ok(ChannelLayout::new(channels))
+    }
+
+    /// The channel mask describing this layout, if its channels are in WAV order.
+    pub fn wav_mask(&self) -> Option<u32> {
+        let mut mask = 0;
+        for pair in self.channels.windows(2) {
+            if pair[0] >= pair[1] {
+                return None;
+            }
+        }
+        for channel in &self.channels {
+            mask |= channel.wav_bit();
+        }
+        Some(mask)
+    }
+
+    pub fn from_tag(tag: ChannelLayoutTag) -> Result<ChannelLayout, String> {
+        match tag {
+            ChannelLayoutTag::UseChannelDescriptions |
+            ChannelLayoutTag::UseChannelBitmap => {
+                Err(format!("{:?} does not describe channels by itself", tag))
+            }
+            _ => Ok(ChannelLayout::new(tag.channels().to_vec())),
+        }
+    }
+
+    /// The CoreAudio tag describing exactly this channel order, if there is one.
+    pub fn tag(&self) -> Option<ChannelLayoutTag> {
+        LAYOUT_TAGS.iter().cloned().find(|tag| tag.channels() == &self.channels[..])
+    }
+
+    /// Parses the bytes of a CoreAudio `AudioChannelLayout` in native byte order.
+    pub fn from_audio_channel_layout(bytes: &[u8]) -> Result<ChannelLayout, String> {
+        let word = |i: usize| -> Result<u32, String> {
+            if bytes.len() < i + 4 {
+                return Err("truncated AudioChannelLayout".to_owned());
+            }
+            let mut raw = [0u8; 4];
+            raw.copy_from_slice(&bytes[i..i + 4]);
+            Ok(u32::from_ne_bytes(raw))
+        };
+        match ChannelLayoutTag::from_u32(word(0)?)? {
+            ChannelLayoutTag::UseChannelBitmap => {
+                // CoreAudio channel bits match the WAV speaker bits.
+                ChannelLayout::from_wav_mask(word(4)?)
+            }
+            ChannelLayoutTag::UseChannelDescriptions => {
+                let count = word(8)? as usize;
+                // Each AudioChannelDescription is a label, flags and three coordinates.
+                let labels = (0..count).map(|i| word(12 + i * 20)).collect::<Result<Vec<_>, _>>()?;
+                // Next to rear surrounds the surrounds are at the sides, as in MPEG_7_1_C.
+                let rear = labels.iter().any(|&label| label == 33 || label == 34);
+                let mut channels = Vec::with_capacity(count);
+                for label in labels {
+                    channels.push(match label {
+                                      5 if rear => Channel::SideLeft,
+                                      6 if rear => Channel::SideRight,
+                                      _ => Channel::from_label(label)?,
+                                  });
+                }
+                Ok(ChannelLayout::new(channels))
+            }
+            known => ChannelLayout::from_tag(known),
+        }
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    fn contains(&self, channel: Channel) -> bool {
+        self.channels.contains(&channel)
+    }
+}
+
+/// Where a channel missing from `to` is folded to, with the gain of each destination.
+fn fallback(channel: Channel, to: &ChannelLayout) -> Vec<(Channel, f32)> {
+    use self::Channel::*;
+    let pair = |left: Channel, right: Channel, gain: f32| vec![(left, gain), (right, gain)];
+    match channel {
+        FrontCenter => pair(FrontLeft, FrontRight, FRAC_1_SQRT_2),
+        FrontLeft | FrontRight => vec![(FrontCenter, FRAC_1_SQRT_2)],
+        LowFrequency => vec![],
+        FrontLeftOfCenter => vec![(FrontLeft, 1.0)],
+        FrontRightOfCenter => vec![(FrontRight, 1.0)],
+        SideLeft if to.contains(BackLeft) => vec![(BackLeft, 1.0)],
+        SideRight if to.contains(BackRight) => vec![(BackRight, 1.0)],
+        BackLeft if to.contains(SideLeft) => vec![(SideLeft, 1.0)],
+        BackRight if to.contains(SideRight) => vec![(SideRight, 1.0)],
+        SideLeft | BackLeft => vec![(FrontLeft, FRAC_1_SQRT_2)],
+        SideRight | BackRight => vec![(FrontRight, FRAC_1_SQRT_2)],
+        BackCenter if to.contains(BackLeft) => pair(BackLeft, BackRight, FRAC_1_SQRT_2),
+        BackCenter if to.contains(SideLeft) => pair(SideLeft, SideRight, FRAC_1_SQRT_2),
+        BackCenter => pair(FrontLeft, FrontRight, 0.5),
+    }
+}
+
+fn route(channel: Channel, gain: f32, to: &ChannelLayout, depth: usize, row: &mut [f32]) {
+    if let Some(index) = to.channels.iter().position(|&c| c == channel) {
+        row[index] += gain;
+    } else if depth < 4 {
+        for (next, next_gain) in fallback(channel, to) {
+            route(next, gain * next_gain, to, depth + 1, row);
+        }
+    }
+}
+
+/// Mixes interleaved frames through an output-by-input gain matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMixer {
+    inputs: usize,
+    outputs: usize,
+    /// Row-major, one row per output channel.
+    matrix: Vec<f32>,
+}
+
+impl ChannelMixer {
+    /// Builds a mixer from a user supplied matrix with one row of input gains per output.
+    pub fn new(matrix: Vec<Vec<f32>>) -> Result<ChannelMixer, String> {
+        let inputs = matrix.first().map(|row| row.len()).unwrap_or(0);
+        if inputs == 0 || matrix.iter().any(|row| row.len() != inputs) {
+            return Err("mixing matrix must be rectangular and non-empty".to_owned());
+        }
+        Ok(ChannelMixer {
+               inputs,
+               outputs: matrix.len(),
+               matrix: matrix.into_iter().flat_map(|row| row.into_iter()).collect(),
+           })
+    }
+
+    /// Reorders channels present in both layouts and folds the rest using the ITU-R BS.775
+    /// downmix coefficients. LFE is dropped when the target has no LFE channel.
+    pub fn between(from: &ChannelLayout, to: &ChannelLayout) -> Result<ChannelMixer, String> {
+        let (inputs, outputs) = (from.len(), to.len());
+        if inputs == 0 || outputs == 0 {
+            return Err("cannot mix between empty channel layouts".to_owned());
+        }
+        let mut matrix = vec![0.0; inputs * outputs];
+        let mut column = vec![0.0; outputs];
+        for (input, &channel) in from.channels.iter().enumerate() {
+            for gain in column.iter_mut() {
+                *gain = 0.0;
+            }
+            route(channel, 1.0, to, 0, &mut column);
+            for (output, &gain) in column.iter().enumerate() {
+                matrix[output * inputs + input] = gain;
+            }
+        }
+        Ok(ChannelMixer {
+               inputs,
+               outputs,
+               matrix,
+           })
+    }
+
+    /// Scales the matrix so no output can exceed full scale.
+    pub fn normalized(mut self) -> ChannelMixer {
+        let loudest = self.matrix
+            .chunks(self.inputs)
+            .map(|row| row.iter().map(|g| g.abs()).sum::<f32>())
+            .fold(0.0, f32::max);
+        if loudest > 1.0 {
+            for gain in self.matrix.iter_mut() {
+                *gain /= loudest;
+            }
+        }
+        self
+    }
+
+    pub fn input_channels(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn output_channels(&self) -> usize {
+        self.outputs
+    }
+
+    pub fn gain(&self, output: usize, input: usize) -> f32 {
+        self.matrix[output * self.inputs + input]
+    }
+
+    /// Mixes whole interleaved input frames, appending the result to `output`.
+    pub fn process(&self, input: &[f32], output: &mut Vec<f32>) {
+        output.reserve(input.len() / self.inputs * self.outputs);
+        for frame in input.chunks(self.inputs) {
+            if frame.len() < self.inputs {
+                break;
+            }
+            for row in self.matrix.chunks(self.inputs) {
+                output.push(row.iter().zip(frame).map(|(g, s)| g * s).sum());
+            }
+        }
+    }
+}
+
+/// Presents a reader's frames in another channel layout, for example "mono f32" from a
+/// file of any channel count.
+pub struct ChannelMapReader<R> {
+    reader: R,
+    mixer: ChannelMixer,
+    layout: ChannelLayout,
+    scratch: Vec<f32>,
+}
+
+impl<R: FrameReader> ChannelMapReader<R> {
+    pub fn new(reader: R, to: ChannelLayout) -> Result<ChannelMapReader<R>, String> {
+        let channels = reader.format().channels;
+        let from = match reader.channel_layout() {
+            Some(layout) => layout,
+            None => {
+                ChannelLayout::default_for(channels)
+                    .ok_or_else(|| format!("no default layout for {} channels", channels))?
+            }
+        };
+        if from.len() != channels as usize {
+            return Err(format!("layout has {} channels, stream has {}", from.len(), channels));
+        }
+        let mixer = ChannelMixer::between(&from, &to)?;
+        ChannelMapReader::with_mixer(reader, mixer, to)
+    }
+
+    pub fn with_mixer(reader: R,
+                      mixer: ChannelMixer,
+                      to: ChannelLayout)
+                      -> Result<ChannelMapReader<R>, String> {
+        if mixer.input_channels() != reader.format().channels as usize ||
+           mixer.output_channels() != to.len() {
+            return Err("mixer does not match the reader and target layout".to_owned());
+        }
+        Ok(ChannelMapReader {
+               reader,
+               mixer,
+               layout: to,
+               scratch: Vec::new(),
+           })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: FrameReader> FrameReader for ChannelMapReader<R> {
+    fn format(&self) -> StreamFormat {
+        let mut format = self.reader.format();
+        format.channels = self.layout.len() as u32;
+        format
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        Some(self.layout.clone())
+    }
+
+    fn length(&self) -> Option<u64> {
+        self.reader.length()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        self.reader.seek(frame)
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let frames = buffer.len() / self.mixer.output_channels();
+        self.scratch.resize(frames * self.mixer.input_channels(), 0.0);
+        let read = self.reader.read_frames(&mut self.scratch)?;
+        let mut mixed = Vec::with_capacity(read * self.mixer.output_channels());
+        self.mixer.process(&self.scratch[..read * self.mixer.input_channels()], &mut mixed);
+        buffer[..mixed.len()].copy_from_slice(&mixed);
+        Ok(read)
+    }
+}
@@ -0,0 +1,338 @@
+use stream_format::*;
+
+const DITHER_SEED: u32 = 0x9e37_79b9;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Dither {
+    None,
+    /// Rectangular noise of one LSB peak to peak.
+    Rectangular,
+    /// Triangular noise of two LSB peak to peak, which decorrelates the quantisation error
+    /// from the signal.
+    Triangular,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Clipping {
+    /// Clamp float output to [-1.0, 1.0].
+    Clamp,
+    /// Let float output exceed full scale. Integer output always saturates.
+    Preserve,
+}
+
+/// Converts linear PCM between two `StreamFormat`s with the same rate and channel count,
+/// the way `ExtAudioFile` converts to its client data format.
+pub struct Converter {
+    input: StreamFormat,
+    output: StreamFormat,
+    dither: Dither,
+    clipping: Clipping,
+    seed: u32,
+    samples: Vec<f64>,
+}
+
+impl Converter {
+    pub fn new(input: StreamFormat, output: StreamFormat) -> Result<Converter, String> {
+        if input.channels != output.channels {
+            return Err(format!("cannot convert {} channels to {}",
+                               input.channels,
+                               output.channels));
+        }
+        if input.channels == 0 {
+            return Err("format has no channels".to_owned());
+        }
+        if input.sample_rate != output.sample_rate {
+            return Err(format!("cannot convert {} Hz to {} Hz",
+                               input.sample_rate,
+                               output.sample_rate));
+        }
+        Ok(Converter {
+               input,
+               output,
+               dither: Dither::None,
+               clipping: Clipping::Clamp,
+               seed: DITHER_SEED,
+               samples: Vec::new(),
+           })
+    }
+
+    pub fn with_dither(mut self, dither: Dither) -> Converter {
+        self.dither = dither;
+        self
+    }
+
+    pub fn with_clipping(mut self, clipping: Clipping) -> Converter {
+        self.clipping = clipping;
+        self
+    }
+
+    pub fn input_format(&self) -> &StreamFormat {
+        &self.input
+    }
+
+    pub fn output_format(&self) -> &StreamFormat {
+        &self.output
+    }
+
+    /// Converts whole frames from `input` into `output`, which are given one buffer per
+    /// `buffer_count()` of their format. Output buffers are overwritten. Returns the number
+    /// of frames converted.
+    pub fn convert(&mut self, input: &[&[u8]], output: &mut [Vec<u8>]) -> Result<usize, String> {
+        let frames = decode_into(&self.input, input, &mut self.samples)?;
+        let dither = if self.needs_dither() {
+            self.dither
+        } else {
+            Dither::None
+        };
+        let mut noise = NoiseSource {
+            state: self.seed,
+            dither,
+        };
+        encode_from(&self.output, &self.samples, self.clipping, &mut noise, output)?;
+        self.seed = noise.state;
+        Ok(frames)
+    }
+
+    fn needs_dither(&self) -> bool {
+        let (from, to) = (self.input.sample_format, self.output.sample_format);
+        !to.is_float() && (from.is_float() || from.bits_per_sample() > to.bits_per_sample())
+    }
+}
+
+/// Decodes any supported layout to interleaved f32 samples in [-1.0, 1.0).
+pub fn decode_f32(format: &StreamFormat, buffers: &[&[u8]]) -> Result<Vec<f32>, String> {
+    let mut samples = Vec::new();
+    decode_into(format, buffers, &mut samples)?;
+    Ok(samples.into_iter().map(|s| s as f32).collect())
+}
+
+/// Encodes interleaved f32 samples to `format`, without dither and clamping floats.
+pub fn encode_f32(format: &StreamFormat, samples: &[f32]) -> Result<Vec<Vec<u8>>, String> {
+    let mut buffers = vec![Vec::new(); format.buffer_count()];
+    Encoder::new(*format).encode(samples, &mut buffers)?;
+    Ok(buffers)
+}
+
+/// Encodes interleaved f32 samples to a `StreamFormat`, carrying dither state between calls.
+pub struct Encoder {
+    format: StreamFormat,
+    clipping: Clipping,
+    noise: NoiseSource,
+    samples: Vec<f64>,
+}
+
+impl Encoder {
+    pub fn new(format: StreamFormat) -> Encoder {
+        Encoder {
+            format,
+            clipping: Clipping::Clamp,
+            noise: NoiseSource {
+                state: DITHER_SEED,
+                dither: Dither::None,
+            },
+            samples: Vec::new(),
+        }
+    }
+
+    /// Dither is ignored when encoding to a float format.
+    pub fn with_dither(mut self, dither: Dither) -> Encoder {
+        if !self.format.sample_format.is_float() {
+            self.noise.dither = dither;
+        }
+        self
+    }
+
+    pub fn with_clipping(mut self, clipping: Clipping) -> Encoder {
+        self.clipping = clipping;
+        self
+    }
+
+    pub fn format(&self) -> &StreamFormat {
+        &self.format
+    }
+
+    /// Overwrites `output`, one buffer per `buffer_count()`, returning the frames encoded.
+    pub fn encode(&mut self, samples: &[f32], output: &mut [Vec<u8>]) -> Result<usize, String> {
+        self.samples.clear();
+        self.samples.extend(samples.iter().map(|&s| s as f64));
+        encode_from(&self.format, &self.samples, self.clipping, &mut self.noise, output)?;
+        Ok(samples.len() / self.format.channels as usize)
+    }
+}
+
+fn decode_into(format: &StreamFormat,
+               buffers: &[&[u8]],
+               samples: &mut Vec<f64>)
+               -> Result<usize, String> {
+    if format.channels == 0 {
+        return Err("format has no channels".to_owned());
+    }
+    if buffers.len() != format.buffer_count() {
+        return Err(format!("expected {} buffers, got {}",
+                           format.buffer_count(),
+                           buffers.len()));
+    }
+    let bytes_per_frame = format.bytes_per_frame();
+    let frames = buffers.first().map(|b| b.len() / bytes_per_frame).unwrap_or(0);
+    for buffer in buffers {
+        if buffer.len() % bytes_per_frame != 0 || buffer.len() / bytes_per_frame != frames {
+            return Err(format!("buffer of {} bytes does not hold {} whole frames",
+                               buffer.len(),
+                               frames));
+        }
+    }
+
+    let channels = format.channels as usize;
+    let width = format.sample_format.bytes_per_sample();
+    samples.clear();
+    samples.reserve(frames * channels);
+    for frame in 0..frames {
+        for channel in 0..channels {
+            let (buffer, offset) = if format.interleaved {
+                (buffers[0], (frame * channels + channel) * width)
+            } else {
+                (buffers[channel], frame * width)
+            };
+            samples.push(decode_sample(format.sample_format,
+                                       format.endianness,
+                                       &buffer[offset..offset + width]));
+        }
+    }
+    Ok(frames)
+}
+
+fn encode_from(format: &StreamFormat,
+               samples: &[f64],
+               clipping: Clipping,
+               noise: &mut NoiseSource,
+               buffers: &mut [Vec<u8>])
+               -> Result<(), String> {
+    if format.channels == 0 {
+        return Err("format has no channels".to_owned());
+    }
+    if buffers.len() != format.buffer_count() {
+        return Err(format!("expected {} buffers, got {}",
+                           format.buffer_count(),
+                           buffers.len()));
+    }
+    let channels = format.channels as usize;
+    let frames = samples.len() / channels;
+    for buffer in buffers.iter_mut() {
+        buffer.clear();
+        buffer.reserve(frames * format.bytes_per_frame());
+    }
+    for (i, &sample) in samples.iter().take(frames * channels).enumerate() {
+        let buffer = if format.interleaved {
+            &mut buffers[0]
+        } else {
+            &mut buffers[i % channels]
+        };
+        encode_sample(format.sample_format,
+                      format.endianness,
+                      sample,
+                      clipping,
+                      noise,
+                      buffer);
+    }
+    Ok(())
+}
+
+fn read_uint(bytes: &[u8], endianness: Endianness) -> u64 {
+    let mut value = 0u64;
+    match endianness {
+        Endianness::Big => {
+            for &byte in bytes {
+                value = (value << 8) | byte as u64;
+            }
+        }
+        Endianness::Little => {
+            for &byte in bytes.iter().rev() {
+                value = (value << 8) | byte as u64;
+            }
+        }
+    }
+    value
+}
+
+fn write_uint(value: u64, width: usize, endianness: Endianness, out: &mut Vec<u8>) {
+    for i in 0..width {
+        let shift = match endianness {
+            Endianness::Big => (width - 1 - i) * 8,
+            Endianness::Little => i * 8,
+        };
+        out.push((value >> shift) as u8);
+    }
+}
+
+fn full_scale(sample_format: SampleFormat) -> f64 {
+    (1u64 << (sample_format.bits_per_sample() - 1)) as f64
+}
+
+fn decode_sample(sample_format: SampleFormat, endianness: Endianness, bytes: &[u8]) -> f64 {
+    let raw = read_uint(bytes, endianness);
+    match sample_format {
+        SampleFormat::F32 => f32::from_bits(raw as u32) as f64,
+        SampleFormat::F64 => f64::from_bits(raw),
+        _ => {
+            // Sign extend from the sample width.
+            let unused_bits = 64 - sample_format.bits_per_sample();
+            (((raw << unused_bits) as i64) >> unused_bits) as f64 / full_scale(sample_format)
+        }
+    }
+}
+
+fn encode_sample(sample_format: SampleFormat,
+                 endianness: Endianness,
+                 value: f64,
+                 clipping: Clipping,
+                 noise: &mut NoiseSource,
+                 out: &mut Vec<u8>) {
+    let width = sample_format.bytes_per_sample();
+    match sample_format {
+        SampleFormat::F32 | SampleFormat::F64 => {
+            let value = match clipping {
+                Clipping::Clamp => value.clamp(-1.0, 1.0),
+                Clipping::Preserve => value,
+            };
+            let raw = if sample_format == SampleFormat::F32 {
+                (value as f32).to_bits() as u64
+            } else {
+                value.to_bits()
+            };
+            write_uint(raw, width, endianness, out);
+        }
+        _ => {
+            let scale = full_scale(sample_format);
+            let quantised = if value.is_nan() {
+                0
+            } else {
+                (value * scale + noise.next_lsb()).round().max(-scale).min(scale - 1.0) as i64
+            };
+            write_uint(quantised as u64, width, endianness, out);
+        }
+    }
+}
+
+/// Dither noise measured in LSBs of the output format.
+struct NoiseSource {
+    state: u32,
+    dither: Dither,
+}
+
+impl NoiseSource {
+    fn uniform(&mut self) -> f64 {
+        // xorshift32
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f64 / 4294967296.0 - 0.5
+    }
+
+    fn next_lsb(&mut self) -> f64 {
+        match self.dither {
+            Dither::None => 0.0,
+            Dither::Rectangular => self.uniform(),
+            Dither::Triangular => self.uniform() + self.uniform(),
+        }
+    }
+}
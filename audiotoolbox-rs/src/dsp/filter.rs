@@ -0,0 +1,417 @@
+use std::f64::consts::PI;
+
+/// The response shapes of Robert Bristow-Johnson's Audio EQ Cookbook.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    /// Unity gain at the center frequency.
+    BandPass,
+    Notch,
+    AllPass,
+    /// A bell with this gain in dB at the center frequency.
+    Peaking(f64),
+    /// A shelf with this gain in dB below the corner frequency.
+    LowShelf(f64),
+    /// A shelf with this gain in dB above the corner frequency.
+    HighShelf(f64),
+}
+
+/// A filter's complex gain at one frequency.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrequencyResponse {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl FrequencyResponse {
+    pub fn magnitude(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// The magnitude in dB.
+    pub fn gain(&self) -> f64 {
+        20.0 * self.magnitude().log10()
+    }
+
+    /// Radians.
+    pub fn phase(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    /// The response of this filter followed by `other`.
+    pub fn then(&self, other: &FrequencyResponse) -> FrequencyResponse {
+        FrequencyResponse {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+/// The coefficients of a second order section, scaled so that `a0` is one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Coefficients {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+fn check_frequency(sample_rate: f64, frequency: f64) -> Result<(), String> {
+    if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+        return Err(format!("invalid sample rate {} Hz", sample_rate));
+    }
+    if !(frequency > 0.0 && frequency < sample_rate / 2.0) {
+        return Err(format!("filter frequency {} Hz is not between 0 and {} Hz",
+                           frequency,
+                           sample_rate / 2.0));
+    }
+    Ok(())
+}
+
+impl Coefficients {
+    pub fn new(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Coefficients {
+        Coefficients {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+
+    /// Designs a cookbook filter. For shelves `q` sets the slope, with 1/√2 the steepest
+    /// that does not overshoot.
+    pub fn design(filter_type: FilterType,
+                  sample_rate: f64,
+                  frequency: f64,
+                  q: f64)
+                  -> Result<Coefficients, String> {
+        check_frequency(sample_rate, frequency)?;
+        if !(q > 0.0 && q.is_finite()) {
+            return Err(format!("invalid filter Q {}", q));
+        }
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin, cos) = (w0.sin(), w0.cos());
+        let alpha = sin / (2.0 * q);
+        let coefficients = match filter_type {
+            FilterType::LowPass => {
+                Coefficients::new((1.0 - cos) / 2.0,
+                                  1.0 - cos,
+                                  (1.0 - cos) / 2.0,
+                                  1.0 + alpha,
+                                  -2.0 * cos,
+                                  1.0 - alpha)
+            }
+            FilterType::HighPass => {
+                Coefficients::new((1.0 + cos) / 2.0,
+                                  -(1.0 + cos),
+                                  (1.0 + cos) / 2.0,
+                                  1.0 + alpha,
+                                  -2.0 * cos,
+                                  1.0 - alpha)
+            }
+            FilterType::BandPass => {
+                Coefficients::new(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+            }
+            FilterType::Notch => {
+                Coefficients::new(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+            }
+            FilterType::AllPass => {
+                Coefficients::new(1.0 - alpha,
+                                  -2.0 * cos,
+                                  1.0 + alpha,
+                                  1.0 + alpha,
+                                  -2.0 * cos,
+                                  1.0 - alpha)
+            }
+            FilterType::Peaking(gain) => {
+                let a = 10f64.powf(gain / 40.0);
+                Coefficients::new(1.0 + alpha * a,
+                                  -2.0 * cos,
+                                  1.0 - alpha * a,
+                                  1.0 + alpha / a,
+                                  -2.0 * cos,
+                                  1.0 - alpha / a)
+            }
+            FilterType::LowShelf(gain) => {
+                let a = 10f64.powf(gain / 40.0);
+                let beta = 2.0 * a.sqrt() * alpha;
+                Coefficients::new(a * ((a + 1.0) - (a - 1.0) * cos + beta),
+                                  2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                                  a * ((a + 1.0) - (a - 1.0) * cos - beta),
+                                  (a + 1.0) + (a - 1.0) * cos + beta,
+                                  -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                                  (a + 1.0) + (a - 1.0) * cos - beta)
+            }
+            FilterType::HighShelf(gain) => {
+                let a = 10f64.powf(gain / 40.0);
+                let beta = 2.0 * a.sqrt() * alpha;
+                Coefficients::new(a * ((a + 1.0) + (a - 1.0) * cos + beta),
+                                  -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                                  a * ((a + 1.0) + (a - 1.0) * cos - beta),
+                                  (a + 1.0) - (a - 1.0) * cos + beta,
+                                  2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                                  (a + 1.0) - (a - 1.0) * cos - beta)
+            }
+        };
+        Ok(coefficients)
+    }
+
+    /// A first order low-pass, as a section with no second order terms.
+    pub fn first_order_low_pass(sample_rate: f64,
+                                frequency: f64)
+                                -> Result<Coefficients, String> {
+        check_frequency(sample_rate, frequency)?;
+        let k = (PI * frequency / sample_rate).tan();
+        Ok(Coefficients::new(k, k, 0.0, k + 1.0, k - 1.0, 0.0))
+    }
+
+    /// A first order high-pass, as a section with no second order terms.
+    pub fn first_order_high_pass(sample_rate: f64,
+                                 frequency: f64)
+                                 -> Result<Coefficients, String> {
+        check_frequency(sample_rate, frequency)?;
+        let k = (PI * frequency / sample_rate).tan();
+        Ok(Coefficients::new(1.0, -1.0, 0.0, k + 1.0, k - 1.0, 0.0))
+    }
+
+    pub fn response(&self, sample_rate: f64, frequency: f64) -> FrequencyResponse {
+        // H(z) at z = e^jw, with z^-1 = cos w - j sin w.
+        let w = 2.0 * PI * frequency / sample_rate;
+        let (cos1, sin1) = (w.cos(), -w.sin());
+        let (cos2, sin2) = ((2.0 * w).cos(), -(2.0 * w).sin());
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = self.b1 * sin1 + self.b2 * sin2;
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = self.a1 * sin1 + self.a2 * sin2;
+        let den = den_re * den_re + den_im * den_im;
+        FrequencyResponse {
+            re: (num_re * den_re + num_im * den_im) / den,
+            im: (num_im * den_re - num_re * den_im) / den,
+        }
+    }
+}
+
+/// Second order sections run one after another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cascade {
+    sections: Vec<Coefficients>,
+}
+
+/// The Q of each conjugate pole pair of an analog Butterworth filter of `order`.
+fn butterworth_qs(order: usize) -> Vec<f64> {
+    (1..order / 2 + 1)
+        .map(|k| 1.0 / (2.0 * (PI * (2 * k - 1) as f64 / (2 * order) as f64).sin()))
+        .collect()
+}
+
+impl Cascade {
+    pub fn new(sections: Vec<Coefficients>) -> Cascade {
+        Cascade { sections }
+    }
+
+    pub fn butterworth_low_pass(order: usize,
+                                sample_rate: f64,
+                                frequency: f64)
+                                -> Result<Cascade, String> {
+        Cascade::butterworth(order, sample_rate, frequency, false)
+    }
+
+    pub fn butterworth_high_pass(order: usize,
+                                 sample_rate: f64,
+                                 frequency: f64)
+                                 -> Result<Cascade, String> {
+        Cascade::butterworth(order, sample_rate, frequency, true)
+    }
+
+    fn butterworth(order: usize,
+                   sample_rate: f64,
+                   frequency: f64,
+                   high_pass: bool)
+                   -> Result<Cascade, String> {
+        if order == 0 {
+            return Err("filter order must be at least 1".to_owned());
+        }
+        let filter_type = if high_pass { FilterType::HighPass } else { FilterType::LowPass };
+        let mut sections = Vec::with_capacity(order.div_ceil(2));
+        for q in butterworth_qs(order) {
+            sections.push(Coefficients::design(filter_type, sample_rate, frequency, q)?);
+        }
+        if order % 2 == 1 {
+            sections.push(if high_pass {
+                              Coefficients::first_order_high_pass(sample_rate, frequency)?
+                          } else {
+                              Coefficients::first_order_low_pass(sample_rate, frequency)?
+                          });
+        }
+        Ok(Cascade::new(sections))
+    }
+
+    /// A Linkwitz-Riley low-pass of even `order`: two Butterworth filters of half the order,
+    /// 6 dB down at the crossover frequency.
+    pub fn linkwitz_riley_low_pass(order: usize,
+                                   sample_rate: f64,
+                                   frequency: f64)
+                                   -> Result<Cascade, String> {
+        Cascade::linkwitz_riley(order, sample_rate, frequency, false)
+    }
+
+    /// The high-pass half of a Linkwitz-Riley crossover. Its sum with the low-pass of the
+    /// same order is flat; orders 2, 6, 10 and so on are inverted to make it so.
+    pub fn linkwitz_riley_high_pass(order: usize,
+                                    sample_rate: f64,
+                                    frequency: f64)
+                                    -> Result<Cascade, String> {
+        Cascade::linkwitz_riley(order, sample_rate, frequency, true)
+    }
+
+    fn linkwitz_riley(order: usize,
+                      sample_rate: f64,
+                      frequency: f64,
+                      high_pass: bool)
+                      -> Result<Cascade, String> {
+        if order == 0 || order % 2 == 1 {
+            return Err(format!("Linkwitz-Riley order must be even, not {}", order));
+        }
+        let half = Cascade::butterworth(order / 2, sample_rate, frequency, high_pass)?;
+        let mut sections = half.sections.clone();
+        sections.extend(half.sections);
+        // Odd order Butterworth low and high-passes are 90 or 270 degrees apart, so squared
+        // they would cancel at the crossover frequency.
+        if high_pass && order % 4 == 2 {
+            let first = &mut sections[0];
+            first.b0 = -first.b0;
+            first.b1 = -first.b1;
+            first.b2 = -first.b2;
+        }
+        Ok(Cascade::new(sections))
+    }
+
+    pub fn sections(&self) -> &[Coefficients] {
+        &self.sections
+    }
+
+    pub fn response(&self, sample_rate: f64, frequency: f64) -> FrequencyResponse {
+        self.sections
+            .iter()
+            .fold(FrequencyResponse { re: 1.0, im: 0.0 },
+                  |response, section| response.then(&section.response(sample_rate, frequency)))
+    }
+}
+
+impl From<Coefficients> for Cascade {
+    fn from(coefficients: Coefficients) -> Cascade {
+        Cascade::new(vec![coefficients])
+    }
+}
+
+/// A second order section with its state, in transposed direct form II.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Biquad {
+    coefficients: Coefficients,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub fn new(coefficients: Coefficients) -> Biquad {
+        Biquad {
+            coefficients,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn coefficients(&self) -> &Coefficients {
+        &self.coefficients
+    }
+
+    /// Changes the response without clearing the state, so that it can be swept while
+    /// running.
+    pub fn set_coefficients(&mut self, coefficients: Coefficients) {
+        self.coefficients = coefficients;
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        let c = &self.coefficients;
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// Runs a cascade over every channel of interleaved frames, in place and without
+/// allocating, so it can be used from an audio callback.
+pub struct Filter {
+    channels: usize,
+    /// The sections of each channel in turn.
+    biquads: Vec<Biquad>,
+}
+
+impl Filter {
+    pub fn new<C: Into<Cascade>>(cascade: C, channels: usize) -> Result<Filter, String> {
+        if channels == 0 {
+            return Err("filter needs at least one channel".to_owned());
+        }
+        let cascade = cascade.into();
+        let mut biquads = Vec::with_capacity(channels * cascade.sections.len());
+        for _ in 0..channels {
+            biquads.extend(cascade.sections.iter().map(|&section| Biquad::new(section)));
+        }
+        Ok(Filter {
+               channels,
+               biquads,
+           })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Changes the response of every channel without clearing their state. The new cascade
+    /// must have as many sections as the old.
+    pub fn set_cascade(&mut self, cascade: &Cascade) -> Result<(), String> {
+        let sections = cascade.sections.len();
+        if sections * self.channels != self.biquads.len() {
+            return Err(format!("filter has {} sections, not {}",
+                               self.biquads.len() / self.channels,
+                               sections));
+        }
+        for (i, biquad) in self.biquads.iter_mut().enumerate() {
+            biquad.set_coefficients(cascade.sections[i % sections]);
+        }
+        Ok(())
+    }
+
+    pub fn process(&mut self, frames: &mut [f32]) {
+        let channels = self.channels;
+        assert!(frames.len().is_multiple_of(channels), "input must contain whole frames");
+        let sections = self.biquads.len() / channels;
+        if sections == 0 {
+            return;
+        }
+        for frame in frames.chunks_mut(channels) {
+            for (sample, biquads) in frame.iter_mut().zip(self.biquads.chunks_mut(sections)) {
+                let mut value = *sample as f64;
+                for biquad in biquads.iter_mut() {
+                    value = biquad.process(value);
+                }
+                *sample = value as f32;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for biquad in self.biquads.iter_mut() {
+            biquad.reset();
+        }
+    }
+}
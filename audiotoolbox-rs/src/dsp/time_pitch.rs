@@ -0,0 +1,685 @@
+use std::f64::consts::PI;
+use std::mem;
+
+use channel_map::ChannelLayout;
+use frame_reader::FrameReader;
+use resample::{Quality, Resampler};
+use stream_format::StreamFormat;
+
+const READ_FRAMES: usize = 4096;
+/// The play rates and pitch shifts AudioQueue's time pitch processing allows.
+const MIN_RATE: f64 = 1.0 / 8.0;
+const MAX_RATE: f64 = 8.0;
+const MAX_PITCH: f64 = 2400.0;
+
+/// Settings suited to a kind of material.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// WSOLA over 30 ms windows, which keeps consonants crisp and voices free of phasiness.
+    Speech,
+    /// A phase vocoder over windows of about 85 ms, which keeps tones and chords steady.
+    Music,
+}
+
+impl Preset {
+    pub fn algorithm(&self) -> Algorithm {
+        match *self {
+            Preset::Speech => Algorithm::Wsola,
+            Preset::Music => Algorithm::PhaseVocoder,
+        }
+    }
+
+    /// Seconds.
+    fn window(&self) -> f64 {
+        match *self {
+            Preset::Speech => 0.03,
+            Preset::Music => 0.085,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// Waveform similarity overlap-add: windows of input half overlapping, each moved by up
+    /// to a third of a window so that it lines up with what came before. Repeats or drops
+    /// whole periods, which suits a single voice.
+    Wsola,
+    /// Moves the phase of each spectral peak on by its own frequency and locks the bins
+    /// around the peak to it, as Laroche and Dolson describe. Smooth on sustained and
+    /// polyphonic material; softens sharp attacks.
+    PhaseVocoder,
+}
+
+/// An in-place radix-2 complex FFT.
+struct Fft {
+    size: usize,
+    cos: Vec<f64>,
+    sin: Vec<f64>,
+}
+
+impl Fft {
+    fn new(size: usize) -> Fft {
+        let angle = |k: usize| 2.0 * PI * k as f64 / size as f64;
+        Fft {
+            size,
+            cos: (0..size / 2).map(|k| angle(k).cos()).collect(),
+            sin: (0..size / 2).map(|k| angle(k).sin()).collect(),
+        }
+    }
+
+    /// Unscaled in both directions.
+    fn transform(&self, re: &mut [f64], im: &mut [f64], inverse: bool) {
+        let n = self.size;
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+        let mut length = 2;
+        while length <= n {
+            let half = length / 2;
+            let step = n / length;
+            for start in (0..n).step_by(length) {
+                for k in 0..half {
+                    let c = self.cos[k * step];
+                    let s = if inverse { self.sin[k * step] } else { -self.sin[k * step] };
+                    let (a, b) = (start + k, start + k + half);
+                    let tr = re[b] * c - im[b] * s;
+                    let ti = re[b] * s + im[b] * c;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            length <<= 1;
+        }
+    }
+}
+
+/// Wraps an angle into -π..π.
+fn principal(angle: f64) -> f64 {
+    angle - 2.0 * PI * (angle / (2.0 * PI)).round()
+}
+
+/// The phase vocoder's state between windows.
+struct Vocoder {
+    fft: Fft,
+    /// The phase of each bin up to Nyquist in the last analysis window, and what it became
+    /// in the output, channel after channel.
+    analysis: Vec<f64>,
+    synthesis: Vec<f64>,
+    re: Vec<f64>,
+    im: Vec<f64>,
+    magnitudes: Vec<f64>,
+    phases: Vec<f64>,
+    peaks: Vec<usize>,
+}
+
+/// Changes the speed and pitch of interleaved frames independently of each other, the way
+/// AudioQueue's time pitch processing does during playback.
+///
+/// The input is stretched in time by the pitch ratio over the play rate, then resampled by
+/// the pitch ratio. Chunks of any size may be pushed through `process`; `flush` emits the
+/// tail once the input has ended. Output starts in line with the input, and `n` input
+/// frames produce `output_frames_for(n)` frames.
+pub struct TimePitch {
+    sample_rate: f64,
+    channels: usize,
+    rate: f64,
+    pitch: f64,
+    preset: Preset,
+    algorithm: Algorithm,
+    /// Output duration over input duration before resampling.
+    stretch: f64,
+    size: usize,
+    /// Output frames between windows.
+    hop: usize,
+    /// How far WSOLA may move a window from where it belongs, in input frames.
+    tolerance: usize,
+    window: Vec<f64>,
+    vocoder: Option<Vocoder>,
+    resampler: Option<Resampler>,
+    input: Vec<f32>,
+    input_start: u64,
+    frames_in: u64,
+    /// The overlapping windows, and the sum of their weights, from `frames_out` on.
+    sums: Vec<f64>,
+    weights: Vec<f64>,
+    frames_out: u64,
+    /// The next window to lay down.
+    next: u64,
+    /// Centre of the last window taken from the input.
+    previous: Option<i64>,
+    /// The input WSOLA searches for a window, and what it should resemble.
+    search: Vec<f64>,
+    target: Vec<f64>,
+    stretched: Vec<f32>,
+}
+
+impl TimePitch {
+    /// Plays at `rate` times the original speed, with the pitch moved by `pitch` cents.
+    pub fn new(sample_rate: f64,
+               channels: usize,
+               rate: f64,
+               pitch: f64,
+               preset: Preset)
+               -> Result<TimePitch, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {} Hz", sample_rate));
+        }
+        if channels == 0 {
+            return Err("time pitch needs at least one channel".to_owned());
+        }
+        if !(MIN_RATE..=MAX_RATE).contains(&rate) {
+            return Err(format!("play rate {} is not between {} and {}", rate, MIN_RATE, MAX_RATE));
+        }
+        if !(-MAX_PITCH..=MAX_PITCH).contains(&pitch) {
+            return Err(format!("pitch shift {} cents is not between -{} and {}",
+                               pitch,
+                               MAX_PITCH,
+                               MAX_PITCH));
+        }
+        let ratio = 2f64.powf(pitch / 1200.0);
+        let resampler = if pitch != 0.0 {
+            Some(Resampler::new(channels, sample_rate * ratio, sample_rate, Quality::Medium)?)
+        } else {
+            None
+        };
+        let mut time_pitch = TimePitch {
+            sample_rate,
+            channels,
+            rate,
+            pitch,
+            preset,
+            algorithm: preset.algorithm(),
+            stretch: ratio / rate,
+            size: 0,
+            hop: 0,
+            tolerance: 0,
+            window: Vec::new(),
+            vocoder: None,
+            resampler,
+            input: Vec::new(),
+            input_start: 0,
+            frames_in: 0,
+            sums: Vec::new(),
+            weights: Vec::new(),
+            frames_out: 0,
+            next: 0,
+            previous: None,
+            search: Vec::new(),
+            target: Vec::new(),
+            stretched: Vec::new(),
+        };
+        time_pitch.configure();
+        Ok(time_pitch)
+    }
+
+    /// Uses `algorithm` with the preset's window length.
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> TimePitch {
+        self.algorithm = algorithm;
+        self.configure();
+        self
+    }
+
+    fn configure(&mut self) {
+        let length = self.preset.window() * self.sample_rate;
+        match self.algorithm {
+            Algorithm::Wsola => {
+                self.size = ((length / 2.0).round() as usize).max(2) * 2;
+                self.hop = self.size / 2;
+                self.tolerance = self.size / 3;
+                self.vocoder = None;
+            }
+            Algorithm::PhaseVocoder => {
+                self.size = 1 << (length.log2().round().max(4.0) as u32);
+                self.hop = self.size / 4;
+                self.tolerance = 0;
+                let size = self.size;
+                let bins = self.channels * (size / 2 + 1);
+                self.vocoder = Some(Vocoder {
+                                        fft: Fft::new(size),
+                                        analysis: vec![0.0; bins],
+                                        synthesis: vec![0.0; bins],
+                                        re: vec![0.0; size],
+                                        im: vec![0.0; size],
+                                        magnitudes: vec![0.0; size / 2 + 1],
+                                        phases: vec![0.0; size / 2 + 1],
+                                        peaks: Vec::new(),
+                                    });
+            }
+        }
+        let size = self.size;
+        // A periodic Hann window.
+        self.window = (0..size)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f64 / size as f64).cos())
+            .collect();
+        self.reset();
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Cents.
+    pub fn pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Number of output frames `input_frames` of input turn into once flushed.
+    pub fn output_frames_for(&self, input_frames: u64) -> u64 {
+        let stretched = self.stretched_frames(input_frames);
+        match self.resampler {
+            Some(ref resampler) => resampler.output_frames_for(stretched),
+            None => stretched,
+        }
+    }
+
+    fn stretched_frames(&self, input_frames: u64) -> u64 {
+        (input_frames as f64 * self.stretch).round() as u64
+    }
+
+    /// Consumes interleaved input frames and appends every output frame that no longer
+    /// depends on future input to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        assert!(input.len().is_multiple_of(self.channels), "input must contain whole frames");
+        self.input.extend_from_slice(input);
+        self.frames_in += (input.len() / self.channels) as u64;
+        loop {
+            let (_, end) = self.span(self.next);
+            if end > self.frames_in as i64 {
+                break;
+            }
+            self.render();
+        }
+        let ready = self.window_start(self.next);
+        self.deliver(ready, output);
+
+        let (start, _) = self.span(self.next);
+        if start > self.input_start as i64 {
+            let drop = ((start as u64 - self.input_start) as usize)
+                .min(self.input.len() / self.channels);
+            self.input.drain(..drop * self.channels);
+            self.input_start += drop as u64;
+        }
+    }
+
+    /// Treats the input as ended and emits the remaining output frames.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let total = self.stretched_frames(self.frames_in) as i64;
+        while self.window_start(self.next) < total {
+            self.render();
+        }
+        self.deliver(total, output);
+        if let Some(ref mut resampler) = self.resampler {
+            resampler.flush(output);
+        }
+        self.reset();
+    }
+
+    /// Forgets all buffered input so that a new stream can start.
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.input_start = 0;
+        self.frames_in = 0;
+        self.sums.clear();
+        self.weights.clear();
+        self.frames_out = 0;
+        self.next = 0;
+        self.previous = None;
+        if let Some(ref mut resampler) = self.resampler {
+            resampler.reset();
+        }
+    }
+
+    /// The output frame where window `index` begins.
+    fn window_start(&self, index: u64) -> i64 {
+        (index * self.hop as u64) as i64 - (self.size / 2) as i64
+    }
+
+    /// The input frame window `index` is centred on before WSOLA moves it.
+    fn centre(&self, index: u64) -> i64 {
+        (index as f64 * self.hop as f64 / self.stretch).round() as i64
+    }
+
+    /// The input frames window `index` may draw on.
+    fn span(&self, index: u64) -> (i64, i64) {
+        let centre = self.centre(index);
+        let tolerance = self.tolerance as i64;
+        let (mut first, mut last) = (centre - tolerance, centre + tolerance);
+        if let (Algorithm::Wsola, Some(previous)) = (self.algorithm, self.previous) {
+            // The input that followed the last window, which the next one should resemble.
+            let natural = previous + self.hop as i64;
+            first = first.min(natural);
+            last = last.max(natural);
+        }
+        let half = (self.size / 2) as i64;
+        (first - half, last + half)
+    }
+
+    /// An input sample, silent outside the input.
+    fn sample(&self, frame: i64, channel: usize) -> f64 {
+        if frame < self.input_start as i64 || frame >= self.frames_in as i64 {
+            return 0.0;
+        }
+        let index = (frame as u64 - self.input_start) as usize * self.channels + channel;
+        self.input[index] as f64
+    }
+
+    /// Replaces `into` with `frames` input frames from `first` on.
+    fn copy_input(&self, first: i64, frames: usize, into: &mut Vec<f64>) {
+        into.clear();
+        for frame in first..first + frames as i64 {
+            for channel in 0..self.channels {
+                into.push(self.sample(frame, channel));
+            }
+        }
+    }
+
+    /// Lays down the next window.
+    fn render(&mut self) {
+        let start = self.window_start(self.next);
+        let end = start + self.size as i64;
+        let needed = (end - self.frames_out as i64).max(0) as usize;
+        if self.weights.len() < needed {
+            self.weights.resize(needed, 0.0);
+            self.sums.resize(needed * self.channels, 0.0);
+        }
+        match self.algorithm {
+            Algorithm::Wsola => self.render_wsola(start),
+            Algorithm::PhaseVocoder => self.render_vocoder(start),
+        }
+        self.next += 1;
+    }
+
+    /// Adds a windowed sample to the output frame `frame`, if it has not gone out yet.
+    fn add(&mut self, frame: i64, channel: usize, value: f64) {
+        if frame >= self.frames_out as i64 {
+            let index = (frame as u64 - self.frames_out) as usize;
+            self.sums[index * self.channels + channel] += value;
+        }
+    }
+
+    fn add_weight(&mut self, frame: i64, weight: f64) {
+        if frame >= self.frames_out as i64 {
+            self.weights[(frame as u64 - self.frames_out) as usize] += weight;
+        }
+    }
+
+    fn render_wsola(&mut self, start: i64) {
+        let centre = self.centre(self.next);
+        let half = (self.size / 2) as i64;
+        let chosen = match self.previous {
+            None => centre,
+            Some(previous) => {
+                // Where the window overlaps the last one, find the offset at which the
+                // input looks most like what followed the last window.
+                let channels = self.channels;
+                let overlap = self.size - self.hop;
+                let tolerance = self.tolerance as i64;
+                let first = centre - tolerance - half;
+                let mut search = mem::take(&mut self.search);
+                let mut target = mem::take(&mut self.target);
+                self.copy_input(first, 2 * self.tolerance + overlap, &mut search);
+                self.copy_input(previous + self.hop as i64 - half, overlap, &mut target);
+                let mut energy: f64 = search[..overlap * channels].iter().map(|x| x * x).sum();
+                let mut best = (centre, f64::NEG_INFINITY);
+                for offset in 0..2 * self.tolerance + 1 {
+                    let candidate = &search[offset * channels..(offset + overlap) * channels];
+                    if offset > 0 {
+                        // Slide the energy along by a frame.
+                        let (left, entered) = (offset - 1, offset + overlap - 1);
+                        for channel in 0..channels {
+                            let old = search[left * channels + channel];
+                            let new = search[entered * channels + channel];
+                            energy += new * new - old * old;
+                        }
+                    }
+                    let correlation: f64 = candidate.iter().zip(&target).map(|(x, y)| x * y).sum();
+                    let score = if energy > 1e-12 { correlation / energy.sqrt() } else { 0.0 };
+                    if score > best.1 {
+                        best = (first + half + offset as i64, score);
+                    }
+                }
+                self.search = search;
+                self.target = target;
+                best.0
+            }
+        };
+        self.previous = Some(chosen);
+        for n in 0..self.size {
+            let weight = self.window[n];
+            for channel in 0..self.channels {
+                let value = self.sample(chosen - half + n as i64, channel) * weight;
+                self.add(start + n as i64, channel, value);
+            }
+            self.add_weight(start + n as i64, weight);
+        }
+    }
+
+    fn render_vocoder(&mut self, start: i64) {
+        let centre = self.centre(self.next);
+        let half = (self.size / 2) as i64;
+        let bins = self.size / 2 + 1;
+        // Input frames between this window and the last.
+        let advance = self.previous.map(|previous| (centre - previous) as f64);
+        self.previous = Some(centre);
+        let mut vocoder = self.vocoder.take().expect("phase vocoder state");
+        for channel in 0..self.channels {
+            for n in 0..self.size {
+                vocoder.re[n] = self.sample(centre - half + n as i64, channel) * self.window[n];
+                vocoder.im[n] = 0.0;
+            }
+            vocoder.fft.transform(&mut vocoder.re, &mut vocoder.im, false);
+            for bin in 0..bins {
+                vocoder.magnitudes[bin] = vocoder.re[bin].hypot(vocoder.im[bin]);
+                vocoder.phases[bin] = vocoder.im[bin].atan2(vocoder.re[bin]);
+            }
+            let offset = channel * bins;
+            match advance {
+                None => {
+                    vocoder.synthesis[offset..offset + bins].copy_from_slice(&vocoder.phases);
+                }
+                Some(advance) => self.lock_phases(&mut vocoder, offset, advance),
+            }
+            vocoder.analysis[offset..offset + bins].copy_from_slice(&vocoder.phases);
+
+            for bin in 0..bins {
+                let (magnitude, phase) = (vocoder.magnitudes[bin], vocoder.synthesis[offset + bin]);
+                vocoder.re[bin] = magnitude * phase.cos();
+                vocoder.im[bin] = magnitude * phase.sin();
+            }
+            for bin in bins..self.size {
+                vocoder.re[bin] = vocoder.re[self.size - bin];
+                vocoder.im[bin] = -vocoder.im[self.size - bin];
+            }
+            vocoder.fft.transform(&mut vocoder.re, &mut vocoder.im, true);
+            for n in 0..self.size {
+                let value = vocoder.re[n] / self.size as f64 * self.window[n];
+                self.add(start + n as i64, channel, value);
+            }
+        }
+        for n in 0..self.size {
+            let weight = self.window[n];
+            self.add_weight(start + n as i64, weight * weight);
+        }
+        self.vocoder = Some(vocoder);
+    }
+
+    /// Moves each peak's phase on by its measured frequency over the output hop and keeps
+    /// the bins around it at the same phase relative to it.
+    fn lock_phases(&self, vocoder: &mut Vocoder, offset: usize, advance: f64) {
+        let bins = self.size / 2 + 1;
+        let magnitudes = &vocoder.magnitudes;
+        vocoder.peaks.clear();
+        for bin in 0..bins {
+            let below = if bin > 0 { magnitudes[bin - 1] } else { 0.0 };
+            let above = if bin + 1 < bins { magnitudes[bin + 1] } else { 0.0 };
+            if magnitudes[bin] > below && magnitudes[bin] >= above {
+                vocoder.peaks.push(bin);
+            }
+        }
+        if vocoder.peaks.is_empty() {
+            vocoder.synthesis[offset..offset + bins].copy_from_slice(&vocoder.phases);
+            return;
+        }
+        let mut region_start = 0;
+        for (i, &peak) in vocoder.peaks.iter().enumerate() {
+            // Each peak rules the bins up to the quietest one before the next peak.
+            let region_end = match vocoder.peaks.get(i + 1) {
+                Some(&next) => {
+                    (peak + 1..next + 1)
+                        .fold(peak + 1, |lowest, bin| {
+                            if magnitudes[bin] < magnitudes[lowest] { bin } else { lowest }
+                        })
+                }
+                None => bins,
+            };
+            let bin_frequency = 2.0 * PI * peak as f64 / self.size as f64;
+            let frequency = if advance > 0.0 {
+                let expected = vocoder.analysis[offset + peak] + bin_frequency * advance;
+                bin_frequency + principal(vocoder.phases[peak] - expected) / advance
+            } else {
+                bin_frequency
+            };
+            let phase = vocoder.synthesis[offset + peak] + frequency * self.hop as f64;
+            let analysis_phase = vocoder.phases[peak];
+            for bin in region_start..region_end {
+                vocoder.synthesis[offset + bin] = phase + vocoder.phases[bin] - analysis_phase;
+            }
+            region_start = region_end;
+        }
+    }
+
+    /// Moves the output frames before `end` from the overlap buffers out, through the
+    /// resampler when shifting pitch.
+    fn deliver(&mut self, end: i64, output: &mut Vec<f32>) {
+        let frames = (end - self.frames_out as i64).max(0) as usize;
+        let frames = frames.min(self.weights.len());
+        self.stretched.clear();
+        for i in 0..frames {
+            let weight = self.weights[i];
+            for channel in 0..self.channels {
+                let sum = self.sums[i * self.channels + channel];
+                let value = if weight > 1e-6 { sum / weight } else { 0.0 };
+                self.stretched.push(value as f32);
+            }
+        }
+        self.weights.drain(..frames);
+        self.sums.drain(..frames * self.channels);
+        self.frames_out += frames as u64;
+        match self.resampler {
+            Some(ref mut resampler) => resampler.process(&self.stretched, output),
+            None => output.extend_from_slice(&self.stretched),
+        }
+    }
+}
+
+/// Changes the speed and pitch of the frames of another reader.
+pub struct TimePitchReader<R> {
+    reader: R,
+    time_pitch: TimePitch,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    /// Samples of `output` already read.
+    position: usize,
+    finished: bool,
+}
+
+impl<R: FrameReader> TimePitchReader<R> {
+    pub fn new(reader: R, time_pitch: TimePitch) -> Result<TimePitchReader<R>, String> {
+        let format = reader.format();
+        if format.channels as usize != time_pitch.channels {
+            return Err(format!("time pitch does not have the reader's {} channels",
+                               format.channels));
+        }
+        if format.sample_rate != time_pitch.sample_rate {
+            return Err(format!("time pitch is not set up for the reader's {} Hz",
+                               format.sample_rate));
+        }
+        Ok(TimePitchReader {
+               input: vec![0.0; READ_FRAMES * format.channels as usize],
+               reader,
+               time_pitch,
+               output: Vec::new(),
+               position: 0,
+               finished: false,
+           })
+    }
+
+    pub fn time_pitch(&self) -> &TimePitch {
+        &self.time_pitch
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: FrameReader> FrameReader for TimePitchReader<R> {
+    fn format(&self) -> StreamFormat {
+        self.reader.format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.reader.channel_layout()
+    }
+
+    fn length(&self) -> Option<u64> {
+        self.reader.length().map(|length| self.time_pitch.output_frames_for(length))
+    }
+
+    /// Seeks the source to the input frame that plays at `frame`.
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        self.reader.seek((frame as f64 * self.time_pitch.rate).round() as u64)?;
+        self.time_pitch.reset();
+        self.output.clear();
+        self.position = 0;
+        self.finished = false;
+        Ok(())
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.time_pitch.channels;
+        loop {
+            let available = (self.output.len() - self.position) / channels;
+            if available > 0 {
+                let frames = available.min(buffer.len() / channels);
+                let samples = frames * channels;
+                buffer[..samples]
+                    .copy_from_slice(&self.output[self.position..self.position + samples]);
+                self.position += samples;
+                return Ok(frames);
+            }
+            if self.finished || buffer.len() < channels {
+                return Ok(0);
+            }
+            self.output.clear();
+            self.position = 0;
+            let frames = self.reader.read_frames(&mut self.input)?;
+            if frames == 0 {
+                self.time_pitch.flush(&mut self.output);
+                self.finished = true;
+            } else {
+                self.time_pitch.process(&self.input[..frames * channels], &mut self.output);
+            }
+        }
+    }
+}
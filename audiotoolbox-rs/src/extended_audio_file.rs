@@ -18,6 +18,7 @@ pub enum ExtAudioFileProperty {
     FileMaxPacketSize(u32),
     ClientMaxPacketSize(u32),
     FileLengthFrames(u32),
+    FileChannelLayout(Vec<u8>),
 }
 
 #[repr(u32)]
@@ -28,6 +29,7 @@ pub enum ExtAudioFilePropertyId {
     FileMaxPacketSize = 1718448243,
     ClientMaxPacketSize = 1668116595,
     FileLengthFrames = 593916525,
+    FileChannelLayout = 1717791855,
 }
 
 impl ExtAudioFile {
@@ -42,6 +44,27 @@ impl ExtAudioFile {
         }
     }
 
+    pub fn create(url: CFURL,
+                  file_type: AudioFileTypeID,
+                  format: &AudioStreamBasicDescription,
+                  flags: u32)
+                  -> Result<ExtAudioFile, OSStatus> {
+        let mut ext_audio_file_ref: ExtAudioFileRef = ptr::null_mut();
+        let error = unsafe {
+            ExtAudioFileCreateWithURL(url.as_concrete_TypeRef(),
+                                      file_type,
+                                      format as *const AudioStreamBasicDescription,
+                                      ptr::null(),
+                                      flags,
+                                      &mut ext_audio_file_ref)
+        };
+        if error != 0 {
+            Err(error)
+        } else {
+            Ok(ExtAudioFile(ext_audio_file_ref))
+        }
+    }
+
     pub fn read(&mut self,
                 buffers: *mut AudioBufferList,
                 num_frames: u32)
@@ -59,16 +82,20 @@ impl ExtAudioFile {
                  buffers: &mut AudioBufferList,
                  num_frames: u32)
                  -> Result<u32, OSStatus> {
-        let mut frames_write: u32 = num_frames;
         let error =
-            unsafe { ExtAudioFileRead(self.0, &mut frames_write, buffers as *mut AudioBufferList) };
+            unsafe { ExtAudioFileWrite(self.0, num_frames, buffers as *const AudioBufferList) };
         if error != 0 {
             Err(error)
         } else {
-            Ok(frames_write)
+            Ok(num_frames)
         }
     }
 
+    pub fn seek(&mut self, frame: i64) -> Result<(), OSStatus> {
+        let error = unsafe { ExtAudioFileSeek(self.0, frame) };
+        if error != 0 { Err(error) } else { Ok(()) }
+    }
+
     pub fn set_property(&mut self, property: ExtAudioFileProperty) -> Result<(), OSStatus> {
         let error = match property {
             ExtAudioFileProperty::ClientDataFormat(mut desc) => {
@@ -125,6 +152,9 @@ impl ExtAudioFile {
                 let file_length_frames: *const u32 = mem::transmute(data.as_ptr());
                 Ok(ExtAudioFileProperty::FileLengthFrames(*file_length_frames))
             },
+            ExtAudioFilePropertyId::FileChannelLayout => {
+                Ok(ExtAudioFileProperty::FileChannelLayout(data))
+            },
         }
     }
 
@@ -0,0 +1,612 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use channel_map::ChannelLayout;
+use file_type::{fourcc_code, AudioFileTypeId};
+use stream_format::*;
+
+/// WAVE format tags.
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_ALAW: u16 = 6;
+const WAVE_FORMAT_MULAW: u16 = 7;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// CoreAudio's format ID for a WAVE format tag it has no name for is `'ms'` followed by
+/// the tag.
+const MS_FORMAT_BASE: u32 = 0x6d73_0000;
+/// `kAudioFormatAppleIMA4`, whose packets hold 64 frames in 34 bytes per channel.
+const FORMAT_APPLE_IMA4: u32 = 0x696d_6134;
+
+/// CAF linear PCM flags, which differ from the `AudioStreamBasicDescription` flags.
+const CAF_FLAG_IS_FLOAT: u32 = 1;
+const CAF_FLAG_IS_LITTLE_ENDIAN: u32 = 2;
+
+/// What the header of an audio file says about its contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFileInfo {
+    pub file_type: AudioFileTypeId,
+    pub description: StreamDescription,
+    /// Byte offset of the first audio byte, when known.
+    pub data_offset: Option<u64>,
+    /// Bytes of audio data.
+    pub data_size: u64,
+    pub packet_count: Option<u64>,
+    /// Frames of audio, excluding priming and remainder frames of compressed formats.
+    pub frame_count: Option<u64>,
+    pub magic_cookie: Option<Vec<u8>>,
+    pub channel_layout: Option<ChannelLayout>,
+    /// Text metadata as key and value, in file order, with keys named as in CoreAudio's
+    /// info dictionary where there is an equivalent.
+    pub metadata: Vec<(String, String)>,
+}
+
+impl AudioFileInfo {
+    /// Reads the header of a WAV, AIFF, AIFC or CAF file. With the `coreaudio` feature,
+    /// other containers are read with `AudioFile`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<AudioFileInfo, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("unable to open {}: {}", path.as_ref().display(), e))?;
+        let native = AudioFileInfo::read(&mut BufReader::new(file));
+        #[cfg(feature = "coreaudio")]
+        {
+            if native.is_err() {
+                if let Ok(info) = core_audio_info(path.as_ref()) {
+                    return Ok(info);
+                }
+            }
+        }
+        native
+    }
+
+    /// Reads the header of a WAV, AIFF, AIFC or CAF stream.
+    pub fn read<R: Read + Seek>(input: &mut R) -> Result<AudioFileInfo, String> {
+        let length = input.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        input.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+        let mut header = [0u8; 12];
+        input.read_exact(&mut header).map_err(|_| "file is too short".to_owned())?;
+        match (&header[0..4], &header[8..12]) {
+            (b"RIFF", b"WAVE") => read_wave(input, length),
+            (b"FORM", b"AIFF") => read_aiff(input, length, false),
+            (b"FORM", b"AIFC") => read_aiff(input, length, true),
+            (b"caff", _) => read_caf(input, length),
+            _ => Err("not a WAV, AIFF or CAF file".to_owned()),
+        }
+    }
+
+    pub fn duration(&self) -> Option<f64> {
+        match self.frame_count {
+            Some(frames) if self.description.sample_rate > 0.0 => {
+                Some(frames as f64 / self.description.sample_rate)
+            }
+            _ => None,
+        }
+    }
+
+    /// Average bits per second of audio data.
+    pub fn bit_rate(&self) -> Option<f64> {
+        match self.duration() {
+            Some(duration) if duration > 0.0 => Some(self.data_size as f64 * 8.0 / duration),
+            _ => None,
+        }
+    }
+}
+
+/// A chunk's four character ID, the offset of its body and the body's length.
+struct Chunk {
+    id: [u8; 4],
+    offset: u64,
+    size: u64,
+}
+
+/// Walks the chunks from `start` to `end`. `header` decodes a chunk header into its ID and
+/// body size, which `None` marks as running to the end of the file.
+fn chunks<R, F>(input: &mut R,
+                start: u64,
+                end: u64,
+                header_size: u64,
+                align: u64,
+                header: F)
+                -> Result<Vec<Chunk>, String>
+    where R: Read + Seek,
+          F: Fn(&[u8]) -> ([u8; 4], Option<u64>)
+{
+    let mut chunks = Vec::new();
+    let mut position = start;
+    let mut bytes = vec![0u8; header_size as usize];
+    while position + header_size <= end {
+        input.seek(SeekFrom::Start(position)).map_err(|e| e.to_string())?;
+        input.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+        let (id, size) = header(&bytes);
+        let offset = position + header_size;
+        // Streaming writers leave sizes unset or too large; the chunk then runs to the end.
+        let size = size.unwrap_or(end - offset).min(end - offset);
+        chunks.push(Chunk {
+                        id,
+                        offset,
+                        size,
+                    });
+        position = offset + size + (size % align);
+    }
+    Ok(chunks)
+}
+
+fn read_body<R: Read + Seek>(input: &mut R, chunk: &Chunk) -> Result<Vec<u8>, String> {
+    input.seek(SeekFrom::Start(chunk.offset)).map_err(|e| e.to_string())?;
+    let mut body = vec![0u8; chunk.size as usize];
+    input.read_exact(&mut body).map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+fn check_length(body: &[u8], length: usize, id: &[u8; 4]) -> Result<(), String> {
+    if body.len() < length {
+        Err(format!("'{}' chunk is truncated", String::from_utf8_lossy(id)))
+    } else {
+        Ok(())
+    }
+}
+
+fn le_u16(b: &[u8]) -> u16 {
+    b[0] as u16 | (b[1] as u16) << 8
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    le_u16(b) as u32 | (le_u16(&b[2..]) as u32) << 16
+}
+
+fn be_u16(b: &[u8]) -> u16 {
+    (b[0] as u16) << 8 | b[1] as u16
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    (be_u16(b) as u32) << 16 | be_u16(&b[2..]) as u32
+}
+
+fn be_u64(b: &[u8]) -> u64 {
+    (be_u32(b) as u64) << 32 | be_u32(&b[4..]) as u64
+}
+
+fn id(bytes: &[u8]) -> [u8; 4] {
+    [bytes[0], bytes[1], bytes[2], bytes[3]]
+}
+
+/// Text up to the first NUL, without trailing spaces.
+fn text(bytes: &[u8]) -> String {
+    let mut end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    while end > 0 && bytes[end - 1] == b' ' {
+        end -= 1;
+    }
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Flags for integer linear PCM of `bits` valid bits in `bytes_per_sample` bytes.
+fn integer_flags(bits: u32, bytes_per_sample: u32, signed: bool, big_endian: bool) -> u32 {
+    let mut flags = if bits == bytes_per_sample * 8 {
+        FLAG_IS_PACKED
+    } else {
+        FLAG_IS_ALIGNED_HIGH
+    };
+    if signed {
+        flags |= FLAG_IS_SIGNED_INTEGER;
+    }
+    if big_endian {
+        flags |= FLAG_IS_BIG_ENDIAN;
+    }
+    flags
+}
+
+fn read_wave<R: Read + Seek>(input: &mut R, length: u64) -> Result<AudioFileInfo, String> {
+    let chunks = chunks(input, 12, length, 8, 2, |h| (id(h), Some(le_u32(&h[4..]) as u64)))?;
+    let mut description = None;
+    let mut layout = None;
+    let mut data = None;
+    let mut fact_frames = None;
+    let mut metadata = Vec::new();
+    for chunk in &chunks {
+        match &chunk.id {
+            b"fmt " => {
+                let body = read_body(input, chunk)?;
+                check_length(&body, 16, &chunk.id)?;
+                let mut tag = le_u16(&body[0..]);
+                let channels = le_u16(&body[2..]) as u32;
+                let block_align = le_u16(&body[12..]) as u32;
+                let mut bits = le_u16(&body[14..]) as u32;
+                if tag == WAVE_FORMAT_EXTENSIBLE {
+                    check_length(&body, 40, &chunk.id)?;
+                    let valid_bits = le_u16(&body[18..]) as u32;
+                    if valid_bits != 0 {
+                        bits = valid_bits;
+                    }
+                    let mask = le_u32(&body[20..]);
+                    if mask != 0 {
+                        layout = ChannelLayout::from_wav_mask(mask).ok();
+                    }
+                    // The subformat GUID starts with the format tag.
+                    tag = le_u16(&body[24..]);
+                }
+                let bytes_per_sample = block_align.checked_div(channels).unwrap_or(0);
+                let (format_id, flags) = match tag {
+                    WAVE_FORMAT_PCM => {
+                        // 8-bit WAV is unsigned.
+                        (FORMAT_LINEAR_PCM, integer_flags(bits, bytes_per_sample, bits > 8, false))
+                    }
+                    WAVE_FORMAT_IEEE_FLOAT => (FORMAT_LINEAR_PCM, FLAG_IS_FLOAT | FLAG_IS_PACKED),
+                    WAVE_FORMAT_ALAW => (FORMAT_ALAW, 0),
+                    WAVE_FORMAT_MULAW => (FORMAT_ULAW, 0),
+                    other => (MS_FORMAT_BASE | other as u32, 0),
+                };
+                let uncompressed = format_id == FORMAT_LINEAR_PCM || format_id == FORMAT_ALAW ||
+                                   format_id == FORMAT_ULAW;
+                description = Some(StreamDescription {
+                                       sample_rate: le_u32(&body[4..]) as f64,
+                                       format_id,
+                                       format_flags: flags,
+                                       bytes_per_packet: block_align,
+                                       frames_per_packet: if uncompressed { 1 } else { 0 },
+                                       bytes_per_frame: if uncompressed { block_align } else { 0 },
+                                       channels_per_frame: channels,
+                                       bits_per_channel: if uncompressed { bits } else { 0 },
+                                   });
+            }
+            b"data" => data = Some((chunk.offset, chunk.size)),
+            b"fact" => {
+                let body = read_body(input, chunk)?;
+                check_length(&body, 4, &chunk.id)?;
+                fact_frames = Some(le_u32(&body) as u64);
+            }
+            b"LIST" => {
+                let body = read_body(input, chunk)?;
+                if body.len() >= 4 && &body[0..4] == b"INFO" {
+                    let mut position = 4;
+                    while position + 8 <= body.len() {
+                        let key = id(&body[position..]);
+                        let size = le_u32(&body[position + 4..]) as usize;
+                        let start = position + 8;
+                        let end = (start + size).min(body.len());
+                        metadata.push((info_key(&key), text(&body[start..end])));
+                        position = start + size + size % 2;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let description = description.ok_or_else(|| "WAV file has no 'fmt ' chunk".to_owned())?;
+    let (data_offset, data_size) = data.ok_or_else(|| "WAV file has no 'data' chunk".to_owned())?;
+    let packet_count = if description.bytes_per_packet > 0 {
+        Some(data_size / description.bytes_per_packet as u64)
+    } else {
+        None
+    };
+    Ok(AudioFileInfo {
+           file_type: AudioFileTypeId::WAVE,
+           description,
+           data_offset: Some(data_offset),
+           data_size,
+           packet_count,
+           frame_count: if description.frames_per_packet == 1 {
+               packet_count
+           } else {
+               fact_frames
+           },
+           magic_cookie: None,
+           channel_layout: layout,
+           metadata,
+       })
+}
+
+/// CoreAudio's info dictionary key for a RIFF INFO chunk ID.
+fn info_key(id: &[u8; 4]) -> String {
+    let key = match id {
+        b"INAM" => "title",
+        b"IART" => "artist",
+        b"IPRD" => "album",
+        b"ICMT" => "comments",
+        b"ICRD" => "year",
+        b"IGNR" => "genre",
+        b"ICOP" => "copyright",
+        b"ISFT" => "encoding application",
+        b"ITRK" | b"IPRT" => "track number",
+        _ => return String::from_utf8_lossy(id).into_owned(),
+    };
+    key.to_owned()
+}
+
+/// Decodes the 80-bit IEEE 754 extended precision number AIFF stores its sample rate in.
+fn extended_to_f64(b: &[u8]) -> f64 {
+    let exponent = ((b[0] as i32 & 0x7f) << 8) | b[1] as i32;
+    let mantissa = be_u64(&b[2..]);
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    let value = mantissa as f64 * 2f64.powi(exponent - 16383 - 63);
+    if b[0] & 0x80 != 0 { -value } else { value }
+}
+
+fn read_aiff<R: Read + Seek>(input: &mut R,
+                             length: u64,
+                             compressed: bool)
+                             -> Result<AudioFileInfo, String> {
+    let chunks = chunks(input, 12, length, 8, 2, |h| (id(h), Some(be_u32(&h[4..]) as u64)))?;
+    let mut common = None;
+    let mut data = None;
+    let mut metadata = Vec::new();
+    for chunk in &chunks {
+        match &chunk.id {
+            b"COMM" => {
+                let body = read_body(input, chunk)?;
+                check_length(&body, if compressed { 22 } else { 18 }, &chunk.id)?;
+                let channels = be_u16(&body[0..]) as u32;
+                let frames = be_u32(&body[2..]) as u64;
+                let bits = be_u16(&body[6..]) as u32;
+                let sample_rate = extended_to_f64(&body[8..18]);
+                let compression = if compressed {
+                    id(&body[18..])
+                } else {
+                    *b"NONE"
+                };
+                common = Some((channels, frames, bits, sample_rate, compression));
+            }
+            b"SSND" => {
+                let body_offset = chunk.offset;
+                let mut header = [0u8; 8];
+                input.seek(SeekFrom::Start(body_offset)).map_err(|e| e.to_string())?;
+                input.read_exact(&mut header).map_err(|e| e.to_string())?;
+                let offset = 8 + be_u32(&header) as u64;
+                data = Some((body_offset + offset, chunk.size.saturating_sub(offset)));
+            }
+            b"NAME" | b"AUTH" | b"(c) " | b"ANNO" => {
+                let key = match &chunk.id {
+                    b"NAME" => "title",
+                    b"AUTH" => "artist",
+                    b"(c) " => "copyright",
+                    _ => "comments",
+                };
+                metadata.push((key.to_owned(), text(&read_body(input, chunk)?)));
+            }
+            _ => {}
+        }
+    }
+
+    let (channels, frames, bits, sample_rate, compression) =
+        common.ok_or_else(|| "AIFF file has no 'COMM' chunk".to_owned())?;
+    let (data_offset, data_size) = match data {
+        Some((offset, size)) => (Some(offset), size),
+        None => (None, 0),
+    };
+    let pcm = |bits: u32, flags: u32| {
+        let bytes_per_frame = bits.div_ceil(8) * channels;
+        StreamDescription {
+            sample_rate,
+            format_id: FORMAT_LINEAR_PCM,
+            format_flags: flags,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: channels,
+            bits_per_channel: bits,
+        }
+    };
+    let compressed_format = |format_id: u32, bytes_per_packet: u32, frames_per_packet: u32| {
+        StreamDescription {
+            sample_rate,
+            format_id,
+            format_flags: 0,
+            bytes_per_packet,
+            frames_per_packet,
+            bytes_per_frame: if frames_per_packet == 1 { bytes_per_packet } else { 0 },
+            channels_per_frame: channels,
+            bits_per_channel: if frames_per_packet == 1 { 8 } else { 0 },
+        }
+    };
+    let bytes_per_sample = bits.div_ceil(8);
+    let (description, packet_count, frame_count) = match &compression {
+        b"NONE" | b"twos" => {
+            (pcm(bits, integer_flags(bits, bytes_per_sample, true, true)), Some(frames), frames)
+        }
+        b"sowt" => {
+            (pcm(bits, integer_flags(bits, bytes_per_sample, true, false)), Some(frames), frames)
+        }
+        b"in24" => (pcm(24, integer_flags(24, 3, true, true)), Some(frames), frames),
+        b"in32" => (pcm(32, integer_flags(32, 4, true, true)), Some(frames), frames),
+        b"fl32" | b"FL32" => {
+            (pcm(32, FLAG_IS_FLOAT | FLAG_IS_BIG_ENDIAN | FLAG_IS_PACKED), Some(frames), frames)
+        }
+        b"fl64" | b"FL64" => {
+            (pcm(64, FLAG_IS_FLOAT | FLAG_IS_BIG_ENDIAN | FLAG_IS_PACKED), Some(frames), frames)
+        }
+        b"ulaw" | b"ULAW" => (compressed_format(FORMAT_ULAW, channels, 1), Some(frames), frames),
+        b"alaw" | b"ALAW" => (compressed_format(FORMAT_ALAW, channels, 1), Some(frames), frames),
+        // The COMM frame count of IMA4 counts packets.
+        b"ima4" => {
+            (compressed_format(FORMAT_APPLE_IMA4, 34 * channels, 64), Some(frames), frames * 64)
+        }
+        other => (compressed_format(fourcc_code(other), 0, 0), None, frames),
+    };
+    Ok(AudioFileInfo {
+           file_type: if compressed {
+               AudioFileTypeId::AIFC
+           } else {
+               AudioFileTypeId::AIFF
+           },
+           description,
+           data_offset,
+           data_size,
+           packet_count,
+           frame_count: Some(frame_count),
+           magic_cookie: None,
+           channel_layout: None,
+           metadata,
+       })
+}
+
+fn read_caf<R: Read + Seek>(input: &mut R, length: u64) -> Result<AudioFileInfo, String> {
+    let chunks = chunks(input, 8, length, 12, 1, |h| {
+        let size = be_u64(&h[4..]);
+        // A data chunk of size -1 runs to the end of the file.
+        (id(h), if size == u64::MAX { None } else { Some(size) })
+    })?;
+    let mut description = None;
+    let mut data = None;
+    let mut packet_table = None;
+    let mut magic_cookie = None;
+    let mut layout = None;
+    let mut metadata = Vec::new();
+    for chunk in &chunks {
+        match &chunk.id {
+            b"desc" => {
+                let body = read_body(input, chunk)?;
+                check_length(&body, 32, &chunk.id)?;
+                let format_id = be_u32(&body[8..]);
+                let caf_flags = be_u32(&body[12..]);
+                let bytes_per_packet = be_u32(&body[16..]);
+                let frames_per_packet = be_u32(&body[20..]);
+                let channels = be_u32(&body[24..]);
+                let bits = be_u32(&body[28..]);
+                let flags = if format_id == FORMAT_LINEAR_PCM {
+                    if caf_flags & CAF_FLAG_IS_FLOAT != 0 {
+                        let mut flags = FLAG_IS_FLOAT | FLAG_IS_PACKED;
+                        if caf_flags & CAF_FLAG_IS_LITTLE_ENDIAN == 0 {
+                            flags |= FLAG_IS_BIG_ENDIAN;
+                        }
+                        flags
+                    } else {
+                        let bytes_per_sample = bytes_per_packet.checked_div(channels).unwrap_or(0);
+                        integer_flags(bits,
+                                      bytes_per_sample,
+                                      true,
+                                      caf_flags & CAF_FLAG_IS_LITTLE_ENDIAN == 0)
+                    }
+                } else {
+                    caf_flags
+                };
+                description = Some(StreamDescription {
+                                       sample_rate: f64::from_bits(be_u64(&body[0..])),
+                                       format_id,
+                                       format_flags: flags,
+                                       bytes_per_packet,
+                                       frames_per_packet,
+                                       bytes_per_frame: if frames_per_packet == 1 {
+                                           bytes_per_packet
+                                       } else {
+                                           0
+                                       },
+                                       channels_per_frame: channels,
+                                       bits_per_channel: bits,
+                                   });
+            }
+            b"data" => {
+                // The audio follows a 32-bit edit count.
+                data = Some((chunk.offset + 4, chunk.size.saturating_sub(4)));
+            }
+            b"pakt" => {
+                let body = read_body(input, chunk)?;
+                check_length(&body, 16, &chunk.id)?;
+                packet_table = Some((be_u64(&body[0..]), be_u64(&body[8..])));
+            }
+            b"kuki" => magic_cookie = Some(read_body(input, chunk)?),
+            b"chan" => {
+                // Every field of an AudioChannelLayout is a 32-bit word.
+                let body = read_body(input, chunk)?;
+                let native: Vec<u8> = body.chunks(4)
+                    .filter(|word| word.len() == 4)
+                    .flat_map(|word| {
+                        let mut bytes = [0u8; 4];
+                        bytes.copy_from_slice(word);
+                        u32::from_be_bytes(bytes).to_ne_bytes().to_vec()
+                    })
+                    .collect();
+                layout = ChannelLayout::from_audio_channel_layout(&native).ok();
+            }
+            b"info" => {
+                let body = read_body(input, chunk)?;
+                let mut strings = body.get(4..)
+                    .unwrap_or(&[])
+                    .split(|&b| b == 0)
+                    .map(|s| String::from_utf8_lossy(s).into_owned());
+                while let (Some(key), Some(value)) = (strings.next(), strings.next()) {
+                    metadata.push((key, value));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let description = description.ok_or_else(|| "CAF file has no 'desc' chunk".to_owned())?;
+    let (data_offset, data_size) = data.ok_or_else(|| "CAF file has no 'data' chunk".to_owned())?;
+    let (packet_count, frame_count) = match packet_table {
+        Some((packets, frames)) => (Some(packets), Some(frames)),
+        None if description.bytes_per_packet > 0 => {
+            let packets = data_size / description.bytes_per_packet as u64;
+            (Some(packets), Some(packets * description.frames_per_packet as u64))
+        }
+        None => (None, None),
+    };
+    Ok(AudioFileInfo {
+           file_type: AudioFileTypeId::CAF,
+           description,
+           data_offset: Some(data_offset),
+           data_size,
+           packet_count,
+           frame_count,
+           magic_cookie,
+           channel_layout: layout.filter(|l| l.len() == description.channels_per_frame as usize),
+           metadata,
+       })
+}
+
+#[cfg(feature = "coreaudio")]
+fn core_audio_info(path: &Path) -> Result<AudioFileInfo, String> {
+    use audio_file::*;
+    use core_foundation::string::CFString;
+    use core_foundation::url::{kCFURLPOSIXPathStyle, CFURL};
+
+    let path = path.to_str().ok_or_else(|| "path is not valid UTF-8".to_owned())?;
+    let url = CFURL::from_file_system_path(CFString::new(path), kCFURLPOSIXPathStyle, false);
+    let file = AudioFile::open(url).map_err(|status| format!("unable to open file: {}", status))?;
+    let property = |id: AudioFilePropertyId| file.get_property(id).ok();
+
+    let file_type = match property(AudioFilePropertyId::FileFormat) {
+        Some(AudioFileProperty::FileFormat(file_type)) => file_type,
+        _ => return Err("unable to get file format".to_owned()),
+    };
+    let description = match property(AudioFilePropertyId::DataFormat) {
+        Some(AudioFileProperty::DataFormat(asbd)) => StreamDescription::from_asbd(&asbd),
+        _ => return Err("unable to get data format".to_owned()),
+    };
+    let data_size = match property(AudioFilePropertyId::AudioDataByteCount) {
+        Some(AudioFileProperty::AudioDataByteCount(bytes)) => bytes,
+        _ => 0,
+    };
+    let packet_count = match property(AudioFilePropertyId::AudioDataPacketCount) {
+        Some(AudioFileProperty::AudioDataPacketCount(packets)) => Some(packets),
+        _ => None,
+    };
+    Ok(AudioFileInfo {
+           file_type: file_type,
+           description: description,
+           data_offset: match property(AudioFilePropertyId::DataOffset) {
+               Some(AudioFileProperty::DataOffset(offset)) if offset >= 0 => Some(offset as u64),
+               _ => None,
+           },
+           data_size: data_size,
+           packet_count: packet_count,
+           frame_count: match (packet_count, description.frames_per_packet) {
+               (Some(packets), frames) if frames > 0 => Some(packets * frames as u64),
+               _ => None,
+           },
+           magic_cookie: match property(AudioFilePropertyId::MagicCookie) {
+               Some(AudioFileProperty::MagicCookie(cookie)) => Some(cookie),
+               _ => None,
+           },
+           channel_layout: match property(AudioFilePropertyId::ChannelLayout) {
+               Some(AudioFileProperty::ChannelLayout(bytes)) => {
+                   ChannelLayout::from_audio_channel_layout(&bytes).ok()
+               }
+               _ => None,
+           },
+           metadata: Vec::new(),
+       })
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[allow(non_camel_case_types)]
+pub enum AudioFileTypeId {
+    AIFF = 1095321158,
+    AIFC = 1095321155,
+    WAVE = 1463899717,
+    SoundDesigner2 = 1399075430,
+    Next = 1315264596,
+    MP3 = 1297106739,
+    MP2 = 1297106738,
+    MP1 = 1297106737,
+    AC3 = 1633889587,
+    AAC_ADTS = 1633973363,
+    MPEG4 = 1836069990,
+    M4A = 1832149350,
+    M4B = 1832149606,
+    CAF = 1667327590,
+    _3GP = 862417008,
+    _3GP2 = 862416946,
+    AMR = 1634562662,
+}
+
+impl AudioFileTypeId {
+    pub fn from_u32(v: u32) -> Result<AudioFileTypeId, String> {
+        match v {
+            1095321158 => Ok(AudioFileTypeId::AIFF),
+            1095321155 => Ok(AudioFileTypeId::AIFC),
+            1463899717 => Ok(AudioFileTypeId::WAVE),
+            1399075430 => Ok(AudioFileTypeId::SoundDesigner2),
+            1315264596 => Ok(AudioFileTypeId::Next),
+            1297106739 => Ok(AudioFileTypeId::MP3),
+            1297106738 => Ok(AudioFileTypeId::MP2),
+            1297106737 => Ok(AudioFileTypeId::MP1),
+            1633889587 => Ok(AudioFileTypeId::AC3),
+            1633973363 => Ok(AudioFileTypeId::AAC_ADTS),
+            1836069990 => Ok(AudioFileTypeId::MPEG4),
+            1832149350 => Ok(AudioFileTypeId::M4A),
+            1832149606 => Ok(AudioFileTypeId::M4B),
+            1667327590 => Ok(AudioFileTypeId::CAF),
+            862417008 => Ok(AudioFileTypeId::_3GP),
+            862416946 => Ok(AudioFileTypeId::_3GP2),
+            1634562662 => Ok(AudioFileTypeId::AMR),
+            _ => Err(format!("unsupported file type {}", fourcc(v))),
+        }
+    }
+
+    /// The container conventionally used for a file extension, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<AudioFileTypeId> {
+        match &extension.to_lowercase()[..] {
+            "aif" | "aiff" => Some(AudioFileTypeId::AIFF),
+            "aifc" => Some(AudioFileTypeId::AIFC),
+            "wav" | "wave" => Some(AudioFileTypeId::WAVE),
+            "sd2" => Some(AudioFileTypeId::SoundDesigner2),
+            "au" | "snd" => Some(AudioFileTypeId::Next),
+            "mp3" => Some(AudioFileTypeId::MP3),
+            "mp2" => Some(AudioFileTypeId::MP2),
+            "mp1" => Some(AudioFileTypeId::MP1),
+            "ac3" => Some(AudioFileTypeId::AC3),
+            "aac" | "adts" => Some(AudioFileTypeId::AAC_ADTS),
+            "mp4" => Some(AudioFileTypeId::MPEG4),
+            "m4a" => Some(AudioFileTypeId::M4A),
+            "m4b" => Some(AudioFileTypeId::M4B),
+            "caf" => Some(AudioFileTypeId::CAF),
+            "3gp" => Some(AudioFileTypeId::_3GP),
+            "3g2" => Some(AudioFileTypeId::_3GP2),
+            "amr" => Some(AudioFileTypeId::AMR),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AudioFileTypeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&fourcc(*self as u32))
+    }
+}
+
+/// A four character code as text, such as `lpcm` or `WAVE`, or in hex if it is not
+/// printable.
+pub fn fourcc(code: u32) -> String {
+    let bytes = [(code >> 24) as u8, (code >> 16) as u8, (code >> 8) as u8, code as u8];
+    if bytes.iter().all(|b| (0x20..0x7f).contains(b)) {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        format!("{:#010x}", code)
+    }
+}
+
+pub fn fourcc_code(text: &[u8; 4]) -> u32 {
+    (text[0] as u32) << 24 | (text[1] as u32) << 16 | (text[2] as u32) << 8 | text[3] as u32
+}
@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+#[cfg(feature = "coreaudio")]
+use std::os::raw::c_void;
+use std::path::Path;
+
+#[cfg(feature = "coreaudio")]
+use audiotoolbox_sys::*;
+#[cfg(feature = "coreaudio")]
+use core_foundation::url::CFURL;
+
+use channel_map::ChannelLayout;
+use convert::decode_f32;
+#[cfg(feature = "coreaudio")]
+use extended_audio_file::*;
+use file_info::AudioFileInfo;
+use stream_format::*;
+
+const READ_BUFFER_FRAMES: usize = 4096;
+
+/// A source of interleaved f32 frames at the rate and channel count given by `format()`.
+pub trait FrameReader {
+    fn format(&self) -> StreamFormat;
+
+    /// The speaker position of each channel, when the source knows it.
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        None
+    }
+
+    /// Total frames the source will produce, when it knows in advance.
+    fn length(&self) -> Option<u64> {
+        None
+    }
+
+    /// Moves to `frame`, so that the next read starts there.
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let _ = frame;
+        Err("source cannot seek".to_owned())
+    }
+
+    /// Fills `buffer` with as many whole frames as fit, returning the number of frames read.
+    /// Zero frames means the source is exhausted.
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String>;
+
+    fn read_to_end(&mut self) -> Result<Vec<f32>, String> {
+        let channels = self.format().channels as usize;
+        let mut buffer = vec![0.0; READ_BUFFER_FRAMES * channels];
+        let mut samples = Vec::new();
+        loop {
+            let frames = self.read_frames(&mut buffer)?;
+            if frames == 0 {
+                return Ok(samples);
+            }
+            samples.extend_from_slice(&buffer[..frames * channels]);
+        }
+    }
+}
+
+impl<R: FrameReader + ?Sized> FrameReader for &mut R {
+    fn format(&self) -> StreamFormat {
+        (**self).format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        (**self).channel_layout()
+    }
+
+    fn length(&self) -> Option<u64> {
+        (**self).length()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        (**self).seek(frame)
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        (**self).read_frames(buffer)
+    }
+}
+
+impl<R: FrameReader + ?Sized> FrameReader for Box<R> {
+    fn format(&self) -> StreamFormat {
+        (**self).format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        (**self).channel_layout()
+    }
+
+    fn length(&self) -> Option<u64> {
+        (**self).length()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        (**self).seek(frame)
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        (**self).read_frames(buffer)
+    }
+}
+
+/// Opens `path` with `NativeFileReader`, falling back to `ExtAudioFileReader` for formats it
+/// cannot read when built with the `coreaudio` feature.
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn FrameReader>, String> {
+    let native = NativeFileReader::open(path.as_ref());
+    #[cfg(feature = "coreaudio")]
+    {
+        if native.is_err() {
+            use core_foundation::string::CFString;
+            use core_foundation::url::kCFURLPOSIXPathStyle;
+
+            if let Some(text) = path.as_ref().to_str() {
+                let url = CFURL::from_file_system_path(CFString::new(text),
+                                                       kCFURLPOSIXPathStyle,
+                                                       false);
+                if let Ok(reader) = ExtAudioFileReader::open(url) {
+                    return Ok(Box::new(reader));
+                }
+            }
+        }
+    }
+    native.map(|reader| Box::new(reader) as Box<dyn FrameReader>)
+}
+
+/// Reads the frames of another reader that fall within a range, as if they were all it
+/// held.
+pub struct RangeReader<R> {
+    reader: R,
+    range: Range<u64>,
+    position: u64,
+}
+
+impl<R: FrameReader> RangeReader<R> {
+    /// Seeks `reader` to the start of `range`.
+    pub fn new(mut reader: R, range: Range<u64>) -> Result<RangeReader<R>, String> {
+        if range.end < range.start {
+            return Err(format!("invalid frame range {}..{}", range.start, range.end));
+        }
+        reader.seek(range.start)?;
+        Ok(RangeReader {
+               position: range.start,
+               reader,
+               range,
+           })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: FrameReader> FrameReader for RangeReader<R> {
+    fn format(&self) -> StreamFormat {
+        self.reader.format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.reader.channel_layout()
+    }
+
+    fn length(&self) -> Option<u64> {
+        self.reader
+            .length()
+            .map(|length| length.min(self.range.end).saturating_sub(self.range.start))
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let frame = frame.min(self.range.end - self.range.start);
+        self.reader.seek(self.range.start + frame)?;
+        self.position = self.range.start + frame;
+        Ok(())
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.reader.format().channels as usize;
+        let frames = ((buffer.len() / channels) as u64).min(self.range.end - self.position);
+        if frames == 0 {
+            return Ok(0);
+        }
+        let read = self.reader.read_frames(&mut buffer[..frames as usize * channels])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+/// Reads the linear PCM in a WAV, AIFF, AIFC or CAF file without CoreAudio.
+pub struct NativeFileReader<R = BufReader<File>> {
+    input: R,
+    info: AudioFileInfo,
+    file_format: StreamFormat,
+    /// 8-bit WAV samples are unsigned and are flipped to signed before decoding.
+    unsigned: bool,
+    length: u64,
+    remaining: u64,
+    raw: Vec<u8>,
+}
+
+impl NativeFileReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<NativeFileReader, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("unable to open {}: {}", path.as_ref().display(), e))?;
+        NativeFileReader::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> NativeFileReader<R> {
+    pub fn new(mut input: R) -> Result<NativeFileReader<R>, String> {
+        let info = AudioFileInfo::read(&mut input)?;
+        let mut description = info.description;
+        let unsigned = description.is_linear_pcm() && description.bits_per_channel == 8 &&
+                       description.format_flags & (FLAG_IS_FLOAT | FLAG_IS_SIGNED_INTEGER) == 0;
+        if unsigned {
+            description.format_flags |= FLAG_IS_SIGNED_INTEGER;
+        }
+        let file_format = description.stream_format()?;
+        let offset = info.data_offset.ok_or_else(|| "file has no audio data".to_owned())?;
+        input.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+        let available = info.data_size / file_format.bytes_per_frame() as u64;
+        let length = info.frame_count.map_or(available, |frames| frames.min(available));
+        Ok(NativeFileReader {
+               input,
+               info,
+               file_format,
+               unsigned,
+               length,
+               remaining: length,
+               raw: Vec::new(),
+           })
+    }
+
+    pub fn info(&self) -> &AudioFileInfo {
+        &self.info
+    }
+
+    /// The layout of the samples in the file.
+    pub fn file_format(&self) -> StreamFormat {
+        self.file_format
+    }
+
+    pub fn into_inner(self) -> R {
+        self.input
+    }
+}
+
+impl<R: Read + Seek> FrameReader for NativeFileReader<R> {
+    fn format(&self) -> StreamFormat {
+        StreamFormat::new(self.file_format.sample_rate,
+                          self.file_format.channels,
+                          SampleFormat::F32)
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.info.channel_layout.clone()
+    }
+
+    fn length(&self) -> Option<u64> {
+        Some(self.length)
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        let frame = frame.min(self.length);
+        let offset = self.info.data_offset.unwrap_or(0) +
+                     frame * self.file_format.bytes_per_frame() as u64;
+        self.input
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| format!("could not seek in file: {}", e))?;
+        self.remaining = self.length - frame;
+        Ok(())
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.file_format.channels as usize;
+        let frames = ((buffer.len() / channels) as u64).min(self.remaining) as usize;
+        if frames == 0 {
+            return Ok(0);
+        }
+        self.raw.resize(frames * self.file_format.bytes_per_frame(), 0);
+        self.input
+            .read_exact(&mut self.raw)
+            .map_err(|e| format!("could not read from file: {}", e))?;
+        if self.unsigned {
+            for byte in &mut self.raw {
+                *byte ^= 0x80;
+            }
+        }
+        let samples = decode_f32(&self.file_format, &[&self.raw])?;
+        buffer[..samples.len()].copy_from_slice(&samples);
+        self.remaining -= frames as u64;
+        Ok(frames)
+    }
+}
+
+/// Reads any file `ExtAudioFile` can decode as f32 frames at the file's own rate and
+/// channel count.
+#[cfg(feature = "coreaudio")]
+pub struct ExtAudioFileReader {
+    file: ExtAudioFile,
+    format: StreamFormat,
+    layout: Option<ChannelLayout>,
+    length: Option<u64>,
+    raw: Vec<u8>,
+}
+
+#[cfg(feature = "coreaudio")]
+impl ExtAudioFileReader {
+    pub fn open(url: CFURL) -> Result<ExtAudioFileReader, String> {
+        let mut file = ExtAudioFile::open(url)
+            .map_err(|status| format!("unable to open file: {}", status))?;
+        let file_format = file.get_data_format()
+            .map_err(|status| format!("unable to get data format: {}", status))?;
+        let format = StreamFormat::new(file_format.mSampleRate,
+                                       file_format.mChannelsPerFrame,
+                                       SampleFormat::F32);
+        file.set_property(ExtAudioFileProperty::ClientDataFormat(format.to_asbd()))
+            .map_err(|status| format!("unable to set client data format: {}", status))?;
+        let layout = match file.get_property(ExtAudioFilePropertyId::FileChannelLayout) {
+            Ok(ExtAudioFileProperty::FileChannelLayout(bytes)) => {
+                ChannelLayout::from_audio_channel_layout(&bytes).ok()
+            }
+            _ => None,
+        };
+        let length = match file.get_property(ExtAudioFilePropertyId::FileLengthFrames) {
+            Ok(ExtAudioFileProperty::FileLengthFrames(frames)) => Some(frames as u64),
+            _ => None,
+        };
+        Ok(ExtAudioFileReader {
+               file: file,
+               format: format,
+               layout: layout.filter(|l| l.len() == format.channels as usize),
+               length: length,
+               raw: Vec::new(),
+           })
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl FrameReader for ExtAudioFileReader {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.layout.clone()
+    }
+
+    fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        self.file
+            .seek(frame as i64)
+            .map_err(|status| format!("could not seek in file: {}", status))
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let bytes_per_frame = self.format.bytes_per_frame();
+        let frames = buffer.len() / self.format.channels as usize;
+        self.raw.resize(frames * bytes_per_frame, 0);
+        let mut list = AudioBufferList {
+            mNumberBuffers: 1,
+            mBuffers: [AudioBuffer {
+                           mNumberChannels: self.format.channels,
+                           mDataByteSize: self.raw.len() as u32,
+                           mData: self.raw.as_mut_ptr() as *mut c_void,
+                       }],
+        };
+        let frames_read = self.file
+            .read(&mut list, frames as u32)
+            .map_err(|status| format!("could not read from file: {}", status))?;
+        let frames_read = frames_read as usize;
+        let samples = decode_f32(&self.format, &[&self.raw[..frames_read * bytes_per_frame]])?;
+        buffer[..samples.len()].copy_from_slice(&samples);
+        Ok(frames_read)
+    }
+}
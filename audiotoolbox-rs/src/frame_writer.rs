@@ -0,0 +1,463 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+#[cfg(feature = "coreaudio")]
+use std::os::raw::c_void;
+use std::path::Path;
+
+#[cfg(feature = "coreaudio")]
+use audiotoolbox_sys::*;
+#[cfg(feature = "coreaudio")]
+use core_foundation::url::CFURL;
+
+use channel_map::{ChannelLayout, ChannelLayoutTag};
+use convert::{Dither, Encoder};
+#[cfg(feature = "coreaudio")]
+use convert::encode_f32;
+#[cfg(feature = "coreaudio")]
+use extended_audio_file::*;
+use file_type::AudioFileTypeId;
+use stream_format::*;
+
+/// `KSDATAFORMAT_SUBTYPE` GUIDs are the format tag followed by these bytes.
+const WAVE_SUBFORMAT_SUFFIX: [u8; 14] = [0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00,
+                                         0xaa, 0x00, 0x38, 0x9b, 0x71];
+/// Timestamp of the only AIFC version.
+const AIFC_VERSION: u32 = 0xa280_5140;
+
+/// A sink of interleaved f32 frames at the rate and channel count given by `format()`.
+pub trait FrameWriter {
+    fn format(&self) -> StreamFormat;
+
+    /// Writes whole frames.
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String>;
+
+    /// Completes the file's headers. Nothing may be written afterwards.
+    fn finish(&mut self) -> Result<(), String>;
+}
+
+impl<W: FrameWriter + ?Sized> FrameWriter for &mut W {
+    fn format(&self) -> StreamFormat {
+        (**self).format()
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        (**self).write_frames(frames)
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        (**self).finish()
+    }
+}
+
+impl<W: FrameWriter + ?Sized> FrameWriter for Box<W> {
+    fn format(&self) -> StreamFormat {
+        (**self).format()
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        (**self).write_frames(frames)
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        (**self).finish()
+    }
+}
+
+/// Creates `path` holding `description`, with `NativeFileWriter` for linear PCM in WAV,
+/// AIFF, AIFC and CAF and, when built with the `coreaudio` feature, `ExtAudioFileWriter`
+/// for everything else.
+pub fn create<P: AsRef<Path>>(path: P,
+                              file_type: AudioFileTypeId,
+                              description: &StreamDescription,
+                              layout: Option<ChannelLayout>)
+                              -> Result<Box<dyn FrameWriter>, String> {
+    if description.is_linear_pcm() && NativeFileWriter::supports(file_type) {
+        let format = description.stream_format()?;
+        return Ok(Box::new(NativeFileWriter::create(path, file_type, format, layout)?));
+    }
+    #[cfg(feature = "coreaudio")]
+    {
+        use core_foundation::string::CFString;
+        use core_foundation::url::kCFURLPOSIXPathStyle;
+
+        let text = path.as_ref().to_str().ok_or_else(|| "path is not valid UTF-8".to_owned())?;
+        let url = CFURL::from_file_system_path(CFString::new(text), kCFURLPOSIXPathStyle, false);
+        Ok(Box::new(ExtAudioFileWriter::create(url, file_type, description)?))
+    }
+    #[cfg(not(feature = "coreaudio"))]
+    {
+        Err(format!("writing '{}' to {} requires the coreaudio feature",
+                    ::file_type::fourcc(description.format_id),
+                    file_type))
+    }
+}
+
+/// Writes linear PCM to a WAV, AIFF, AIFC or CAF file without CoreAudio. Header sizes are
+/// filled in by `finish`, which also runs when the writer is dropped.
+pub struct NativeFileWriter<W: Write + Seek = BufWriter<File>> {
+    output: W,
+    file_type: AudioFileTypeId,
+    encoder: Encoder,
+    buffers: Vec<Vec<u8>>,
+    /// Offset of the first audio byte.
+    data_offset: u64,
+    /// Offset of the AIFF COMM chunk's frame count.
+    frame_count_offset: u64,
+    frames: u64,
+    finished: bool,
+}
+
+impl NativeFileWriter {
+    pub fn create<P: AsRef<Path>>(path: P,
+                                  file_type: AudioFileTypeId,
+                                  format: StreamFormat,
+                                  layout: Option<ChannelLayout>)
+                                  -> Result<NativeFileWriter, String> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| format!("unable to create {}: {}", path.as_ref().display(), e))?;
+        NativeFileWriter::new(BufWriter::new(file), file_type, format, layout)
+    }
+
+    pub fn supports(file_type: AudioFileTypeId) -> bool {
+        matches!(file_type,
+                 AudioFileTypeId::WAVE | AudioFileTypeId::AIFF | AudioFileTypeId::AIFC |
+                 AudioFileTypeId::CAF)
+    }
+}
+
+impl<W: Write + Seek> NativeFileWriter<W> {
+    /// Writes the header for `format`. WAV is always little-endian and AIFF big-endian,
+    /// whatever `format` asks for; CAF keeps its endianness.
+    pub fn new(mut output: W,
+               file_type: AudioFileTypeId,
+               format: StreamFormat,
+               layout: Option<ChannelLayout>)
+               -> Result<NativeFileWriter<W>, String> {
+        if format.channels == 0 {
+            return Err("format has no channels".to_owned());
+        }
+        let layout = layout.filter(|l| l.len() == format.channels as usize);
+        let mut format = format.with_interleaved(true);
+        let (header, frame_count_offset) = match file_type {
+            AudioFileTypeId::WAVE => {
+                format.endianness = Endianness::Little;
+                (wave_header(&format, layout.as_ref()), 0)
+            }
+            AudioFileTypeId::AIFF | AudioFileTypeId::AIFC => {
+                if format.sample_format.is_float() && file_type == AudioFileTypeId::AIFF {
+                    return Err("AIFF cannot hold floating point samples; use AIFC or CAF"
+                                   .to_owned());
+                }
+                format.endianness = Endianness::Big;
+                aiff_header(&format, file_type == AudioFileTypeId::AIFC)
+            }
+            AudioFileTypeId::CAF => (caf_header(&format, layout.as_ref()), 0),
+            other => return Err(format!("cannot write {} files natively", other)),
+        };
+        output.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(NativeFileWriter {
+               output,
+               file_type,
+               encoder: Encoder::new(format),
+               buffers: vec![Vec::new()],
+               data_offset: header.len() as u64,
+               frame_count_offset,
+               frames: 0,
+               finished: false,
+           })
+    }
+
+    /// Dither applied when quantising to integer samples.
+    pub fn with_dither(mut self, dither: Dither) -> NativeFileWriter<W> {
+        self.encoder = Encoder::new(*self.encoder.format()).with_dither(dither);
+        self
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.frames
+    }
+
+    fn patch(&mut self, offset: u64, bytes: &[u8]) -> Result<(), String> {
+        self.output.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+        self.output.write_all(bytes).map_err(|e| e.to_string())
+    }
+
+    fn write_sizes(&mut self) -> Result<(), String> {
+        let data_size = self.frames * self.encoder.format().bytes_per_frame() as u64;
+        let pad = data_size % 2;
+        if self.file_type != AudioFileTypeId::CAF && pad == 1 {
+            self.output.write_all(&[0]).map_err(|e| e.to_string())?;
+        }
+        let total = self.data_offset + data_size + pad;
+        let overflow = || "file is too large for its container".to_owned();
+        let data_offset = self.data_offset;
+        match self.file_type {
+            AudioFileTypeId::WAVE => {
+                if total - 8 > u32::MAX as u64 {
+                    return Err(overflow());
+                }
+                self.patch(4, &((total - 8) as u32).to_le_bytes())?;
+                self.patch(data_offset - 4, &(data_size as u32).to_le_bytes())?;
+            }
+            AudioFileTypeId::CAF => {
+                // The data chunk also holds a 32-bit edit count.
+                self.patch(data_offset - 12, &(data_size + 4).to_be_bytes())?;
+            }
+            _ => {
+                if total - 8 > u32::MAX as u64 {
+                    return Err(overflow());
+                }
+                self.patch(4, &((total - 8) as u32).to_be_bytes())?;
+                let frame_count_offset = self.frame_count_offset;
+                self.patch(frame_count_offset, &(self.frames as u32).to_be_bytes())?;
+                // The SSND chunk's offset and block size precede the audio.
+                self.patch(data_offset - 12, &((data_size + 8) as u32).to_be_bytes())?;
+            }
+        }
+        self.output.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        self.output.flush().map_err(|e| e.to_string())
+    }
+}
+
+impl<W: Write + Seek> FrameWriter for NativeFileWriter<W> {
+    fn format(&self) -> StreamFormat {
+        *self.encoder.format()
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        if self.finished {
+            return Err("file has been finished".to_owned());
+        }
+        let count = self.encoder.encode(frames, &mut self.buffers)?;
+        // 8-bit WAV samples are unsigned.
+        if self.file_type == AudioFileTypeId::WAVE &&
+           self.encoder.format().sample_format == SampleFormat::I8 {
+            for byte in &mut self.buffers[0] {
+                *byte ^= 0x80;
+            }
+        }
+        self.output
+            .write_all(&self.buffers[0])
+            .map_err(|e| format!("could not write to file: {}", e))?;
+        self.frames += count as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        self.write_sizes()
+    }
+}
+
+impl<W: Write + Seek> Drop for NativeFileWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn chunk_header(out: &mut Vec<u8>, id: &[u8; 4], size: u32, big_endian: bool) {
+    out.extend_from_slice(id);
+    if big_endian {
+        out.extend_from_slice(&size.to_be_bytes());
+    } else {
+        out.extend_from_slice(&size.to_le_bytes());
+    }
+}
+
+/// Sizes are left zero for `finish` to fill in.
+fn wave_header(format: &StreamFormat, layout: Option<&ChannelLayout>) -> Vec<u8> {
+    let bits = format.sample_format.bits_per_sample() as u16;
+    let block_align = format.bytes_per_frame() as u16;
+    let tag: u16 = if format.sample_format.is_float() { 3 } else { 1 };
+    let extensible = format.channels > 2 || bits > 16;
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&(if extensible { 0xfffe } else { tag }).to_le_bytes());
+    fmt.extend_from_slice(&(format.channels as u16).to_le_bytes());
+    let sample_rate = format.sample_rate.round() as u32;
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+    if extensible {
+        let mask = layout.and_then(|l| l.wav_mask())
+            .or_else(|| ChannelLayout::default_for(format.channels).and_then(|l| l.wav_mask()))
+            .unwrap_or(0);
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        fmt.extend_from_slice(&mask.to_le_bytes());
+        fmt.extend_from_slice(&tag.to_le_bytes());
+        fmt.extend_from_slice(&WAVE_SUBFORMAT_SUFFIX);
+    }
+
+    let mut out = Vec::new();
+    chunk_header(&mut out, b"RIFF", 0, false);
+    out.extend_from_slice(b"WAVE");
+    chunk_header(&mut out, b"fmt ", fmt.len() as u32, false);
+    out.extend(fmt);
+    chunk_header(&mut out, b"data", 0, false);
+    out
+}
+
+/// The 80-bit extended float AIFF uses for its sample rate.
+fn f64_to_extended(value: f64) -> [u8; 10] {
+    let mut out = [0u8; 10];
+    if value <= 0.0 || !value.is_finite() {
+        return out;
+    }
+    let exponent = value.log2().floor() as i32;
+    let mantissa = (value / 2f64.powi(exponent - 63)) as u64;
+    out[0..2].copy_from_slice(&((exponent + 16383) as u16).to_be_bytes());
+    out[2..10].copy_from_slice(&mantissa.to_be_bytes());
+    out
+}
+
+/// Returns the header and the offset of the COMM chunk's frame count.
+fn aiff_header(format: &StreamFormat, compressed: bool) -> (Vec<u8>, u64) {
+    let mut out = Vec::new();
+    chunk_header(&mut out, b"FORM", 0, true);
+    out.extend_from_slice(if compressed { b"AIFC" } else { b"AIFF" });
+    if compressed {
+        chunk_header(&mut out, b"FVER", 4, true);
+        out.extend_from_slice(&AIFC_VERSION.to_be_bytes());
+    }
+
+    let mut comm = Vec::new();
+    comm.extend_from_slice(&(format.channels as u16).to_be_bytes());
+    let frame_count_offset = comm.len();
+    comm.extend_from_slice(&[0; 4]);
+    comm.extend_from_slice(&(format.sample_format.bits_per_sample() as u16).to_be_bytes());
+    comm.extend_from_slice(&f64_to_extended(format.sample_rate));
+    if compressed {
+        let (id, name): (&[u8; 4], &str) = match format.sample_format {
+            SampleFormat::F32 => (b"fl32", "32-bit floating point"),
+            SampleFormat::F64 => (b"fl64", "64-bit floating point"),
+            _ => (b"NONE", "not compressed"),
+        };
+        comm.extend_from_slice(id);
+        // A Pascal string padded to an even length.
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name.as_bytes());
+        if name.len() % 2 == 0 {
+            comm.push(0);
+        }
+    }
+    chunk_header(&mut out, b"COMM", comm.len() as u32, true);
+    let frame_count_offset = (out.len() + frame_count_offset) as u64;
+    out.extend(comm);
+    chunk_header(&mut out, b"SSND", 0, true);
+    out.extend_from_slice(&[0; 8]);
+    (out, frame_count_offset)
+}
+
+fn caf_header(format: &StreamFormat, layout: Option<&ChannelLayout>) -> Vec<u8> {
+    let mut out = b"caff".to_vec();
+    out.extend_from_slice(&[0, 1, 0, 0]);
+
+    let mut flags = 0u32;
+    if format.sample_format.is_float() {
+        flags |= 1;
+    }
+    if format.endianness == Endianness::Little {
+        flags |= 2;
+    }
+    out.extend_from_slice(b"desc");
+    out.extend_from_slice(&32u64.to_be_bytes());
+    out.extend_from_slice(&format.sample_rate.to_bits().to_be_bytes());
+    for &field in [FORMAT_LINEAR_PCM,
+                   flags,
+                   format.bytes_per_frame() as u32,
+                   1,
+                   format.channels,
+                   format.sample_format.bits_per_sample() as u32]
+                .iter() {
+        out.extend_from_slice(&field.to_be_bytes());
+    }
+
+    let words = match layout {
+        Some(layout) => {
+            match (layout.tag(), layout.wav_mask()) {
+                (Some(tag), _) => Some([tag as u32, 0, 0]),
+                (None, Some(mask)) => Some([ChannelLayoutTag::UseChannelBitmap as u32, mask, 0]),
+                (None, None) => None,
+            }
+        }
+        None => None,
+    };
+    if let Some(words) = words {
+        out.extend_from_slice(b"chan");
+        out.extend_from_slice(&12u64.to_be_bytes());
+        for word in words.iter() {
+            out.extend_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    // A data chunk of size -1 runs to the end of the file until `finish` sets its size.
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&u64::MAX.to_be_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out
+}
+
+/// Encodes f32 frames to any file type and format `ExtAudioFile` can write.
+#[cfg(feature = "coreaudio")]
+pub struct ExtAudioFileWriter {
+    file: Option<ExtAudioFile>,
+    format: StreamFormat,
+}
+
+#[cfg(feature = "coreaudio")]
+impl ExtAudioFileWriter {
+    /// Fields of `description` left zero are filled in by the encoder.
+    pub fn create(url: CFURL,
+                  file_type: AudioFileTypeId,
+                  description: &StreamDescription)
+                  -> Result<ExtAudioFileWriter, String> {
+        let mut file = ExtAudioFile::create(url,
+                                            file_type as u32,
+                                            &description.to_asbd(),
+                                            kAudioFileFlags_EraseFile as u32)
+            .map_err(|status| format!("unable to create file: {}", status))?;
+        let format = StreamFormat::new(description.sample_rate,
+                                       description.channels_per_frame,
+                                       SampleFormat::F32);
+        file.set_property(ExtAudioFileProperty::ClientDataFormat(format.to_asbd()))
+            .map_err(|status| format!("unable to set client data format: {}", status))?;
+        Ok(ExtAudioFileWriter {
+               file: Some(file),
+               format: format,
+           })
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl FrameWriter for ExtAudioFileWriter {
+    fn format(&self) -> StreamFormat {
+        self.format
+    }
+
+    fn write_frames(&mut self, frames: &[f32]) -> Result<(), String> {
+        let file = self.file.as_mut().ok_or_else(|| "file has been finished".to_owned())?;
+        let mut raw = encode_f32(&self.format, frames)?.remove(0);
+        let count = frames.len() / self.format.channels as usize;
+        let mut list = AudioBufferList {
+            mNumberBuffers: 1,
+            mBuffers: [AudioBuffer {
+                           mNumberChannels: self.format.channels,
+                           mDataByteSize: raw.len() as u32,
+                           mData: raw.as_mut_ptr() as *mut c_void,
+                       }],
+        };
+        file.write(&mut list, count as u32)
+            .map(|_| ())
+            .map_err(|status| format!("could not write to file: {}", status))
+    }
+
+    /// Disposing of the file writes its remaining packets and headers.
+    fn finish(&mut self) -> Result<(), String> {
+        self.file.take();
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+/// The zeroth order modified Bessel function of the first kind, which shapes Kaiser windows.
+pub fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= (half / k) * (half / k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
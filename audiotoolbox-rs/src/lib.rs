@@ -1,9 +1,33 @@
+#[cfg(feature = "coreaudio")]
 extern crate audiotoolbox_sys;
+#[cfg(feature = "coreaudio")]
 extern crate core_foundation_sys;
+#[cfg(feature = "coreaudio")]
 extern crate core_foundation;
 extern crate libc;
 
+#[cfg(feature = "coreaudio")]
 pub mod audio_file;
+#[cfg(feature = "coreaudio")]
 pub mod audio_queue;
+#[cfg(feature = "coreaudio")]
 pub mod audio_hardware_base;
+#[cfg(feature = "coreaudio")]
 pub mod extended_audio_file;
+pub mod file_type;
+pub mod stream_format;
+pub mod convert;
+pub mod resample;
+pub mod channel_map;
+pub mod frame_reader;
+pub mod frame_writer;
+pub mod audio_converter;
+pub mod file_info;
+pub mod audio_device;
+pub mod loudness;
+pub mod normalize;
+pub mod silence;
+pub mod dsp;
+pub mod meter;
+
+mod kaiser;
@@ -0,0 +1,398 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use channel_map::{Channel, ChannelLayout};
+use dsp::filter::{Biquad, Coefficients};
+use frame_reader::FrameReader;
+use kaiser::bessel_i0;
+
+const READ_FRAMES: usize = 4096;
+/// Loudness is measured over blocks of this many 100 ms steps.
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+/// Blocks quieter than this are left out of the integrated loudness and loudness range.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Relative gates, below the ungated loudness of the blocks that pass the absolute gate.
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Taps of the true peak interpolator on either side of the point it interpolates.
+const TRUE_PEAK_HALF_TAPS: usize = 8;
+/// Kaiser window shape of the interpolator, for about 60 dB of stopband attenuation.
+const TRUE_PEAK_BETA: f64 = 5.65;
+
+/// Loudness in LUFS of a block's weighted mean square.
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn loudness_to_energy(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+fn gain_to_db(gain: f64) -> f64 {
+    20.0 * gain.log10()
+}
+
+/// The BS.1770 weight of a channel: the surrounds either side count 1.5 dB more than the
+/// front, the LFE channel not at all and a single back centre as much as the front.
+fn channel_weight(channel: Channel) -> f64 {
+    match channel {
+        Channel::LowFrequency => 0.0,
+        Channel::BackLeft | Channel::BackRight | Channel::SideLeft | Channel::SideRight => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn max_loudness(blocks: &[f64]) -> Option<f64> {
+    if blocks.is_empty() {
+        return None;
+    }
+    Some(energy_to_loudness(blocks.iter().fold(0.0, |max: f64, &e| max.max(e))))
+}
+
+/// The blocks above the absolute gate and above `relative_gate` LU below the mean of the
+/// blocks above the absolute gate, or `None` when no block passes.
+fn gated_blocks(blocks: &[f64], relative_gate: f64) -> Option<Vec<f64>> {
+    let absolute = loudness_to_energy(ABSOLUTE_GATE);
+    let loud: Vec<f64> = blocks.iter().cloned().filter(|&e| e > absolute).collect();
+    if loud.is_empty() {
+        return None;
+    }
+    let mean = loud.iter().sum::<f64>() / loud.len() as f64;
+    let relative = loudness_to_energy(energy_to_loudness(mean) + relative_gate);
+    let gated: Vec<f64> = loud.into_iter().filter(|&e| e > relative).collect();
+    if gated.is_empty() { None } else { Some(gated) }
+}
+
+/// The K-weighting curve of ITU-R BS.1770: a high shelf modelling the head followed by the
+/// revised low-frequency B-curve high-pass, designed for any sample rate.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let shelf = Coefficients::new(vh + vb * k / q + k * k,
+                                  2.0 * (k * k - vh),
+                                  vh - vb * k / q + k * k,
+                                  1.0 + k / q + k * k,
+                                  2.0 * (k * k - 1.0),
+                                  1.0 - k / q + k * k);
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let high_pass = Coefficients::new(1.0,
+                                      -2.0,
+                                      1.0,
+                                      1.0 + k / q + k * k,
+                                      2.0 * (k * k - 1.0),
+                                      1.0 - k / q + k * k);
+    [Biquad::new(shelf), Biquad::new(high_pass)]
+}
+
+/// Interpolates between the samples of one channel to find peaks that fall between them.
+pub struct TruePeak {
+    /// `kernels[p]` gives the point `(p + 1) / factor` of the way from the middle sample of
+    /// the history to the next.
+    kernels: Vec<Vec<f64>>,
+    /// The latest samples twice over, so that `history[next..next + taps]` is always the
+    /// whole history, oldest first.
+    history: Vec<f64>,
+    next: usize,
+    peak: f64,
+}
+
+impl TruePeak {
+    /// Oversamples to at least 192 kHz, as BS.1770 asks.
+    pub fn new(sample_rate: f64) -> TruePeak {
+        let factor = if sample_rate < 96000.0 {
+            4
+        } else if sample_rate < 192000.0 {
+            2
+        } else {
+            1
+        };
+        let taps = 2 * TRUE_PEAK_HALF_TAPS;
+        let i0_beta = bessel_i0(TRUE_PEAK_BETA);
+        let kernels = (1..factor)
+            .map(|p| {
+                let fraction = p as f64 / factor as f64;
+                (0..taps)
+                    .map(|j| {
+                        // Distance from the interpolated point to tap `j`.
+                        let x = j as f64 - (TRUE_PEAK_HALF_TAPS - 1) as f64 - fraction;
+                        let r = x / TRUE_PEAK_HALF_TAPS as f64;
+                        let sinc = (PI * x).sin() / (PI * x);
+                        sinc * bessel_i0(TRUE_PEAK_BETA * (1.0 - r * r).max(0.0).sqrt()) /
+                        i0_beta
+                    })
+                    .collect()
+            })
+            .collect();
+        TruePeak {
+            kernels,
+            history: vec![0.0; 2 * taps],
+            next: 0,
+            peak: 0.0,
+        }
+    }
+
+    /// How many samples the interpolated points lag behind the latest sample.
+    pub fn latency(&self) -> usize {
+        TRUE_PEAK_HALF_TAPS
+    }
+
+    /// Returns the largest magnitude among `sample` and the points interpolated between the
+    /// samples `latency()` and `latency() - 1` back.
+    pub fn process(&mut self, sample: f64) -> f64 {
+        let taps = self.history.len() / 2;
+        self.history[self.next] = sample;
+        self.history[self.next + taps] = sample;
+        self.next = (self.next + 1) % taps;
+        let history = &self.history[self.next..self.next + taps];
+        let mut peak = sample.abs();
+        for kernel in &self.kernels {
+            let value: f64 = kernel.iter().zip(history).map(|(k, x)| k * x).sum();
+            peak = peak.max(value.abs());
+        }
+        self.peak = self.peak.max(peak);
+        peak
+    }
+
+    /// The largest magnitude seen so far, as a gain.
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+
+    /// Forgets the history and the peak.
+    pub fn reset(&mut self) {
+        for sample in self.history.iter_mut() {
+            *sample = 0.0;
+        }
+        self.peak = 0.0;
+    }
+}
+
+/// Loudness, loudness range and true peak per EBU R 128 and ITU-R BS.1770-4.
+///
+/// Interleaved frames are pushed through `process` in chunks of any size. Momentary and
+/// short-term loudness cover the last 400 ms and 3 s, updated every 100 ms; integrated
+/// loudness and loudness range cover everything processed, gated as the standards
+/// describe. Loudness is in LUFS, ranges in LU and peaks in dBFS.
+pub struct LoudnessMeter {
+    sample_rate: f64,
+    weights: Vec<f64>,
+    filters: Vec<[Biquad; 2]>,
+    true_peaks: Vec<TruePeak>,
+    sample_peak: f64,
+    frames: u64,
+    /// Frame at which the current 100 ms step ends.
+    step_end: u64,
+    steps: u64,
+    /// Weighted sum of squares of the current step.
+    step_sum: f64,
+    /// The sum of squares and frame count of the latest steps, the newest last.
+    recent: VecDeque<(f64, u64)>,
+    momentary_blocks: Vec<f64>,
+    short_term_blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    /// Weights the channels by the layout `ChannelLayout::default_for` gives, or equally
+    /// when there is none.
+    pub fn new(sample_rate: f64, channels: usize) -> Result<LoudnessMeter, String> {
+        if channels == 0 {
+            return Err("loudness meter needs at least one channel".to_owned());
+        }
+        let weights = match ChannelLayout::default_for(channels as u32) {
+            Some(layout) => layout.channels().iter().map(|&c| channel_weight(c)).collect(),
+            None => vec![1.0; channels],
+        };
+        LoudnessMeter::with_weights(sample_rate, weights)
+    }
+
+    pub fn with_layout(sample_rate: f64, layout: &ChannelLayout) -> Result<LoudnessMeter, String> {
+        let weights = layout.channels().iter().map(|&c| channel_weight(c)).collect();
+        LoudnessMeter::with_weights(sample_rate, weights)
+    }
+
+    /// Weights each channel's mean square by the given factor.
+    pub fn with_weights(sample_rate: f64, weights: Vec<f64>) -> Result<LoudnessMeter, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {} Hz", sample_rate));
+        }
+        if weights.is_empty() {
+            return Err("loudness meter needs at least one channel".to_owned());
+        }
+        let channels = weights.len();
+        Ok(LoudnessMeter {
+               sample_rate,
+               weights,
+               filters: vec![k_weighting(sample_rate); channels],
+               true_peaks: (0..channels).map(|_| TruePeak::new(sample_rate)).collect(),
+               sample_peak: 0.0,
+               frames: 0,
+               step_end: (sample_rate / 10.0).round() as u64,
+               steps: 0,
+               step_sum: 0.0,
+               recent: VecDeque::new(),
+               momentary_blocks: Vec::new(),
+               short_term_blocks: Vec::new(),
+           })
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    pub fn channel_weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn process(&mut self, frames: &[f32]) {
+        let channels = self.weights.len();
+        assert!(frames.len().is_multiple_of(channels), "input must contain whole frames");
+        for frame in frames.chunks(channels) {
+            let mut sum = 0.0;
+            for (i, &sample) in frame.iter().enumerate() {
+                let sample = sample as f64;
+                self.sample_peak = self.sample_peak.max(sample.abs());
+                self.true_peaks[i].process(sample);
+                let shelved = self.filters[i][0].process(sample);
+                let weighted = self.filters[i][1].process(shelved);
+                sum += self.weights[i] * weighted * weighted;
+            }
+            self.step_sum += sum;
+            self.frames += 1;
+            if self.frames == self.step_end {
+                self.end_step();
+            }
+        }
+    }
+
+    fn end_step(&mut self) {
+        let start = (self.steps as f64 * self.sample_rate / 10.0).round() as u64;
+        self.recent.push_back((self.step_sum, self.frames - start));
+        if self.recent.len() > SHORT_TERM_STEPS {
+            self.recent.pop_front();
+        }
+        self.step_sum = 0.0;
+        self.steps += 1;
+        self.step_end = ((self.steps + 1) as f64 * self.sample_rate / 10.0).round() as u64;
+        if let Some(energy) = self.block_energy(MOMENTARY_STEPS) {
+            self.momentary_blocks.push(energy);
+        }
+        if let Some(energy) = self.block_energy(SHORT_TERM_STEPS) {
+            self.short_term_blocks.push(energy);
+        }
+    }
+
+    /// Weighted mean square of the latest `steps` steps, once that many have passed.
+    fn block_energy(&self, steps: usize) -> Option<f64> {
+        if self.recent.len() < steps {
+            return None;
+        }
+        let (sum, frames) = self.recent
+            .iter()
+            .skip(self.recent.len() - steps)
+            .fold((0.0, 0), |(sum, frames), &(s, f)| (sum + s, frames + f));
+        Some(sum / frames as f64)
+    }
+
+    /// Loudness of the last 400 ms.
+    pub fn momentary(&self) -> Option<f64> {
+        self.block_energy(MOMENTARY_STEPS).map(energy_to_loudness)
+    }
+
+    /// Loudness of the last 3 s.
+    pub fn short_term(&self) -> Option<f64> {
+        self.block_energy(SHORT_TERM_STEPS).map(energy_to_loudness)
+    }
+
+    pub fn max_momentary(&self) -> Option<f64> {
+        max_loudness(&self.momentary_blocks)
+    }
+
+    pub fn max_short_term(&self) -> Option<f64> {
+        max_loudness(&self.short_term_blocks)
+    }
+
+    /// Gated loudness of everything processed, or `None` before the first 400 ms block or
+    /// when every block is below the absolute gate.
+    pub fn integrated(&self) -> Option<f64> {
+        gated_blocks(&self.momentary_blocks, INTEGRATED_RELATIVE_GATE)
+            .map(|blocks| energy_to_loudness(blocks.iter().sum::<f64>() / blocks.len() as f64))
+    }
+
+    /// The spread between the 10th and 95th percentiles of the gated short-term loudness,
+    /// per EBU Tech 3342.
+    pub fn loudness_range(&self) -> Option<f64> {
+        let mut loudness: Vec<f64> = gated_blocks(&self.short_term_blocks, RANGE_RELATIVE_GATE)?
+            .into_iter()
+            .map(energy_to_loudness)
+            .collect();
+        loudness.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+        Some(percentile(0.95) - percentile(0.10))
+    }
+
+    /// The highest true peak over all channels.
+    pub fn true_peak(&self) -> f64 {
+        gain_to_db(self.true_peaks.iter().fold(0.0, |max, p| max.max(p.peak())))
+    }
+
+    pub fn channel_true_peaks(&self) -> Vec<f64> {
+        self.true_peaks.iter().map(|p| gain_to_db(p.peak())).collect()
+    }
+
+    /// The highest sample over all channels.
+    pub fn sample_peak(&self) -> f64 {
+        gain_to_db(self.sample_peak)
+    }
+
+    pub fn report(&self) -> LoudnessReport {
+        LoudnessReport {
+            integrated: self.integrated(),
+            loudness_range: self.loudness_range(),
+            max_momentary: self.max_momentary(),
+            max_short_term: self.max_short_term(),
+            true_peak: self.true_peak(),
+            sample_peak: self.sample_peak(),
+        }
+    }
+}
+
+/// Summary of a loudness measurement. Peaks of silence are negative infinity.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LoudnessReport {
+    /// LUFS.
+    pub integrated: Option<f64>,
+    /// LU.
+    pub loudness_range: Option<f64>,
+    /// LUFS.
+    pub max_momentary: Option<f64>,
+    /// LUFS.
+    pub max_short_term: Option<f64>,
+    /// dBTP.
+    pub true_peak: f64,
+    /// dBFS.
+    pub sample_peak: f64,
+}
+
+/// Measures everything `reader` produces, weighting channels by its layout when it has one.
+pub fn measure<R: FrameReader>(mut reader: R) -> Result<LoudnessReport, String> {
+    let format = reader.format();
+    let mut meter = match reader.channel_layout() {
+        Some(ref layout) if layout.len() == format.channels as usize => {
+            LoudnessMeter::with_layout(format.sample_rate, layout)?
+        }
+        _ => LoudnessMeter::new(format.sample_rate, format.channels as usize)?,
+    };
+    let mut buffer = vec![0.0; READ_FRAMES * format.channels as usize];
+    loop {
+        let frames = reader.read_frames(&mut buffer)?;
+        if frames == 0 {
+            return Ok(meter.report());
+        }
+        meter.process(&buffer[..frames * format.channels as usize]);
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicU32, AtomicUsize, Ordering};
+
+use loudness::TruePeak;
+
+const DEFAULT_INTEGRATION_TIME: f64 = 0.3;
+const DEFAULT_PEAK_HOLD: f64 = 1.5;
+/// Scales the rectified average of a sine to its RMS, as VU meters are calibrated.
+const VU_SCALE: f64 = 1.1107207345395915;
+/// The values published for each channel.
+const VALUES: usize = 5;
+
+/// How a meter's needle or bar follows the signal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Ballistics {
+    /// IEC 60268-17: rises to 99% of a steady tone in 300 ms and falls as fast, reading the
+    /// RMS of a sine. Approximated to first order, without the overshoot.
+    Vu,
+    /// IEC 60268-10 type I, the DIN meter: a 5 ms step reads 2 dB low and the reading falls
+    /// 20 dB in 1.5 s.
+    PpmType1,
+    /// IEC 60268-10 type II, the BBC meter: a 10 ms step reads 2 dB low and the reading falls
+    /// 24 dB in 2.8 s.
+    PpmType2,
+}
+
+impl Ballistics {
+    /// The per-frame coefficient for rising, and the factor by which a peak meter falls per
+    /// frame.
+    fn coefficients(&self, sample_rate: f64) -> (f64, f64) {
+        let (rise, reached, fall, seconds) = match *self {
+            Ballistics::Vu => (0.3, 0.99, 0.0, 0.0),
+            Ballistics::PpmType1 => (0.005, 10f64.powf(-2.0 / 20.0), 20.0, 1.5),
+            Ballistics::PpmType2 => (0.010, 10f64.powf(-2.0 / 20.0), 24.0, 2.8),
+        };
+        let time_constant = rise / -(1.0 - reached).ln();
+        let attack = 1.0 - (-1.0 / (time_constant * sample_rate)).exp();
+        let release = if seconds > 0.0 {
+            10f64.powf(-fall / 20.0 / (seconds * sample_rate))
+        } else {
+            1.0
+        };
+        (attack, release)
+    }
+}
+
+/// The levels of one channel as linear amplitudes, where full scale is one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ChannelLevels {
+    /// The highest sample in the latest block.
+    pub peak: f32,
+    /// The highest sample over the last hold time.
+    pub peak_hold: f32,
+    pub rms: f32,
+    /// The highest peak in the latest block, including those between samples.
+    pub true_peak: f32,
+    /// The reading of a meter with the chosen ballistics.
+    pub ballistic: f32,
+}
+
+const SILENCE: ChannelLevels = ChannelLevels {
+    peak: 0.0,
+    peak_hold: 0.0,
+    rms: 0.0,
+    true_peak: 0.0,
+    ballistic: 0.0,
+};
+
+impl ChannelLevels {
+    /// The levels as AudioQueue reports them, with the RMS as the average power.
+    pub fn level_meter_state(&self) -> LevelMeterState {
+        LevelMeterState {
+            average_power: self.rms,
+            peak_power: self.peak,
+        }
+    }
+}
+
+/// One channel's levels laid out like AudioQueue's `AudioQueueLevelMeterState`: linear for
+/// `kAudioQueueProperty_CurrentLevelMeter`, in dB for
+/// `kAudioQueueProperty_CurrentLevelMeterDB`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LevelMeterState {
+    pub average_power: f32,
+    pub peak_power: f32,
+}
+
+impl LevelMeterState {
+    /// Converts linear levels to dB, with silence as negative infinity.
+    pub fn to_db(&self) -> LevelMeterState {
+        LevelMeterState {
+            average_power: 20.0 * self.average_power.log10(),
+            peak_power: 20.0 * self.peak_power.log10(),
+        }
+    }
+}
+
+/// Readings a meter shares with other threads.
+struct Shared {
+    /// Odd while a block's readings are being written.
+    sequence: AtomicUsize,
+    /// The bits of each channel's levels, in `ChannelLevels` order.
+    values: Vec<AtomicU32>,
+}
+
+/// The state of one channel between blocks.
+struct ChannelState {
+    mean_square: f64,
+    /// The frame and magnitude of each sample in the hold time louder than all since, oldest
+    /// and so loudest first.
+    peaks: VecDeque<(u64, f32)>,
+    /// Frames processed.
+    frames: u64,
+    ballistic: f64,
+    true_peak: TruePeak,
+}
+
+/// Measures interleaved frames as they pass, for level meters.
+///
+/// Processing allocates nothing and takes no locks, so it can run in an audio callback.
+/// After each block the readings are published for `MeterReadings` handles, which other
+/// threads read without blocking the audio.
+pub struct LevelMeter {
+    sample_rate: f64,
+    channels: usize,
+    /// Per-frame coefficient of the mean square's exponential average.
+    rms_coefficient: f64,
+    hold_frames: u64,
+    ballistics: Ballistics,
+    attack: f64,
+    release: f64,
+    states: Vec<ChannelState>,
+    latest: Vec<ChannelLevels>,
+    shared: Arc<Shared>,
+}
+
+impl LevelMeter {
+    /// A meter integrating RMS over 300 ms, holding peaks for 1.5 s, with VU ballistics.
+    pub fn new(sample_rate: f64, channels: usize) -> Result<LevelMeter, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {} Hz", sample_rate));
+        }
+        if channels == 0 {
+            return Err("level meter needs at least one channel".to_owned());
+        }
+        let (attack, release) = Ballistics::Vu.coefficients(sample_rate);
+        let hold_frames = (DEFAULT_PEAK_HOLD * sample_rate).round() as u64;
+        Ok(LevelMeter {
+               sample_rate,
+               channels,
+               rms_coefficient: time_coefficient(DEFAULT_INTEGRATION_TIME, sample_rate),
+               hold_frames,
+               ballistics: Ballistics::Vu,
+               attack,
+               release,
+               states: (0..channels)
+                   .map(|_| {
+                            ChannelState {
+                                mean_square: 0.0,
+                                peaks: VecDeque::with_capacity(hold_frames as usize + 1),
+                                frames: 0,
+                                ballistic: 0.0,
+                                true_peak: TruePeak::new(sample_rate),
+                            }
+                        })
+                   .collect(),
+               latest: vec![SILENCE; channels],
+               shared: Arc::new(Shared {
+                                    sequence: AtomicUsize::new(0),
+                                    values: (0..channels * VALUES)
+                                        .map(|_| AtomicU32::new(0))
+                                        .collect(),
+                                }),
+           })
+    }
+
+    /// Sets the time constant in seconds over which the RMS is averaged.
+    pub fn with_integration_time(mut self, seconds: f64) -> LevelMeter {
+        self.rms_coefficient = time_coefficient(seconds, self.sample_rate);
+        self
+    }
+
+    /// Sets how many seconds the peak hold keeps a peak.
+    pub fn with_peak_hold(mut self, seconds: f64) -> LevelMeter {
+        self.hold_frames = (seconds.max(0.0) * self.sample_rate).round() as u64;
+        for state in &mut self.states {
+            state.peaks = VecDeque::with_capacity(self.hold_frames as usize + 1);
+        }
+        self
+    }
+
+    pub fn with_ballistics(mut self, ballistics: Ballistics) -> LevelMeter {
+        let (attack, release) = ballistics.coefficients(self.sample_rate);
+        self.ballistics = ballistics;
+        self.attack = attack;
+        self.release = release;
+        self
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn ballistics(&self) -> Ballistics {
+        self.ballistics
+    }
+
+    /// A handle through which any thread can read the latest levels.
+    pub fn readings(&self) -> MeterReadings {
+        MeterReadings {
+            shared: self.shared.clone(),
+            channels: self.channels,
+        }
+    }
+
+    /// The levels after the latest block.
+    pub fn levels(&self) -> &[ChannelLevels] {
+        &self.latest
+    }
+
+    pub fn process(&mut self, frames: &[f32]) {
+        let channels = self.channels;
+        assert!(frames.len().is_multiple_of(channels), "input must contain whole frames");
+        if frames.is_empty() {
+            return;
+        }
+        for (channel, (state, levels)) in self.states
+            .iter_mut()
+            .zip(self.latest.iter_mut())
+            .enumerate() {
+            let (mut peak, mut true_peak) = (0.0, 0.0);
+            for &sample in frames[channel..].iter().step_by(channels) {
+                let magnitude = sample.abs();
+                peak = magnitude.max(peak);
+                let x = sample as f64;
+                state.mean_square += (x * x - state.mean_square) * self.rms_coefficient;
+                true_peak = state.true_peak.process(x).max(true_peak);
+
+                // Quieter samples before this one can no longer be held, and the loudest
+                // leaves once its hold is over.
+                state.frames += 1;
+                while state.peaks.back().is_some_and(|&(_, held)| held <= magnitude) {
+                    state.peaks.pop_back();
+                }
+                state.peaks.push_back((state.frames, magnitude));
+                while state.frames - state.peaks[0].0 > self.hold_frames {
+                    state.peaks.pop_front();
+                }
+
+                let magnitude = magnitude as f64;
+                state.ballistic = match self.ballistics {
+                    Ballistics::Vu => {
+                        state.ballistic + (magnitude * VU_SCALE - state.ballistic) * self.attack
+                    }
+                    _ if magnitude > state.ballistic => {
+                        state.ballistic + (magnitude - state.ballistic) * self.attack
+                    }
+                    _ => state.ballistic * self.release,
+                };
+            }
+            *levels = ChannelLevels {
+                peak,
+                peak_hold: state.peaks.front().map_or(0.0, |&(_, held)| held),
+                rms: state.mean_square.sqrt() as f32,
+                true_peak: true_peak as f32,
+                ballistic: state.ballistic as f32,
+            };
+        }
+        self.publish();
+    }
+
+    /// Returns to silence.
+    pub fn reset(&mut self) {
+        for (state, levels) in self.states.iter_mut().zip(self.latest.iter_mut()) {
+            state.mean_square = 0.0;
+            state.peaks.clear();
+            state.frames = 0;
+            state.ballistic = 0.0;
+            state.true_peak.reset();
+            *levels = SILENCE;
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        // A sequence lock: readers retry if the sequence was odd or changed while they read.
+        let shared = &*self.shared;
+        let sequence = shared.sequence.load(Ordering::Relaxed);
+        shared.sequence.store(sequence.wrapping_add(1), Ordering::Relaxed);
+        atomic::fence(Ordering::Release);
+        for (levels, values) in self.latest.iter().zip(shared.values.chunks(VALUES)) {
+            let bits = [levels.peak, levels.peak_hold, levels.rms, levels.true_peak,
+                        levels.ballistic];
+            for (value, level) in values.iter().zip(bits.iter()) {
+                value.store(level.to_bits(), Ordering::Relaxed);
+            }
+        }
+        shared.sequence.store(sequence.wrapping_add(2), Ordering::Release);
+    }
+}
+
+/// The per-frame coefficient of an exponential average with a time constant in seconds.
+fn time_coefficient(seconds: f64, sample_rate: f64) -> f64 {
+    if seconds > 0.0 {
+        1.0 - (-1.0 / (seconds * sample_rate)).exp()
+    } else {
+        1.0
+    }
+}
+
+/// Reads the levels a `LevelMeter` publishes, from any thread.
+#[derive(Clone)]
+pub struct MeterReadings {
+    shared: Arc<Shared>,
+    channels: usize,
+}
+
+impl MeterReadings {
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Fills `levels` with the levels of as many channels as it holds, all from the same
+    /// block.
+    pub fn read(&self, levels: &mut [ChannelLevels]) {
+        let shared = &*self.shared;
+        loop {
+            let sequence = shared.sequence.load(Ordering::Acquire);
+            if sequence % 2 == 1 {
+                continue;
+            }
+            for (levels, values) in levels.iter_mut().zip(shared.values.chunks(VALUES)) {
+                let value = |i: usize| f32::from_bits(values[i].load(Ordering::Relaxed));
+                *levels = ChannelLevels {
+                    peak: value(0),
+                    peak_hold: value(1),
+                    rms: value(2),
+                    true_peak: value(3),
+                    ballistic: value(4),
+                };
+            }
+            atomic::fence(Ordering::Acquire);
+            if shared.sequence.load(Ordering::Relaxed) == sequence {
+                return;
+            }
+        }
+    }
+
+    pub fn levels(&self) -> Vec<ChannelLevels> {
+        let mut levels = vec![SILENCE; self.channels];
+        self.read(&mut levels);
+        levels
+    }
+
+    /// Linear levels per channel, as AudioQueue's `kAudioQueueProperty_CurrentLevelMeter`
+    /// gives them.
+    pub fn current_level_meter(&self) -> Vec<LevelMeterState> {
+        self.levels().iter().map(ChannelLevels::level_meter_state).collect()
+    }
+
+    /// Levels per channel in dB, as `kAudioQueueProperty_CurrentLevelMeterDB` gives them.
+    pub fn current_level_meter_db(&self) -> Vec<LevelMeterState> {
+        self.current_level_meter().iter().map(LevelMeterState::to_db).collect()
+    }
+}
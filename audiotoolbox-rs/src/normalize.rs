@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+
+use channel_map::ChannelLayout;
+use frame_reader::FrameReader;
+use loudness::{LoudnessReport, TruePeak};
+use stream_format::StreamFormat;
+
+const DEFAULT_LOOKAHEAD: f64 = 0.005;
+const DEFAULT_RELEASE: f64 = 0.1;
+
+/// The level to normalise to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Target {
+    /// Integrated loudness in LUFS.
+    Loudness(f64),
+    /// True peak in dBTP.
+    Peak(f64),
+}
+
+impl Target {
+    /// The gain in dB that brings a source with the given measurement to the target, or
+    /// `None` when the source is silent.
+    pub fn gain(&self, report: &LoudnessReport) -> Option<f64> {
+        match *self {
+            Target::Loudness(target) => report.integrated.map(|loudness| target - loudness),
+            Target::Peak(target) if report.true_peak.is_finite() => {
+                Some(target - report.true_peak)
+            }
+            Target::Peak(_) => None,
+        }
+    }
+}
+
+/// A look-ahead limiter that keeps the true peak of interleaved frames under a ceiling.
+///
+/// The gain is the same across channels. It falls smoothly over the look-ahead time before
+/// each peak and recovers over the release time. Processing is in place and delays the
+/// audio by `latency()` frames; nothing is allocated after construction.
+pub struct Limiter {
+    sample_rate: f64,
+    channels: usize,
+    /// Linear.
+    ceiling: f64,
+    /// Seconds.
+    lookahead: f64,
+    release: f64,
+    lookahead_frames: usize,
+    release_coefficient: f64,
+    detectors: Vec<TruePeak>,
+    /// The delayed samples, `latency()` frames of them.
+    delay: Vec<f32>,
+    delay_position: usize,
+    /// The frame count and gain needed of the frames in the minimum window, increasing.
+    minimum: VecDeque<(u64, f64)>,
+    frames: u64,
+    envelope: f64,
+    /// The latest `lookahead_frames` envelope values and their sum.
+    recent: Vec<f64>,
+    recent_position: usize,
+    recent_sum: f64,
+    /// The least gain applied so far.
+    deepest: f64,
+}
+
+impl Limiter {
+    /// A limiter with a ceiling in dBTP, looking 5 ms ahead and releasing over 100 ms.
+    pub fn new(sample_rate: f64, channels: usize, ceiling: f64) -> Result<Limiter, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {} Hz", sample_rate));
+        }
+        if channels == 0 {
+            return Err("limiter needs at least one channel".to_owned());
+        }
+        if !ceiling.is_finite() {
+            return Err(format!("invalid ceiling {} dBTP", ceiling));
+        }
+        let detectors = (0..channels).map(|_| TruePeak::new(sample_rate)).collect();
+        Ok(Limiter::with_detectors(sample_rate,
+                                   10f64.powf(ceiling / 20.0),
+                                   DEFAULT_LOOKAHEAD,
+                                   DEFAULT_RELEASE,
+                                   detectors))
+    }
+
+    fn with_detectors(sample_rate: f64,
+                      ceiling: f64,
+                      lookahead: f64,
+                      release: f64,
+                      detectors: Vec<TruePeak>)
+                      -> Limiter {
+        let channels = detectors.len();
+        let lookahead_frames = ((lookahead.max(0.0) * sample_rate).round() as usize).max(1);
+        let latency = lookahead_frames - 1 + detectors[0].latency();
+        let mut limiter = Limiter {
+            sample_rate,
+            channels,
+            ceiling,
+            lookahead,
+            release: release.max(0.0),
+            lookahead_frames,
+            release_coefficient: release_coefficient(release, sample_rate),
+            detectors,
+            delay: vec![0.0; latency * channels],
+            delay_position: 0,
+            minimum: VecDeque::with_capacity(latency + 2),
+            frames: 0,
+            envelope: 1.0,
+            recent: vec![1.0; lookahead_frames],
+            recent_position: 0,
+            recent_sum: 0.0,
+            deepest: 1.0,
+        };
+        limiter.reset();
+        limiter
+    }
+
+    /// Sets how long before a peak the gain starts to fall, in seconds.
+    pub fn with_lookahead(self, lookahead: f64) -> Limiter {
+        Limiter::with_detectors(self.sample_rate,
+                                self.ceiling,
+                                lookahead,
+                                self.release,
+                                self.detectors)
+    }
+
+    /// Sets the time constant in seconds over which the gain recovers after a peak.
+    pub fn with_release(self, release: f64) -> Limiter {
+        Limiter::with_detectors(self.sample_rate,
+                                self.ceiling,
+                                self.lookahead,
+                                release,
+                                self.detectors)
+    }
+
+    /// Frames by which the output lags the input.
+    pub fn latency(&self) -> usize {
+        self.delay.len() / self.channels
+    }
+
+    /// The most the gain has been reduced since the limiter was made or reset, in dB.
+    pub fn max_gain_reduction(&self) -> f64 {
+        -20.0 * self.deepest.log10()
+    }
+
+    /// Forgets all input, as after a seek.
+    pub fn reset(&mut self) {
+        for detector in self.detectors.iter_mut() {
+            detector.reset();
+        }
+        for sample in self.delay.iter_mut() {
+            *sample = 0.0;
+        }
+        self.delay_position = 0;
+        self.minimum.clear();
+        self.frames = 0;
+        self.envelope = 1.0;
+        for gain in self.recent.iter_mut() {
+            *gain = 1.0;
+        }
+        self.recent_position = 0;
+        self.recent_sum = self.lookahead_frames as f64;
+        self.deepest = 1.0;
+    }
+
+    /// Limits whole interleaved frames in place, replacing them with the frames `latency()`
+    /// earlier.
+    pub fn process(&mut self, frames: &mut [f32]) {
+        let channels = self.channels;
+        assert!(frames.len().is_multiple_of(channels), "input must contain whole frames");
+        // A detected peak may belong to any of the last `detector latency + 1` frames, so
+        // the gain each frame needs is the least over a window that much longer than the
+        // look-ahead.
+        let window = self.latency() as u64 + 1;
+        for frame in frames.chunks_mut(channels) {
+            let mut peak = 0.0;
+            for (detector, &sample) in self.detectors.iter_mut().zip(frame.iter()) {
+                peak = detector.process(sample as f64).max(peak);
+            }
+            let needed = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+            while self.minimum.back().is_some_and(|&(_, gain)| gain >= needed) {
+                self.minimum.pop_back();
+            }
+            self.minimum.push_back((self.frames, needed));
+            let oldest = (self.frames + 1).saturating_sub(window);
+            while self.minimum.front().is_some_and(|&(frame, _)| frame < oldest) {
+                self.minimum.pop_front();
+            }
+            let least = self.minimum.front().map_or(1.0, |&(_, gain)| gain);
+            self.frames += 1;
+
+            let released = self.envelope + (1.0 - self.envelope) * self.release_coefficient;
+            self.envelope = released.min(least);
+            // Averaging over the look-ahead turns steps in gain into ramps that finish just
+            // as the frame that needed them comes out.
+            self.recent_sum += self.envelope - self.recent[self.recent_position];
+            self.recent[self.recent_position] = self.envelope;
+            self.recent_position += 1;
+            if self.recent_position == self.lookahead_frames {
+                self.recent_position = 0;
+                self.recent_sum = self.recent.iter().sum();
+            }
+            let gain = (self.recent_sum / self.lookahead_frames as f64).min(1.0);
+            self.deepest = self.deepest.min(gain);
+
+            let delayed = &mut self.delay[self.delay_position..self.delay_position + channels];
+            for (sample, old) in frame.iter_mut().zip(delayed.iter_mut()) {
+                let input = *sample;
+                *sample = (*old as f64 * gain) as f32;
+                *old = input;
+            }
+            self.delay_position = (self.delay_position + channels) % self.delay.len();
+        }
+    }
+}
+
+/// The per-frame factor by which the distance from unity gain shrinks, for a recovery of
+/// about 63% in `seconds`.
+fn release_coefficient(seconds: f64, sample_rate: f64) -> f64 {
+    if seconds > 0.0 {
+        1.0 - (-1.0 / (seconds * sample_rate)).exp()
+    } else {
+        1.0
+    }
+}
+
+/// Applies a gain and optionally a limiter to the frames of another reader, hiding the
+/// limiter's latency, so that the output lines up with the input frame for frame.
+pub struct NormalizingReader<R> {
+    reader: R,
+    gain: f32,
+    limiter: Option<Limiter>,
+    /// Frames of the limiter's initial delay still to drop.
+    skip: usize,
+    /// Frames of silence still to push through the limiter once the source is exhausted.
+    tail: usize,
+}
+
+impl<R: FrameReader> NormalizingReader<R> {
+    /// Applies `gain` in dB, then `limiter`.
+    pub fn new(reader: R,
+               gain: f64,
+               limiter: Option<Limiter>)
+               -> Result<NormalizingReader<R>, String> {
+        let channels = reader.format().channels as usize;
+        if limiter.as_ref().is_some_and(|limiter| limiter.channels != channels) {
+            return Err(format!("limiter does not have the reader's {} channels", channels));
+        }
+        let latency = limiter.as_ref().map_or(0, |limiter| limiter.latency());
+        Ok(NormalizingReader {
+               reader,
+               gain: 10f64.powf(gain / 20.0) as f32,
+               limiter,
+               skip: latency,
+               tail: latency,
+           })
+    }
+
+    pub fn limiter(&self) -> Option<&Limiter> {
+        self.limiter.as_ref()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: FrameReader> FrameReader for NormalizingReader<R> {
+    fn format(&self) -> StreamFormat {
+        self.reader.format()
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.reader.channel_layout()
+    }
+
+    fn length(&self) -> Option<u64> {
+        self.reader.length()
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), String> {
+        self.reader.seek(frame)?;
+        if let Some(ref mut limiter) = self.limiter {
+            limiter.reset();
+            self.skip = limiter.latency();
+            self.tail = limiter.latency();
+        }
+        Ok(())
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.reader.format().channels as usize;
+        let wanted = buffer.len() / channels;
+        if wanted == 0 {
+            return Ok(0);
+        }
+        loop {
+            let mut frames = self.reader.read_frames(&mut buffer[..wanted * channels])?;
+            if frames == 0 {
+                frames = self.tail.min(wanted);
+                if frames == 0 {
+                    return Ok(0);
+                }
+                self.tail -= frames;
+                for sample in buffer[..frames * channels].iter_mut() {
+                    *sample = 0.0;
+                }
+            } else if self.gain != 1.0 {
+                for sample in buffer[..frames * channels].iter_mut() {
+                    *sample *= self.gain;
+                }
+            }
+            let limiter = match self.limiter {
+                Some(ref mut limiter) => limiter,
+                None => return Ok(frames),
+            };
+            limiter.process(&mut buffer[..frames * channels]);
+            let skip = self.skip.min(frames);
+            self.skip -= skip;
+            if skip < frames {
+                for i in skip * channels..frames * channels {
+                    buffer[i - skip * channels] = buffer[i];
+                }
+                return Ok(frames - skip);
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::f64::consts::PI;
+
+use kaiser::bessel_i0;
+
+/// Kernel samples tabulated per zero crossing of the sinc.
+const TABLE_RESOLUTION: usize = 512;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// 8 zero crossings, 60 dB stopband.
+    Fast,
+    /// 24 zero crossings, 90 dB stopband.
+    Medium,
+    /// 64 zero crossings, 120 dB stopband.
+    Best,
+}
+
+impl Quality {
+    fn zero_crossings(&self) -> usize {
+        match *self {
+            Quality::Fast => 8,
+            Quality::Medium => 24,
+            Quality::Best => 64,
+        }
+    }
+
+    fn attenuation_db(&self) -> f64 {
+        match *self {
+            Quality::Fast => 60.0,
+            Quality::Medium => 90.0,
+            Quality::Best => 120.0,
+        }
+    }
+}
+
+/// Band-limited sample-rate converter using a Kaiser-windowed sinc kernel.
+///
+/// Input and output are interleaved f32 frames. Chunks of any size may be pushed through
+/// `process`; `flush` emits the tail once the input has ended. Output is time aligned with
+/// the input, so `n` input frames produce `ceil(n * output_rate / input_rate)` frames.
+pub struct Resampler {
+    channels: usize,
+    input_rate: f64,
+    output_rate: f64,
+    /// Kernel bandwidth relative to the input Nyquist frequency.
+    scale: f64,
+    /// Kernel reach on either side of an output frame, in input frames.
+    half_width: f64,
+    table: Vec<f64>,
+    weights: Vec<f64>,
+    history: Vec<f32>,
+    history_start: u64,
+    frames_in: u64,
+    frames_out: u64,
+}
+
+impl Resampler {
+    pub fn new(channels: usize,
+               input_rate: f64,
+               output_rate: f64,
+               quality: Quality)
+               -> Result<Resampler, String> {
+        if channels == 0 {
+            return Err("resampler needs at least one channel".to_owned());
+        }
+        if !(input_rate > 0.0 && output_rate > 0.0) {
+            return Err(format!("invalid rates {} Hz -> {} Hz", input_rate, output_rate));
+        }
+
+        // Place the Kaiser transition band just below the lower Nyquist frequency. Its width
+        // is the main lobe of the window, which ends where the stopband starts; Kaiser's
+        // estimate of beta leaves the first sidelobe about a decibel short, so aim above it.
+        let zero_crossings = quality.zero_crossings();
+        let beta = 0.1102 * (quality.attenuation_db() + 1.0 - 8.7);
+        let transition = (beta * beta + PI * PI).sqrt() / (PI * zero_crossings as f64);
+        let cutoff = 1.0 / (1.0 + transition);
+        let scale = cutoff * (output_rate / input_rate).min(1.0);
+
+        let length = zero_crossings * TABLE_RESOLUTION;
+        let i0_beta = bessel_i0(beta);
+        let mut table: Vec<f64> = (0..length)
+            .map(|i| {
+                let x = i as f64 / TABLE_RESOLUTION as f64;
+                let sinc = if i == 0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let r = x / zero_crossings as f64;
+                sinc * bessel_i0(beta * (1.0 - r * r).sqrt()) / i0_beta
+            })
+            .collect();
+        table.push(0.0);
+
+        Ok(Resampler {
+               channels,
+               input_rate,
+               output_rate,
+               scale,
+               half_width: zero_crossings as f64 / scale,
+               table,
+               weights: Vec::new(),
+               history: Vec::new(),
+               history_start: 0,
+               frames_in: 0,
+               frames_out: 0,
+           })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn input_rate(&self) -> f64 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    /// Number of output frames `input_frames` of input turn into once flushed.
+    pub fn output_frames_for(&self, input_frames: u64) -> u64 {
+        (input_frames as f64 * self.output_rate / self.input_rate).ceil() as u64
+    }
+
+    /// Consumes interleaved input frames and appends every output frame that no longer
+    /// depends on future input to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        assert!(input.len().is_multiple_of(self.channels),
+                "input must contain whole frames");
+        self.history.extend_from_slice(input);
+        self.frames_in += (input.len() / self.channels) as u64;
+
+        loop {
+            let time = self.output_time(self.frames_out);
+            if (time + self.half_width).floor() >= self.frames_in as f64 {
+                break;
+            }
+            self.render(time, output);
+            self.frames_out += 1;
+        }
+        self.discard_history();
+    }
+
+    /// Treats the input as ended and emits the remaining output frames.
+    pub fn flush(&mut self, output: &mut Vec<f32>) {
+        let total = self.output_frames_for(self.frames_in);
+        while self.frames_out < total {
+            let time = self.output_time(self.frames_out);
+            self.render(time, output);
+            self.frames_out += 1;
+        }
+        self.reset();
+    }
+
+    /// Forgets all buffered input so the resampler can start a new stream.
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.history_start = 0;
+        self.frames_in = 0;
+        self.frames_out = 0;
+    }
+
+    fn output_time(&self, frame: u64) -> f64 {
+        frame as f64 * self.input_rate / self.output_rate
+    }
+
+    fn kernel(&self, distance: f64) -> f64 {
+        let position = (distance * self.scale).abs() * TABLE_RESOLUTION as f64;
+        let index = position as usize;
+        if index + 1 >= self.table.len() {
+            return 0.0;
+        }
+        let fraction = position - index as f64;
+        self.table[index] + (self.table[index + 1] - self.table[index]) * fraction
+    }
+
+    fn render(&mut self, time: f64, output: &mut Vec<f32>) {
+        // Frames before the start and after the end of the input are silent.
+        let first = (time - self.half_width).ceil().max(0.0) as u64;
+        let end = ((time + self.half_width).floor() as u64 + 1).min(self.frames_in);
+        let mut weights = ::std::mem::take(&mut self.weights);
+        weights.clear();
+        for k in first..end {
+            weights.push(self.kernel(time - k as f64) * self.scale);
+        }
+        for channel in 0..self.channels {
+            let mut acc = 0.0;
+            for (i, weight) in weights.iter().enumerate() {
+                let frame = (first + i as u64 - self.history_start) as usize;
+                acc += self.history[frame * self.channels + channel] as f64 * weight;
+            }
+            output.push(acc as f32);
+        }
+        self.weights = weights;
+    }
+
+    fn discard_history(&mut self) {
+        let next = self.output_time(self.frames_out);
+        let keep_from = (next - self.half_width).ceil().max(0.0) as u64;
+        if keep_from > self.history_start {
+            let drop = ((keep_from - self.history_start) as usize)
+                .min(self.history.len() / self.channels);
+            self.history.drain(..drop * self.channels);
+            self.history_start += drop as u64;
+        }
+    }
+}
+
+/// Resamples a complete interleaved signal in one call.
+pub fn resample(input: &[f32],
+                channels: usize,
+                input_rate: f64,
+                output_rate: f64,
+                quality: Quality)
+                -> Result<Vec<f32>, String> {
+    let mut resampler = Resampler::new(channels, input_rate, output_rate, quality)?;
+    if !input.len().is_multiple_of(channels) {
+        return Err("input must contain whole frames".to_owned());
+    }
+    let frames = resampler.output_frames_for((input.len() / channels) as u64) as usize;
+    let mut output = Vec::with_capacity(frames * channels);
+    resampler.process(input, &mut output);
+    resampler.flush(&mut output);
+    Ok(output)
+}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use frame_reader::FrameReader;
+
+const READ_FRAMES: usize = 4096;
+/// The level of a frame is the highest peak over this many seconds up to it, so that
+/// waveforms passing through zero do not look silent.
+const LEVEL_WINDOW: f64 = 0.02;
+const DEFAULT_HOLD: f64 = 0.5;
+const DEFAULT_HYSTERESIS: f64 = 3.0;
+
+/// Finds the stretches of interleaved frames that stay quiet for at least a hold time.
+///
+/// Audio falls silent when its level drops below the threshold and becomes sound again only
+/// once it rises above the threshold plus the hysteresis, so that noise hovering around the
+/// threshold does not break a silence up. Levels are peaks across all channels.
+pub struct SilenceDetector {
+    channels: usize,
+    sample_rate: f64,
+    /// Linear levels for entering and leaving silence.
+    enter: f32,
+    leave: f32,
+    hold: u64,
+    window: u64,
+    /// Frame index and peak of the frames that can still be the loudest in the window,
+    /// loudest first.
+    peaks: VecDeque<(u64, f32)>,
+    frames: u64,
+    /// Where the current quiet stretch began, while in one.
+    quiet_since: Option<u64>,
+    regions: Vec<Range<u64>>,
+}
+
+impl SilenceDetector {
+    /// A detector with a threshold in dBFS, holding for half a second with 3 dB of
+    /// hysteresis.
+    pub fn new(sample_rate: f64,
+               channels: usize,
+               threshold: f64)
+               -> Result<SilenceDetector, String> {
+        if !(sample_rate > 0.0 && sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {} Hz", sample_rate));
+        }
+        if channels == 0 {
+            return Err("silence detector needs at least one channel".to_owned());
+        }
+        if threshold.is_nan() {
+            return Err("invalid silence threshold".to_owned());
+        }
+        let enter = 10f64.powf(threshold / 20.0);
+        let window = ((LEVEL_WINDOW * sample_rate).round() as u64).max(1);
+        Ok(SilenceDetector {
+               channels,
+               sample_rate,
+               enter: enter as f32,
+               leave: (enter * 10f64.powf(DEFAULT_HYSTERESIS / 20.0)) as f32,
+               hold: (DEFAULT_HOLD * sample_rate).round() as u64,
+               window,
+               peaks: VecDeque::with_capacity(window as usize + 1),
+               frames: 0,
+               quiet_since: None,
+               regions: Vec::new(),
+           })
+    }
+
+    /// Sets how many seconds audio must stay quiet to count as silence.
+    pub fn with_hold(mut self, hold: f64) -> SilenceDetector {
+        self.hold = (hold.max(0.0) * self.sample_rate).round() as u64;
+        self
+    }
+
+    /// Sets how many dB above the threshold audio must rise to end a silence.
+    pub fn with_hysteresis(mut self, hysteresis: f64) -> SilenceDetector {
+        self.leave = self.enter * 10f32.powf(hysteresis.max(0.0) as f32 / 20.0);
+        self
+    }
+
+    pub fn process(&mut self, frames: &[f32]) {
+        let channels = self.channels;
+        assert!(frames.len().is_multiple_of(channels), "input must contain whole frames");
+        for frame in frames.chunks(channels) {
+            let peak = frame.iter().fold(0.0, |max: f32, &sample| max.max(sample.abs()));
+            while self.peaks.back().is_some_and(|&(_, loudest)| loudest <= peak) {
+                self.peaks.pop_back();
+            }
+            self.peaks.push_back((self.frames, peak));
+            let oldest = (self.frames + 1).saturating_sub(self.window);
+            while self.peaks.front().is_some_and(|&(frame, _)| frame < oldest) {
+                self.peaks.pop_front();
+            }
+            let level = self.peaks.front().map_or(0.0, |&(_, peak)| peak);
+            match self.quiet_since {
+                // The whole window is quiet, so the silence began where it did.
+                None if level < self.enter => self.quiet_since = Some(oldest),
+                Some(start) if level > self.leave => {
+                    if self.frames - start >= self.hold {
+                        self.regions.push(start..self.frames);
+                    }
+                    self.quiet_since = None;
+                }
+                _ => {}
+            }
+            self.frames += 1;
+        }
+    }
+
+    /// Whether the latest frames have been quiet for at least the hold time.
+    pub fn is_silent(&self) -> bool {
+        self.quiet_since.is_some_and(|start| self.frames - start >= self.hold)
+    }
+
+    /// The silent regions that have ended so far, as frame ranges.
+    pub fn regions(&self) -> &[Range<u64>] {
+        &self.regions
+    }
+
+    /// Ends any silence still going on at the end of the input.
+    pub fn finish(mut self) -> Silence {
+        if self.is_silent() {
+            let start = self.quiet_since.unwrap();
+            self.regions.push(start..self.frames);
+        }
+        Silence {
+            regions: self.regions,
+            length: self.frames,
+        }
+    }
+}
+
+/// The silent regions of a whole source, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Silence {
+    pub regions: Vec<Range<u64>>,
+    /// Frames in the source.
+    pub length: u64,
+}
+
+impl Silence {
+    /// The stretches of sound between silent regions, each keeping up to `pad` frames of
+    /// the silence on either side. Silences between two sounds are shared between them.
+    pub fn segments(&self, pad: u64) -> Vec<Range<u64>> {
+        let mut sounds = Vec::new();
+        let mut position = 0;
+        for region in &self.regions {
+            if region.start > position {
+                sounds.push(position..region.start);
+            }
+            position = region.end;
+        }
+        if self.length > position {
+            sounds.push(position..self.length);
+        }
+        sounds.into_iter()
+            .map(|sound| {
+                let before = match self.regions.iter().find(|r| r.end == sound.start) {
+                    Some(r) if r.start == 0 => r.end - r.start,
+                    Some(r) => (r.end - r.start) / 2,
+                    None => 0,
+                };
+                let after = match self.regions.iter().find(|r| r.start == sound.end) {
+                    Some(r) if r.end == self.length => r.end - r.start,
+                    Some(r) => (r.end - r.start).div_ceil(2),
+                    None => 0,
+                };
+                sound.start - before.min(pad)..sound.end + after.min(pad)
+            })
+            .collect()
+    }
+
+    /// The frames between the leading and trailing silence, keeping up to `pad` frames of
+    /// each. Empty when the source is all silence.
+    pub fn trimmed(&self, pad: u64) -> Range<u64> {
+        let segments = self.segments(pad);
+        match (segments.first(), segments.last()) {
+            (Some(first), Some(last)) => first.start..last.end,
+            _ => 0..0,
+        }
+    }
+}
+
+/// Runs everything `reader` produces through `detector`.
+pub fn detect<R: FrameReader>(mut reader: R,
+                              mut detector: SilenceDetector)
+                              -> Result<Silence, String> {
+    let channels = reader.format().channels as usize;
+    if channels != detector.channels {
+        return Err(format!("silence detector does not have the reader's {} channels",
+                           channels));
+    }
+    let mut buffer = vec![0.0; READ_FRAMES * channels];
+    loop {
+        let frames = reader.read_frames(&mut buffer)?;
+        if frames == 0 {
+            return Ok(detector.finish());
+        }
+        detector.process(&buffer[..frames * channels]);
+    }
+}
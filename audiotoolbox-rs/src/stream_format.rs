@@ -0,0 +1,310 @@
+use std::fmt;
+
+#[cfg(feature = "coreaudio")]
+use audiotoolbox_sys::*;
+
+use file_type::fourcc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+    I8,
+    I16,
+    /// Signed 24-bit integers packed into three bytes.
+    I24,
+    I32,
+    F32,
+    F64,
+}
+
+impl SampleFormat {
+    pub fn bytes_per_sample(&self) -> usize {
+        match *self {
+            SampleFormat::I8 => 1,
+            SampleFormat::I16 => 2,
+            SampleFormat::I24 => 3,
+            SampleFormat::I32 | SampleFormat::F32 => 4,
+            SampleFormat::F64 => 8,
+        }
+    }
+
+    pub fn bits_per_sample(&self) -> usize {
+        self.bytes_per_sample() * 8
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(*self, SampleFormat::F32 | SampleFormat::F64)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn native() -> Endianness {
+        if cfg!(target_endian = "big") {
+            Endianness::Big
+        } else {
+            Endianness::Little
+        }
+    }
+}
+
+/// Layout of uncompressed linear PCM audio, the portable counterpart of a linear PCM
+/// `AudioStreamBasicDescription`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StreamFormat {
+    pub sample_rate: f64,
+    pub channels: u32,
+    pub sample_format: SampleFormat,
+    pub endianness: Endianness,
+    /// Interleaved audio lives in one buffer; planar audio has one buffer per channel.
+    pub interleaved: bool,
+}
+
+impl StreamFormat {
+    /// A native endian, interleaved format.
+    pub fn new(sample_rate: f64, channels: u32, sample_format: SampleFormat) -> StreamFormat {
+        StreamFormat {
+            sample_rate,
+            channels,
+            sample_format,
+            endianness: Endianness::native(),
+            interleaved: true,
+        }
+    }
+
+    pub fn with_endianness(mut self, endianness: Endianness) -> StreamFormat {
+        self.endianness = endianness;
+        self
+    }
+
+    pub fn with_interleaved(mut self, interleaved: bool) -> StreamFormat {
+        self.interleaved = interleaved;
+        self
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        if self.interleaved {
+            1
+        } else {
+            self.channels as usize
+        }
+    }
+
+    /// Bytes a single frame occupies in each buffer.
+    pub fn bytes_per_frame(&self) -> usize {
+        let channels_per_buffer = if self.interleaved { self.channels as usize } else { 1 };
+        channels_per_buffer * self.sample_format.bytes_per_sample()
+    }
+}
+
+/// `kAudioFormatLinearPCM`.
+pub const FORMAT_LINEAR_PCM: u32 = 0x6c70636d;
+/// `kAudioFormatULaw`.
+pub const FORMAT_ULAW: u32 = 0x756c6177;
+/// `kAudioFormatALaw`.
+pub const FORMAT_ALAW: u32 = 0x616c6177;
+
+/// `kAudioFormatFlag` values for linear PCM.
+pub const FLAG_IS_FLOAT: u32 = 1 << 0;
+pub const FLAG_IS_BIG_ENDIAN: u32 = 1 << 1;
+pub const FLAG_IS_SIGNED_INTEGER: u32 = 1 << 2;
+pub const FLAG_IS_PACKED: u32 = 1 << 3;
+pub const FLAG_IS_ALIGNED_HIGH: u32 = 1 << 4;
+pub const FLAG_IS_NON_INTERLEAVED: u32 = 1 << 5;
+pub const FLAG_IS_NON_MIXABLE: u32 = 1 << 6;
+
+static FLAG_NAMES: [(u32, &str); 7] = [(FLAG_IS_FLOAT, "Float"),
+                                      (FLAG_IS_BIG_ENDIAN, "BigEndian"),
+                                      (FLAG_IS_SIGNED_INTEGER, "SignedInteger"),
+                                      (FLAG_IS_PACKED, "Packed"),
+                                      (FLAG_IS_ALIGNED_HIGH, "AlignedHigh"),
+                                      (FLAG_IS_NON_INTERLEAVED, "NonInterleaved"),
+                                      (FLAG_IS_NON_MIXABLE, "NonMixable")];
+
+/// The fields of an `AudioStreamBasicDescription`, which can describe compressed formats
+/// as well as linear PCM, without depending on CoreAudio.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StreamDescription {
+    pub sample_rate: f64,
+    pub format_id: u32,
+    pub format_flags: u32,
+    pub bytes_per_packet: u32,
+    /// Zero when packets vary in length.
+    pub frames_per_packet: u32,
+    pub bytes_per_frame: u32,
+    pub channels_per_frame: u32,
+    pub bits_per_channel: u32,
+}
+
+impl StreamDescription {
+    pub fn from_stream_format(format: &StreamFormat) -> StreamDescription {
+        let mut flags = FLAG_IS_PACKED;
+        flags |= if format.sample_format.is_float() {
+            FLAG_IS_FLOAT
+        } else {
+            FLAG_IS_SIGNED_INTEGER
+        };
+        if format.endianness == Endianness::Big {
+            flags |= FLAG_IS_BIG_ENDIAN;
+        }
+        if !format.interleaved {
+            flags |= FLAG_IS_NON_INTERLEAVED;
+        }
+        let bytes_per_frame = format.bytes_per_frame() as u32;
+        StreamDescription {
+            sample_rate: format.sample_rate,
+            format_id: FORMAT_LINEAR_PCM,
+            format_flags: flags,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: format.channels,
+            bits_per_channel: format.sample_format.bits_per_sample() as u32,
+        }
+    }
+
+    pub fn is_linear_pcm(&self) -> bool {
+        self.format_id == FORMAT_LINEAR_PCM
+    }
+
+    /// The packed linear PCM layout this describes.
+    pub fn stream_format(&self) -> Result<StreamFormat, String> {
+        if !self.is_linear_pcm() {
+            return Err(format!("format '{}' is not linear PCM", fourcc(self.format_id)));
+        }
+        if self.channels_per_frame == 0 {
+            return Err("format has no channels".to_owned());
+        }
+        let flags = self.format_flags;
+        let is_float = flags & FLAG_IS_FLOAT != 0;
+        let is_signed = flags & FLAG_IS_SIGNED_INTEGER != 0;
+        let sample_format = match (is_float, self.bits_per_channel) {
+            (true, 32) => SampleFormat::F32,
+            (true, 64) => SampleFormat::F64,
+            (false, 8) if is_signed => SampleFormat::I8,
+            (false, 16) if is_signed => SampleFormat::I16,
+            (false, 24) if is_signed => SampleFormat::I24,
+            (false, 32) if is_signed => SampleFormat::I32,
+            _ => {
+                return Err(format!("unsupported sample layout: {} bits, flags {:#x}",
+                                   self.bits_per_channel,
+                                   flags))
+            }
+        };
+        let format = StreamFormat {
+            sample_rate: self.sample_rate,
+            channels: self.channels_per_frame,
+            sample_format,
+            endianness: if flags & FLAG_IS_BIG_ENDIAN != 0 {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            },
+            interleaved: flags & FLAG_IS_NON_INTERLEAVED == 0,
+        };
+        if self.bytes_per_frame as usize != format.bytes_per_frame() {
+            return Err(format!("{} bytes per frame is not packed {:?}",
+                               self.bytes_per_frame,
+                               sample_format));
+        }
+        Ok(format)
+    }
+
+    /// Names of the linear PCM flags that are set.
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        if !self.is_linear_pcm() {
+            return Vec::new();
+        }
+        FLAG_NAMES.iter()
+            .filter(|&&(flag, _)| self.format_flags & flag != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+}
+
+/// One line in the manner of `afinfo`, such as
+/// `2 ch, 44100 Hz, 'lpcm' (0x0000000C) 16-bit little-endian signed integer`.
+impl fmt::Display for StreamDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} ch, {} Hz, '{}' ({:#010X})",
+               self.channels_per_frame,
+               self.sample_rate,
+               fourcc(self.format_id),
+               self.format_flags)?;
+        if self.is_linear_pcm() {
+            let flags = self.format_flags;
+            write!(f, " {}-bit", self.bits_per_channel)?;
+            if self.bits_per_channel > 8 {
+                f.write_str(if flags & FLAG_IS_BIG_ENDIAN != 0 {
+                                " big-endian"
+                            } else {
+                                " little-endian"
+                            })?;
+            }
+            f.write_str(if flags & FLAG_IS_FLOAT != 0 {
+                            " float"
+                        } else if flags & FLAG_IS_SIGNED_INTEGER != 0 {
+                            " signed integer"
+                        } else {
+                            " unsigned integer"
+                        })?;
+            if flags & FLAG_IS_NON_INTERLEAVED != 0 {
+                f.write_str(", deinterleaved")?;
+            }
+            Ok(())
+        } else {
+            write!(f,
+                   " {} bits/channel, {} bytes/packet, {} frames/packet, {} bytes/frame",
+                   self.bits_per_channel,
+                   self.bytes_per_packet,
+                   self.frames_per_packet,
+                   self.bytes_per_frame)
+        }
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl StreamDescription {
+    pub fn from_asbd(asbd: &AudioStreamBasicDescription) -> StreamDescription {
+        StreamDescription {
+            sample_rate: asbd.mSampleRate,
+            format_id: asbd.mFormatID,
+            format_flags: asbd.mFormatFlags,
+            bytes_per_packet: asbd.mBytesPerPacket,
+            frames_per_packet: asbd.mFramesPerPacket,
+            bytes_per_frame: asbd.mBytesPerFrame,
+            channels_per_frame: asbd.mChannelsPerFrame,
+            bits_per_channel: asbd.mBitsPerChannel,
+        }
+    }
+
+    pub fn to_asbd(&self) -> AudioStreamBasicDescription {
+        AudioStreamBasicDescription {
+            mSampleRate: self.sample_rate,
+            mFormatID: self.format_id,
+            mFormatFlags: self.format_flags,
+            mBytesPerPacket: self.bytes_per_packet,
+            mFramesPerPacket: self.frames_per_packet,
+            mBytesPerFrame: self.bytes_per_frame,
+            mChannelsPerFrame: self.channels_per_frame,
+            mBitsPerChannel: self.bits_per_channel,
+            mReserved: 0,
+        }
+    }
+}
+
+#[cfg(feature = "coreaudio")]
+impl StreamFormat {
+    pub fn from_asbd(asbd: &AudioStreamBasicDescription) -> Result<StreamFormat, String> {
+        StreamDescription::from_asbd(asbd).stream_format()
+    }
+
+    pub fn to_asbd(&self) -> AudioStreamBasicDescription {
+        StreamDescription::from_stream_format(self).to_asbd()
+    }
+}
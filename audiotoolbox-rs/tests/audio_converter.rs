@@ -0,0 +1,192 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use audiotoolbox::audio_converter::*;
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::convert::{decode_f32, encode_f32};
+use audiotoolbox::resample::Quality;
+use audiotoolbox::stream_format::*;
+use common::*;
+
+/// Pulls every output packet from `converter`, `packets` at a time, feeding it `input`
+/// encoded in the converter's input format. Returns the decoded output and the number of
+/// packets of each input request.
+fn run(converter: &mut AudioConverter, input: &[f32], packets: usize) -> (Vec<f32>, Vec<usize>) {
+    let format = converter.input_format();
+    let channels = format.channels as usize;
+    let mut position = 0;
+    let mut requests = Vec::new();
+    let mut output = Vec::new();
+    let mut buffers = vec![Vec::new(); converter.output_format().buffer_count()];
+    {
+        let mut proc = |wanted: usize, data: &mut [Vec<u8>]| -> Result<usize, String> {
+            requests.push(wanted);
+            let frames = wanted.min(input.len() / channels - position);
+            let encoded = encode_f32(&format, &input[position * channels..
+                                                     (position + frames) * channels])?;
+            for (buffer, encoded) in data.iter_mut().zip(encoded) {
+                *buffer = encoded;
+            }
+            position += frames;
+            Ok(frames)
+        };
+        loop {
+            let produced = converter.fill_complex_buffer(&mut proc, packets, &mut buffers)
+                .unwrap();
+            assert!(produced <= packets);
+            if produced == 0 {
+                break;
+            }
+            let buffers: Vec<&[u8]> = buffers.iter().map(|buffer| &buffer[..]).collect();
+            output.extend(decode_f32(&converter.output_format(), &buffers).unwrap());
+        }
+    }
+    (output, requests)
+}
+
+#[test]
+fn converts_sample_format_without_resampling() {
+    let input_format = StreamFormat::new(44100.0, 2, SampleFormat::I16)
+        .with_endianness(Endianness::Big);
+    let output_format = StreamFormat::new(44100.0, 2, SampleFormat::F32).with_interleaved(false);
+    let mut converter = AudioConverter::new(input_format, output_format).unwrap();
+    assert_eq!(converter.maximum_output_packet_size(), Ok(4));
+    let input: Vec<f32> = (0..2000).map(|i| (i as f32 - 1000.0) / 1024.0).collect();
+    let (output, requests) = run(&mut converter, &input, 256);
+    assert_eq!(output, input);
+    assert!(requests.iter().all(|&wanted| wanted == 256));
+}
+
+#[test]
+fn converts_sample_rate() {
+    for &(from, to) in &[(44100.0, 48000.0), (48000.0, 22050.0)] {
+        let input_format = StreamFormat::new(from, 2, SampleFormat::I24);
+        let output_format = StreamFormat::new(to, 2, SampleFormat::F32);
+        let mut converter = AudioConverter::new(input_format, output_format).unwrap();
+        converter.set_quality(Quality::Best).unwrap();
+        let (output, requests) = run(&mut converter, &tone(1000.0, from, &[0.5, 0.5], 10000), 500);
+        assert_eq!(output.len(), 2 * (10000.0 * to / from).ceil() as usize);
+        for channel in 0..2 {
+            assert!((amplitude(&output, 2, channel, 1000.0, to) - 0.5).abs() < 0.001);
+        }
+        // Input is asked for in proportion to the output still missing.
+        let most = (500.0 * from / to).ceil() as usize;
+        assert!(requests.iter().all(|&wanted| wanted >= 1 && wanted <= most),
+                "{:?}",
+                requests);
+    }
+}
+
+#[test]
+fn converts_channel_count() {
+    let mono = StreamFormat::new(48000.0, 1, SampleFormat::F32);
+    let stereo = StreamFormat::new(48000.0, 2, SampleFormat::F32);
+    let mut converter = AudioConverter::new(stereo, mono).unwrap();
+    let (output, _) = run(&mut converter, &[0.5, 0.25, 0.5, 0.25], 64);
+    let expected = 0.75 * FRAC_1_SQRT_2;
+    assert!(output.len() == 2 && output.iter().all(|s| (s - expected).abs() < 1e-6),
+            "{:?}",
+            output);
+
+    let mut converter = AudioConverter::new(mono, stereo).unwrap();
+    let (output, _) = run(&mut converter, &[1.0, -1.0], 64);
+    let expected = [FRAC_1_SQRT_2, FRAC_1_SQRT_2, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2];
+    assert!(output.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-6),
+            "{:?}",
+            output);
+
+    // Rate and channel count at once, with a chosen mapping.
+    let surround = StreamFormat::new(44100.0, 6, SampleFormat::I16);
+    let mut converter = AudioConverter::new(surround, stereo).unwrap();
+    let stereo_layout = ChannelLayout::stereo();
+    assert!(converter.set_channel_layouts(&stereo_layout, &stereo_layout).is_err());
+    converter.set_channel_layouts(&ChannelLayout::surround_5_1(), &stereo_layout).unwrap();
+    let mut input = Vec::new();
+    for frame in tone(440.0, 44100.0, &[0.5], 8820) {
+        // Front left only.
+        input.extend_from_slice(&[frame, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+    let (output, _) = run(&mut converter, &input, 1000);
+    assert_eq!(output.len(), 2 * 9600);
+    assert!((amplitude(&output, 2, 0, 440.0, 48000.0) - 0.5).abs() < 0.001);
+    assert!(amplitude(&output, 2, 1, 440.0, 48000.0) < 1e-4);
+}
+
+#[test]
+fn flushes_buffered_audio_at_the_end_of_the_input() {
+    let input_format = StreamFormat::new(44100.0, 1, SampleFormat::F32);
+    let output_format = StreamFormat::new(48000.0, 1, SampleFormat::F32);
+    let input = tone(300.0, 44100.0, &[0.5], 4410);
+    let mut converter = AudioConverter::new(input_format, output_format).unwrap();
+    let (whole, requests) = run(&mut converter, &input, 100000);
+    assert_eq!(whole.len(), 4800);
+    // The first request asks for all of it, the second finds the input ended.
+    assert_eq!(requests.len(), 2);
+
+    // Small requests return the same audio, the resampler's tail included.
+    let mut converter = AudioConverter::new(input_format, output_format).unwrap();
+    let (pieces, _) = run(&mut converter, &input, 7);
+    assert_eq!(pieces, whole);
+    assert!(whole[4700..].iter().any(|&s| s != 0.0));
+
+    // Once drained the converter keeps reporting the end.
+    let mut buffers = vec![Vec::new()];
+    let mut proc = |_: usize, _: &mut [Vec<u8>]| -> Result<usize, String> { Ok(0) };
+    assert_eq!(converter.fill_complex_buffer(&mut proc, 10, &mut buffers), Ok(0));
+    assert!(buffers[0].is_empty());
+}
+
+#[test]
+fn reset_starts_a_new_stream() {
+    let input_format = StreamFormat::new(48000.0, 2, SampleFormat::I16);
+    let output_format = StreamFormat::new(32000.0, 2, SampleFormat::F32);
+    let first = tone(1000.0, 48000.0, &[0.5, 0.5], 3000);
+    let second = tone(250.0, 48000.0, &[0.5, 0.5], 3000);
+    let mut fresh = AudioConverter::new(input_format, output_format).unwrap();
+    let (expected, _) = run(&mut fresh, &second, 100);
+
+    // Stop part way through the first stream, leaving audio buffered.
+    let mut converter = AudioConverter::new(input_format, output_format).unwrap();
+    let mut buffers = vec![Vec::new()];
+    let mut proc = |wanted: usize, data: &mut [Vec<u8>]| -> Result<usize, String> {
+        let frames = wanted.min(500);
+        data[0] = encode_f32(&input_format, &first[..2 * frames])?.remove(0);
+        Ok(frames)
+    };
+    assert_eq!(converter.fill_complex_buffer(&mut proc, 100, &mut buffers), Ok(100));
+    converter.reset().unwrap();
+    let (output, _) = run(&mut converter, &second, 100);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn reports_input_errors() {
+    let format = StreamFormat::new(44100.0, 2, SampleFormat::I16);
+    let mut converter = AudioConverter::new(format, format.with_interleaved(false)).unwrap();
+    let mut buffers = vec![Vec::new(); 2];
+    let mut failing = |_: usize, _: &mut [Vec<u8>]| -> Result<usize, String> {
+        Err("device unplugged".to_owned())
+    };
+    assert_eq!(converter.fill_complex_buffer(&mut failing, 10, &mut buffers),
+               Err("device unplugged".to_owned()));
+
+    // Claiming more packets than the buffers hold.
+    let mut short = |_: usize, data: &mut [Vec<u8>]| -> Result<usize, String> {
+        data[0] = vec![0; 4];
+        Ok(10)
+    };
+    assert!(converter.fill_complex_buffer(&mut short, 10, &mut buffers).is_err());
+    assert!(converter.fill_complex_buffer(&mut short, 10, &mut buffers[..1]).is_err());
+}
+
+#[test]
+fn linear_pcm_has_no_magic_cookie() {
+    let format = StreamFormat::new(44100.0, 2, SampleFormat::I16);
+    let mut converter = AudioConverter::new(format, format).unwrap();
+    assert_eq!(converter.get_magic_cookie(), Ok(None));
+    assert!(converter.set_magic_cookie(Vec::new()).is_ok());
+    assert!(converter.set_magic_cookie(vec![0, 0, 0, 1]).is_err());
+}
@@ -0,0 +1,113 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+use audiotoolbox::audio_converter::{AudioConverter, AudioConverterReader};
+use audiotoolbox::audio_device::*;
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::{FrameReader, NativeFileReader};
+use audiotoolbox::frame_writer::FrameWriter;
+use audiotoolbox::stream_format::*;
+use common::reader;
+
+fn device(name: &str, uid: &str, input_channels: u32, output_channels: u32) -> DeviceInfo {
+    DeviceInfo {
+        name: name.to_owned(),
+        uid: uid.to_owned(),
+        input_channels,
+        output_channels,
+        sample_rate: 48000.0,
+        default_input: false,
+        default_output: false,
+    }
+}
+
+/// A reader of a CAF file of mono f32 frames counting up from zero in thousandths.
+fn counting_reader(frames: usize) -> NativeFileReader<Cursor<Vec<u8>>> {
+    let samples: Vec<f32> = (0..frames).map(|i| i as f32 / 1000.0).collect();
+    reader(AudioFileTypeId::CAF,
+           StreamFormat::new(1000.0, 1, SampleFormat::F32),
+           &samples)
+}
+
+#[test]
+fn devices_are_found_by_uid_name_or_unique_part_of_name() {
+    let devices = vec![device("Built-in Microphone", "BuiltInMic", 2, 0),
+                       device("Built-in Output", "BuiltInSpeaker", 0, 2),
+                       device("USB Audio", "usb:1", 2, 2),
+                       device("USB Audio", "usb:2", 2, 2)];
+    let found = find_device(&devices, "usb:2", Direction::Output).unwrap();
+    assert_eq!(found.uid, "usb:2");
+    let found = find_device(&devices, "Built-in Output", Direction::Output).unwrap();
+    assert_eq!(found.uid, "BuiltInSpeaker");
+    // Only one device with inputs has "built-in" in its name.
+    let found = find_device(&devices, "built-in", Direction::Input).unwrap();
+    assert_eq!(found.uid, "BuiltInMic");
+
+    assert!(find_device(&devices, "microphone", Direction::Output).is_err());
+    assert!(find_device(&devices, "usb", Direction::Input).is_err());
+    assert!(find_device(&devices, "BuiltInMic", Direction::Output).is_err());
+}
+
+#[test]
+fn null_backend_records_silence_and_accepts_any_output() {
+    let backend = backend("null").unwrap();
+    let devices = backend.devices().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert!(devices[0].default_input && devices[0].default_output);
+    assert!(backend.open_output(Some("missing"), StreamFormat::new(48000.0, 2, SampleFormat::F32))
+                .is_err());
+
+    let null = NullBackend { realtime: false };
+    let format = StreamFormat::new(44100.0, 3, SampleFormat::I16);
+    let mut input = null.open_input(Some(&devices[0].uid), format).unwrap();
+    assert_eq!(input.format(), StreamFormat::new(44100.0, 3, SampleFormat::F32));
+    let mut buffer = vec![1.0; 3 * 100 + 2];
+    assert_eq!(input.read_frames(&mut buffer).unwrap(), 100);
+    assert!(buffer[..300].iter().all(|&s| s == 0.0));
+
+    let mut output = null.open_output(None, format).unwrap();
+    output.write_frames(&buffer[..300]).unwrap();
+    output.finish().unwrap();
+}
+
+#[test]
+fn null_backend_keeps_real_time() {
+    let format = StreamFormat::new(1000.0, 1, SampleFormat::F32);
+    let mut output = NullBackend::new().open_output(None, format).unwrap();
+    let start = Instant::now();
+    for _ in 0..5 {
+        output.write_frames(&[0.0; 20]).unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn readers_seek_to_a_frame() {
+    let mut reader = counting_reader(100);
+    let mut buffer = [0.0; 4];
+    reader.seek(90).unwrap();
+    assert_eq!(reader.read_frames(&mut buffer).unwrap(), 4);
+    assert_eq!(buffer, [0.090, 0.091, 0.092, 0.093]);
+    reader.seek(10).unwrap();
+    assert_eq!(reader.read_frames(&mut buffer).unwrap(), 4);
+    assert_eq!(buffer, [0.010, 0.011, 0.012, 0.013]);
+    reader.seek(1000).unwrap();
+    assert_eq!(reader.read_frames(&mut buffer).unwrap(), 0);
+
+    // Converting readers seek their source to the same time.
+    let reader = counting_reader(1000);
+    let converter = AudioConverter::new(reader.format(),
+                                        StreamFormat::new(1000.0, 2, SampleFormat::F32))
+        .unwrap();
+    let mut reader = AudioConverterReader::new(reader, converter).unwrap();
+    reader.seek(500).unwrap();
+    let mut buffer = [0.0; 2];
+    assert_eq!(reader.read_frames(&mut buffer).unwrap(), 1);
+    assert!(buffer.iter().all(|&s| s == 0.5 || (s - 0.5 * 0.5f32.sqrt()).abs() < 1e-6),
+            "{:?}",
+            buffer);
+}
@@ -0,0 +1,208 @@
+extern crate audiotoolbox;
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use audiotoolbox::channel_map::*;
+use audiotoolbox::frame_reader::FrameReader;
+use audiotoolbox::stream_format::*;
+
+const TAGS: [ChannelLayoutTag; 10] = [ChannelLayoutTag::Mono,
+                                      ChannelLayoutTag::Stereo,
+                                      ChannelLayoutTag::Quadraphonic,
+                                      ChannelLayoutTag::MPEG_5_1_A,
+                                      ChannelLayoutTag::MPEG_5_1_B,
+                                      ChannelLayoutTag::MPEG_5_1_C,
+                                      ChannelLayoutTag::MPEG_5_1_D,
+                                      ChannelLayoutTag::MPEG_7_1_A,
+                                      ChannelLayoutTag::MPEG_7_1_B,
+                                      ChannelLayoutTag::MPEG_7_1_C];
+
+/// The words of an `AudioChannelLayout`: tag, bitmap, then one description per label.
+fn audio_channel_layout(tag: u32, bitmap: u32, labels: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for &word in &[tag, bitmap, labels.len() as u32] {
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    for &label in labels {
+        bytes.extend_from_slice(&label.to_ne_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+    }
+    bytes
+}
+
+fn labels(layout: &ChannelLayout) -> Vec<u32> {
+    layout.channels().iter().map(|channel| channel.label()).collect()
+}
+
+/// Interleaved frames held in memory.
+struct Frames {
+    samples: Vec<f32>,
+    channels: u32,
+    layout: Option<ChannelLayout>,
+}
+
+impl FrameReader for Frames {
+    fn format(&self) -> StreamFormat {
+        StreamFormat::new(48000.0, self.channels, SampleFormat::F32)
+    }
+
+    fn channel_layout(&self) -> Option<ChannelLayout> {
+        self.layout.clone()
+    }
+
+    fn read_frames(&mut self, buffer: &mut [f32]) -> Result<usize, String> {
+        let channels = self.channels as usize;
+        let len = buffer.len().min(self.samples.len()) / channels * channels;
+        buffer[..len].copy_from_slice(&self.samples[..len]);
+        self.samples.drain(..len);
+        Ok(len / channels)
+    }
+}
+
+#[test]
+fn tags_round_trip_through_bitmaps_and_labels() {
+    for &tag in &TAGS {
+        let layout = ChannelLayout::from_tag(tag).unwrap();
+        assert_eq!(layout.tag(), Some(tag));
+        assert_eq!(ChannelLayoutTag::from_u32(tag as u32), Ok(tag));
+        assert_eq!(ChannelLayout::from_audio_channel_layout(&audio_channel_layout(tag as u32,
+                                                                                   0,
+                                                                                   &[])),
+                   Ok(layout.clone()));
+
+        if let Some(mask) = layout.wav_mask() {
+            assert_eq!(ChannelLayout::from_wav_mask(mask), Ok(layout.clone()), "{:?}", tag);
+            let bytes =
+                audio_channel_layout(ChannelLayoutTag::UseChannelBitmap as u32, mask, &[]);
+            assert_eq!(ChannelLayout::from_audio_channel_layout(&bytes),
+                       Ok(layout.clone()),
+                       "{:?}",
+                       tag);
+        }
+
+        let bytes = audio_channel_layout(ChannelLayoutTag::UseChannelDescriptions as u32,
+                                         0,
+                                         &labels(&layout));
+        assert_eq!(ChannelLayout::from_audio_channel_layout(&bytes), Ok(layout), "{:?}", tag);
+    }
+}
+
+#[test]
+fn wav_order_layouts_match_their_tags() {
+    assert_eq!(ChannelLayout::surround_5_1(),
+               ChannelLayout::from_tag(ChannelLayoutTag::MPEG_5_1_A).unwrap());
+    assert_eq!(ChannelLayout::surround_5_1().wav_mask(), Some(0x3f));
+    assert_eq!(ChannelLayout::default_for(4).unwrap().tag(),
+               Some(ChannelLayoutTag::Quadraphonic));
+    assert_eq!(ChannelLayout::from_tag(ChannelLayoutTag::MPEG_7_1_A).unwrap().wav_mask(),
+               Some(0xff));
+    assert_eq!(ChannelLayout::surround_7_1().wav_mask(), Some(0x63f));
+    assert_eq!(ChannelLayout::stereo().tag(), Some(ChannelLayoutTag::Stereo));
+}
+
+#[test]
+fn rear_surrounds_put_the_surrounds_at_the_sides() {
+    // MPEG_7_1_C spelled out with CoreAudio's own labels: L R C LFE Ls Rs Rls Rrs.
+    let bytes = audio_channel_layout(ChannelLayoutTag::UseChannelDescriptions as u32,
+                                     0,
+                                     &[1, 2, 3, 4, 5, 6, 33, 34]);
+    let layout = ChannelLayout::from_audio_channel_layout(&bytes).unwrap();
+    assert_eq!(layout.tag(), Some(ChannelLayoutTag::MPEG_7_1_C));
+
+    // Without them the surrounds are the back pair the WAV bitmap calls them.
+    let bytes = audio_channel_layout(ChannelLayoutTag::UseChannelDescriptions as u32,
+                                     0,
+                                     &[1, 2, 3, 4, 5, 6]);
+    let layout = ChannelLayout::from_audio_channel_layout(&bytes).unwrap();
+    assert_eq!(layout, ChannelLayout::surround_5_1());
+}
+
+#[test]
+fn rejects_unknown_layouts() {
+    assert!(ChannelLayoutTag::from_u32(1).unwrap_err().contains("0x1"));
+    assert!(Channel::from_label(100).unwrap_err().contains("100"));
+    assert!(ChannelLayout::from_wav_mask(0x800).is_err());
+    assert!(ChannelLayout::from_tag(ChannelLayoutTag::UseChannelBitmap).is_err());
+    let bytes = audio_channel_layout(ChannelLayoutTag::UseChannelDescriptions as u32, 0, &[100]);
+    assert!(ChannelLayout::from_audio_channel_layout(&bytes).is_err());
+    assert!(ChannelLayout::from_audio_channel_layout(&bytes[..20]).is_err());
+}
+
+#[test]
+fn downmixes_with_bs_775_coefficients() {
+    let mixer = ChannelMixer::between(&ChannelLayout::surround_5_1(), &ChannelLayout::stereo())
+        .unwrap();
+    assert_eq!(mixer.input_channels(), 6);
+    assert_eq!(mixer.output_channels(), 2);
+    let left: Vec<f32> = (0..6).map(|input| mixer.gain(0, input)).collect();
+    let right: Vec<f32> = (0..6).map(|input| mixer.gain(1, input)).collect();
+    assert_eq!(left, vec![1.0, 0.0, FRAC_1_SQRT_2, 0.0, FRAC_1_SQRT_2, 0.0]);
+    assert_eq!(right, vec![0.0, 1.0, FRAC_1_SQRT_2, 0.0, 0.0, FRAC_1_SQRT_2]);
+
+    // 7.1 to 5.1 folds the sides onto the back pair.
+    let mixer = ChannelMixer::between(&ChannelLayout::surround_7_1(),
+                                      &ChannelLayout::surround_5_1())
+        .unwrap();
+    assert_eq!(mixer.gain(4, 4), 1.0);
+    assert_eq!(mixer.gain(4, 6), 1.0);
+    assert_eq!(mixer.gain(5, 7), 1.0);
+
+    let mixer = ChannelMixer::between(&ChannelLayout::mono(), &ChannelLayout::stereo()).unwrap();
+    assert_eq!((mixer.gain(0, 0), mixer.gain(1, 0)), (FRAC_1_SQRT_2, FRAC_1_SQRT_2));
+
+    let normalized = ChannelMixer::between(&ChannelLayout::surround_5_1(),
+                                           &ChannelLayout::stereo())
+        .unwrap()
+        .normalized();
+    let loudest = (0..6).map(|input| normalized.gain(0, input)).sum::<f32>();
+    assert!((loudest - 1.0).abs() < 1e-6);
+
+    let empty = ChannelLayout::new(Vec::new());
+    assert!(ChannelMixer::between(&empty, &ChannelLayout::stereo()).is_err());
+    assert!(ChannelMixer::between(&ChannelLayout::stereo(), &empty).is_err());
+}
+
+#[test]
+fn reorders_channels_between_tags() {
+    let from = ChannelLayout::surround_5_1();
+    let to = ChannelLayout::from_tag(ChannelLayoutTag::MPEG_5_1_D).unwrap();
+    let mixer = ChannelMixer::between(&from, &to).unwrap();
+    let mut output = Vec::new();
+    mixer.process(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], &mut output);
+    // C L R Ls Rs LFE
+    assert_eq!(output, vec![3.0, 1.0, 2.0, 5.0, 6.0, 4.0]);
+}
+
+#[test]
+fn reader_mixes_to_another_layout() {
+    let frames = Frames {
+        samples: vec![1.0, 0.0, 0.5, 0.5, 0.0, 1.0],
+        channels: 2,
+        layout: None,
+    };
+    let mut reader = ChannelMapReader::new(frames, ChannelLayout::mono()).unwrap();
+    assert_eq!(reader.format().channels, 1);
+    assert_eq!(reader.channel_layout(), Some(ChannelLayout::mono()));
+    let output = reader.read_to_end().unwrap();
+    let expected = [FRAC_1_SQRT_2, FRAC_1_SQRT_2, FRAC_1_SQRT_2];
+    assert!(output.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-6), "{:?}", output);
+
+    let frames = Frames {
+        samples: Vec::new(),
+        channels: 3,
+        layout: None,
+    };
+    assert!(ChannelMapReader::new(frames, ChannelLayout::stereo()).is_err());
+    let frames = Frames {
+        samples: Vec::new(),
+        channels: 2,
+        layout: Some(ChannelLayout::surround_5_1()),
+    };
+    assert!(ChannelMapReader::new(frames, ChannelLayout::stereo()).is_err());
+    let frames = Frames {
+        samples: vec![1.0, 0.0],
+        channels: 2,
+        layout: None,
+    };
+    assert!(ChannelMapReader::new(frames, ChannelLayout::new(Vec::new())).is_err());
+}
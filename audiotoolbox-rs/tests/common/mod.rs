@@ -0,0 +1,95 @@
+// Signals and files shared by the integration tests. Test crates declare this module `pub`, so the
+// helpers one of them leaves unused are not dead code.
+
+use std::f64::consts::PI;
+use std::io::Cursor;
+
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::NativeFileReader;
+use audiotoolbox::frame_writer::{FrameWriter, NativeFileWriter};
+use audiotoolbox::stream_format::*;
+
+pub fn db(gain: f64) -> f64 {
+    20.0 * gain.log10()
+}
+
+/// The gain of a level in dB, where negative infinity is silence.
+pub fn gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Interleaved frames of a sine starting at `phase`, one channel per amplitude.
+pub fn sine(frequency: f64,
+            phase: f64,
+            rate: f64,
+            amplitudes: &[f64],
+            frames: usize)
+            -> Vec<f32> {
+    let mut samples = Vec::with_capacity(frames * amplitudes.len());
+    for i in 0..frames {
+        let value = (2.0 * PI * frequency * i as f64 / rate + phase).sin();
+        for &amplitude in amplitudes {
+            samples.push((amplitude * value) as f32);
+        }
+    }
+    samples
+}
+
+pub fn tone(frequency: f64, rate: f64, amplitudes: &[f64], frames: usize) -> Vec<f32> {
+    sine(frequency, 0.0, rate, amplitudes, frames)
+}
+
+/// `channels` of a 1 kHz sine starting at `phase` whose level in dBFS changes after each of
+/// the given durations in seconds.
+pub fn segments(phase: f64, rate: f64, channels: usize, segments: &[(f64, f64)]) -> Vec<f32> {
+    let mut samples = Vec::new();
+    let mut i = 0;
+    for &(level, seconds) in segments {
+        for _ in 0..(seconds * rate).round() as usize {
+            let value = (gain(level) * (2.0 * PI * 1000.0 * i as f64 / rate + phase).sin()) as f32;
+            for _ in 0..channels {
+                samples.push(value);
+            }
+            i += 1;
+        }
+    }
+    samples
+}
+
+/// Amplitude of the `frequency` component of one channel of interleaved `signal`,
+/// ignoring the first and last eighth.
+pub fn amplitude(signal: &[f32],
+                 channels: usize,
+                 channel: usize,
+                 frequency: f64,
+                 rate: f64)
+                 -> f64 {
+    let frames = signal.len() / channels;
+    let margin = frames / 8;
+    let (mut re, mut im) = (0.0, 0.0);
+    for i in margin..frames - margin {
+        let phase = 2.0 * PI * frequency * i as f64 / rate;
+        re += signal[i * channels + channel] as f64 * phase.cos();
+        im += signal[i * channels + channel] as f64 * phase.sin();
+    }
+    2.0 * (re * re + im * im).sqrt() / (frames - 2 * margin) as f64
+}
+
+/// A file of `file_type` holding `samples` in `format`.
+pub fn file(file_type: AudioFileTypeId, format: StreamFormat, samples: &[f32]) -> Vec<u8> {
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = NativeFileWriter::new(&mut cursor, file_type, format, None).unwrap();
+        writer.write_frames(samples).unwrap();
+        writer.finish().unwrap();
+    }
+    cursor.into_inner()
+}
+
+/// A reader of `samples` written to a file of `file_type` in `format`.
+pub fn reader(file_type: AudioFileTypeId,
+              format: StreamFormat,
+              samples: &[f32])
+              -> NativeFileReader<Cursor<Vec<u8>>> {
+    NativeFileReader::new(Cursor::new(file(file_type, format, samples))).unwrap()
+}
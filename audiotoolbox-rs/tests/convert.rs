@@ -0,0 +1,259 @@
+extern crate audiotoolbox;
+
+use audiotoolbox::convert::*;
+use audiotoolbox::stream_format::*;
+
+const SAMPLE_FORMATS: [SampleFormat; 6] = [SampleFormat::I8,
+                                           SampleFormat::I16,
+                                           SampleFormat::I24,
+                                           SampleFormat::I32,
+                                           SampleFormat::F32,
+                                           SampleFormat::F64];
+
+/// Stereo samples that every format holds exactly, from -1.0 up to just below full scale.
+fn exact_samples() -> Vec<f32> {
+    (0..256).map(|i| (i as f32 - 128.0) / 128.0).collect()
+}
+
+fn formats() -> Vec<StreamFormat> {
+    let mut formats = Vec::new();
+    for &sample_format in &SAMPLE_FORMATS {
+        for &endianness in &[Endianness::Little, Endianness::Big] {
+            for &interleaved in &[true, false] {
+                formats.push(StreamFormat::new(44100.0, 2, sample_format)
+                                 .with_endianness(endianness)
+                                 .with_interleaved(interleaved));
+            }
+        }
+    }
+    formats
+}
+
+fn convert(converter: &mut Converter, input: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let input: Vec<&[u8]> = input.iter().map(|buffer| &buffer[..]).collect();
+    let mut output = vec![Vec::new(); converter.output_format().buffer_count()];
+    converter.convert(&input, &mut output).unwrap();
+    output
+}
+
+/// Encodes `samples` with the given clipping by converting them from unclamped `F64` input.
+fn encode(format: StreamFormat, clipping: Clipping, samples: &[f32]) -> Vec<Vec<u8>> {
+    let wide = StreamFormat::new(format.sample_rate, format.channels, SampleFormat::F64)
+        .with_endianness(Endianness::Little);
+    let mut bytes = Vec::new();
+    for &sample in samples {
+        let bits = (sample as f64).to_bits();
+        bytes.extend((0..8).map(|i| (bits >> (8 * i)) as u8));
+    }
+    let mut converter = Converter::new(wide, format).unwrap().with_clipping(clipping);
+    convert(&mut converter, &[bytes])
+}
+
+fn decode(format: &StreamFormat, buffers: &[Vec<u8>]) -> Vec<f32> {
+    let buffers: Vec<&[u8]> = buffers.iter().map(|buffer| &buffer[..]).collect();
+    decode_f32(format, &buffers).unwrap()
+}
+
+#[test]
+fn round_trips_every_layout() {
+    let samples = exact_samples();
+    for format in formats() {
+        let encoded = encode_f32(&format, &samples).unwrap();
+        assert_eq!(encoded.len(), format.buffer_count());
+        for buffer in &encoded {
+            assert_eq!(buffer.len(), 128 * format.bytes_per_frame());
+        }
+        assert_eq!(decode(&format, &encoded), samples, "{:?}", format);
+
+        // Through every other layout and back.
+        for other in formats() {
+            let mut there = Converter::new(format, other).unwrap();
+            let mut back = Converter::new(other, format).unwrap();
+            let converted = convert(&mut there, &encoded);
+            assert_eq!(decode(&other, &converted), samples, "{:?} to {:?}", format, other);
+            assert_eq!(convert(&mut back, &converted), encoded, "{:?} to {:?}", other, format);
+        }
+    }
+}
+
+#[test]
+fn packs_24_bit_samples_into_three_bytes() {
+    let samples = [0.5, -1.0 / 8388608.0, 1.0 / 8388608.0, -1.0];
+    let little = StreamFormat::new(44100.0, 1, SampleFormat::I24)
+        .with_endianness(Endianness::Little);
+    let encoded = encode_f32(&little, &samples).unwrap();
+    assert_eq!(encoded[0],
+               vec![0x00, 0x00, 0x40, 0xff, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00, 0x00, 0x80]);
+    // Negative samples are sign extended from bit 23.
+    assert_eq!(decode(&little, &encoded), samples);
+
+    let big = little.with_endianness(Endianness::Big);
+    let encoded = encode_f32(&big, &samples).unwrap();
+    assert_eq!(encoded[0],
+               vec![0x40, 0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00, 0x01, 0x80, 0x00, 0x00]);
+    assert_eq!(decode(&big, &encoded), samples);
+}
+
+#[test]
+fn orders_bytes_by_endianness() {
+    let cases: [(SampleFormat, f32, &[u8]); 4] =
+        [(SampleFormat::I16, 0x1234 as f32 / 32768.0, &[0x12, 0x34]),
+         (SampleFormat::I32, -0.5, &[0xc0, 0x00, 0x00, 0x00]),
+         (SampleFormat::F32, 1.0, &[0x3f, 0x80, 0x00, 0x00]),
+         (SampleFormat::F64, -2.0, &[0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])];
+    for &(sample_format, sample, big_endian) in &cases {
+        let format = StreamFormat::new(44100.0, 1, sample_format)
+            .with_endianness(Endianness::Big);
+        let encoded = encode(format, Clipping::Preserve, &[sample]);
+        assert_eq!(&encoded[0][..], big_endian);
+
+        let little = format.with_endianness(Endianness::Little);
+        let encoded = encode(little, Clipping::Preserve, &[sample]);
+        let reversed: Vec<u8> = big_endian.iter().rev().cloned().collect();
+        assert_eq!(encoded[0], reversed);
+        assert_eq!(decode(&little, &encoded), vec![sample]);
+    }
+}
+
+#[test]
+fn planar_buffers_hold_one_channel_each() {
+    let format = StreamFormat::new(44100.0, 3, SampleFormat::I8).with_interleaved(false);
+    assert_eq!(format.buffer_count(), 3);
+    assert_eq!(format.bytes_per_frame(), 1);
+    let samples = [0.0, 0.5, -0.5, 0.25, -0.25, -1.0];
+    let encoded = encode_f32(&format, &samples).unwrap();
+    assert_eq!(encoded, vec![vec![0x00, 0x20], vec![0x40, 0xe0], vec![0xc0, 0x80]]);
+
+    let interleaved = format.with_interleaved(true);
+    let mut converter = Converter::new(format, interleaved).unwrap();
+    assert_eq!(convert(&mut converter, &encoded),
+               vec![vec![0x00, 0x40, 0xc0, 0x20, 0xe0, 0x80]]);
+
+    // Every planar buffer must hold the same number of frames.
+    let uneven: Vec<&[u8]> = vec![&[0, 0], &[0, 0], &[0]];
+    assert!(decode_f32(&format, &uneven).is_err());
+    assert!(decode_f32(&format, &[&[0, 0]]).is_err());
+}
+
+#[test]
+fn clips_out_of_range_samples() {
+    let samples = [1.5, -1.5, 1.0, -1.0, f32::NAN];
+    let float = StreamFormat::new(44100.0, 1, SampleFormat::F32);
+    let clamped = decode(&float, &encode_f32(&float, &samples).unwrap());
+    assert_eq!(&clamped[..4], &[1.0, -1.0, 1.0, -1.0]);
+
+    let buffers = encode(float, Clipping::Preserve, &samples);
+    let preserved = decode(&float, &buffers);
+    assert_eq!(&preserved[..4], &[1.5, -1.5, 1.0, -1.0]);
+
+    // Integers saturate at full scale whatever the clipping, and NaN becomes silence.
+    for &clipping in &[Clipping::Clamp, Clipping::Preserve] {
+        let integer = StreamFormat::new(44100.0, 1, SampleFormat::I16);
+        let buffers = encode(integer, clipping, &samples);
+        assert_eq!(decode(&integer, &buffers), vec![32767.0 / 32768.0, -1.0, 32767.0 / 32768.0,
+                                                    -1.0, 0.0]);
+    }
+
+    // Float input beyond full scale saturates when converted to integers too.
+    let wide = StreamFormat::new(44100.0, 1, SampleFormat::F64);
+    let narrow = StreamFormat::new(44100.0, 1, SampleFormat::I8);
+    let mut converter = Converter::new(wide, narrow).unwrap().with_clipping(Clipping::Preserve);
+    let input = encode(wide, Clipping::Preserve, &[2.0, -2.0]);
+    assert_eq!(decode(&wide, &input), vec![2.0, -2.0]);
+    assert_eq!(convert(&mut converter, &input), vec![vec![0x7f, 0x80]]);
+}
+
+/// Mean and variance, in LSBs, of the error converting a constant `level` LSBs above zero
+/// to 16 bits.
+fn dither_error(dither: Dither, level: f64) -> (f64, f64) {
+    let frames = 20000;
+    let input = StreamFormat::new(44100.0, 1, SampleFormat::F64);
+    let output = StreamFormat::new(44100.0, 1, SampleFormat::I16);
+    let encoded = encode_f32(&input, &vec![(level / 32768.0) as f32; frames]).unwrap();
+    let mut converter = Converter::new(input, output).unwrap().with_dither(dither);
+    let errors: Vec<f64> = decode(&output, &convert(&mut converter, &encoded))
+        .iter()
+        .map(|&sample| sample as f64 * 32768.0 - level)
+        .collect();
+    let mean = errors.iter().sum::<f64>() / frames as f64;
+    let variance = errors.iter().map(|e| (e - mean) * (e - mean)).sum::<f64>() / frames as f64;
+    (mean, variance)
+}
+
+#[test]
+fn dither_removes_quantisation_bias() {
+    for &level in &[0.0, 0.25, 0.5, 0.75] {
+        // Without dither a constant quantises to a constant, biased by up to half an LSB.
+        let (mean, variance) = dither_error(Dither::None, level);
+        assert_eq!(variance, 0.0);
+        assert!((mean - (level.round() - level)).abs() < 1e-9);
+
+        for &dither in &[Dither::Rectangular, Dither::Triangular] {
+            let (mean, _) = dither_error(dither, level);
+            assert!(mean.abs() < 0.02, "{:?} at {}: {}", dither, level, mean);
+        }
+    }
+}
+
+#[test]
+fn triangular_dither_keeps_the_noise_independent_of_the_signal() {
+    // Triangular dither adds two LSB squared of noise to the LSB squared of quantisation,
+    // both over twelve, whatever the signal.
+    for &level in &[0.0, 0.25, 0.5] {
+        let (_, variance) = dither_error(Dither::Triangular, level);
+        assert!((variance - 0.25).abs() < 0.02, "{}: {}", level, variance);
+    }
+    // Rectangular dither lets it vary with the signal.
+    assert!(dither_error(Dither::Rectangular, 0.0).1 < 0.01);
+    assert!((dither_error(Dither::Rectangular, 0.5).1 - 0.25).abs() < 0.02);
+}
+
+#[test]
+fn dithers_only_when_losing_precision() {
+    let samples = exact_samples();
+    let i16_format = StreamFormat::new(44100.0, 2, SampleFormat::I16);
+    let i16_input = encode_f32(&i16_format, &samples).unwrap();
+    for &output in &[SampleFormat::I16, SampleFormat::I24, SampleFormat::F32] {
+        let format = StreamFormat::new(44100.0, 2, output);
+        let mut converter = Converter::new(i16_format, format)
+            .unwrap()
+            .with_dither(Dither::Triangular);
+        assert_eq!(decode(&format, &convert(&mut converter, &i16_input)), samples);
+    }
+
+    // Dither state carries between calls, so splitting the input changes nothing.
+    let f32_format = StreamFormat::new(44100.0, 2, SampleFormat::F32);
+    let quiet: Vec<f32> = samples.iter().map(|s| s / 1000.0).collect();
+    let whole = encode_f32(&f32_format, &quiet).unwrap();
+    let mut converter = Converter::new(f32_format, i16_format)
+        .unwrap()
+        .with_dither(Dither::Triangular);
+    let expected = convert(&mut converter, &whole);
+    assert!(expected != encode_f32(&i16_format, &quiet).unwrap());
+
+    let mut converter = Converter::new(f32_format, i16_format)
+        .unwrap()
+        .with_dither(Dither::Triangular);
+    let half = whole[0].len() / 2;
+    let mut split = convert(&mut converter, &[whole[0][..half].to_vec()]);
+    split[0].extend(convert(&mut converter, &[whole[0][half..].to_vec()]).remove(0));
+    assert_eq!(split, expected);
+}
+
+#[test]
+fn rejects_mismatched_formats() {
+    let format = StreamFormat::new(44100.0, 2, SampleFormat::I16);
+    assert!(Converter::new(format, StreamFormat::new(48000.0, 2, SampleFormat::I16)).is_err());
+    assert!(Converter::new(format, StreamFormat::new(44100.0, 1, SampleFormat::I16)).is_err());
+    let mut converter = Converter::new(format, format.with_interleaved(false)).unwrap();
+    assert!(converter.convert(&[&[0, 0, 0]], &mut [Vec::new(), Vec::new()]).is_err());
+    assert!(converter.convert(&[&[0, 0, 0, 0]], &mut [Vec::new()]).is_err());
+}
+
+#[test]
+fn rejects_formats_without_channels() {
+    let empty = StreamFormat::new(44100.0, 0, SampleFormat::I16);
+    assert!(Converter::new(empty, empty.with_interleaved(false)).is_err());
+    assert!(decode_f32(&empty, &[&[0, 0]]).is_err());
+    assert!(encode_f32(&empty, &[0.0]).is_err());
+}
@@ -0,0 +1,271 @@
+extern crate audiotoolbox;
+
+use std::io::Cursor;
+
+use audiotoolbox::channel_map::{ChannelLayout, ChannelLayoutTag};
+use audiotoolbox::file_info::AudioFileInfo;
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::stream_format::*;
+
+fn le16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn le32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn be32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// A RIFF or FORM chunk, padded to an even length.
+fn chunk(id: &[u8; 4], body: &[u8], big_endian: bool) -> Vec<u8> {
+    let mut out = id.to_vec();
+    if big_endian {
+        be32(&mut out, body.len() as u32);
+    } else {
+        le32(&mut out, body.len() as u32);
+    }
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
+
+fn wave(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = chunks.iter().flat_map(|c| c.clone()).collect();
+    let mut out = b"RIFF".to_vec();
+    le32(&mut out, body.len() as u32 + 4);
+    out.extend_from_slice(b"WAVE");
+    out.extend(body);
+    out
+}
+
+fn fmt(tag: u16, channels: u16, rate: u32, bits: u16) -> Vec<u8> {
+    let block_align = channels * bits.div_ceil(8);
+    let mut body = Vec::new();
+    le16(&mut body, tag);
+    le16(&mut body, channels);
+    le32(&mut body, rate);
+    le32(&mut body, rate * block_align as u32);
+    le16(&mut body, block_align);
+    le16(&mut body, bits);
+    body
+}
+
+fn read(bytes: Vec<u8>) -> Result<AudioFileInfo, String> {
+    AudioFileInfo::read(&mut Cursor::new(bytes))
+}
+
+#[test]
+fn wav_pcm_with_info() {
+    let mut list = b"INFO".to_vec();
+    list.extend(chunk(b"INAM", b"Test tone\0", false));
+    list.extend(chunk(b"IART", b"Nobody\0", false));
+    let bytes = wave(&[chunk(b"fmt ", &fmt(1, 2, 44100, 16), false),
+                       chunk(b"LIST", &list, false),
+                       chunk(b"data", &vec![0; 4 * 1000], false)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.file_type, AudioFileTypeId::WAVE);
+    let description = info.description;
+    assert_eq!(description.format_id, FORMAT_LINEAR_PCM);
+    assert_eq!(description.format_flags, FLAG_IS_SIGNED_INTEGER | FLAG_IS_PACKED);
+    assert_eq!(description.channels_per_frame, 2);
+    assert_eq!(description.bits_per_channel, 16);
+    assert_eq!(description.bytes_per_frame, 4);
+    assert_eq!(info.data_offset, Some(12 + 8 + 16 + 8 + list.len() as u64 + 8));
+    assert_eq!(info.data_size, 4000);
+    assert_eq!(info.frame_count, Some(1000));
+    assert!((info.duration().unwrap() - 1000.0 / 44100.0).abs() < 1e-12);
+    assert!((info.bit_rate().unwrap() - 44100.0 * 32.0).abs() < 1e-6);
+    assert_eq!(info.metadata,
+               vec![("title".to_owned(), "Test tone".to_owned()),
+                    ("artist".to_owned(), "Nobody".to_owned())]);
+    assert_eq!(description.to_string(),
+               "2 ch, 44100 Hz, 'lpcm' (0x0000000C) 16-bit little-endian signed integer");
+}
+
+#[test]
+fn wav_8_bit_is_unsigned() {
+    let bytes = wave(&[chunk(b"fmt ", &fmt(1, 1, 8000, 8), false),
+                       chunk(b"data", &[0x80; 801], false)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.description.format_flags & FLAG_IS_SIGNED_INTEGER, 0);
+    // The data chunk's pad byte is not audio.
+    assert_eq!(info.data_size, 801);
+    assert_eq!(info.frame_count, Some(801));
+}
+
+#[test]
+fn wav_extensible_float_with_mask() {
+    let mut body = fmt(0xfffe, 6, 48000, 32);
+    le16(&mut body, 22);
+    le16(&mut body, 32);
+    le32(&mut body, ChannelLayout::surround_5_1().wav_mask().unwrap());
+    le16(&mut body, 3);
+    body.extend_from_slice(&[0, 0, 0, 0, 0x10, 0, 0x80, 0, 0, 0xaa, 0, 0x38, 0x9b, 0x71]);
+    let bytes = wave(&[chunk(b"fmt ", &body, false), chunk(b"data", &vec![0; 24 * 10], false)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.description.format_flags, FLAG_IS_FLOAT | FLAG_IS_PACKED);
+    assert_eq!(info.channel_layout, Some(ChannelLayout::surround_5_1()));
+    assert_eq!(info.frame_count, Some(10));
+    assert_eq!(info.description.stream_format().unwrap().sample_format,
+               SampleFormat::F32);
+}
+
+#[test]
+fn wav_without_data_is_an_error() {
+    let bytes = wave(&[chunk(b"fmt ", &fmt(1, 2, 44100, 16), false)]);
+    assert!(read(bytes).is_err());
+    assert!(read(b"not an audio file".to_vec()).is_err());
+}
+
+fn form(kind: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+    let body: Vec<u8> = chunks.iter().flat_map(|c| c.clone()).collect();
+    let mut out = b"FORM".to_vec();
+    be32(&mut out, body.len() as u32 + 4);
+    out.extend_from_slice(kind);
+    out.extend(body);
+    out
+}
+
+/// COMM body for 44.1 kHz audio.
+fn comm(channels: u16, frames: u32, bits: u16, compression: Option<&[u8; 4]>) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&channels.to_be_bytes());
+    be32(&mut body, frames);
+    body.extend_from_slice(&bits.to_be_bytes());
+    body.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+    if let Some(compression) = compression {
+        body.extend_from_slice(compression);
+        body.extend_from_slice(&[0, 0]);
+    }
+    body
+}
+
+fn ssnd(bytes: usize) -> Vec<u8> {
+    let mut body = vec![0; 8];
+    body.extend(vec![0; bytes]);
+    chunk(b"SSND", &body, true)
+}
+
+#[test]
+fn aiff_pcm() {
+    let bytes = form(b"AIFF",
+                     &[chunk(b"COMM", &comm(2, 500, 24, None), true),
+                       chunk(b"NAME", b"Sweep", true),
+                       ssnd(6 * 500)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.file_type, AudioFileTypeId::AIFF);
+    assert_eq!(info.description.sample_rate, 44100.0);
+    assert_eq!(info.description.format_flags,
+               FLAG_IS_BIG_ENDIAN | FLAG_IS_SIGNED_INTEGER | FLAG_IS_PACKED);
+    assert_eq!(info.description.bytes_per_frame, 6);
+    assert_eq!(info.frame_count, Some(500));
+    assert_eq!(info.data_size, 3000);
+    assert_eq!(info.data_offset, Some(12 + 8 + 18 + 8 + 6 + 8 + 8));
+    assert_eq!(info.metadata, vec![("title".to_owned(), "Sweep".to_owned())]);
+    let format = info.description.stream_format().unwrap();
+    assert_eq!(format.endianness, Endianness::Big);
+    assert_eq!(format.sample_format, SampleFormat::I24);
+}
+
+#[test]
+fn aifc_compressions() {
+    let sowt = form(b"AIFC",
+                    &[chunk(b"COMM", &comm(1, 100, 16, Some(b"sowt")), true), ssnd(200)]);
+    let info = read(sowt).unwrap();
+    assert_eq!(info.file_type, AudioFileTypeId::AIFC);
+    assert_eq!(info.description.format_flags & FLAG_IS_BIG_ENDIAN, 0);
+    assert_eq!(info.description.stream_format().unwrap().endianness,
+               Endianness::Little);
+
+    let ima4 = form(b"AIFC",
+                    &[chunk(b"COMM", &comm(2, 10, 16, Some(b"ima4")), true), ssnd(680)]);
+    let info = read(ima4).unwrap();
+    assert_eq!(info.description.frames_per_packet, 64);
+    assert_eq!(info.description.bytes_per_packet, 68);
+    assert_eq!(info.packet_count, Some(10));
+    assert_eq!(info.frame_count, Some(640));
+}
+
+fn caf_chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = id.to_vec();
+    out.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    out.extend_from_slice(body);
+    out
+}
+
+fn desc(format_id: u32,
+        flags: u32,
+        bytes_per_packet: u32,
+        frames_per_packet: u32,
+        channels: u32,
+        bits: u32)
+        -> Vec<u8> {
+    let mut body = 48000f64.to_bits().to_be_bytes().to_vec();
+    for &v in [format_id, flags, bytes_per_packet, frames_per_packet, channels, bits].iter() {
+        be32(&mut body, v);
+    }
+    body
+}
+
+fn caf(chunks: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = b"caff".to_vec();
+    out.extend_from_slice(&[0, 1, 0, 0]);
+    for c in chunks {
+        out.extend_from_slice(c);
+    }
+    out
+}
+
+#[test]
+fn caf_pcm_with_layout_and_info() {
+    let mut chan = Vec::new();
+    be32(&mut chan, ChannelLayoutTag::Stereo as u32);
+    be32(&mut chan, 0);
+    be32(&mut chan, 0);
+    let mut info_body = Vec::new();
+    be32(&mut info_body, 1);
+    info_body.extend_from_slice(b"title\0Loop\0");
+    let mut data = vec![0; 4];
+    data.extend(vec![0; 8 * 300]);
+    let bytes = caf(&[caf_chunk(b"desc", &desc(FORMAT_LINEAR_PCM, 3, 8, 1, 2, 32)),
+                      caf_chunk(b"chan", &chan),
+                      caf_chunk(b"info", &info_body),
+                      caf_chunk(b"data", &data)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.file_type, AudioFileTypeId::CAF);
+    // CAF's little-endian float flags become native ASBD flags.
+    assert_eq!(info.description.format_flags, FLAG_IS_FLOAT | FLAG_IS_PACKED);
+    assert_eq!(info.description.sample_rate, 48000.0);
+    assert_eq!(info.channel_layout, Some(ChannelLayout::stereo()));
+    assert_eq!(info.frame_count, Some(300));
+    assert_eq!(info.data_size, 2400);
+    assert_eq!(info.metadata, vec![("title".to_owned(), "Loop".to_owned())]);
+}
+
+#[test]
+fn caf_compressed_with_packet_table() {
+    let mut pakt = Vec::new();
+    pakt.extend_from_slice(&40u64.to_be_bytes());
+    pakt.extend_from_slice(&40000u64.to_be_bytes());
+    be32(&mut pakt, 2112);
+    be32(&mut pakt, 848);
+    let aac = 0x6161_6320;
+    let mut data = vec![0; 4];
+    data.extend(vec![0; 12000]);
+    let bytes = caf(&[caf_chunk(b"desc", &desc(aac, 0, 0, 1024, 2, 0)),
+                      caf_chunk(b"kuki", &[1, 2, 3, 4, 5]),
+                      caf_chunk(b"pakt", &pakt),
+                      caf_chunk(b"data", &data)]);
+    let info = read(bytes).unwrap();
+    assert_eq!(info.packet_count, Some(40));
+    assert_eq!(info.frame_count, Some(40000));
+    assert_eq!(info.magic_cookie, Some(vec![1, 2, 3, 4, 5]));
+    assert!(info.description.flag_names().is_empty());
+    assert!(info.description.stream_format().is_err());
+    assert!((info.bit_rate().unwrap() - 12000.0 * 8.0 / (40000.0 / 48000.0)).abs() < 1e-6);
+}
@@ -0,0 +1,136 @@
+extern crate audiotoolbox;
+
+use std::f64::consts::PI;
+
+use audiotoolbox::dsp::filter::*;
+
+const RATE: f64 = 48000.0;
+/// The gain of a Butterworth filter at its cutoff, and of each half of a Linkwitz-Riley
+/// crossover at the crossover frequency.
+const HALF_POWER: f64 = -3.0103;
+const HALF_AMPLITUDE: f64 = -6.0206;
+
+fn gain(filter_type: FilterType, q: f64, frequency: f64) -> f64 {
+    Coefficients::design(filter_type, RATE, 1000.0, q).unwrap().response(RATE, frequency).gain()
+}
+
+fn assert_db(value: f64, expected: f64, tolerance: f64) {
+    assert!((value - expected).abs() <= tolerance,
+            "{} dB, expected {} ± {} dB",
+            value,
+            expected,
+            tolerance);
+}
+
+/// Peak amplitude of the last half of a sine at `frequency` after filtering every channel of
+/// interleaved stereo, with the second channel silent.
+fn filtered_amplitude(filter: &mut Filter, frequency: f64) -> f64 {
+    let frames = RATE as usize;
+    let mut samples = Vec::with_capacity(2 * frames);
+    for i in 0..frames {
+        samples.push((2.0 * PI * frequency * i as f64 / RATE).sin() as f32);
+        samples.push(0.0);
+    }
+    for chunk in samples.chunks_mut(2 * 333) {
+        filter.process(chunk);
+    }
+    assert!(samples.iter().skip(1).step_by(2).all(|&s| s == 0.0));
+    samples[frames..].iter().step_by(2).fold(0.0, |max: f64, &s| max.max(s.abs() as f64))
+}
+
+#[test]
+fn cookbook_filters_have_their_gain_at_the_center_frequency() {
+    let q = 0.5f64.sqrt();
+    assert_db(gain(FilterType::LowPass, q, 1000.0), HALF_POWER, 0.01);
+    assert_db(gain(FilterType::LowPass, q, 10.0), 0.0, 0.1);
+    assert_db(gain(FilterType::HighPass, q, 1000.0), HALF_POWER, 0.01);
+    assert_db(gain(FilterType::HighPass, q, 20000.0), 0.0, 0.1);
+    // The resonant peak of a second order filter is Q at the corner.
+    assert_db(gain(FilterType::LowPass, 4.0, 1000.0), 20.0 * 4f64.log10(), 0.01);
+
+    assert_db(gain(FilterType::BandPass, 2.0, 1000.0), 0.0, 0.01);
+    assert!(gain(FilterType::BandPass, 2.0, 100.0) < -20.0);
+    assert!(gain(FilterType::Notch, 2.0, 1000.0) < -100.0);
+    assert_db(gain(FilterType::Notch, 2.0, 100.0), 0.0, 0.1);
+    for &frequency in &[20.0, 1000.0, 15000.0] {
+        assert_db(gain(FilterType::AllPass, 1.0, frequency), 0.0, 0.01);
+    }
+
+    assert_db(gain(FilterType::Peaking(6.0), 1.0, 1000.0), 6.0, 0.01);
+    assert_db(gain(FilterType::Peaking(-12.0), 1.0, 1000.0), -12.0, 0.01);
+    assert_db(gain(FilterType::Peaking(6.0), 1.0, 20.0), 0.0, 0.1);
+
+    assert_db(gain(FilterType::LowShelf(6.0), q, 1.0), 6.0, 0.1);
+    assert_db(gain(FilterType::LowShelf(6.0), q, 1000.0), 3.0, 0.01);
+    assert_db(gain(FilterType::LowShelf(6.0), q, 23999.0), 0.0, 0.1);
+    assert_db(gain(FilterType::HighShelf(-6.0), q, 1.0), 0.0, 0.1);
+    assert_db(gain(FilterType::HighShelf(-6.0), q, 1000.0), -3.0, 0.01);
+    assert_db(gain(FilterType::HighShelf(-6.0), q, 23999.0), -6.0, 0.1);
+}
+
+#[test]
+fn butterworth_filters_are_half_power_at_cutoff() {
+    for order in 1..9 {
+        let low = Cascade::butterworth_low_pass(order, RATE, 2000.0).unwrap();
+        let high = Cascade::butterworth_high_pass(order, RATE, 2000.0).unwrap();
+        assert_eq!(low.sections().len(), order.div_ceil(2));
+        assert_db(low.response(RATE, 2000.0).gain(), HALF_POWER, 0.01);
+        assert_db(high.response(RATE, 2000.0).gain(), HALF_POWER, 0.01);
+        assert_db(low.response(RATE, 100.0).gain(), 0.0, 0.1);
+        assert_db(high.response(RATE, 20000.0).gain(), 0.0, 0.1);
+        // The analog response, with frequencies warped as the bilinear transform does.
+        let warp = |frequency: f64| (PI * frequency / RATE).tan();
+        let ratio = warp(2000.0) / warp(500.0);
+        let expected = -10.0 * (1.0 + ratio.powi(2 * order as i32)).log10();
+        assert_db(high.response(RATE, 500.0).gain(), expected, 0.01);
+    }
+}
+
+#[test]
+fn linkwitz_riley_crossovers_sum_flat() {
+    for &order in &[2, 4, 6, 8] {
+        let low = Cascade::linkwitz_riley_low_pass(order, RATE, 3000.0).unwrap();
+        let high = Cascade::linkwitz_riley_high_pass(order, RATE, 3000.0).unwrap();
+        assert_db(low.response(RATE, 3000.0).gain(), HALF_AMPLITUDE, 0.01);
+        assert_db(high.response(RATE, 3000.0).gain(), HALF_AMPLITUDE, 0.01);
+        for &frequency in &[50.0, 1000.0, 3000.0, 5000.0, 20000.0] {
+            let (low, high) = (low.response(RATE, frequency), high.response(RATE, frequency));
+            let sum = FrequencyResponse {
+                re: low.re + high.re,
+                im: low.im + high.im,
+            };
+            assert_db(sum.gain(), 0.0, 0.01);
+        }
+    }
+    assert!(Cascade::linkwitz_riley_low_pass(3, RATE, 3000.0).is_err());
+}
+
+#[test]
+fn designs_reject_frequencies_outside_the_band() {
+    assert!(Coefficients::design(FilterType::LowPass, RATE, 24000.0, 0.7).is_err());
+    assert!(Coefficients::design(FilterType::LowPass, RATE, 0.0, 0.7).is_err());
+    assert!(Coefficients::design(FilterType::LowPass, RATE, 1000.0, 0.0).is_err());
+    assert!(Cascade::butterworth_low_pass(0, RATE, 1000.0).is_err());
+}
+
+#[test]
+fn filters_process_each_channel_in_place() {
+    let cascade = Cascade::butterworth_low_pass(4, RATE, 1000.0).unwrap();
+    let mut filter = Filter::new(cascade.clone(), 2).unwrap();
+    let amplitude = filtered_amplitude(&mut filter, 1000.0);
+    assert_db(20.0 * amplitude.log10(), HALF_POWER, 0.01);
+    filter.reset();
+    assert!(filtered_amplitude(&mut filter, 4000.0) < 0.01);
+
+    // Coefficients can change under a running filter, but not the number of sections.
+    let mut filter = Filter::new(Coefficients::design(FilterType::Peaking(6.0), RATE, 500.0, 1.0)
+                                     .unwrap(),
+                                 2)
+        .unwrap();
+    let amplitude = filtered_amplitude(&mut filter, 500.0);
+    assert!((amplitude - 2.0).abs() < 0.01, "{}", amplitude);
+    assert!(filter.set_cascade(&cascade).is_err());
+    let notch = Coefficients::design(FilterType::Notch, RATE, 500.0, 1.0).unwrap();
+    filter.set_cascade(&notch.into()).unwrap();
+    assert!(filtered_amplitude(&mut filter, 500.0) < 0.001);
+}
@@ -0,0 +1,138 @@
+extern crate audiotoolbox;
+
+use std::f64::consts::PI;
+use std::io::Cursor;
+
+use audiotoolbox::audio_converter::{AudioConverter, AudioConverterReader};
+use audiotoolbox::channel_map::ChannelLayout;
+use audiotoolbox::file_info::AudioFileInfo;
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::{FrameReader, NativeFileReader};
+use audiotoolbox::frame_writer::{FrameWriter, NativeFileWriter};
+use audiotoolbox::stream_format::*;
+
+const FILE_TYPES: [AudioFileTypeId; 4] = [AudioFileTypeId::WAVE,
+                                          AudioFileTypeId::AIFF,
+                                          AudioFileTypeId::AIFC,
+                                          AudioFileTypeId::CAF];
+const SAMPLE_FORMATS: [SampleFormat; 6] = [SampleFormat::I8,
+                                           SampleFormat::I16,
+                                           SampleFormat::I24,
+                                           SampleFormat::I32,
+                                           SampleFormat::F32,
+                                           SampleFormat::F64];
+
+/// Interleaved stereo with a different tone in each channel.
+fn signal(frames: usize) -> Vec<f32> {
+    (0..frames * 2)
+        .map(|i| {
+            let t = (i / 2) as f64 / 44100.0;
+            let frequency = if i % 2 == 0 { 440.0 } else { 1000.0 };
+            (0.8 * (2.0 * PI * frequency * t).sin()) as f32
+        })
+        .collect()
+}
+
+fn round_trip(file_type: AudioFileTypeId,
+              format: StreamFormat,
+              layout: Option<ChannelLayout>,
+              samples: &[f32])
+              -> Result<(Vec<u8>, Vec<f32>), String> {
+    let channels = format.channels as usize;
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = NativeFileWriter::new(&mut cursor, file_type, format, layout)?;
+        // Several writes of odd sizes, as a streaming converter would make.
+        for chunk in samples.chunks(channels * 333) {
+            writer.write_frames(chunk)?;
+        }
+        writer.finish()?;
+        assert_eq!(writer.frames_written(), (samples.len() / channels) as u64);
+    }
+    let bytes = cursor.into_inner();
+    let mut reader = NativeFileReader::new(Cursor::new(bytes.clone()))?;
+    assert_eq!(reader.length(), Some((samples.len() / channels) as u64));
+    Ok((bytes, reader.read_to_end()?))
+}
+
+#[test]
+fn every_container_and_sample_format_round_trips() {
+    let samples = signal(1001);
+    for &file_type in FILE_TYPES.iter() {
+        for &sample_format in SAMPLE_FORMATS.iter() {
+            let format = StreamFormat::new(44100.0, 2, sample_format);
+            if file_type == AudioFileTypeId::AIFF && sample_format.is_float() {
+                assert!(NativeFileWriter::new(Cursor::new(Vec::new()), file_type, format, None)
+                            .is_err());
+                continue;
+            }
+            let (bytes, decoded) = round_trip(file_type, format, None, &samples).unwrap();
+            let info = AudioFileInfo::read(&mut Cursor::new(bytes)).unwrap();
+            assert_eq!(info.file_type, file_type);
+            assert_eq!(info.description.sample_rate, 44100.0);
+            assert_eq!(info.description.bits_per_channel as usize,
+                       sample_format.bits_per_sample());
+            assert_eq!(decoded.len(), samples.len());
+            let tolerance = match sample_format {
+                SampleFormat::I8 => 1.0 / 64.0,
+                SampleFormat::I16 => 1.0 / 16384.0,
+                _ => 1e-6,
+            };
+            for (a, b) in decoded.iter().zip(&samples) {
+                assert!((a - b).abs() <= tolerance,
+                        "{} {:?}: {} became {}",
+                        file_type,
+                        sample_format,
+                        b,
+                        a);
+            }
+        }
+    }
+}
+
+#[test]
+fn wav_8_bit_is_written_unsigned() {
+    let format = StreamFormat::new(8000.0, 2, SampleFormat::I8);
+    let (bytes, _) = round_trip(AudioFileTypeId::WAVE, format, None, &[0.0, 0.0]).unwrap();
+    assert_eq!(&bytes[bytes.len() - 2..], &[0x80, 0x80]);
+}
+
+#[test]
+fn headers_record_sizes_and_layout() {
+    let samples = vec![0.25; 6 * 100];
+    let format = StreamFormat::new(48000.0, 6, SampleFormat::I24);
+    for &file_type in [AudioFileTypeId::WAVE, AudioFileTypeId::CAF].iter() {
+        let layout = Some(ChannelLayout::surround_5_1());
+        let (bytes, _) = round_trip(file_type, format, layout, &samples).unwrap();
+        let info = AudioFileInfo::read(&mut Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(info.data_size, 6 * 3 * 100);
+        assert_eq!(info.frame_count, Some(100));
+        assert_eq!(info.channel_layout, Some(ChannelLayout::surround_5_1()));
+        assert_eq!(info.data_offset.unwrap() + info.data_size, bytes.len() as u64);
+    }
+
+    // Odd data sizes are padded in WAV and AIFF.
+    let format = StreamFormat::new(22050.0, 1, SampleFormat::I8);
+    for &file_type in [AudioFileTypeId::WAVE, AudioFileTypeId::AIFF].iter() {
+        let (bytes, decoded) = round_trip(file_type, format, None, &[0.5, 0.5, 0.5]).unwrap();
+        assert_eq!(bytes.len() % 2, 0);
+        assert_eq!(decoded.len(), 3);
+    }
+}
+
+#[test]
+fn converter_reader_changes_rate_and_channels() {
+    let samples = signal(4410);
+    let format = StreamFormat::new(44100.0, 2, SampleFormat::F32);
+    let (bytes, _) = round_trip(AudioFileTypeId::CAF, format, None, &samples).unwrap();
+    let reader = NativeFileReader::new(Cursor::new(bytes)).unwrap();
+    let converter = AudioConverter::new(reader.format(),
+                                        StreamFormat::new(22050.0, 1, SampleFormat::F32))
+        .unwrap();
+    let mut reader = AudioConverterReader::new(reader, converter).unwrap();
+    assert_eq!(reader.length(), Some(2205));
+    assert_eq!(reader.channel_layout(), Some(ChannelLayout::mono()));
+    let output = reader.read_to_end().unwrap();
+    assert_eq!(output.len(), 2205);
+    assert!(output.iter().any(|s| s.abs() > 0.3));
+}
@@ -0,0 +1,176 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::f64::consts::PI;
+
+use audiotoolbox::channel_map::{Channel, ChannelLayout};
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::loudness::*;
+use audiotoolbox::stream_format::*;
+use common::*;
+
+/// A 1 kHz sine per channel with the given peak levels in dBFS, as the EBU test vectors
+/// use.
+fn levels(rate: f64, levels: &[f64], seconds: f64) -> Vec<f32> {
+    let amplitudes: Vec<f64> = levels.iter().map(|&level| gain(level)).collect();
+    tone(1000.0, rate, &amplitudes, (seconds * rate).round() as usize)
+}
+
+fn measured(rate: f64, channels: usize, samples: &[f32]) -> LoudnessMeter {
+    let mut meter = LoudnessMeter::new(rate, channels).unwrap();
+    // Uneven chunks, as a streaming reader would supply.
+    for chunk in samples.chunks(channels * 1234) {
+        meter.process(chunk);
+    }
+    meter
+}
+
+fn assert_near(value: Option<f64>, expected: f64, tolerance: f64) {
+    let value = value.expect("no measurement");
+    assert!((value - expected).abs() <= tolerance,
+            "measured {}, expected {} ± {}",
+            value,
+            expected,
+            tolerance);
+}
+
+#[test]
+fn tech_3341_stationary_sines() {
+    for &rate in &[48000.0, 44100.0] {
+        for &level in &[-23.0, -33.0] {
+            let meter = measured(rate, 2, &levels(rate, &[level, level], 4.0));
+            assert_near(meter.momentary(), level, 0.1);
+            assert_near(meter.short_term(), level, 0.1);
+            assert_near(meter.integrated(), level, 0.1);
+        }
+    }
+}
+
+#[test]
+fn tech_3341_gating() {
+    // The EBU cases, shortened: quiet passages the gates remove around a -23 LUFS tone, and
+    // equal parts 3 dB either side of it.
+    let rate = 48000.0;
+    let cases: [&[(f64, f64)]; 3] = [&[(-36.0, 1.0), (-23.0, 20.0), (-36.0, 1.0)],
+                                     &[(-72.0, 1.0),
+                                       (-36.0, 1.0),
+                                       (-23.0, 20.0),
+                                       (-36.0, 1.0),
+                                       (-72.0, 1.0)],
+                                     &[(-26.0, 4.0), (-20.0, 4.02), (-26.0, 4.0)]];
+    for case in cases.iter() {
+        let meter = measured(rate, 2, &segments(0.0, rate, 2, case));
+        assert_near(meter.integrated(), -23.0, 0.1);
+    }
+}
+
+#[test]
+fn tech_3341_surround_channels_are_weighted() {
+    let rate = 48000.0;
+    let layout = ChannelLayout::new(vec![Channel::FrontLeft,
+                                         Channel::FrontRight,
+                                         Channel::FrontCenter,
+                                         Channel::SideLeft,
+                                         Channel::SideRight]);
+    let mut meter = LoudnessMeter::with_layout(rate, &layout).unwrap();
+    meter.process(&levels(rate, &[-28.0, -28.0, -24.0, -30.0, -30.0], 2.0));
+    assert_near(meter.integrated(), -23.0, 0.1);
+
+    // The LFE channel of 5.1 is not measured.
+    let mut meter = LoudnessMeter::new(rate, 6).unwrap();
+    meter.process(&levels(rate, &[-28.0, -28.0, -24.0, 0.0, -30.0, -30.0], 2.0));
+    assert_near(meter.integrated(), -23.0, 0.1);
+
+    // A back centre counts as much as a front channel, so it has to be 1.5 dB louder than
+    // the pair of surrounds together.
+    let layout = ChannelLayout::new(vec![Channel::FrontLeft,
+                                         Channel::FrontRight,
+                                         Channel::FrontCenter,
+                                         Channel::BackCenter]);
+    let mut meter = LoudnessMeter::with_layout(rate, &layout).unwrap();
+    meter.process(&levels(rate, &[-28.0, -28.0, -24.0, -25.5], 2.0));
+    assert_near(meter.integrated(), -23.0, 0.1);
+}
+
+#[test]
+fn tech_3341_short_term_is_steady_over_a_three_second_period() {
+    let rate = 48000.0;
+    let mut pattern = Vec::new();
+    for _ in 0..4 {
+        pattern.push((-20.0, 1.34));
+        pattern.push((-30.0, 1.66));
+    }
+    let samples = segments(0.0, rate, 2, &pattern);
+    let mut meter = LoudnessMeter::new(rate, 2).unwrap();
+    // Every 100 ms step.
+    for (i, chunk) in samples.chunks(2 * 4800).enumerate() {
+        meter.process(chunk);
+        if i >= 30 {
+            assert_near(meter.short_term(), -23.0, 0.1);
+        }
+    }
+}
+
+#[test]
+fn tech_3342_loudness_range() {
+    // Shortened from the EBU cases; the -50 LUFS ends only have to be gated out.
+    let rate = 48000.0;
+    let cases: [(&[(f64, f64)], f64); 4] = [(&[(-20.0, 5.0), (-30.0, 5.0)], 10.0),
+                                            (&[(-20.0, 5.0), (-15.0, 5.0)], 5.0),
+                                            (&[(-40.0, 5.0), (-20.0, 5.0)], 20.0),
+                                            (&[(-50.0, 1.0),
+                                               (-35.0, 15.0),
+                                               (-20.0, 15.0),
+                                               (-35.0, 15.0),
+                                               (-50.0, 1.0)],
+                                             15.0)];
+    for &(case, expected) in cases.iter() {
+        let meter = measured(rate, 2, &segments(0.0, rate, 2, case));
+        assert_near(meter.loudness_range(), expected, 1.0);
+    }
+}
+
+#[test]
+fn true_peak_finds_peaks_between_samples() {
+    let rate = 48000.0;
+    // A quarter of the sample rate 45 degrees out of phase peaks halfway between samples,
+    // 3 dB above them.
+    let mut meter = LoudnessMeter::new(rate, 1).unwrap();
+    meter.process(&sine(rate / 4.0, PI / 4.0, rate, &[gain(-6.0)], 48000));
+    assert!((meter.sample_peak() + 9.01).abs() < 0.05, "{}", meter.sample_peak());
+    let true_peak = meter.true_peak();
+    assert!(true_peak > -6.4 && true_peak < -5.8, "{}", true_peak);
+
+    let mut meter = LoudnessMeter::new(44100.0, 2).unwrap();
+    meter.process(&sine(997.0, 0.3, 44100.0, &[gain(-1.0), gain(-12.0)], 44100));
+    let peaks = meter.channel_true_peaks();
+    assert!((peaks[0] + 1.0).abs() < 0.2, "{:?}", peaks);
+    assert!((peaks[1] + 12.0).abs() < 0.2, "{:?}", peaks);
+}
+
+#[test]
+fn silence_has_no_loudness() {
+    let meter = measured(48000.0, 2, &vec![0.0; 2 * 48000]);
+    assert_eq!(meter.integrated(), None);
+    assert_eq!(meter.loudness_range(), None);
+    assert_eq!(meter.true_peak(), f64::NEG_INFINITY);
+
+    // Less than one block has no momentary loudness yet.
+    let meter = measured(48000.0, 2, &levels(48000.0, &[-23.0, -23.0], 0.3));
+    assert_eq!(meter.momentary(), None);
+    assert_eq!(meter.integrated(), None);
+}
+
+#[test]
+fn measure_reads_a_whole_file() {
+    let rate = 48000.0;
+    let format = StreamFormat::new(rate, 2, SampleFormat::I24);
+    let samples = segments(0.0, rate, 2, &[(-36.0, 1.0), (-23.0, 10.0)]);
+    let report = measure(reader(AudioFileTypeId::WAVE, format, &samples)).unwrap();
+    assert_near(report.integrated, -23.0, 0.1);
+    assert_near(report.max_momentary, -23.0, 0.1);
+    assert_near(report.max_short_term, -23.0, 0.1);
+    assert!(report.loudness_range.is_some());
+    assert!((report.sample_peak + 23.0).abs() < 0.01);
+}
@@ -0,0 +1,181 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::f64::consts::PI;
+use std::thread;
+
+use audiotoolbox::meter::*;
+
+const RATE: f64 = 48000.0;
+
+/// Stereo, with a sine of `amplitude` on the first channel and silence on the second.
+fn sine(amplitude: f64, frequency: f64, seconds: f64) -> Vec<f32> {
+    common::sine(frequency, 0.0, RATE, &[amplitude, 0.0], (seconds * RATE).round() as usize)
+}
+
+fn process(meter: &mut LevelMeter, samples: &[f32]) {
+    for block in samples.chunks(2 * 512) {
+        meter.process(block);
+    }
+}
+
+fn silence() -> ChannelLevels {
+    ChannelLevels {
+        peak: 0.0,
+        peak_hold: 0.0,
+        rms: 0.0,
+        true_peak: 0.0,
+        ballistic: 0.0,
+    }
+}
+
+fn assert_close(value: f32, expected: f64, tolerance: f64) {
+    assert!((value as f64 - expected).abs() <= tolerance,
+            "{}, expected {} ± {}",
+            value,
+            expected,
+            tolerance);
+}
+
+#[test]
+fn measures_a_steady_sine() {
+    let mut meter = LevelMeter::new(RATE, 2).unwrap();
+    process(&mut meter, &sine(0.5, 997.0, 2.0));
+    let levels = meter.levels();
+    assert_close(levels[0].peak, 0.5, 0.001);
+    assert_close(levels[0].peak_hold, 0.5, 0.001);
+    assert_close(levels[0].rms, 0.5 / 2f64.sqrt(), 0.002);
+    assert!(levels[0].true_peak >= levels[0].peak);
+    assert_close(levels[0].true_peak, 0.5, 0.005);
+    // VU meters read the RMS of a sine.
+    assert_close(levels[0].ballistic, 0.5 / 2f64.sqrt(), 0.005);
+    assert_eq!(levels[1].peak, 0.0);
+    assert_eq!(levels[1].rms, 0.0);
+}
+
+#[test]
+fn true_peak_finds_peaks_between_samples() {
+    // A quarter of the sample rate, sampled 45 degrees off its crests.
+    let samples: Vec<f32> = (0..4800)
+        .flat_map(|i| {
+            let value = (0.9 * (PI / 2.0 * i as f64 + PI / 4.0).sin()) as f32;
+            vec![value, value]
+        })
+        .collect();
+    let mut meter = LevelMeter::new(RATE, 2).unwrap();
+    process(&mut meter, &samples);
+    let levels = meter.levels();
+    assert_close(levels[0].peak, 0.9 / 2f64.sqrt(), 0.001);
+    assert_close(levels[0].true_peak, 0.9, 0.01);
+}
+
+#[test]
+fn peak_hold_keeps_peaks_for_the_hold_time() {
+    let mut meter = LevelMeter::new(RATE, 2).unwrap().with_peak_hold(0.5);
+    process(&mut meter, &sine(0.8, 1000.0, 0.1));
+    process(&mut meter, &sine(0.1, 1000.0, 0.3));
+    assert_close(meter.levels()[0].peak, 0.1, 0.001);
+    assert_close(meter.levels()[0].peak_hold, 0.8, 0.001);
+    process(&mut meter, &sine(0.1, 1000.0, 0.3));
+    assert_close(meter.levels()[0].peak_hold, 0.1, 0.001);
+}
+
+#[test]
+fn peak_hold_falls_to_the_highest_peak_since() {
+    let mut meter = LevelMeter::new(RATE, 2).unwrap().with_peak_hold(0.5);
+    process(&mut meter, &sine(0.8, 1000.0, 0.1));
+    process(&mut meter, &sine(0.5, 1000.0, 0.2));
+    process(&mut meter, &sine(0.1, 1000.0, 0.35));
+    // Just after the first peak's hold is over, the second is held for the rest of its own.
+    assert_close(meter.levels()[0].peak, 0.1, 0.001);
+    assert_close(meter.levels()[0].peak_hold, 0.5, 0.001);
+    process(&mut meter, &sine(0.1, 1000.0, 0.2));
+    assert_close(meter.levels()[0].peak_hold, 0.1, 0.001);
+}
+
+#[test]
+fn integration_time_sets_how_fast_rms_follows() {
+    let mut slow = LevelMeter::new(RATE, 2).unwrap().with_integration_time(1.0);
+    let mut fast = LevelMeter::new(RATE, 2).unwrap().with_integration_time(0.01);
+    let samples = sine(1.0, 1000.0, 0.1);
+    process(&mut slow, &samples);
+    process(&mut fast, &samples);
+    // After a tenth of the time constant the mean square reaches 1 - e^-0.1 of its level.
+    let expected = ((1.0 - (-0.1f64).exp()) / 2.0).sqrt();
+    assert_close(slow.levels()[0].rms, expected, 0.01);
+    assert_close(fast.levels()[0].rms, 0.5f64.sqrt(), 0.001);
+}
+
+#[test]
+fn ballistics_rise_and_fall_as_specified() {
+    // A VU meter reaches 99% of a steady tone in 300 ms.
+    let mut meter = LevelMeter::new(RATE, 2).unwrap();
+    assert_eq!(meter.ballistics(), Ballistics::Vu);
+    process(&mut meter, &sine(1.0, 1000.0, 0.3));
+    assert_close(meter.levels()[0].ballistic, 0.99 * 0.5f64.sqrt(), 0.01);
+
+    for &(ballistics, burst, fall, seconds) in &[(Ballistics::PpmType1, 0.005, 20.0, 1.5),
+                                                 (Ballistics::PpmType2, 0.010, 24.0, 2.8)] {
+        let mut meter = LevelMeter::new(RATE, 2).unwrap().with_ballistics(ballistics);
+        let frames = (burst * RATE) as usize;
+        meter.process(&vec![0.5; 2 * frames]);
+        let reading = meter.levels()[0].ballistic as f64;
+        assert!((20.0 * (reading / 0.5).log10() + 2.0).abs() < 0.05, "{}", reading);
+
+        process(&mut meter, &vec![0.5; 2 * 48000]);
+        assert_close(meter.levels()[0].ballistic, 0.5, 0.001);
+        meter.process(&vec![0.0; 2 * (seconds * RATE) as usize]);
+        let reading = meter.levels()[0].ballistic as f64;
+        assert!((20.0 * (reading / 0.5).log10() + fall).abs() < 0.01, "{}", reading);
+    }
+}
+
+#[test]
+fn readings_match_audio_queue_level_meters() {
+    let mut meter = LevelMeter::new(RATE, 2).unwrap();
+    let readings = meter.readings();
+    assert_eq!(readings.channels(), 2);
+    process(&mut meter, &sine(0.5, 997.0, 3.0));
+    let linear = readings.current_level_meter();
+    assert_eq!(linear, vec![meter.levels()[0].level_meter_state(),
+                            meter.levels()[1].level_meter_state()]);
+    assert_eq!(linear[0].peak_power, meter.levels()[0].peak);
+    assert_eq!(linear[0].average_power, meter.levels()[0].rms);
+
+    let db = readings.current_level_meter_db();
+    assert_close(db[0].peak_power, 20.0 * 0.5f64.log10(), 0.02);
+    assert_close(db[0].average_power, 20.0 * 0.5f64.log10() - 3.0103, 0.05);
+    assert_eq!(db[1].peak_power, f32::NEG_INFINITY);
+
+    meter.reset();
+    assert!(readings.levels().iter().all(|levels| levels.peak_hold == 0.0));
+}
+
+#[test]
+fn publishes_readings_to_other_threads() {
+    let mut meter = LevelMeter::new(RATE, 2).unwrap().with_ballistics(Ballistics::PpmType2);
+    let readings = meter.readings();
+    // Every block holds one level, so a reading mixing two blocks would show.
+    let reader = thread::spawn(move || {
+        let mut levels = vec![silence(); 2];
+        for _ in 0..20000 {
+            readings.read(&mut levels);
+            assert_eq!(levels[0].peak, levels[1].peak);
+            assert_eq!(levels[0].rms, levels[1].rms);
+        }
+    });
+    for block in 0..20000 {
+        let level = (block % 100) as f32 / 100.0;
+        meter.process(&[level; 2 * 64]);
+    }
+    reader.join().unwrap();
+    let levels = meter.readings().levels();
+    assert_eq!(&levels[..], meter.levels());
+}
+
+#[test]
+fn rejects_invalid_settings() {
+    assert!(LevelMeter::new(0.0, 2).is_err());
+    assert!(LevelMeter::new(RATE, 0).is_err());
+}
@@ -0,0 +1,141 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::io::Cursor;
+
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::{FrameReader, NativeFileReader};
+use audiotoolbox::loudness::{self, LoudnessMeter, LoudnessReport};
+use audiotoolbox::normalize::*;
+use audiotoolbox::stream_format::*;
+use common::*;
+
+/// A stereo 1 kHz sine whose level in dBFS changes after each of the given durations.
+fn stereo(rate: f64, levels: &[(f64, f64)]) -> Vec<f32> {
+    segments(0.0, rate, 2, levels)
+}
+
+/// A reader of f32 frames held in memory.
+fn f32_reader(rate: f64, channels: u32, samples: &[f32]) -> NativeFileReader<Cursor<Vec<u8>>> {
+    reader(AudioFileTypeId::CAF,
+           StreamFormat::new(rate, channels, SampleFormat::F32),
+           samples)
+}
+
+/// Reads in uneven chunks, as a writer pulling through the converter would.
+fn read_all<R: FrameReader>(mut reader: R) -> Vec<f32> {
+    let channels = reader.format().channels as usize;
+    let mut buffer = vec![0.0; 777 * channels];
+    let mut samples = Vec::new();
+    loop {
+        let frames = reader.read_frames(&mut buffer).unwrap();
+        if frames == 0 {
+            return samples;
+        }
+        samples.extend_from_slice(&buffer[..frames * channels]);
+    }
+}
+
+fn report(rate: f64, samples: &[f32]) -> LoudnessReport {
+    let mut meter = LoudnessMeter::new(rate, 2).unwrap();
+    meter.process(samples);
+    meter.report()
+}
+
+#[test]
+fn targets_give_the_gain_to_apply() {
+    let rate = 48000.0;
+    let measured = report(rate, &stereo(rate, &[(-20.0, 5.0)]));
+    let gain = Target::Loudness(-23.0).gain(&measured).unwrap();
+    assert!((gain + 3.0).abs() < 0.1, "{}", gain);
+    let gain = Target::Peak(-1.0).gain(&measured).unwrap();
+    assert!((gain - 19.0).abs() < 0.1, "{}", gain);
+
+    let silence = report(rate, &vec![0.0; 2 * 48000]);
+    assert_eq!(Target::Loudness(-23.0).gain(&silence), None);
+    assert_eq!(Target::Peak(-1.0).gain(&silence), None);
+}
+
+#[test]
+fn limiter_keeps_true_peaks_under_the_ceiling() {
+    let rate = 48000.0;
+    let samples = stereo(rate,
+                         &[(-20.0, 1.0), (0.0, 0.5), (-20.0, 1.0), (-3.0, 0.01), (-20.0, 1.0)]);
+    // Twelve dB of gain takes the bursts well over full scale.
+    let limiter = Limiter::new(rate, 2, -1.0).unwrap();
+    let input = f32_reader(rate, 2, &samples);
+    let output = read_all(NormalizingReader::new(input, 12.0, Some(limiter)).unwrap());
+    assert_eq!(output.len(), samples.len());
+    let measured = report(rate, &output);
+    assert!(measured.true_peak < -0.99, "{}", measured.true_peak);
+
+    // Away from the bursts the gain is untouched, and nothing is shifted in time.
+    let linear = gain(12.0) as f32;
+    for &second in &[0.5, 2.3, 3.4] {
+        let i = (second * rate) as usize * 2;
+        assert!((output[i] - samples[i] * linear).abs() < 1e-4,
+                "{} {}",
+                output[i],
+                samples[i] * linear);
+    }
+}
+
+#[test]
+fn limiter_passes_quiet_audio_unchanged() {
+    let rate = 44100.0;
+    let samples = stereo(rate, &[(-6.0, 0.5)]);
+    let mut limiter = Limiter::new(rate, 2, -1.0)
+        .unwrap()
+        .with_lookahead(0.002)
+        .with_release(0.05);
+    let latency = limiter.latency();
+    assert_eq!(latency, 88 - 1 + 8);
+
+    let mut buffer = samples.clone();
+    buffer.extend(vec![0.0; 2 * latency]);
+    limiter.process(&mut buffer);
+    assert!(buffer[..2 * latency].iter().all(|&s| s == 0.0));
+    assert_eq!(&buffer[2 * latency..], &samples[..]);
+    assert_eq!(limiter.max_gain_reduction(), 0.0);
+}
+
+#[test]
+fn normalizes_loudness_in_one_pass() {
+    let rate = 48000.0;
+    // A short burst near full scale takes the peak far above the loudness.
+    let samples = stereo(rate, &[(-30.0, 3.0), (-12.0, 3.0), (0.0, 0.01), (-12.0, 3.0)]);
+    let measured = loudness::measure(f32_reader(rate, 2, &samples)).unwrap();
+    let gain = Target::Loudness(-8.0).gain(&measured).unwrap();
+    let limiter = Limiter::new(rate, 2, -1.0).unwrap();
+    let input = f32_reader(rate, 2, &samples);
+    let mut normalizing = NormalizingReader::new(input, gain, Some(limiter)).unwrap();
+    let output = read_all(&mut normalizing);
+    let limited = normalizing.limiter().unwrap().max_gain_reduction();
+    assert!(limited > 4.0, "{}", limited);
+
+    let normalized = report(rate, &output);
+    assert!(normalized.true_peak < -0.99, "{}", normalized.true_peak);
+    let integrated = normalized.integrated.unwrap();
+    assert!((integrated + 8.0).abs() < 0.2, "{}", integrated);
+}
+
+#[test]
+fn normalizing_reader_seeks() {
+    let rate = 48000.0;
+    let samples = stereo(rate, &[(-20.0, 1.0)]);
+    let limiter = Limiter::new(rate, 2, -1.0).unwrap();
+    let input = f32_reader(rate, 2, &samples);
+    let mut normalizing = NormalizingReader::new(input, 6.0, Some(limiter)).unwrap();
+    read_all(&mut normalizing);
+    normalizing.seek(24000).unwrap();
+    let output = read_all(&mut normalizing);
+    assert_eq!(output.len(), samples.len() / 2);
+    let linear = gain(6.0) as f32;
+    assert!((output[1000] - samples[48000 + 1000] * linear).abs() < 1e-4);
+
+    assert!(NormalizingReader::new(f32_reader(rate, 1, &[0.0; 10]),
+                                   0.0,
+                                   Some(Limiter::new(rate, 2, -1.0).unwrap()))
+                .is_err());
+}
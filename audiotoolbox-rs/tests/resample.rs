@@ -0,0 +1,102 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use audiotoolbox::resample::*;
+use common::*;
+
+fn rms(signal: &[f32]) -> f64 {
+    let margin = signal.len() / 8;
+    let middle = &signal[margin..signal.len() - margin];
+    (middle.iter().map(|&s| s as f64 * s as f64).sum::<f64>() / middle.len() as f64).sqrt()
+}
+
+#[test]
+fn output_length_follows_ratio() {
+    for &(from, to) in &[(44100.0, 48000.0), (48000.0, 44100.0), (48000.0, 41000.0)] {
+        let output = resample(&vec![0.0; 2 * 10000], 2, from, to, Quality::Fast).unwrap();
+        assert_eq!(output.len(), 2 * (10000.0 * to / from).ceil() as usize);
+    }
+}
+
+#[test]
+fn passband_ripple_is_small() {
+    for &(quality, edge, ripple) in &[(Quality::Medium, 0.75, 0.01), (Quality::Best, 0.85, 0.01)] {
+        for &(from, to) in &[(44100.0, 48000.0), (48000.0, 44100.0), (44100.0, 41000.0)] {
+            let nyquist = 0.5 * f64::min(from, to);
+            for step in 1..10 {
+                let frequency = nyquist * edge * step as f64 / 9.0;
+                let output = resample(&tone(frequency, from, &[1.0], 20000), 1, from, to, quality)
+                    .unwrap();
+                let gain = db(amplitude(&output, 1, 0, frequency, to));
+                assert!(gain.abs() < ripple,
+                        "{:?} {} -> {}: {} Hz has gain {} dB",
+                        quality,
+                        from,
+                        to,
+                        frequency,
+                        gain);
+            }
+        }
+    }
+}
+
+#[test]
+fn downsampling_rejects_aliases() {
+    for &(quality, rejection) in &[(Quality::Fast, -50.0), (Quality::Medium, -80.0),
+                                   (Quality::Best, -100.0)] {
+        // Everything above the output Nyquist frequency would fold back into the band.
+        for &frequency in &[22500.0, 23000.0, 23800.0] {
+            let input = tone(frequency, 48000.0, &[1.0], 20000);
+            let output = resample(&input, 1, 48000.0, 44100.0, quality).unwrap();
+            let level = db(rms(&output) * 2f64.sqrt());
+            assert!(level < rejection,
+                    "{:?}: {} Hz leaked at {} dB",
+                    quality,
+                    frequency,
+                    level);
+        }
+    }
+}
+
+#[test]
+fn streaming_matches_one_shot() {
+    let input: Vec<f32> = tone(1000.0, 44100.0, &[1.0], 5000)
+        .into_iter()
+        .flat_map(|s| vec![s, -s])
+        .collect();
+    let expected = resample(&input, 2, 44100.0, 48000.0, Quality::Medium).unwrap();
+
+    let mut resampler = Resampler::new(2, 44100.0, 48000.0, Quality::Medium).unwrap();
+    let mut output = Vec::new();
+    let mut offset = 0;
+    for chunk in [1usize, 7, 64, 333, 1000, 2].iter().cycle() {
+        if offset == input.len() {
+            break;
+        }
+        let end = (offset + chunk * 2).min(input.len());
+        resampler.process(&input[offset..end], &mut output);
+        offset = end;
+    }
+    resampler.flush(&mut output);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn stopband_starts_at_the_output_nyquist_frequency() {
+    // Tones at and just above the output Nyquist frequency fold back to just below it, so
+    // they must be attenuated as much as the quality promises.
+    for &(quality, rejection) in &[(Quality::Fast, -59.0), (Quality::Medium, -89.0),
+                                   (Quality::Best, -119.0)] {
+        for &frequency in &[22050.0, 22060.0, 22100.0, 22200.0] {
+            let input = tone(frequency, 48000.0, &[1.0], 40000);
+            let output = resample(&input, 1, 48000.0, 44100.0, quality).unwrap();
+            let level = db(rms(&output) * 2f64.sqrt());
+            assert!(level < rejection,
+                    "{:?}: {} Hz leaked at {} dB",
+                    quality,
+                    frequency,
+                    level);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::f64::consts::FRAC_PI_2;
+use std::ops::Range;
+
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::{FrameReader, RangeReader};
+use audiotoolbox::silence::*;
+use audiotoolbox::stream_format::*;
+use common::*;
+
+const RATE: f64 = 8000.0;
+
+/// A level for `segments` that is digital silence.
+const SILENT: f64 = f64::NEG_INFINITY;
+
+/// A mono 1 kHz cosine, so that every segment starts on a peak; see `segments`.
+fn cosine(levels: &[(f64, f64)]) -> Vec<f32> {
+    segments(FRAC_PI_2, RATE, 1, levels)
+}
+
+fn silence(detector: SilenceDetector, samples: &[f32]) -> Silence {
+    let mut detector = detector;
+    for chunk in samples.chunks(999) {
+        detector.process(chunk);
+    }
+    detector.finish()
+}
+
+#[test]
+fn finds_silence_to_the_frame() {
+    let samples = cosine(&[(-20.0, 1.0), (SILENT, 1.0), (-20.0, 1.0), (SILENT, 0.7)]);
+    let detector = SilenceDetector::new(RATE, 1, -60.0).unwrap();
+    let found = silence(detector, &samples);
+    assert_eq!(found.regions, vec![8000..16000, 24000..29600]);
+    assert_eq!(found.length, 29600);
+}
+
+#[test]
+fn short_pauses_are_not_silence() {
+    let samples = cosine(&[(-20.0, 1.0), (SILENT, 0.3), (-20.0, 1.0)]);
+    let found = silence(SilenceDetector::new(RATE, 1, -60.0).unwrap(), &samples);
+    assert!(found.regions.is_empty());
+    let found = silence(SilenceDetector::new(RATE, 1, -60.0).unwrap().with_hold(0.2), &samples);
+    assert_eq!(found.regions, vec![8000..10400]);
+}
+
+#[test]
+fn hysteresis_keeps_noise_near_the_threshold_silent() {
+    // Room tone with a bump just over the threshold, then one well over it.
+    let samples = cosine(&[(-20.0, 1.0),
+                           (-65.0, 1.0),
+                           (-58.0, 0.1),
+                           (-65.0, 1.0),
+                           (-50.0, 0.1),
+                           (-65.0, 1.0)]);
+    let found = silence(SilenceDetector::new(RATE, 1, -60.0).unwrap(), &samples);
+    assert_eq!(found.regions, vec![8000..24800, 25600..33600]);
+
+    // Without hysteresis the first bump ends the silence too. Its last sample above the
+    // threshold is the final crest of the cosine, three frames before it stops.
+    let detector = SilenceDetector::new(RATE, 1, -60.0).unwrap().with_hysteresis(0.0);
+    let found = silence(detector, &samples);
+    assert_eq!(found.regions, vec![8000..16000, 16797..24800, 25600..33600]);
+}
+
+#[test]
+fn reports_silence_while_streaming() {
+    let mut detector = SilenceDetector::new(RATE, 2, -60.0).unwrap();
+    detector.process(&vec![0.5; 2 * 8000]);
+    assert!(!detector.is_silent());
+    detector.process(&vec![0.0; 2 * 3000]);
+    assert!(!detector.is_silent());
+    detector.process(&vec![0.0; 2 * 1000]);
+    assert!(detector.is_silent());
+    assert!(detector.regions().is_empty());
+    detector.process(&[0.5, -0.5]);
+    assert!(!detector.is_silent());
+    assert_eq!(detector.regions().to_vec(), vec![8000..12000]);
+}
+
+#[test]
+fn segments_share_the_silence_between_them() {
+    let silence = Silence {
+        regions: vec![0..100, 300..310, 500..600],
+        length: 600,
+    };
+    assert_eq!(silence.segments(0), vec![100..300, 310..500]);
+    assert_eq!(silence.segments(20), vec![80..305, 305..520]);
+    assert_eq!(silence.trimmed(0), 100..500);
+    assert_eq!(silence.trimmed(200), 0..600);
+
+    let silence = Silence {
+        regions: vec![Range { start: 0, end: 600 }],
+        length: 600,
+    };
+    assert!(silence.segments(10).is_empty());
+    assert_eq!(silence.trimmed(10), 0..0);
+
+    let silence = Silence {
+        regions: vec![],
+        length: 600,
+    };
+    assert_eq!(silence.segments(10), vec![0..600]);
+}
+
+#[test]
+fn trims_a_file() {
+    let samples = cosine(&[(SILENT, 0.5), (-20.0, 1.0), (SILENT, 1.0)]);
+    let format = StreamFormat::new(RATE, 1, SampleFormat::I16);
+    let mut reader = reader(AudioFileTypeId::WAVE, format, &samples);
+    let detector = SilenceDetector::new(RATE, 1, -60.0).unwrap();
+    let found = detect(&mut reader, detector).unwrap();
+    let trimmed = found.trimmed(80);
+    assert_eq!(trimmed, 3920..12080);
+
+    let mut range = RangeReader::new(reader, trimmed).unwrap();
+    assert_eq!(range.length(), Some(8160));
+    let frames = range.read_to_end().unwrap();
+    assert_eq!(frames.len(), 8160);
+    assert!(frames[..80].iter().all(|&s| s == 0.0));
+    assert!((frames[80] - 0.1).abs() < 1e-3);
+    range.seek(8100).unwrap();
+    assert_eq!(range.read_to_end().unwrap().len(), 60);
+}
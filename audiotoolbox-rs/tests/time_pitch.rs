@@ -0,0 +1,147 @@
+extern crate audiotoolbox;
+
+pub mod common;
+
+use std::f64::consts::PI;
+
+use audiotoolbox::dsp::time_pitch::*;
+use audiotoolbox::file_type::AudioFileTypeId;
+use audiotoolbox::frame_reader::FrameReader;
+use audiotoolbox::stream_format::*;
+use common::{amplitude, reader, sine};
+
+/// Stereo, with the second channel an octave above the first and half as loud.
+fn tone(frequency: f64, rate: f64, frames: usize) -> Vec<f32> {
+    let octave = sine(2.0 * frequency, 0.0, rate, &[0.0, 0.25], frames);
+    sine(frequency, 0.0, rate, &[0.5, 0.0], frames)
+        .iter()
+        .zip(&octave)
+        .map(|(a, b)| a + b)
+        .collect()
+}
+
+/// A stereo signal with no steady period, so that WSOLA has only one place to line up.
+fn chirp(rate: f64, frames: usize) -> Vec<f32> {
+    let mut samples = Vec::with_capacity(2 * frames);
+    for i in 0..frames {
+        let t = i as f64 / rate;
+        samples.push((0.5 * (2.0 * PI * (200.0 + 300.0 * t) * t).sin()) as f32);
+        samples.push((0.5 * (2.0 * PI * (900.0 - 200.0 * t) * t).cos()) as f32);
+    }
+    samples
+}
+
+fn run(time_pitch: &mut TimePitch, input: &[f32], chunk_frames: usize) -> Vec<f32> {
+    let mut output = Vec::new();
+    for chunk in input.chunks(2 * chunk_frames) {
+        time_pitch.process(chunk, &mut output);
+    }
+    time_pitch.flush(&mut output);
+    output
+}
+
+#[test]
+fn stretching_keeps_the_pitch() {
+    let rate = 16000.0;
+    let input = tone(440.0, rate, 32000);
+    for &preset in &[Preset::Speech, Preset::Music] {
+        for &play_rate in &[0.5, 0.8, 1.25, 2.0] {
+            let mut time_pitch = TimePitch::new(rate, 2, play_rate, 0.0, preset).unwrap();
+            let output = run(&mut time_pitch, &input, 1000);
+            let frames = (32000.0 / play_rate).round() as usize;
+            assert_eq!(output.len(), 2 * frames);
+            assert_eq!(time_pitch.output_frames_for(32000), frames as u64);
+            for &(channel, frequency, expected) in &[(0, 440.0, 0.5), (1, 880.0, 0.25)] {
+                let found = amplitude(&output, 2, channel, frequency, rate);
+                assert!((20.0 * (found / expected).log10()).abs() < 1.0,
+                        "{:?} at {}: {} Hz has amplitude {}",
+                        preset,
+                        play_rate,
+                        frequency,
+                        found);
+            }
+        }
+    }
+}
+
+#[test]
+fn pitch_shifts_keep_the_length() {
+    let rate = 16000.0;
+    let input = tone(440.0, rate, 32000);
+    for &preset in &[Preset::Speech, Preset::Music] {
+        for &(play_rate, cents) in &[(1.0, 700.0), (1.0, -1200.0), (1.5, 300.0)] {
+            let mut time_pitch = TimePitch::new(rate, 2, play_rate, cents, preset).unwrap();
+            let output = run(&mut time_pitch, &input, 1000);
+            let frames = time_pitch.output_frames_for(32000) as usize;
+            assert_eq!(output.len(), 2 * frames);
+            assert!((frames as f64 - 32000.0 / play_rate).abs() <= 1.0, "{}", frames);
+
+            let shifted = 440.0 * 2f64.powf(cents / 1200.0);
+            let found = amplitude(&output, 2, 0, shifted, rate);
+            assert!((20.0 * (found / 0.5).log10()).abs() < 1.0,
+                    "{:?} {} cents: {} Hz has amplitude {}",
+                    preset,
+                    cents,
+                    shifted,
+                    found);
+            assert!(amplitude(&output, 2, 0, 440.0, rate) < 0.01);
+        }
+    }
+}
+
+#[test]
+fn unity_settings_pass_audio_through() {
+    let rate = 16000.0;
+    let input = chirp(rate, 16000);
+    for &algorithm in &[Algorithm::Wsola, Algorithm::PhaseVocoder] {
+        let mut time_pitch = TimePitch::new(rate, 2, 1.0, 0.0, Preset::Speech)
+            .unwrap()
+            .with_algorithm(algorithm);
+        assert_eq!(time_pitch.algorithm(), algorithm);
+        let output = run(&mut time_pitch, &input, 500);
+        assert_eq!(output.len(), input.len());
+        let error = input.iter().zip(&output).fold(0.0, |max: f32, (a, b)| max.max((a - b).abs()));
+        assert!(error < 1e-4, "{:?}: {}", algorithm, error);
+    }
+}
+
+#[test]
+fn output_does_not_depend_on_chunk_size() {
+    let rate = 16000.0;
+    let input = chirp(rate, 20000);
+    for &preset in &[Preset::Speech, Preset::Music] {
+        let mut time_pitch = TimePitch::new(rate, 2, 0.7, -300.0, preset).unwrap();
+        let whole = run(&mut time_pitch, &input, 20000);
+        // Flushing leaves it ready for the next stream.
+        assert_eq!(run(&mut time_pitch, &input, 1), whole);
+        assert_eq!(run(&mut time_pitch, &input, 777), whole);
+    }
+}
+
+#[test]
+fn reader_changes_speed_and_pitch() {
+    let rate = 16000.0;
+    let input = tone(440.0, rate, 16000);
+    let format = StreamFormat::new(rate, 2, SampleFormat::F32);
+    let reader = reader(AudioFileTypeId::CAF, format, &input);
+    let time_pitch = TimePitch::new(rate, 2, 2.0, 1200.0, Preset::Music).unwrap();
+    let mut reader = TimePitchReader::new(reader, time_pitch).unwrap();
+    assert_eq!(reader.length(), Some(8000));
+    let output = reader.read_to_end().unwrap();
+    assert_eq!(output.len(), 2 * 8000);
+    assert!(amplitude(&output, 2, 0, 880.0, rate) > 0.45);
+
+    reader.seek(6000).unwrap();
+    assert_eq!(reader.read_to_end().unwrap().len(), 2 * 2000);
+
+    let time_pitch = TimePitch::new(rate, 1, 2.0, 0.0, Preset::Music).unwrap();
+    assert!(TimePitchReader::new(reader.into_inner(), time_pitch).is_err());
+}
+
+#[test]
+fn rejects_settings_out_of_range() {
+    assert!(TimePitch::new(16000.0, 2, 0.0, 0.0, Preset::Speech).is_err());
+    assert!(TimePitch::new(16000.0, 2, 10.0, 0.0, Preset::Speech).is_err());
+    assert!(TimePitch::new(16000.0, 2, 1.0, 3000.0, Preset::Speech).is_err());
+    assert!(TimePitch::new(16000.0, 0, 1.0, 0.0, Preset::Speech).is_err());
+}
@@ -93,8 +93,13 @@ fn main() {
     .whitelisted_type("AudioQueueRef")
     .whitelisted_type("AudioQueueInputCallback")
     .whitelisted_type("AudioQueueOutputCallback")
+    .whitelisted_type("AudioQueueLevelMeterState")
 
     .whitelisted_var("kAudioQueueProperty_MagicCookie")
+    .whitelisted_var("kAudioQueueProperty_CurrentDevice")
+    .whitelisted_var("kAudioQueueProperty_EnableLevelMetering")
+    .whitelisted_var("kAudioQueueProperty_CurrentLevelMeter")
+    .whitelisted_var("kAudioQueueProperty_CurrentLevelMeterDB")
 
     // Core Audio
     .whitelisted_function("AudioObjectGetPropertyData")
@@ -105,7 +110,13 @@ fn main() {
     .whitelisted_var("kAudioObjectPropertyScopeGlobal")
     .whitelisted_var("kAudioObjectSystemObject")
     .whitelisted_var("kAudioDevicePropertyNominalSampleRate")
+    .whitelisted_var("kAudioDevicePropertyDeviceUID")
+    .whitelisted_var("kAudioDevicePropertyStreamConfiguration")
+    .whitelisted_var("kAudioObjectPropertyName")
     .whitelisted_var("kAudioObjectPropertyScopeGlobal")
+    .whitelisted_var("kAudioObjectPropertyScopeInput")
+    .whitelisted_var("kAudioObjectPropertyScopeOutput")
+    .whitelisted_var("kAudioObjectPropertyElementMaster")
 
     // Extended Audio File
     .whitelisted_function("ExtAudioFileOpenURL")
@@ -158,6 +169,7 @@ fn main() {
     .whitelisted_type("AudioDeviceID")
 
     .whitelisted_var("kAudioHardwarePropertyDefaultInputDevice")
+    .whitelisted_var("kAudioHardwarePropertyDefaultOutputDevice")
     .whitelisted_var("kAudioHardwarePropertyDevices")
 
     // Already in corefoundation
@@ -213,7 +225,27 @@ fn main() {
     .whitelisted_var("kAudioFormatFlagIsFloat")
     .whitelisted_var("kAudioFormatFlagIsNonInterleaved")
 
+    // Audio Converter
+    .whitelisted_function("AudioConverterNew")
+    .whitelisted_function("AudioConverterDispose")
+    .whitelisted_function("AudioConverterReset")
+    .whitelisted_function("AudioConverterFillComplexBuffer")
+    .whitelisted_function("AudioConverterGetProperty")
+    .whitelisted_function("AudioConverterGetPropertyInfo")
+    .whitelisted_function("AudioConverterSetProperty")
+
+    .whitelisted_type("AudioConverterRef")
+    .whitelisted_type("AudioConverterComplexInputDataProc")
+
     .whitelisted_var("kAudioConverterPropertyMaximumOutputPacketSize")
+    .whitelisted_var("kAudioConverterCompressionMagicCookie")
+    .whitelisted_var("kAudioConverterDecompressionMagicCookie")
+    .whitelisted_var("kAudioConverterSampleRateConverterQuality")
+    .whitelisted_var("kAudioConverterQuality_Max")
+    .whitelisted_var("kAudioConverterQuality_High")
+    .whitelisted_var("kAudioConverterQuality_Medium")
+    .whitelisted_var("kAudioConverterQuality_Low")
+    .whitelisted_var("kAudioConverterQuality_Min")
 
     .generate()
     // Unwrap the Result and panic on failure.
@@ -0,0 +1,61 @@
+//! Times forward real transforms at typical window sizes for each available backend.
+//!
+//! Run with `cargo bench`, adding `--features fftw` to include FFTW.
+
+extern crate num_complex;
+extern crate spectrogram;
+
+use std::time::{Duration, Instant};
+
+use num_complex::Complex;
+use spectrogram::fft::{FftBackend, FftFloat, RealFft, RustFft};
+#[cfg(feature = "fftw")]
+use spectrogram::fft::Fftw;
+
+const SIZES: [usize; 7] = [256, 512, 1000, 1024, 2048, 4096, 8192];
+const TARGET: u64 = 200;
+
+fn nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
+
+/// Average nanoseconds per forward transform, repeating for about `TARGET` milliseconds.
+fn time_forward<T, B>(backend: &B, size: usize) -> f64
+    where T: FftFloat,
+          B: FftBackend<T>
+{
+    let mut fft = backend.plan(size).unwrap();
+    let input: Vec<T> = (0..size).map(|i| T::from_usize(i % 7).unwrap()).collect();
+    let mut output = vec![Complex::new(T::zero(), T::zero()); size / 2 + 1];
+    let mut iterations = 0u64;
+    let start = Instant::now();
+    while nanos(start.elapsed()) < TARGET * 1_000_000 {
+        for _ in 0..16 {
+            fft.forward(&input, &mut output);
+        }
+        iterations += 16;
+    }
+    nanos(start.elapsed()) as f64 / iterations as f64
+}
+
+fn report<T, B>(name: &str, backend: &B)
+    where T: FftFloat,
+          B: FftBackend<T>
+{
+    for &size in SIZES.iter() {
+        println!("{:<12} {:>6} {:>12.0} ns",
+                 name,
+                 size,
+                 time_forward::<T, B>(backend, size));
+    }
+}
+
+fn main() {
+    report::<f32, _>("rust f32", &RustFft);
+    report::<f64, _>("rust f64", &RustFft);
+    #[cfg(feature = "fftw")]
+    {
+        report::<f32, _>("fftw f32", &Fftw);
+        report::<f64, _>("fftw f64", &Fftw);
+    }
+}
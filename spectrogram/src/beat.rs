@@ -0,0 +1,204 @@
+use onset::OnsetEnvelope;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoConfig {
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+    /// Centre of a log-normal weighting of candidate tempi that resolves octave ambiguity;
+    /// `None` weights every tempo equally.
+    pub prior_bpm: Option<f64>,
+    /// Standard deviation of the weighting in octaves.
+    pub prior_octaves: f64,
+}
+
+impl TempoConfig {
+    pub fn new() -> TempoConfig {
+        TempoConfig {
+            min_bpm: 60.0,
+            max_bpm: 240.0,
+            prior_bpm: Some(120.0),
+            prior_octaves: 1.0,
+        }
+    }
+}
+
+impl Default for TempoConfig {
+    fn default() -> TempoConfig {
+        TempoConfig::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeatConfig {
+    pub tempo: TempoConfig,
+    /// Track at this tempo instead of estimating one.
+    pub bpm: Option<f64>,
+    /// How strongly beat spacing is held to the tempo.
+    pub tightness: f64,
+}
+
+impl BeatConfig {
+    pub fn new() -> BeatConfig {
+        BeatConfig {
+            tempo: TempoConfig::new(),
+            bpm: None,
+            tightness: 100.0,
+        }
+    }
+}
+
+impl Default for BeatConfig {
+    fn default() -> BeatConfig {
+        BeatConfig::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Beats {
+    pub bpm: f64,
+    /// Envelope frame of each beat.
+    pub frames: Vec<usize>,
+    /// Time of each beat in seconds.
+    pub times: Vec<f64>,
+}
+
+/// Tempo in beats per minute from the autocorrelation of the onset envelope, or `None` if
+/// the envelope has no periodicity in range.
+pub fn estimate_tempo(envelope: &OnsetEnvelope, config: &TempoConfig) -> Option<f64> {
+    if !(config.min_bpm > 0.0 && config.min_bpm < config.max_bpm) {
+        return None;
+    }
+    // Smoothing spreads each onset over neighbouring lags, so periods that fall between
+    // frames are not penalised.
+    let x = smoothed(&envelope.values, 1.0);
+    let mean = x.iter().sum::<f64>() / x.len().max(1) as f64;
+    let centered: Vec<f64> = x.iter().map(|&v| v - mean).collect();
+    let lag_of = |bpm: f64| 60.0 * envelope.frame_rate / bpm;
+    let min_lag = lag_of(config.max_bpm).floor().max(1.0) as usize;
+    let max_lag = (lag_of(config.min_bpm).ceil() as usize + 1).min(x.len().saturating_sub(1));
+    if min_lag + 2 > max_lag {
+        return None;
+    }
+
+    let autocorrelation = |lag: usize| {
+        centered.iter().zip(&centered[lag..]).map(|(a, b)| a * b).sum::<f64>()
+    };
+    let values: Vec<f64> = (min_lag - 1..max_lag + 2).map(&autocorrelation).collect();
+    let weight = |lag: f64| match config.prior_bpm {
+        Some(prior) => {
+            let octaves = (lag_of(prior) / lag).log2() / config.prior_octaves;
+            (-0.5 * octaves * octaves).exp()
+        }
+        None => 1.0,
+    };
+
+    // Only local maxima of the autocorrelation are candidates, so the weighting cannot
+    // drag the estimate off a peak.
+    let best = (1..values.len() - 1)
+        .filter(|&i| values[i] > 0.0 && values[i] >= values[i - 1] && values[i] >= values[i + 1])
+        .map(|i| (i, values[i] * weight((min_lag - 1 + i) as f64)))
+        .fold(None, |best: Option<(usize, f64)>, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((i, score)),
+        });
+    best.map(|(i, _)| {
+        // Parabolic interpolation between lags.
+        let (a, b, c) = (values[i - 1], values[i], values[i + 1]);
+        let denominator = a - 2.0 * b + c;
+        let offset = if denominator < 0.0 {
+            0.5 * (a - c) / denominator
+        } else {
+            0.0
+        };
+        60.0 * envelope.frame_rate / ((min_lag - 1 + i) as f64 + offset)
+    })
+}
+
+/// Beats by dynamic programming (Ellis, "Beat Tracking by Dynamic Programming"): the
+/// sequence that best balances landing on strong onsets against keeping beat spacing
+/// close to the tempo.
+pub fn track_beats(envelope: &OnsetEnvelope, config: &BeatConfig) -> Option<Beats> {
+    let bpm = match config.bpm {
+        Some(bpm) => bpm,
+        None => estimate_tempo(envelope, &config.tempo)?,
+    };
+    let period = 60.0 * envelope.frame_rate / bpm;
+    let n = envelope.values.len();
+    if !(period >= 1.0 && period.is_finite()) || n == 0 {
+        return None;
+    }
+
+    let local = smoothed(&envelope.values, period / 32.0);
+    let mut score = vec![0.0; n];
+    let mut previous: Vec<Option<usize>> = vec![None; n];
+    let earliest = (2.0 * period).round() as usize;
+    let latest = ((period / 2.0).round() as usize).max(1);
+    for i in 0..n {
+        let mut best: Option<(usize, f64)> = None;
+        let (start, end) = (i.saturating_sub(earliest), i.saturating_sub(latest - 1));
+        for (j, &earlier) in score.iter().enumerate().take(end).skip(start) {
+            let log_ratio = ((i - j) as f64 / period).ln();
+            let candidate = earlier - config.tightness * log_ratio * log_ratio;
+            if best.is_none_or(|(_, s)| candidate > s) {
+                best = Some((j, candidate));
+            }
+        }
+        score[i] = local[i];
+        if let Some((j, s)) = best {
+            if s > 0.0 {
+                score[i] += s;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    // Start the backtrace from the best score within the last beat period.
+    let tail = n.saturating_sub(period.ceil() as usize);
+    let mut beat = (tail..n).fold(tail, |best, i| if score[i] > score[best] { i } else { best });
+    let mut frames = vec![beat];
+    while let Some(j) = previous[beat] {
+        frames.push(j);
+        beat = j;
+    }
+    frames.reverse();
+
+    // Drop leading and trailing beats that land on silence.
+    let threshold = 0.5 * (local.iter().map(|v| v * v).sum::<f64>() / n as f64).sqrt();
+    while frames.first().is_some_and(|&f| local[f] < threshold) {
+        frames.remove(0);
+    }
+    while frames.last().is_some_and(|&f| local[f] < threshold) {
+        frames.pop();
+    }
+    Some(Beats {
+             bpm,
+             times: frames.iter().map(|&f| envelope.times[f]).collect(),
+             frames,
+         })
+}
+
+/// `values` convolved with a Gaussian of standard deviation `sigma` frames and scaled to
+/// unit standard deviation.
+fn smoothed(values: &[f32], sigma: f64) -> Vec<f64> {
+    let sigma = sigma.max(0.5);
+    let radius = (3.0 * sigma).ceil() as isize;
+    let kernel: Vec<f64> = (-radius..radius + 1)
+        .map(|k| (-0.5 * (k as f64 / sigma).powi(2)).exp())
+        .collect();
+    let n = values.len() as isize;
+    let output: Vec<f64> = (0..n)
+        .map(|i| {
+            (-radius..radius + 1)
+                .filter(|&k| i + k >= 0 && i + k < n)
+                .map(|k| values[(i + k) as usize] as f64 * kernel[(k + radius) as usize])
+                .sum()
+        })
+        .collect();
+    let mean = output.iter().sum::<f64>() / n as f64;
+    let deviation = (output.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n as f64).sqrt();
+    if deviation > 0.0 {
+        output.iter().map(|v| v / deviation).collect()
+    } else {
+        output
+    }
+}
@@ -0,0 +1,240 @@
+extern crate audiotoolbox;
+extern crate clap;
+extern crate spectrogram;
+
+use std::process;
+
+use audiotoolbox::frame_reader::{self, FrameReader};
+use clap::{App, Arg, ArgMatches};
+use spectrogram::*;
+
+const READ_FRAMES: usize = 8192;
+
+fn parse<T: ::std::str::FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, String> {
+    match matches.value_of(name) {
+        Some(text) => {
+            text.parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value '{}' for --{}", text, name))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Which channel of the file to analyse.
+enum Channel {
+    Mix,
+    /// Zero-based index.
+    Index(usize),
+}
+
+/// The selected channel of frames `start..end` of `reader`, read without holding the rest
+/// of the file.
+fn read_signal(reader: &mut dyn FrameReader,
+               channel: &Channel,
+               start: u64,
+               end: Option<u64>)
+               -> Result<Vec<f32>, String> {
+    let channels = reader.format().channels as usize;
+    if let Channel::Index(index) = *channel {
+        if index >= channels {
+            return Err(format!("the file has {} channels", channels));
+        }
+    }
+    reader.seek(start)?;
+    let mut buffer = vec![0.0; READ_FRAMES * channels];
+    let mut signal = Vec::new();
+    let mut position = start;
+    loop {
+        if end.is_some_and(|end| position >= end) {
+            return Ok(signal);
+        }
+        let frames = reader.read_frames(&mut buffer)?;
+        if frames == 0 {
+            return Ok(signal);
+        }
+        for frame in buffer[..frames * channels].chunks(channels) {
+            if end.is_some_and(|end| position >= end) {
+                break;
+            }
+            signal.push(match *channel {
+                            Channel::Mix => frame.iter().sum::<f32>() / channels as f32,
+                            Channel::Index(index) => frame[index],
+                        });
+            position += 1;
+        }
+    }
+}
+
+fn run(matches: &ArgMatches) -> Result<(), String> {
+    let input = matches.value_of("INPUT").unwrap();
+    let output = matches.value_of("output").unwrap_or("spectrogram.png");
+
+    let mut reader = frame_reader::open(input)?;
+    let sample_rate = reader.format().sample_rate;
+
+    let channel = match matches.value_of("channel").unwrap_or("mix") {
+        "mix" => Channel::Mix,
+        text => {
+            match text.parse::<usize>() {
+                Ok(number) if number > 0 => Channel::Index(number - 1),
+                _ => return Err(format!("invalid value '{}' for --channel", text)),
+            }
+        }
+    };
+    let start_time: f64 = parse(matches, "start")?.unwrap_or(0.0);
+    let end_time: Option<f64> = parse(matches, "end")?;
+    if start_time < 0.0 || end_time.is_some_and(|end| end <= start_time) {
+        return Err("the time range is empty".to_owned());
+    }
+    let start = (start_time * sample_rate).round() as u64;
+    let end = end_time.map(|end| (end * sample_rate).round() as u64);
+    let signal = read_signal(&mut *reader, &channel, start, end)?;
+    if signal.is_empty() {
+        return Err("no audio in the time range".to_owned());
+    }
+
+    let window_length = parse(matches, "window-size")?.unwrap_or(2048);
+    let mut config = StftConfig::new(sample_rate, window_length);
+    config.window = match matches.value_of("window").unwrap_or("hann") {
+        "rectangular" => Window::Rectangular,
+        "hamming" => Window::Hamming,
+        "blackman-harris" => Window::BlackmanHarris,
+        "flat-top" => Window::FlatTop,
+        _ => Window::Hann,
+    };
+    if let Some(hop_size) = parse(matches, "hop")? {
+        config.hop_size = hop_size;
+    }
+    if let Some(fft_size) = parse(matches, "fft-size")? {
+        config.fft_size = fft_size;
+    }
+    let range: f32 = parse(matches, "range")?.unwrap_or(80.0);
+    if range <= 0.0 {
+        return Err("the dB range must be positive".to_owned());
+    }
+
+    let spectrum = stft(&signal, &config)?;
+    let scaling = Scaling::new(Scale::AmplitudeDb {
+                                   reference: Reference::Max,
+                                   floor: 1e-10,
+                                   top_db: Some(range),
+                               });
+    let mut bands = BandSpectrogram::linear(&spectrum, &scaling);
+    for time in bands.times.iter_mut() {
+        *time += start as f64 / sample_rate;
+    }
+
+    let mut render_config = RenderConfig::new();
+    render_config.color_map = match matches.value_of("colormap").unwrap_or("viridis") {
+        "magma" => ColorMap::Magma,
+        "inferno" => ColorMap::Inferno,
+        "grayscale" => ColorMap::Grayscale,
+        _ => ColorMap::Viridis,
+    };
+    render_config.frequency_axis = match matches.value_of("scale").unwrap_or("linear") {
+        "log" => FrequencyAxis::Log,
+        "mel" => FrequencyAxis::Mel,
+        _ => FrequencyAxis::Linear,
+    };
+    render_config.min_frequency = parse(matches, "min-freq")?;
+    render_config.max_frequency = parse(matches, "max-freq")?;
+    render_config.dynamic_range = Some(range);
+    render_config.width = parse(matches, "width")?;
+    render_config.height = Some(parse(matches, "height")?.unwrap_or(512));
+    render_config.labels = !matches.is_present("no-labels");
+
+    let image = render(&bands, &render_config)?;
+    image.save(output).map_err(|e| format!("unable to write {}: {}", output, e))?;
+    println!("{}: {} frames of {} Hz audio, {}x{} image",
+             output,
+             signal.len(),
+             sample_rate,
+             image.width(),
+             image.height());
+    Ok(())
+}
+
+fn main() {
+    let matches = App::new("spectrogram")
+        .about("Renders the spectrogram of an audio file as an image")
+        .arg(Arg::with_name("output")
+                 .short("o")
+                 .long("output")
+                 .takes_value(true)
+                 .help("Image path; the extension picks the format [default: spectrogram.png]"))
+        .arg(Arg::with_name("window")
+                 .long("window")
+                 .takes_value(true)
+                 .possible_values(&["hann", "hamming", "blackman-harris", "flat-top",
+                                    "rectangular"])
+                 .help("Analysis window [default: hann]"))
+        .arg(Arg::with_name("window-size")
+                 .short("w")
+                 .long("window-size")
+                 .takes_value(true)
+                 .help("Window length in samples [default: 2048]"))
+        .arg(Arg::with_name("hop")
+                 .long("hop")
+                 .takes_value(true)
+                 .help("Samples between frames [default: a quarter of the window]"))
+        .arg(Arg::with_name("fft-size")
+                 .long("fft-size")
+                 .takes_value(true)
+                 .help("Transform length, at least the window length [default: the window \
+                        length]"))
+        .arg(Arg::with_name("channel")
+                 .short("c")
+                 .long("channel")
+                 .takes_value(true)
+                 .help("Channel to analyse, counting from 1, or 'mix' to average every \
+                        channel [default: mix]"))
+        .arg(Arg::with_name("scale")
+                 .long("scale")
+                 .takes_value(true)
+                 .possible_values(&["linear", "log", "mel"])
+                 .help("Frequency axis [default: linear]"))
+        .arg(Arg::with_name("min-freq")
+                 .long("min-freq")
+                 .takes_value(true)
+                 .help("Lowest frequency shown in Hz"))
+        .arg(Arg::with_name("max-freq")
+                 .long("max-freq")
+                 .takes_value(true)
+                 .help("Highest frequency shown in Hz"))
+        .arg(Arg::with_name("colormap")
+                 .long("colormap")
+                 .takes_value(true)
+                 .possible_values(&["viridis", "magma", "inferno", "grayscale"])
+                 .help("Colour map [default: viridis]"))
+        .arg(Arg::with_name("range")
+                 .short("r")
+                 .long("range")
+                 .takes_value(true)
+                 .help("dB below the peak that the colour map spans [default: 80]"))
+        .arg(Arg::with_name("start")
+                 .long("start")
+                 .takes_value(true)
+                 .help("Start time in seconds"))
+        .arg(Arg::with_name("end")
+                 .long("end")
+                 .takes_value(true)
+                 .help("End time in seconds"))
+        .arg(Arg::with_name("width")
+                 .long("width")
+                 .takes_value(true)
+                 .help("Plot width in pixels [default: one pixel per frame]"))
+        .arg(Arg::with_name("height")
+                 .long("height")
+                 .takes_value(true)
+                 .help("Plot height in pixels [default: 512]"))
+        .arg(Arg::with_name("no-labels")
+                 .long("no-labels")
+                 .help("Leave out the time and frequency axes"))
+        .arg(Arg::with_name("INPUT").required(true))
+        .get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("spectrogram: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,122 @@
+use filterbank::BandSpectrogram;
+use scale::{power_spectrum, WindowCompensation};
+use stft::ComplexSpectrogram;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChromaConfig {
+    /// Frequency of A4 the pitch classes are tuned to.
+    pub reference_a4: f64,
+    /// Bands outside this range are ignored.
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    /// Scale each frame so its largest pitch class is 1.
+    pub normalize: bool,
+}
+
+impl ChromaConfig {
+    pub fn new() -> ChromaConfig {
+        ChromaConfig {
+            reference_a4: 440.0,
+            min_frequency: 55.0,
+            max_frequency: 5000.0,
+            normalize: true,
+        }
+    }
+}
+
+impl Default for ChromaConfig {
+    fn default() -> ChromaConfig {
+        ChromaConfig::new()
+    }
+}
+
+/// Energy in each of the 12 pitch classes per frame, starting from C.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chromagram {
+    pub values: Vec<[f32; 12]>,
+    /// Centre time of each frame in seconds.
+    pub times: Vec<f64>,
+}
+
+impl Chromagram {
+    /// Chroma of the power in each STFT bin.
+    pub fn from_stft(spectrum: &ComplexSpectrogram, config: &ChromaConfig) -> Chromagram {
+        let power = power_spectrum(spectrum, WindowCompensation::None);
+        let frequencies: Vec<f64> = (0..spectrum.bins())
+            .map(|bin| spectrum.bin_frequency(bin))
+            .collect();
+        Chromagram {
+            values: fold(&power, &frequencies, config),
+            times: (0..spectrum.frames.len()).map(|i| spectrum.frame_time(i)).collect(),
+        }
+    }
+
+    /// Chroma of the energy in bands such as those of a constant-Q transform, whose values
+    /// are magnitudes.
+    pub fn from_bands(bands: &BandSpectrogram, config: &ChromaConfig) -> Chromagram {
+        let power: Vec<Vec<f32>> = bands.values
+            .iter()
+            .map(|frame| frame.iter().map(|m| m * m).collect())
+            .collect();
+        Chromagram {
+            values: fold(&power, &bands.frequencies, config),
+            times: bands.times.clone(),
+        }
+    }
+
+    /// Sum of every frame, normalised so the largest pitch class is 1.
+    pub fn total(&self) -> [f32; 12] {
+        self.sum(0, self.values.len())
+    }
+
+    /// Sum of frames `start..end`, normalised so the largest pitch class is 1.
+    pub fn sum(&self, start: usize, end: usize) -> [f32; 12] {
+        let mut total = [0.0; 12];
+        for frame in &self.values[start..end] {
+            for (t, v) in total.iter_mut().zip(frame.iter()) {
+                *t += *v;
+            }
+        }
+        normalize(&mut total);
+        total
+    }
+}
+
+/// Folds frames of power at `frequencies` into pitch classes. A band between two
+/// semitones is shared between them in proportion to its distance from each.
+fn fold(power: &[Vec<f32>], frequencies: &[f64], config: &ChromaConfig) -> Vec<[f32; 12]> {
+    let classes: Vec<Option<(usize, f32)>> = frequencies.iter()
+        .map(|&f| if f >= config.min_frequency && f <= config.max_frequency && f > 0.0 {
+                 let pitch = 69.0 + 12.0 * (f / config.reference_a4).log2();
+                 let lower = pitch.floor();
+                 let class = ((lower as i64 % 12 + 12) % 12) as usize;
+                 Some((class, (pitch - lower) as f32))
+             } else {
+                 None
+             })
+        .collect();
+    power.iter()
+        .map(|frame| {
+            let mut chroma = [0.0; 12];
+            for (p, class) in frame.iter().zip(&classes) {
+                if let Some((class, fraction)) = *class {
+                    chroma[class] += p * (1.0 - fraction);
+                    chroma[(class + 1) % 12] += p * fraction;
+                }
+            }
+            if config.normalize {
+                normalize(&mut chroma);
+            }
+            chroma
+        })
+        .collect()
+}
+
+fn normalize(chroma: &mut [f32; 12]) {
+    let peak = chroma.iter().cloned().fold(0.0, f32::max);
+    if peak > 0.0 {
+        for v in chroma.iter_mut() {
+            *v /= peak;
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use num_complex::Complex;
+
+use fft::{DefaultBackend, FftBackend, RealFft};
+use filterbank::BandSpectrogram;
+use window::Window;
+
+/// Spectral kernel values below this fraction of a kernel's peak are dropped.
+const KERNEL_THRESHOLD: f32 = 1e-3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CqtConfig {
+    pub sample_rate: f64,
+    /// Centre frequency of the lowest bin.
+    pub min_frequency: f64,
+    pub bins: usize,
+    pub bins_per_octave: usize,
+    /// Samples between frame centres.
+    pub hop_size: usize,
+    pub window: Window,
+}
+
+impl CqtConfig {
+    /// Seven octaves of semitones from C1.
+    pub fn new(sample_rate: f64) -> CqtConfig {
+        CqtConfig {
+            sample_rate,
+            min_frequency: 32.703,
+            bins: 84,
+            bins_per_octave: 12,
+            hop_size: 512,
+            window: Window::Hann,
+        }
+    }
+
+    /// Ratio of each bin's centre frequency to its bandwidth.
+    pub fn q(&self) -> f64 {
+        1.0 / (2f64.powf(1.0 / self.bins_per_octave as f64) - 1.0)
+    }
+
+    pub fn frequency(&self, bin: usize) -> f64 {
+        self.min_frequency * 2f64.powf(bin as f64 / self.bins_per_octave as f64)
+    }
+
+    /// Length in samples of the analysis window for `bin`.
+    pub fn window_length(&self, bin: usize) -> usize {
+        (self.q() * self.sample_rate / self.frequency(bin)).ceil() as usize
+    }
+}
+
+/// Constant-Q transform computed with sparse spectral kernels (Brown and Puckette), so
+/// each frame costs one FFT of the longest window plus a short sum per bin.
+pub struct Cqt {
+    config: CqtConfig,
+    fft_size: usize,
+    fft: <DefaultBackend as FftBackend<f32>>::Plan,
+    /// Nonzero `(fft bin, weight)` pairs of each bin's kernel.
+    kernels: Vec<Vec<(usize, Complex<f32>)>>,
+}
+
+impl Cqt {
+    pub fn new(config: &CqtConfig) -> Result<Cqt, String> {
+        if config.bins == 0 || config.bins_per_octave == 0 || config.hop_size == 0 {
+            return Err("bins, bins per octave and hop size must be positive".to_owned());
+        }
+        if !(config.min_frequency > 0.0 && config.min_frequency.is_finite()) {
+            return Err(format!("invalid minimum frequency {}", config.min_frequency));
+        }
+        let top = config.frequency(config.bins - 1);
+        if top * (1.0 + 0.5 / config.q()) >= config.sample_rate / 2.0 {
+            return Err(format!("top bin at {:.1} Hz reaches Nyquist", top));
+        }
+
+        let fft_size = config.window_length(0).next_power_of_two();
+        let backend = DefaultBackend::default();
+        let mut fft = backend.plan(fft_size)?;
+        let mut real = vec![0.0; fft_size];
+        let mut imag = vec![0.0; fft_size];
+        let mut real_spectrum = vec![Complex::new(0.0, 0.0); fft_size / 2 + 1];
+        let mut imag_spectrum = vec![Complex::new(0.0, 0.0); fft_size / 2 + 1];
+
+        let kernels = (0..config.bins)
+            .map(|bin| {
+                // A windowed complex exponential, scaled so a sinusoid of amplitude A at
+                // the bin's frequency reads A, centred in the FFT frame.
+                let length = config.window_length(bin);
+                let window = config.window.coefficients(length);
+                let gain = 2.0 / window.iter().map(|&w| w as f64).sum::<f64>();
+                let omega = 2.0 * ::std::f64::consts::PI * config.frequency(bin) /
+                            config.sample_rate;
+                let offset = (fft_size - length) / 2;
+                for v in real.iter_mut().chain(imag.iter_mut()) {
+                    *v = 0.0;
+                }
+                for (n, &w) in window.iter().enumerate() {
+                    let phase = omega * (n as f64 - length as f64 / 2.0);
+                    real[offset + n] = (w as f64 * gain * phase.cos()) as f32;
+                    imag[offset + n] = (w as f64 * gain * phase.sin()) as f32;
+                }
+                fft.forward(&real, &mut real_spectrum);
+                fft.forward(&imag, &mut imag_spectrum);
+                // By Parseval, correlating with the kernel is a sum against the conjugate
+                // of its spectrum. Its negative-frequency half is negligible.
+                let spectrum: Vec<Complex<f32>> = real_spectrum.iter()
+                    .zip(&imag_spectrum)
+                    .map(|(r, i)| (r + i * Complex::new(0.0, 1.0)).conj() / fft_size as f32)
+                    .collect();
+                let peak = spectrum.iter().map(|c| c.norm()).fold(0.0, f32::max);
+                spectrum.into_iter()
+                    .enumerate()
+                    .filter(|&(_, c)| c.norm() >= peak * KERNEL_THRESHOLD)
+                    .collect()
+            })
+            .collect();
+
+        Ok(Cqt {
+               config: config.clone(),
+               fft_size,
+               fft,
+               kernels,
+           })
+    }
+
+    pub fn config(&self) -> &CqtConfig {
+        &self.config
+    }
+
+    /// Magnitude of each bin for frames centred every `hop_size` samples, with the signal
+    /// zero padded at both ends.
+    pub fn process(&mut self, signal: &[f32]) -> BandSpectrogram {
+        let frames = 1 + signal.len() / self.config.hop_size;
+        let mut input = vec![0.0; self.fft_size];
+        let mut spectrum = vec![Complex::new(0.0, 0.0); self.fft_size / 2 + 1];
+        let mut values = Vec::with_capacity(frames);
+        for frame in 0..frames {
+            let start = (frame * self.config.hop_size) as isize - (self.fft_size / 2) as isize;
+            for (i, v) in input.iter_mut().enumerate() {
+                let index = start + i as isize;
+                *v = if index >= 0 && (index as usize) < signal.len() {
+                    signal[index as usize]
+                } else {
+                    0.0
+                };
+            }
+            self.fft.forward(&input, &mut spectrum);
+            values.push(self.kernels
+                .iter()
+                .map(|kernel| {
+                    kernel.iter()
+                        .fold(Complex::new(0.0, 0.0), |acc, &(k, w)| acc + spectrum[k] * w)
+                        .norm()
+                })
+                .collect());
+        }
+        BandSpectrogram {
+            values,
+            frequencies: (0..self.config.bins).map(|b| self.config.frequency(b)).collect(),
+            times: (0..frames)
+                .map(|f| (f * self.config.hop_size) as f64 / self.config.sample_rate)
+                .collect(),
+        }
+    }
+}
+
+pub fn cqt(signal: &[f32], config: &CqtConfig) -> Result<BandSpectrogram, String> {
+    Ok(Cqt::new(config)?.process(signal))
+}
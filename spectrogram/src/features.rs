@@ -0,0 +1,322 @@
+use std::io::{self, Write};
+
+use stft::{padded_sample, stft, Padding, StftConfig};
+use SpectrogramResult;
+
+/// Smallest power taken into the logarithm for spectral flatness.
+const FLATNESS_FLOOR: f64 = 1e-10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureConfig {
+    /// Fraction of the spectral magnitude below the rolloff frequency.
+    pub rolloff_fraction: f32,
+    /// Octave bands for spectral contrast; the table gets one more column for the band
+    /// below `contrast_min_frequency`.
+    pub contrast_bands: usize,
+    /// Upper edge of the lowest contrast band.
+    pub contrast_min_frequency: f64,
+    /// Fraction of each band's bins averaged for its peak and its valley.
+    pub contrast_quantile: f32,
+}
+
+impl FeatureConfig {
+    pub fn new() -> FeatureConfig {
+        FeatureConfig {
+            rolloff_fraction: 0.85,
+            contrast_bands: 6,
+            contrast_min_frequency: 200.0,
+            contrast_quantile: 0.02,
+        }
+    }
+}
+
+impl Default for FeatureConfig {
+    fn default() -> FeatureConfig {
+        FeatureConfig::new()
+    }
+}
+
+/// One row of feature values per frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureTable {
+    pub columns: Vec<String>,
+    /// Centre time of each frame in seconds.
+    pub times: Vec<f64>,
+    pub rows: Vec<Vec<f32>>,
+}
+
+impl FeatureTable {
+    pub fn column(&self, name: &str) -> Option<Vec<f32>> {
+        self.columns
+            .iter()
+            .position(|c| c == name)
+            .map(|i| self.rows.iter().map(|row| row[i]).collect())
+    }
+
+    /// A header of `time` and the column names, then one line per frame.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "time")?;
+        for column in &self.columns {
+            write!(out, ",{}", column)?;
+        }
+        writeln!(out)?;
+        for (time, row) in self.times.iter().zip(&self.rows) {
+            write!(out, "{}", time)?;
+            for value in row {
+                if value.is_finite() {
+                    write!(out, ",{}", value)?;
+                } else {
+                    write!(out, ",")?;
+                }
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// An array with one object per frame, keyed by `time` and the column names. Values
+    /// that are not finite are written as `null`.
+    pub fn write_json<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "[")?;
+        for (frame, (time, row)) in self.times.iter().zip(&self.rows).enumerate() {
+            if frame > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "\n  {{\"time\": {}", time)?;
+            for (column, value) in self.columns.iter().zip(row) {
+                if value.is_finite() {
+                    write!(out, ", \"{}\": {}", column, value)?;
+                } else {
+                    write!(out, ", \"{}\": null", column)?;
+                }
+            }
+            write!(out, "}}")?;
+        }
+        writeln!(out, "\n]")
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = Vec::new();
+        self.write_csv(&mut out).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("CSV is ASCII")
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = Vec::new();
+        self.write_json(&mut out).expect("writing to a Vec cannot fail");
+        String::from_utf8(out).expect("JSON is ASCII")
+    }
+}
+
+/// Spectral and time-domain features of `signal`, framed exactly as `stft` frames it with
+/// `stft_config`: centroid, bandwidth, rolloff, flatness, flux, zero-crossing rate, RMS
+/// and spectral contrast.
+pub fn features(signal: &[f32],
+                stft_config: &StftConfig,
+                config: &FeatureConfig)
+                -> Result<FeatureTable, String> {
+    let spectrum = stft(signal, stft_config)?;
+    let magnitudes = spectrum.magnitudes();
+    let frequencies: Vec<f64> = (0..spectrum.bins())
+        .map(|bin| spectrum.bin_frequency(bin))
+        .collect();
+    let times: Vec<f64> = (0..magnitudes.len()).map(|i| spectrum.frame_time(i)).collect();
+    let mut table = spectral_features(&magnitudes, &frequencies, &times, config)?;
+
+    let frames = signal_frames(signal, stft_config);
+    let contrast_start = table.columns.iter().position(|c| c.starts_with("contrast")).unwrap();
+    table.columns.insert(contrast_start, "zcr".to_owned());
+    table.columns.insert(contrast_start + 1, "rms".to_owned());
+    for (row, frame) in table.rows.iter_mut().zip(&frames) {
+        row.insert(contrast_start, zero_crossing_rate(frame));
+        row.insert(contrast_start + 1, rms(frame));
+    }
+    Ok(table)
+}
+
+/// Spectral features of a `spectrogram` result, which must be on the `Scale::Magnitude`
+/// scale.
+pub fn spectrogram_features(result: &SpectrogramResult,
+                            config: &FeatureConfig)
+                            -> Result<FeatureTable, String> {
+    spectral_features(&result.v, &result.frequencies, &result.times, config)
+}
+
+/// Centroid, bandwidth, rolloff, flatness, flux and contrast of frames of linear
+/// magnitudes, one per frequency in `frequencies`.
+pub fn spectral_features(magnitudes: &[Vec<f32>],
+                         frequencies: &[f64],
+                         times: &[f64],
+                         config: &FeatureConfig)
+                         -> Result<FeatureTable, String> {
+    if magnitudes.len() != times.len() {
+        return Err(format!("{} frames but {} times", magnitudes.len(), times.len()));
+    }
+    if magnitudes.iter().any(|frame| frame.len() != frequencies.len()) {
+        return Err("every frame must have one magnitude per frequency".to_owned());
+    }
+    if !(config.rolloff_fraction > 0.0 && config.rolloff_fraction <= 1.0) {
+        return Err(format!("invalid rolloff fraction {}", config.rolloff_fraction));
+    }
+    if !(config.contrast_quantile > 0.0 && config.contrast_quantile <= 0.5) {
+        return Err(format!("invalid contrast quantile {}", config.contrast_quantile));
+    }
+    if !(config.contrast_min_frequency > 0.0 && config.contrast_min_frequency.is_finite()) {
+        return Err(format!("invalid contrast frequency {}", config.contrast_min_frequency));
+    }
+
+    let mut columns: Vec<String> = ["centroid", "bandwidth", "rolloff", "flatness", "flux"]
+        .iter()
+        .map(|&c| c.to_owned())
+        .collect();
+    columns.extend((0..config.contrast_bands + 1).map(|band| format!("contrast_{}", band)));
+
+    let flux = spectral_flux(magnitudes);
+    let rows = magnitudes.iter()
+        .zip(flux)
+        .map(|(frame, flux)| {
+            let centroid = centroid(frame, frequencies);
+            let mut row = vec![centroid as f32,
+                               bandwidth(frame, frequencies, centroid) as f32,
+                               rolloff(frame, frequencies, config.rolloff_fraction) as f32,
+                               flatness(frame),
+                               flux];
+            row.extend(contrast(frame, frequencies, config));
+            row
+        })
+        .collect();
+    Ok(FeatureTable {
+           columns,
+           times: times.to_vec(),
+           rows,
+       })
+}
+
+/// Unwindowed frames of `signal` covering the same samples as the frames of `stft`.
+pub fn signal_frames(signal: &[f32], config: &StftConfig) -> Vec<Vec<f32>> {
+    let padding = if config.center {
+        config.padding
+    } else {
+        Padding::Zero
+    };
+    (0..config.frame_count(signal.len()))
+        .map(|frame| {
+            let start = config.frame_start(frame);
+            (0..config.window_length)
+                .map(|i| padded_sample(signal, start + i as isize, padding))
+                .collect()
+        })
+        .collect()
+}
+
+/// Euclidean norm of the increases in magnitude since the previous frame; zero for the
+/// first frame.
+pub fn spectral_flux(magnitudes: &[Vec<f32>]) -> Vec<f32> {
+    (0..magnitudes.len())
+        .map(|i| if i == 0 {
+                 0.0
+             } else {
+                 magnitudes[i]
+                     .iter()
+                     .zip(&magnitudes[i - 1])
+                     .map(|(&m, &previous)| (m - previous).max(0.0).powi(2))
+                     .sum::<f32>()
+                     .sqrt()
+             })
+        .collect()
+}
+
+/// Fraction of adjacent sample pairs that change sign.
+pub fn zero_crossing_rate(frame: &[f32]) -> f32 {
+    if frame.len() < 2 {
+        return 0.0;
+    }
+    let crossings = frame.windows(2).filter(|pair| (pair[0] >= 0.0) != (pair[1] >= 0.0)).count();
+    crossings as f32 / (frame.len() - 1) as f32
+}
+
+pub fn rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = frame.iter().map(|&x| x as f64 * x as f64).sum();
+    (sum / frame.len() as f64).sqrt() as f32
+}
+
+fn centroid(frame: &[f32], frequencies: &[f64]) -> f64 {
+    let total: f64 = frame.iter().map(|&m| m as f64).sum();
+    if total > 0.0 {
+        frame.iter().zip(frequencies).map(|(&m, f)| m as f64 * f).sum::<f64>() / total
+    } else {
+        0.0
+    }
+}
+
+/// Magnitude-weighted standard deviation of frequency about the centroid.
+fn bandwidth(frame: &[f32], frequencies: &[f64], centroid: f64) -> f64 {
+    let total: f64 = frame.iter().map(|&m| m as f64).sum();
+    if total > 0.0 {
+        let spread: f64 = frame.iter()
+            .zip(frequencies)
+            .map(|(&m, f)| m as f64 * (f - centroid) * (f - centroid))
+            .sum();
+        (spread / total).sqrt()
+    } else {
+        0.0
+    }
+}
+
+fn rolloff(frame: &[f32], frequencies: &[f64], fraction: f32) -> f64 {
+    let total: f64 = frame.iter().map(|&m| m as f64).sum();
+    let threshold = total * fraction as f64;
+    let mut sum = 0.0;
+    for (&m, &f) in frame.iter().zip(frequencies) {
+        sum += m as f64;
+        if sum >= threshold {
+            return f;
+        }
+    }
+    frequencies.last().cloned().unwrap_or(0.0)
+}
+
+/// Ratio of the geometric to the arithmetic mean of the power spectrum: near 1 for noise
+/// and near 0 for tones.
+fn flatness(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    let n = frame.len() as f64;
+    let power = frame.iter().map(|&m| (m as f64 * m as f64).max(FLATNESS_FLOOR));
+    let (log_sum, sum) = power.fold((0.0, 0.0), |(l, s), p| (l + p.ln(), s + p));
+    ((log_sum / n).exp() / (sum / n)) as f32
+}
+
+/// Difference in dB between the peaks and valleys of each octave band, taking the mean of
+/// the largest and smallest `contrast_quantile` of its bins.
+fn contrast(frame: &[f32], frequencies: &[f64], config: &FeatureConfig) -> Vec<f32> {
+    let edge = |band: usize| if band == 0 {
+        0.0
+    } else {
+        config.contrast_min_frequency * 2f64.powi(band as i32 - 1)
+    };
+    (0..config.contrast_bands + 1)
+        .map(|band| {
+            let (low, high) = (edge(band), edge(band + 1));
+            let last = band == config.contrast_bands;
+            let mut values: Vec<f32> = frame.iter()
+                .zip(frequencies)
+                .filter(|&(_, &f)| f >= low && (last || f < high))
+                .map(|(&m, _)| m)
+                .collect();
+            if values.is_empty() {
+                return 0.0;
+            }
+            values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+            let count = ((values.len() as f32 * config.contrast_quantile).round() as usize).max(1);
+            let mean = |slice: &[f32]| slice.iter().sum::<f32>() / slice.len() as f32;
+            let valley = mean(&values[..count]).max(1e-10);
+            let peak = mean(&values[values.len() - count..]).max(1e-10);
+            20.0 * (peak / valley).log10()
+        })
+        .collect()
+}
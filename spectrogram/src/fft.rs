@@ -0,0 +1,330 @@
+use std::fmt::Debug;
+
+#[cfg(feature = "fftw")]
+use fftw;
+use num_complex::Complex;
+use num_traits::{Float, FloatConst, FromPrimitive};
+
+/// Sample types the FFT backends work in, `f32` and `f64`.
+pub trait FftFloat: Float + FloatConst + FromPrimitive + Debug + Send + Sync + 'static {}
+
+impl FftFloat for f32 {}
+impl FftFloat for f64 {}
+
+/// A planned real-to-complex transform of a fixed size, reusable across frames.
+pub trait RealFft<T: FftFloat> {
+    fn size(&self) -> usize;
+
+    /// Transforms `size()` real samples into the `size() / 2 + 1` bins from DC to Nyquist.
+    fn forward(&mut self, input: &[T], output: &mut [Complex<T>]);
+
+    /// Transforms `size() / 2 + 1` bins back into `size()` real samples, scaled so that
+    /// `inverse` undoes `forward`.
+    fn inverse(&mut self, input: &[Complex<T>], output: &mut [T]);
+}
+
+/// Creates transforms. Planning may be expensive; transforms should be planned once and
+/// reused.
+pub trait FftBackend<T: FftFloat> {
+    type Plan: RealFft<T>;
+
+    fn plan(&self, size: usize) -> Result<Self::Plan, String>;
+}
+
+/// Pure-Rust backend: radix-2 for powers of two and Bluestein's algorithm for other
+/// sizes, with even sizes computed as a complex transform of half the length.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RustFft;
+
+pub type DefaultBackend = RustFft;
+
+impl<T: FftFloat> FftBackend<T> for RustFft {
+    type Plan = RustRealFft<T>;
+
+    fn plan(&self, size: usize) -> Result<RustRealFft<T>, String> {
+        if size == 0 {
+            return Err("FFT size must be positive".to_owned());
+        }
+        let half = size / 2;
+        let even = size.is_multiple_of(2);
+        let inner = ComplexFft::new(if even { half } else { size });
+        let twiddles = if even {
+            (0..half + 1).map(|k| unit(-(k as f64) / size as f64)).collect()
+        } else {
+            Vec::new()
+        };
+        Ok(RustRealFft {
+               size,
+               inner,
+               twiddles,
+               buffer: vec![zero(); if even { half } else { size }],
+           })
+    }
+}
+
+fn zero<T: FftFloat>() -> Complex<T> {
+    Complex::new(T::zero(), T::zero())
+}
+
+/// `exp(2 pi i turns)`, computed in f64.
+fn unit<T: FftFloat>(turns: f64) -> Complex<T> {
+    let angle = 2.0 * ::std::f64::consts::PI * turns;
+    Complex::new(T::from_f64(angle.cos()).unwrap(),
+                 T::from_f64(angle.sin()).unwrap())
+}
+
+pub struct RustRealFft<T: FftFloat> {
+    size: usize,
+    /// Complex transform of `size / 2` points for even sizes, `size` points otherwise.
+    inner: ComplexFft<T>,
+    /// `exp(-2 pi i k / size)` for the even-size split.
+    twiddles: Vec<Complex<T>>,
+    buffer: Vec<Complex<T>>,
+}
+
+impl<T: FftFloat> RealFft<T> for RustRealFft<T> {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn forward(&mut self, input: &[T], output: &mut [Complex<T>]) {
+        assert_eq!(input.len(), self.size);
+        assert_eq!(output.len(), self.size / 2 + 1);
+        if self.size % 2 == 1 {
+            for (b, &x) in self.buffer.iter_mut().zip(input) {
+                *b = Complex::new(x, T::zero());
+            }
+            self.inner.process(&mut self.buffer, false);
+            output.copy_from_slice(&self.buffer[..output.len()]);
+            return;
+        }
+
+        // Pack even samples into the real part and odd samples into the imaginary part,
+        // transform at half length, then separate the two spectra.
+        let half = self.size / 2;
+        for (j, b) in self.buffer.iter_mut().enumerate() {
+            *b = Complex::new(input[2 * j], input[2 * j + 1]);
+        }
+        self.inner.process(&mut self.buffer, false);
+        let two = T::one() + T::one();
+        for (k, out) in output.iter_mut().enumerate().take(half + 1) {
+            let z = self.buffer[k % half];
+            let z_mirror = self.buffer[(half - k) % half].conj();
+            let even = (z + z_mirror) / two;
+            let odd = (z - z_mirror) / two * Complex::new(T::zero(), -T::one());
+            *out = even + self.twiddles[k] * odd;
+        }
+    }
+
+    fn inverse(&mut self, input: &[Complex<T>], output: &mut [T]) {
+        assert_eq!(input.len(), self.size / 2 + 1);
+        assert_eq!(output.len(), self.size);
+        let scale = T::from_usize(self.size).unwrap();
+        if self.size % 2 == 1 {
+            for k in 0..self.size {
+                self.buffer[k] = if k < input.len() {
+                    input[k]
+                } else {
+                    input[self.size - k].conj()
+                };
+            }
+            self.inner.process(&mut self.buffer, true);
+            for (x, b) in output.iter_mut().zip(&self.buffer) {
+                *x = b.re / scale;
+            }
+            return;
+        }
+
+        let half = self.size / 2;
+        let two = T::one() + T::one();
+        for k in 0..half {
+            let x = input[k];
+            let x_mirror = input[half - k].conj();
+            let even = (x + x_mirror) / two;
+            let odd = (x - x_mirror) / two * self.twiddles[k].conj();
+            self.buffer[k] = even + odd * Complex::new(T::zero(), T::one());
+        }
+        self.inner.process(&mut self.buffer, true);
+        let scale = scale / two;
+        for (j, b) in self.buffer.iter().enumerate() {
+            output[2 * j] = b.re / scale;
+            output[2 * j + 1] = b.im / scale;
+        }
+    }
+}
+
+enum ComplexFft<T: FftFloat> {
+    Radix2 {
+        /// `exp(-2 pi i k / n)` for k < n / 2.
+        twiddles: Vec<Complex<T>>,
+        bit_reverse: Vec<usize>,
+    },
+    Bluestein {
+        n: usize,
+        /// `exp(-pi i k^2 / n)`.
+        chirp: Vec<Complex<T>>,
+        /// Transform of the conjugate chirp, wrapped to the convolution length.
+        kernel: Vec<Complex<T>>,
+        inner: Box<ComplexFft<T>>,
+        scratch: Vec<Complex<T>>,
+    },
+}
+
+impl<T: FftFloat> ComplexFft<T> {
+    fn new(n: usize) -> ComplexFft<T> {
+        if n.is_power_of_two() {
+            let bits = n.trailing_zeros();
+            return ComplexFft::Radix2 {
+                       twiddles: (0..n / 2).map(|k| unit(-(k as f64) / n as f64)).collect(),
+                       bit_reverse: (0..n)
+                           .map(|i| if bits == 0 {
+                                    0
+                                } else {
+                                    i.reverse_bits() >> (0usize.count_zeros() - bits)
+                                })
+                           .collect(),
+                   };
+        }
+
+        let m = (2 * n - 1).next_power_of_two();
+        // k^2 is reduced modulo 2n so the angle stays accurate for large k.
+        let chirp: Vec<Complex<T>> = (0..n)
+            .map(|k| unit(-(((k * k) % (2 * n)) as f64) / (2 * n) as f64))
+            .collect();
+        let mut kernel = vec![zero(); m];
+        for k in 0..n {
+            kernel[k] = chirp[k].conj();
+            if k > 0 {
+                kernel[m - k] = chirp[k].conj();
+            }
+        }
+        let mut inner = ComplexFft::new(m);
+        inner.process(&mut kernel, false);
+        ComplexFft::Bluestein {
+            n,
+            chirp,
+            kernel,
+            inner: Box::new(inner),
+            scratch: vec![zero(); m],
+        }
+    }
+
+    /// In-place unnormalised transform; `inverse` flips the sign of the exponent.
+    fn process(&mut self, data: &mut [Complex<T>], inverse: bool) {
+        if inverse {
+            for x in data.iter_mut() {
+                *x = x.conj();
+            }
+        }
+        match *self {
+            ComplexFft::Radix2 { ref twiddles, ref bit_reverse } => {
+                radix2(data, twiddles, bit_reverse)
+            }
+            ComplexFft::Bluestein { n, ref chirp, ref kernel, ref mut inner, ref mut scratch } => {
+                let m = scratch.len();
+                for k in 0..m {
+                    scratch[k] = if k < n { data[k] * chirp[k] } else { zero() };
+                }
+                inner.process(scratch, false);
+                for (s, k) in scratch.iter_mut().zip(kernel) {
+                    *s = *s * *k;
+                }
+                inner.process(scratch, true);
+                let scale = T::from_usize(m).unwrap();
+                for k in 0..n {
+                    data[k] = scratch[k] * chirp[k] / scale;
+                }
+            }
+        }
+        if inverse {
+            for x in data.iter_mut() {
+                *x = x.conj();
+            }
+        }
+    }
+}
+
+fn radix2<T: FftFloat>(data: &mut [Complex<T>], twiddles: &[Complex<T>], bit_reverse: &[usize]) {
+    let n = data.len();
+    for (i, &j) in bit_reverse.iter().enumerate().take(n) {
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= n {
+        let stride = n / length;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let a = data[start + k];
+                let b = data[start + k + length / 2] * twiddles[k * stride];
+                data[start + k] = a + b;
+                data[start + k + length / 2] = a - b;
+            }
+        }
+        length *= 2;
+    }
+}
+
+/// Backend using the FFTW library through real-to-complex and complex-to-real plans.
+#[cfg(feature = "fftw")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Fftw;
+
+#[cfg(feature = "fftw")]
+pub struct FftwRealFft<T: FftFloat> {
+    /// `size` real samples in `field` and the `size / 2 + 1` bins of their half spectrum
+    /// in `coef`.
+    pair: fftw::Pair<T, Complex<T>>,
+    size: usize,
+}
+
+#[cfg(feature = "fftw")]
+macro_rules! fftw_backend {
+    ($t:ty) => {
+        impl FftBackend<$t> for Fftw {
+            type Plan = FftwRealFft<$t>;
+
+            fn plan(&self, size: usize) -> Result<FftwRealFft<$t>, String> {
+                if size == 0 {
+                    return Err("FFT size must be positive".to_owned());
+                }
+                Ok(FftwRealFft {
+                       pair: fftw::Pair::r2c_1d(size, fftw::FLAG::FFTW_MEASURE),
+                       size,
+                   })
+            }
+        }
+
+        impl RealFft<$t> for FftwRealFft<$t> {
+            fn size(&self) -> usize {
+                self.size
+            }
+
+            fn forward(&mut self, input: &[$t], output: &mut [Complex<$t>]) {
+                assert_eq!(input.len(), self.size);
+                assert_eq!(output.len(), self.size / 2 + 1);
+                self.pair.field.copy_from_slice(input);
+                self.pair.forward();
+                output.copy_from_slice(&self.pair.coef);
+            }
+
+            fn inverse(&mut self, input: &[Complex<$t>], output: &mut [$t]) {
+                assert_eq!(input.len(), self.size / 2 + 1);
+                assert_eq!(output.len(), self.size);
+                // The complex-to-real plan overwrites its input, so it is copied every time.
+                self.pair.coef.copy_from_slice(input);
+                self.pair.backward();
+                let scale = self.size as $t;
+                for (x, &f) in output.iter_mut().zip(self.pair.field.iter()) {
+                    *x = f / scale;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "fftw")]
+fftw_backend!(f32);
+#[cfg(feature = "fftw")]
+fftw_backend!(f64);
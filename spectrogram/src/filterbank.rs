@@ -0,0 +1,276 @@
+use std::f64::consts::PI;
+
+use scale::{power_spectrum, Reference, Scale, Scaling};
+use stft::{ComplexSpectrogram, StftConfig};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MelScale {
+    /// `2595 log10(1 + f / 700)`, as used by HTK.
+    Htk,
+    /// Linear below 1 kHz and logarithmic above, as in Slaney's Auditory Toolbox and
+    /// librosa.
+    Slaney,
+}
+
+/// Perceptual frequency scales filterbank bands are spaced evenly on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FrequencyScale {
+    Linear,
+    Mel(MelScale),
+    /// Traunmüller's approximation of the Bark critical-band rate.
+    Bark,
+    /// Glasberg and Moore's ERB-rate scale.
+    Erb,
+}
+
+const SLANEY_LINEAR_STEP: f64 = 200.0 / 3.0;
+const SLANEY_BREAK_HZ: f64 = 1000.0;
+const SLANEY_BREAK_MEL: f64 = SLANEY_BREAK_HZ / SLANEY_LINEAR_STEP;
+
+impl FrequencyScale {
+    pub fn from_hz(&self, hz: f64) -> f64 {
+        match *self {
+            FrequencyScale::Linear => hz,
+            FrequencyScale::Mel(MelScale::Htk) => 2595.0 * (1.0 + hz / 700.0).log10(),
+            FrequencyScale::Mel(MelScale::Slaney) => {
+                if hz < SLANEY_BREAK_HZ {
+                    hz / SLANEY_LINEAR_STEP
+                } else {
+                    SLANEY_BREAK_MEL + (hz / SLANEY_BREAK_HZ).ln() / slaney_log_step()
+                }
+            }
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+            FrequencyScale::Erb => 21.4 * (1.0 + 0.00437 * hz).log10(),
+        }
+    }
+
+    pub fn to_hz(&self, value: f64) -> f64 {
+        match *self {
+            FrequencyScale::Linear => value,
+            FrequencyScale::Mel(MelScale::Htk) => 700.0 * (10f64.powf(value / 2595.0) - 1.0),
+            FrequencyScale::Mel(MelScale::Slaney) => {
+                if value < SLANEY_BREAK_MEL {
+                    value * SLANEY_LINEAR_STEP
+                } else {
+                    SLANEY_BREAK_HZ * (slaney_log_step() * (value - SLANEY_BREAK_MEL)).exp()
+                }
+            }
+            FrequencyScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            FrequencyScale::Erb => (10f64.powf(value / 21.4) - 1.0) / 0.00437,
+        }
+    }
+}
+
+fn slaney_log_step() -> f64 {
+    6.4f64.ln() / 27.0
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FilterNorm {
+    /// Every filter peaks at 1.
+    Peak,
+    /// Every filter has unit area, so wide high-frequency bands are not favoured.
+    Area,
+}
+
+/// Triangular filters over the bins of an STFT.
+#[derive(Debug, Clone)]
+pub struct Filterbank {
+    /// One row of per-bin weights for each band.
+    pub weights: Vec<Vec<f32>>,
+    /// Centre frequency of each band in Hz.
+    pub frequencies: Vec<f64>,
+}
+
+impl Filterbank {
+    /// `bands` overlapping triangles evenly spaced on `scale` between `min_hz` and `max_hz`.
+    pub fn new(scale: FrequencyScale,
+               bands: usize,
+               min_hz: f64,
+               max_hz: f64,
+               norm: FilterNorm,
+               config: &StftConfig)
+               -> Result<Filterbank, String> {
+        let nyquist = config.sample_rate / 2.0;
+        if bands == 0 {
+            return Err("filterbank needs at least one band".to_owned());
+        }
+        if !(min_hz >= 0.0 && min_hz < max_hz && max_hz <= nyquist) {
+            return Err(format!("invalid band range {} Hz to {} Hz for Nyquist {} Hz",
+                               min_hz,
+                               max_hz,
+                               nyquist));
+        }
+        let low = scale.from_hz(min_hz);
+        let high = scale.from_hz(max_hz);
+        let edges: Vec<f64> = (0..bands + 2)
+            .map(|i| scale.to_hz(low + (high - low) * i as f64 / (bands + 1) as f64))
+            .collect();
+        let bin_hz = config.sample_rate / config.fft_size as f64;
+
+        let weights = (0..bands)
+            .map(|band| {
+                let (lower, center, upper) = (edges[band], edges[band + 1], edges[band + 2]);
+                let gain = match norm {
+                    FilterNorm::Peak => 1.0,
+                    FilterNorm::Area => 2.0 / (upper - lower),
+                };
+                (0..config.bins())
+                    .map(|bin| {
+                        let hz = bin as f64 * bin_hz;
+                        let rising = (hz - lower) / (center - lower);
+                        let falling = (upper - hz) / (upper - center);
+                        (rising.min(falling).max(0.0) * gain) as f32
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Filterbank {
+               weights,
+               frequencies: edges[1..bands + 1].to_vec(),
+           })
+    }
+
+    /// Slaney mel filterbank with unit-area bands covering 0 Hz to Nyquist.
+    pub fn mel(bands: usize, config: &StftConfig) -> Result<Filterbank, String> {
+        Filterbank::new(FrequencyScale::Mel(MelScale::Slaney),
+                        bands,
+                        0.0,
+                        config.sample_rate / 2.0,
+                        FilterNorm::Area,
+                        config)
+    }
+
+    pub fn bands(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Sums each frame of per-bin power into bands.
+    pub fn apply(&self, power: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        power.iter()
+            .map(|frame| {
+                self.weights
+                    .iter()
+                    .map(|weights| weights.iter().zip(frame).map(|(w, p)| w * p).sum())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Values in frequency bands that need not be evenly spaced.
+#[derive(Debug, Clone)]
+pub struct BandSpectrogram {
+    /// One row of band values per frame.
+    pub values: Vec<Vec<f32>>,
+    /// Centre frequency of each band in Hz.
+    pub frequencies: Vec<f64>,
+    /// Centre time of each frame in seconds.
+    pub times: Vec<f64>,
+}
+
+impl BandSpectrogram {
+    /// Every STFT bin as its own band, scaled by `scaling`.
+    pub fn linear(spectrum: &ComplexSpectrogram, scaling: &Scaling) -> BandSpectrogram {
+        BandSpectrogram {
+            values: scaling.apply(spectrum),
+            frequencies: (0..spectrum.bins()).map(|bin| spectrum.bin_frequency(bin)).collect(),
+            times: (0..spectrum.frames.len()).map(|i| spectrum.frame_time(i)).collect(),
+        }
+    }
+}
+
+/// Applies `filterbank` to the power spectrum of `spectrum`, then `scaling`.
+pub fn band_spectrogram(spectrum: &ComplexSpectrogram,
+                        filterbank: &Filterbank,
+                        scaling: &Scaling)
+                        -> BandSpectrogram {
+    let power = power_spectrum(spectrum, scaling.window_compensation);
+    BandSpectrogram {
+        values: scaling.apply_power(filterbank.apply(&power)),
+        frequencies: filterbank.frequencies.clone(),
+        times: (0..spectrum.frames.len()).map(|i| spectrum.frame_time(i)).collect(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MfccConfig {
+    pub bands: usize,
+    pub coefficients: usize,
+    pub mel_scale: MelScale,
+    pub min_hz: f64,
+    /// Upper edge of the filterbank; `None` for Nyquist.
+    pub max_hz: Option<f64>,
+    /// Sinusoidal liftering parameter; zero disables liftering.
+    pub lifter: f32,
+}
+
+impl MfccConfig {
+    pub fn new() -> MfccConfig {
+        MfccConfig {
+            bands: 128,
+            coefficients: 20,
+            mel_scale: MelScale::Slaney,
+            min_hz: 0.0,
+            max_hz: None,
+            lifter: 0.0,
+        }
+    }
+}
+
+impl Default for MfccConfig {
+    fn default() -> MfccConfig {
+        MfccConfig::new()
+    }
+}
+
+/// Mel-frequency cepstral coefficients: an orthonormal DCT-II of the log-power mel
+/// spectrogram, one row of `coefficients` per frame.
+pub fn mfcc(spectrum: &ComplexSpectrogram, config: &MfccConfig) -> Result<Vec<Vec<f32>>, String> {
+    if config.coefficients > config.bands {
+        return Err(format!("cannot take {} coefficients from {} bands",
+                           config.coefficients,
+                           config.bands));
+    }
+    let filterbank = Filterbank::new(FrequencyScale::Mel(config.mel_scale),
+                                     config.bands,
+                                     config.min_hz,
+                                     config.max_hz.unwrap_or(spectrum.config.sample_rate / 2.0),
+                                     FilterNorm::Area,
+                                     &spectrum.config)?;
+    let scaling = Scaling::new(Scale::PowerDb {
+                                   reference: Reference::Fixed(1.0),
+                                   floor: 1e-10,
+                                   top_db: Some(80.0),
+                               });
+    let log_mel = band_spectrogram(spectrum, &filterbank, &scaling).values;
+
+    let n = config.bands as f64;
+    let basis: Vec<Vec<f64>> = (0..config.coefficients)
+        .map(|k| {
+            let norm = if k == 0 { (1.0 / n).sqrt() } else { (2.0 / n).sqrt() };
+            (0..config.bands)
+                .map(|i| norm * (PI * k as f64 * (i as f64 + 0.5) / n).cos())
+                .collect()
+        })
+        .collect();
+    let lifter: Vec<f64> = (0..config.coefficients)
+        .map(|k| if config.lifter > 0.0 {
+                 let l = config.lifter as f64;
+                 1.0 + l / 2.0 * (PI * k as f64 / l).sin()
+             } else {
+                 1.0
+             })
+        .collect();
+    Ok(log_mel.iter()
+           .map(|frame| {
+               basis.iter()
+                   .zip(&lifter)
+                   .map(|(row, l)| {
+                       let sum: f64 = row.iter().zip(frame).map(|(b, &v)| b * v as f64).sum();
+                       (sum * l) as f32
+                   })
+                   .collect()
+           })
+           .collect())
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+
+use chroma::Chromagram;
+use tuner::NoteName;
+
+/// Krumhansl and Kessler's probe-tone ratings for C major and C minor.
+static MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66,
+                                   2.29, 2.88];
+static MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69,
+                                   3.34, 3.17];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Key {
+    pub tonic: NoteName,
+    pub mode: Mode,
+    /// Correlation of the chroma with the key's profile.
+    pub correlation: f32,
+    /// How far the best correlation leads the runner-up, from 0 for a tie.
+    pub confidence: f32,
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Major => write!(f, "{} major", self.tonic),
+            Mode::Minor => write!(f, "{} minor", self.tonic),
+        }
+    }
+}
+
+/// The key whose profile best correlates with `chroma`, or `None` if every pitch class is
+/// equally strong.
+pub fn estimate_key(chroma: &[f32; 12]) -> Option<Key> {
+    let mut scores = Vec::with_capacity(24);
+    for &(mode, profile) in [(Mode::Major, &MAJOR_PROFILE), (Mode::Minor, &MINOR_PROFILE)].iter() {
+        for tonic in 0..12 {
+            let rotated: Vec<f32> = (0..12).map(|i| profile[(i + 12 - tonic) % 12]).collect();
+            match correlation(chroma, &rotated) {
+                Some(r) => scores.push((tonic, mode, r)),
+                None => return None,
+            }
+        }
+    }
+    scores.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(::std::cmp::Ordering::Equal));
+    let (tonic, mode, best) = scores[0];
+    Some(Key {
+             tonic: NoteName::from_pitch_class(tonic),
+             mode,
+             correlation: best,
+             confidence: best - scores[1].2,
+         })
+}
+
+/// Key of a whole chromagram.
+pub fn key(chromagram: &Chromagram) -> Option<Key> {
+    estimate_key(&chromagram.total())
+}
+
+/// Key of each run of `window` frames, starting every `hop` frames, with the time of the
+/// window's centre. Windows without a key are skipped.
+pub fn keys(chromagram: &Chromagram, window: usize, hop: usize) -> Vec<(f64, Key)> {
+    let frames = chromagram.values.len();
+    if window == 0 || hop == 0 || frames == 0 {
+        return Vec::new();
+    }
+    let window = window.min(frames);
+    (0..frames - window + 1)
+        .filter(|start| start % hop == 0)
+        .filter_map(|start| {
+            let time = (chromagram.times[start] + chromagram.times[start + window - 1]) / 2.0;
+            estimate_key(&chromagram.sum(start, start + window)).map(|key| (time, key))
+        })
+        .collect()
+}
+
+/// Pearson correlation, or `None` if either input is constant.
+fn correlation(a: &[f32], b: &[f32]) -> Option<f32> {
+    let n = a.len() as f32;
+    let (mean_a, mean_b) = (a.iter().sum::<f32>() / n, b.iter().sum::<f32>() / n);
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a) * (x - mean_a);
+        variance_b += (y - mean_b) * (y - mean_b);
+    }
+    if variance_a > 0.0 && variance_b > 0.0 {
+        Some(covariance / (variance_a * variance_b).sqrt())
+    } else {
+        None
+    }
+}
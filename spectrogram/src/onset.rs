@@ -0,0 +1,158 @@
+use num_complex::Complex;
+
+use stft::{stft, ComplexSpectrogram, StftConfig};
+
+/// Gain applied to magnitudes before log compression in spectral flux.
+const FLUX_COMPRESSION: f32 = 100.0;
+
+/// Onset detection functions, after Bello et al., "A Tutorial on Onset Detection in Music
+/// Signals".
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum OnsetFunction {
+    /// Summed increases in log-compressed magnitude since the previous frame.
+    SpectralFlux,
+    /// Energy weighted by bin index, emphasising broadband percussive onsets.
+    HighFrequencyContent,
+    /// Distance of each bin from the magnitude and phase predicted by the previous two
+    /// frames, counting only bins that grew (Dixon's rectified complex domain).
+    ComplexDomain,
+}
+
+/// Onset strength at each STFT frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnsetEnvelope {
+    pub values: Vec<f32>,
+    /// Centre time of each frame in seconds.
+    pub times: Vec<f64>,
+    /// Frames per second.
+    pub frame_rate: f64,
+}
+
+impl OnsetEnvelope {
+    pub fn new(spectrum: &ComplexSpectrogram, function: OnsetFunction) -> OnsetEnvelope {
+        let frames = &spectrum.frames;
+        let values = (0..frames.len())
+            .map(|n| match function {
+                OnsetFunction::SpectralFlux => {
+                    if n == 0 {
+                        0.0
+                    } else {
+                        let compress = |c: &Complex<f32>| (1.0 + FLUX_COMPRESSION * c.norm()).ln();
+                        frames[n]
+                            .iter()
+                            .zip(&frames[n - 1])
+                            .map(|(c, p)| (compress(c) - compress(p)).max(0.0))
+                            .sum()
+                    }
+                }
+                OnsetFunction::HighFrequencyContent => {
+                    let sum: f32 = frames[n]
+                        .iter()
+                        .enumerate()
+                        .map(|(k, c)| k as f32 * c.norm_sqr())
+                        .sum();
+                    sum / frames[n].len() as f32
+                }
+                OnsetFunction::ComplexDomain => {
+                    if n < 2 {
+                        0.0
+                    } else {
+                        frames[n]
+                            .iter()
+                            .zip(&frames[n - 1])
+                            .zip(&frames[n - 2])
+                            .filter(|&((c, p), _)| c.norm() >= p.norm())
+                            .map(|((c, p), pp)| {
+                                let phase = 2.0 * p.arg() - pp.arg();
+                                (c - Complex::from_polar(&p.norm(), &phase)).norm()
+                            })
+                            .sum()
+                    }
+                }
+            })
+            .collect();
+        OnsetEnvelope {
+            values,
+            times: (0..frames.len()).map(|i| spectrum.frame_time(i)).collect(),
+            frame_rate: spectrum.config.sample_rate / spectrum.config.hop_size as f64,
+        }
+    }
+
+    /// Frames at which `picking` finds an onset.
+    pub fn peaks(&self, picking: &PeakPicking) -> Vec<usize> {
+        let peak = self.values.iter().cloned().fold(0.0, f32::max);
+        if peak <= 0.0 {
+            return Vec::new();
+        }
+        let x: Vec<f32> = self.values.iter().map(|&v| v / peak).collect();
+        let frames = |seconds: f64| (seconds * self.frame_rate).round() as usize;
+        let (pre_max, post_max) = (frames(picking.pre_max), frames(picking.post_max));
+        let (pre_avg, post_avg) = (frames(picking.pre_avg), frames(picking.post_avg));
+        let wait = frames(picking.wait);
+        let range = |n: usize, before: usize, after: usize| {
+            &x[n.saturating_sub(before)..(n + after + 1).min(x.len())]
+        };
+
+        let mut onsets: Vec<usize> = Vec::new();
+        for (n, &value) in x.iter().enumerate() {
+            let local_max = range(n, pre_max, post_max).iter().cloned().fold(0.0, f32::max);
+            let neighbourhood = range(n, pre_avg, post_avg);
+            let mean = neighbourhood.iter().sum::<f32>() / neighbourhood.len() as f32;
+            let waited = onsets.last().is_none_or(|&last| n - last > wait);
+            if value > 0.0 && value >= local_max && value >= mean + picking.delta && waited {
+                onsets.push(n);
+            }
+        }
+        onsets
+    }
+}
+
+/// Adaptive threshold peak picking: a frame is an onset if it is the largest within
+/// `pre_max` before and `post_max` after it, exceeds the mean from `pre_avg` before to
+/// `post_avg` after it by `delta`, and is more than `wait` after the previous onset. Times
+/// are in seconds; `delta` is relative to the largest value of the envelope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakPicking {
+    pub pre_max: f64,
+    pub post_max: f64,
+    pub pre_avg: f64,
+    pub post_avg: f64,
+    pub delta: f32,
+    pub wait: f64,
+}
+
+impl PeakPicking {
+    pub fn new() -> PeakPicking {
+        PeakPicking {
+            pre_max: 0.03,
+            post_max: 0.0,
+            pre_avg: 0.1,
+            post_avg: 0.1,
+            delta: 0.07,
+            wait: 0.03,
+        }
+    }
+}
+
+impl Default for PeakPicking {
+    fn default() -> PeakPicking {
+        PeakPicking::new()
+    }
+}
+
+pub fn onset_strength(signal: &[f32],
+                      config: &StftConfig,
+                      function: OnsetFunction)
+                      -> Result<OnsetEnvelope, String> {
+    Ok(OnsetEnvelope::new(&stft(signal, config)?, function))
+}
+
+/// Times in seconds of the onsets in `signal`.
+pub fn onsets(signal: &[f32],
+              config: &StftConfig,
+              function: OnsetFunction,
+              picking: &PeakPicking)
+              -> Result<Vec<f64>, String> {
+    let envelope = onset_strength(signal, config, function)?;
+    Ok(envelope.peaks(picking).into_iter().map(|n| envelope.times[n]).collect())
+}
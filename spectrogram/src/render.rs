@@ -0,0 +1,440 @@
+use image::{Rgb, RgbImage};
+
+use filterbank::{BandSpectrogram, FrequencyScale, MelScale};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ColorMap {
+    Viridis,
+    Magma,
+    Inferno,
+    Grayscale,
+}
+
+// Nine evenly spaced stops of matplotlib's perceptually uniform maps.
+const VIRIDIS: [[u8; 3]; 9] = [[68, 1, 84],
+                               [71, 44, 122],
+                               [59, 81, 139],
+                               [44, 113, 142],
+                               [33, 144, 141],
+                               [39, 173, 129],
+                               [92, 200, 99],
+                               [170, 220, 50],
+                               [253, 231, 37]];
+const MAGMA: [[u8; 3]; 9] = [[0, 0, 4],
+                             [28, 16, 68],
+                             [79, 18, 123],
+                             [129, 37, 129],
+                             [181, 54, 122],
+                             [229, 80, 100],
+                             [251, 135, 97],
+                             [254, 194, 135],
+                             [252, 253, 191]];
+const INFERNO: [[u8; 3]; 9] = [[0, 0, 4],
+                               [31, 12, 72],
+                               [85, 15, 109],
+                               [136, 34, 106],
+                               [186, 54, 85],
+                               [227, 89, 51],
+                               [249, 140, 10],
+                               [249, 201, 50],
+                               [252, 255, 164]];
+
+impl ColorMap {
+    /// Colour for `t` in [0, 1]; values outside are clamped and NaN maps to 0.
+    pub fn color(&self, t: f32) -> Rgb<u8> {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let stops = match *self {
+            ColorMap::Viridis => &VIRIDIS,
+            ColorMap::Magma => &MAGMA,
+            ColorMap::Inferno => &INFERNO,
+            ColorMap::Grayscale => {
+                let v = (t * 255.0).round() as u8;
+                return Rgb([v, v, v]);
+            }
+        };
+        let position = t * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+        let (a, b) = (stops[index], stops[index + 1]);
+        let lerp = |i: usize| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * fraction).round() as u8;
+        Rgb([lerp(0), lerp(1), lerp(2)])
+    }
+}
+
+/// How image rows map to frequency.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FrequencyAxis {
+    Linear,
+    Log,
+    Mel,
+}
+
+impl FrequencyAxis {
+    fn scale(&self) -> FrequencyScale {
+        match *self {
+            FrequencyAxis::Mel => FrequencyScale::Mel(MelScale::Slaney),
+            _ => FrequencyScale::Linear,
+        }
+    }
+
+    fn hz_to_axis(self, hz: f64) -> f64 {
+        match self {
+            FrequencyAxis::Log => hz.ln(),
+            _ => self.scale().from_hz(hz),
+        }
+    }
+
+    fn axis_to_hz(self, value: f64) -> f64 {
+        match self {
+            FrequencyAxis::Log => value.exp(),
+            _ => self.scale().to_hz(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderConfig {
+    pub color_map: ColorMap,
+    pub frequency_axis: FrequencyAxis,
+    /// Lowest frequency shown; defaults to the lowest band, or the lowest nonzero band on a
+    /// log axis.
+    pub min_frequency: Option<f64>,
+    /// Highest frequency shown; defaults to the highest band.
+    pub max_frequency: Option<f64>,
+    /// Values from the peak down to `peak - dynamic_range` span the colour map, lower values
+    /// take its first colour. `None` spans the full range of the data.
+    pub dynamic_range: Option<f32>,
+    /// Size of the plot area in pixels; defaults to one pixel per frame and per band.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Draw time and frequency tick marks and labels around the plot.
+    pub labels: bool,
+}
+
+impl RenderConfig {
+    pub fn new() -> RenderConfig {
+        RenderConfig {
+            color_map: ColorMap::Viridis,
+            frequency_axis: FrequencyAxis::Linear,
+            min_frequency: None,
+            max_frequency: None,
+            dynamic_range: None,
+            width: None,
+            height: None,
+            labels: false,
+        }
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> RenderConfig {
+        RenderConfig::new()
+    }
+}
+
+const GLYPH_SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = 3 * GLYPH_SCALE;
+const GLYPH_HEIGHT: u32 = 5 * GLYPH_SCALE;
+const GLYPH_SPACING: u32 = GLYPH_SCALE;
+const TICK_LENGTH: u32 = 4;
+const TICK_TARGET: f64 = 6.0;
+const LABEL_COLOR: [u8; 3] = [255, 255, 255];
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+
+/// Renders `spectrogram` with time running left to right and frequency bottom to top.
+pub fn render(spectrogram: &BandSpectrogram, config: &RenderConfig) -> Result<RgbImage, String> {
+    let values = &spectrogram.values;
+    let frequencies = &spectrogram.frequencies;
+    if values.is_empty() || frequencies.is_empty() {
+        return Err("nothing to render".to_owned());
+    }
+    if values.iter().any(|frame| frame.len() != frequencies.len()) {
+        return Err("every frame must have one value per band".to_owned());
+    }
+
+    let lowest = match config.frequency_axis {
+        FrequencyAxis::Log => {
+            frequencies.iter()
+                .cloned()
+                .find(|&f| f > 0.0)
+                .ok_or_else(|| "a log axis needs a band above 0 Hz".to_owned())?
+        }
+        _ => frequencies[0],
+    };
+    let min_frequency = config.min_frequency.unwrap_or(lowest);
+    let max_frequency = config.max_frequency.unwrap_or(frequencies[frequencies.len() - 1]);
+    let valid = min_frequency < max_frequency &&
+                (config.frequency_axis != FrequencyAxis::Log || min_frequency > 0.0);
+    if !valid {
+        return Err(format!("invalid frequency range {} Hz to {} Hz",
+                           min_frequency,
+                           max_frequency));
+    }
+
+    let all = || values.iter().flat_map(|f| f.iter()).cloned();
+    let peak = all().fold(f32::NEG_INFINITY, f32::max);
+    let floor = match config.dynamic_range {
+        Some(range) => peak - range,
+        None => all().fold(f32::INFINITY, f32::min),
+    };
+    let span = if peak > floor { peak - floor } else { 1.0 };
+
+    let width = config.width.unwrap_or(values.len() as u32).max(1);
+    let height = config.height.unwrap_or(frequencies.len() as u32).max(1);
+    let axis = config.frequency_axis;
+    let (axis_low, axis_high) = (axis.hz_to_axis(min_frequency), axis.hz_to_axis(max_frequency));
+    let row_frequency = |y: u32| {
+        // Row 0 is the top of the plot.
+        let position = 1.0 - (y as f64 + 0.5) / height as f64;
+        axis.axis_to_hz(axis_low + (axis_high - axis_low) * position)
+    };
+    let row_bins: Vec<f64> = (0..height)
+        .map(|y| fractional_band(frequencies, row_frequency(y)))
+        .collect();
+
+    let (left, bottom) = if config.labels {
+        let widest = frequency_ticks(config, min_frequency, max_frequency)
+            .iter()
+            .map(|(_, label)| text_width(label))
+            .max()
+            .unwrap_or(0);
+        (widest + TICK_LENGTH + 4, GLYPH_HEIGHT + TICK_LENGTH + 4)
+    } else {
+        (0, 0)
+    };
+    let right = if config.labels { GLYPH_WIDTH * 2 } else { 0 };
+    let top = if config.labels { GLYPH_HEIGHT / 2 } else { 0 };
+    let mut image = RgbImage::from_pixel(left + width + right,
+                                         top + height + bottom,
+                                         Rgb(BACKGROUND));
+
+    for x in 0..width {
+        let frame = ((x as f64 + 0.5) / width as f64 * values.len() as f64 - 0.5)
+            .max(0.0)
+            .min((values.len() - 1) as f64);
+        for y in 0..height {
+            let value = bilinear(values, frame, row_bins[y as usize]);
+            let color = config.color_map.color((value - floor) / span);
+            image.put_pixel(left + x, top + y, color);
+        }
+    }
+
+    if config.labels {
+        // Ticks whose labels would overlap the previous one are skipped, working down from
+        // the top where log and mel ticks are sparsest.
+        let mut label_bottom = i64::MIN;
+        for (hz, label) in frequency_ticks(config, min_frequency, max_frequency).into_iter().rev() {
+            let position = (axis.hz_to_axis(hz) - axis_low) / (axis_high - axis_low);
+            let y = top + ((1.0 - position) * (height - 1) as f64).round() as u32;
+            let text_y = (y as i64 - GLYPH_HEIGHT as i64 / 2)
+                .max(0)
+                .min((image.height() - GLYPH_HEIGHT) as i64);
+            if text_y < label_bottom {
+                continue;
+            }
+            label_bottom = text_y + (GLYPH_HEIGHT + GLYPH_SPACING) as i64;
+            for i in 0..TICK_LENGTH {
+                image.put_pixel(left - 1 - i, y, Rgb(LABEL_COLOR));
+            }
+            draw_text(&mut image,
+                      &label,
+                      left - TICK_LENGTH - 2 - text_width(&label),
+                      text_y as u32);
+        }
+
+        let (start, end) = time_bounds(spectrogram);
+        let mut label_end = i64::MIN;
+        for (seconds, label) in ticks(start, end, format_seconds) {
+            let position = if end > start {
+                (seconds - start) / (end - start)
+            } else {
+                0.0
+            };
+            let x = left + (position * (width - 1) as f64).round() as u32;
+            // A narrow plot may have no room for a label at all.
+            let label_width = text_width(&label);
+            if label_width > image.width() {
+                continue;
+            }
+            let text_x = (x as i64 - label_width as i64 / 2)
+                .max(0)
+                .min((image.width() - label_width) as i64);
+            if text_x < label_end + GLYPH_WIDTH as i64 {
+                continue;
+            }
+            label_end = text_x + label_width as i64;
+            for i in 0..TICK_LENGTH {
+                image.put_pixel(x, top + height + i, Rgb(LABEL_COLOR));
+            }
+            draw_text(&mut image,
+                      &label,
+                      text_x as u32,
+                      top + height + TICK_LENGTH + 2);
+        }
+    }
+    Ok(image)
+}
+
+/// Index into the increasing `frequencies` of `hz`, interpolated between bands.
+fn fractional_band(frequencies: &[f64], hz: f64) -> f64 {
+    match frequencies.iter().position(|&f| f >= hz) {
+        None => (frequencies.len() - 1) as f64,
+        Some(0) => 0.0,
+        Some(upper) => {
+            let (a, b) = (frequencies[upper - 1], frequencies[upper]);
+            upper as f64 - 1.0 + (hz - a) / (b - a)
+        }
+    }
+}
+
+fn bilinear(values: &[Vec<f32>], frame: f64, band: f64) -> f32 {
+    let lerp = |row: &[f32]| {
+        let i = (band as usize).min(row.len() - 1);
+        let j = (i + 1).min(row.len() - 1);
+        let t = (band - i as f64) as f32;
+        row[i] + (row[j] - row[i]) * t
+    };
+    let i = (frame as usize).min(values.len() - 1);
+    let j = (i + 1).min(values.len() - 1);
+    let t = (frame - i as f64) as f32;
+    let (a, b) = (lerp(&values[i]), lerp(&values[j]));
+    a + (b - a) * t
+}
+
+fn time_bounds(spectrogram: &BandSpectrogram) -> (f64, f64) {
+    match (spectrogram.times.first(), spectrogram.times.last()) {
+        (Some(&start), Some(&end)) => (start.max(0.0), end),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// A step of 1, 2 or 5 times a power of ten giving roughly `TICK_TARGET` ticks.
+fn nice_step(range: f64) -> f64 {
+    let rough = range / TICK_TARGET;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let normalized = rough / magnitude;
+    magnitude *
+    if normalized < 1.5 {
+        1.0
+    } else if normalized < 3.5 {
+        2.0
+    } else if normalized < 7.5 {
+        5.0
+    } else {
+        10.0
+    }
+}
+
+fn ticks<F: Fn(f64) -> String>(low: f64, high: f64, format: F) -> Vec<(f64, String)> {
+    if high <= low {
+        return vec![(low, format(low))];
+    }
+    let step = nice_step(high - low);
+    let mut value = (low / step).ceil() * step;
+    let mut ticks = Vec::new();
+    while value <= high + step * 1e-9 {
+        ticks.push((value, format(value)));
+        value += step;
+    }
+    ticks
+}
+
+fn frequency_ticks(config: &RenderConfig, low: f64, high: f64) -> Vec<(f64, String)> {
+    match config.frequency_axis {
+        FrequencyAxis::Linear => ticks(low, high, format_hz),
+        FrequencyAxis::Log | FrequencyAxis::Mel => {
+            // 1, 2 and 5 in each decade.
+            let mut ticks = Vec::new();
+            let mut decade = 10f64.powf(low.max(1.0).log10().floor());
+            while decade <= high {
+                for &m in [1.0, 2.0, 5.0].iter() {
+                    let hz = decade * m;
+                    if hz >= low && hz <= high {
+                        ticks.push((hz, format_hz(hz)));
+                    }
+                }
+                decade *= 10.0;
+            }
+            ticks
+        }
+    }
+}
+
+fn format_hz(hz: f64) -> String {
+    if hz >= 1000.0 {
+        format_number(hz / 1000.0) + "k"
+    } else {
+        format_number(hz)
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format_number(seconds) + "s"
+}
+
+fn format_number(value: f64) -> String {
+    let mut text = format!("{:.2}", value);
+    while text.ends_with('0') {
+        text.pop();
+    }
+    if text.ends_with('.') {
+        text.pop();
+    }
+    if text.is_empty() || text == "-0" {
+        "0".to_owned()
+    } else {
+        text
+    }
+}
+
+/// 3x5 pixel glyphs, one row per byte with the leftmost pixel in bit 2.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 1, 1, 1],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        '.' => [0, 0, 0, 0, 2],
+        '-' => [0, 0, 7, 0, 0],
+        'k' => [4, 5, 6, 5, 5],
+        's' => [3, 4, 2, 1, 6],
+        _ => [0; 5],
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    let count = text.chars().count() as u32;
+    if count == 0 {
+        0
+    } else {
+        count * (GLYPH_WIDTH + GLYPH_SPACING) - GLYPH_SPACING
+    }
+}
+
+fn draw_text(image: &mut RgbImage, text: &str, x: u32, y: u32) {
+    for (n, c) in text.chars().enumerate() {
+        let origin = x + n as u32 * (GLYPH_WIDTH + GLYPH_SPACING);
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..3 {
+                if bits & (4 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..GLYPH_SCALE {
+                    for dx in 0..GLYPH_SCALE {
+                        let px = origin + column * GLYPH_SCALE + dx;
+                        let py = y + row as u32 * GLYPH_SCALE + dy;
+                        if px < image.width() && py < image.height() {
+                            image.put_pixel(px, py, Rgb(LABEL_COLOR));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
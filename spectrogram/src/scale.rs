@@ -0,0 +1,191 @@
+use stft::ComplexSpectrogram;
+
+/// What a dB value is measured relative to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reference {
+    /// A fixed power for `PowerDb`, or amplitude for `AmplitudeDb`.
+    Fixed(f32),
+    /// The largest value in the spectrogram, which then reads 0 dB.
+    Max,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Scale {
+    Magnitude,
+    Power,
+    /// `10 log10(max(power, floor) / reference)`.
+    PowerDb {
+        reference: Reference,
+        floor: f32,
+        /// Values more than this many dB below the peak are raised to that level.
+        top_db: Option<f32>,
+    },
+    /// `20 log10(max(magnitude, floor) / reference)`.
+    AmplitudeDb {
+        reference: Reference,
+        floor: f32,
+        top_db: Option<f32>,
+    },
+}
+
+impl Scale {
+    pub fn is_decibels(&self) -> bool {
+        matches!(*self, Scale::PowerDb { .. } | Scale::AmplitudeDb { .. })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    None,
+    /// Scale each frame so its peak is 1, or 0 dB.
+    PerFrame,
+    /// Scale the whole spectrogram so its peak is 1, or 0 dB.
+    Global,
+}
+
+/// Corrects for the energy the analysis window removes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WindowCompensation {
+    None,
+    /// A sinusoid of amplitude A reads as magnitude A, whatever the window and its length.
+    Amplitude,
+    /// Power spectral density in units of power per Hz, using the window's equivalent noise
+    /// bandwidth.
+    Density,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scaling {
+    pub scale: Scale,
+    pub normalization: Normalization,
+    pub window_compensation: WindowCompensation,
+}
+
+impl Scaling {
+    pub fn new(scale: Scale) -> Scaling {
+        Scaling {
+            scale,
+            normalization: Normalization::None,
+            window_compensation: WindowCompensation::None,
+        }
+    }
+
+    /// Dynamic range suitable for display: amplitude in dB relative to the peak, 80 dB deep.
+    pub fn display() -> Scaling {
+        Scaling::new(Scale::AmplitudeDb {
+                         reference: Reference::Max,
+                         floor: 1e-10,
+                         top_db: Some(80.0),
+                     })
+    }
+
+    pub fn apply(&self, spectrum: &ComplexSpectrogram) -> Vec<Vec<f32>> {
+        self.apply_power(power_spectrum(spectrum, self.window_compensation))
+    }
+
+    /// Scales and normalises frames of power values, such as a `power_spectrum` or the
+    /// output of a filterbank.
+    pub fn apply_power(&self, mut power: Vec<Vec<f32>>) -> Vec<Vec<f32>> {
+        match self.scale {
+            Scale::Power => {}
+            Scale::Magnitude => map_all(&mut power, |p| p.sqrt()),
+            Scale::PowerDb { reference, floor, top_db } => {
+                to_decibels(&mut power, 10.0, reference, floor, top_db)
+            }
+            Scale::AmplitudeDb { reference, floor, top_db } => {
+                map_all(&mut power, |p| p.sqrt());
+                to_decibels(&mut power, 20.0, reference, floor, top_db)
+            }
+        }
+
+        let decibels = self.scale.is_decibels();
+        let normalize = |frame: &mut Vec<f32>, peak: f32| if decibels {
+            for v in frame.iter_mut() {
+                *v -= peak;
+            }
+        } else if peak > 0.0 {
+            for v in frame.iter_mut() {
+                *v /= peak;
+            }
+        };
+        match self.normalization {
+            Normalization::None => {}
+            Normalization::PerFrame => {
+                for frame in power.iter_mut() {
+                    let peak = max_value(frame.iter());
+                    if peak.is_finite() {
+                        normalize(frame, peak);
+                    }
+                }
+            }
+            Normalization::Global => {
+                let peak = max_value(power.iter().flat_map(|f| f.iter()));
+                if peak.is_finite() {
+                    for frame in power.iter_mut() {
+                        normalize(frame, peak);
+                    }
+                }
+            }
+        }
+        power
+    }
+}
+
+/// `|X|^2` of every bin, compensated for the analysis window.
+pub fn power_spectrum(spectrum: &ComplexSpectrogram,
+                      compensation: WindowCompensation)
+                      -> Vec<Vec<f32>> {
+    let config = &spectrum.config;
+    let window = config.window.coefficients(config.window_length);
+    let sum: f64 = window.iter().map(|&w| w as f64).sum();
+    let sum_squares: f64 = window.iter().map(|&w| w as f64 * w as f64).sum();
+    let nyquist = if config.fft_size.is_multiple_of(2) {
+        Some(config.fft_size / 2)
+    } else {
+        None
+    };
+    // Bins other than DC and Nyquist also carry the energy of their negative frequency.
+    let one_sided = |bin: usize| if bin == 0 || Some(bin) == nyquist { 1.0 } else { 2.0 };
+    let factor = |bin: usize| match compensation {
+        WindowCompensation::None => 1.0,
+        WindowCompensation::Amplitude => {
+            let gain = one_sided(bin) / sum;
+            gain * gain
+        }
+        WindowCompensation::Density => one_sided(bin) / (config.sample_rate * sum_squares),
+    };
+    let factors: Vec<f32> = (0..config.bins()).map(|bin| factor(bin) as f32).collect();
+    spectrum.frames
+        .iter()
+        .map(|frame| frame.iter().zip(&factors).map(|(c, f)| c.norm_sqr() * f).collect())
+        .collect()
+}
+
+fn map_all<F: Fn(f32) -> f32>(values: &mut [Vec<f32>], f: F) {
+    for frame in values.iter_mut() {
+        for v in frame.iter_mut() {
+            *v = f(*v);
+        }
+    }
+}
+
+fn max_value<'a, I: Iterator<Item = &'a f32>>(values: I) -> f32 {
+    values.cloned().fold(f32::NEG_INFINITY, f32::max)
+}
+
+fn to_decibels(values: &mut [Vec<f32>],
+               multiplier: f32,
+               reference: Reference,
+               floor: f32,
+               top_db: Option<f32>) {
+    let reference = match reference {
+        Reference::Fixed(value) => value,
+        Reference::Max => max_value(values.iter().flat_map(|f| f.iter())),
+    };
+    let reference = multiplier * reference.max(floor).log10();
+    map_all(values, |v| multiplier * v.max(floor).log10() - reference);
+    if let Some(top_db) = top_db {
+        let bottom = max_value(values.iter().flat_map(|f| f.iter())) - top_db;
+        map_all(values, |v| v.max(bottom));
+    }
+}
@@ -0,0 +1,293 @@
+use num_complex::Complex;
+
+use fft::{DefaultBackend, FftBackend, RealFft};
+use window::Window;
+
+/// Relative deviation from the mean overlap-add sum accepted as constant.
+const COLA_TOLERANCE: f64 = 1e-6;
+/// Smallest squared-window sum, relative to the largest, accepted as nonzero.
+const NOLA_TOLERANCE: f64 = 1e-10;
+
+/// How samples beyond either end of the signal are filled when frames are centred.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Padding {
+    Zero,
+    /// Mirror the signal about its first and last samples.
+    Reflect,
+    /// Repeat the first and last samples.
+    Edge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StftConfig {
+    pub sample_rate: f64,
+    pub window: Window,
+    /// Samples of signal in each frame.
+    pub window_length: usize,
+    /// Transform length; frames are zero padded from `window_length` up to it.
+    pub fft_size: usize,
+    /// Samples between the starts of consecutive frames.
+    pub hop_size: usize,
+    /// Centre frame `i` on sample `i * hop_size` rather than starting it there.
+    pub center: bool,
+    /// Fill used outside the signal when `center` is set.
+    pub padding: Padding,
+}
+
+impl StftConfig {
+    pub fn new(sample_rate: f64, window_length: usize) -> StftConfig {
+        StftConfig {
+            sample_rate,
+            window: Window::Hann,
+            window_length,
+            fft_size: window_length,
+            hop_size: (window_length / 4).max(1),
+            center: true,
+            padding: Padding::Reflect,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.sample_rate > 0.0 && self.sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {}", self.sample_rate));
+        }
+        if self.window_length == 0 {
+            return Err("window length must be positive".to_owned());
+        }
+        if self.fft_size < self.window_length {
+            return Err(format!("FFT size {} is shorter than the window length {}",
+                               self.fft_size,
+                               self.window_length));
+        }
+        if self.hop_size == 0 {
+            return Err("hop size must be positive".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Whether the analysis window, overlapped at this hop, sums to a constant (Constant
+    /// OverLap-Add), so plain overlap-add of unmodified frames reproduces the signal.
+    pub fn is_cola(&self) -> bool {
+        let window = self.window.coefficients(self.window_length);
+        let sums = overlap_sums(&window, self.hop_size, |w| w as f64);
+        let mean = sums.iter().sum::<f64>() / sums.len() as f64;
+        mean > 0.0 && sums.iter().all(|s| (s - mean).abs() <= mean * COLA_TOLERANCE)
+    }
+
+    /// Whether the squared window, overlapped at this hop, is nowhere zero (Nonzero
+    /// OverLap-Add). This is the condition `istft` needs to invert the transform.
+    pub fn is_nola(&self) -> bool {
+        let window = self.window.coefficients(self.window_length);
+        let sums = overlap_sums(&window, self.hop_size, |w| w as f64 * w as f64);
+        let peak = sums.iter().cloned().fold(0.0, f64::max);
+        peak > 0.0 && sums.iter().all(|&s| s > peak * NOLA_TOLERANCE)
+    }
+
+    /// Number of non-negative frequency bins in each frame.
+    pub fn bins(&self) -> usize {
+        self.fft_size / 2 + 1
+    }
+
+    /// Number of frames `stft` produces for a signal of `signal_length` samples. Centred
+    /// frames cover every sample; otherwise trailing samples that do not fill a frame are
+    /// dropped, except that a signal shorter than one window still yields a padded frame.
+    pub fn frame_count(&self, signal_length: usize) -> usize {
+        if self.center {
+            let padded = signal_length + 2 * (self.window_length / 2);
+            if padded < self.window_length {
+                0
+            } else {
+                1 + (padded - self.window_length) / self.hop_size
+            }
+        } else if signal_length == 0 {
+            0
+        } else if signal_length <= self.window_length {
+            1
+        } else {
+            1 + (signal_length - self.window_length) / self.hop_size
+        }
+    }
+
+    /// Index in the signal of the first sample of frame `frame`, which is negative for
+    /// centred frames that start before the signal.
+    pub fn frame_start(&self, frame: usize) -> isize {
+        let start = (frame * self.hop_size) as isize;
+        if self.center {
+            start - (self.window_length / 2) as isize
+        } else {
+            start
+        }
+    }
+}
+
+/// Complex STFT frames, each holding `config.bins()` bins from DC to Nyquist.
+#[derive(Debug, Clone)]
+pub struct ComplexSpectrogram {
+    pub frames: Vec<Vec<Complex<f32>>>,
+    pub config: StftConfig,
+    pub signal_length: usize,
+}
+
+impl ComplexSpectrogram {
+    pub fn bins(&self) -> usize {
+        self.config.bins()
+    }
+
+    pub fn bin_frequency(&self, bin: usize) -> f64 {
+        bin as f64 * self.config.sample_rate / self.config.fft_size as f64
+    }
+
+    /// Time in seconds of the centre of `frame`.
+    pub fn frame_time(&self, frame: usize) -> f64 {
+        let center = self.config.frame_start(frame) as f64 + self.config.window_length as f64 / 2.0;
+        center / self.config.sample_rate
+    }
+
+    pub fn magnitudes(&self) -> Vec<Vec<f32>> {
+        self.frames
+            .iter()
+            .map(|frame| frame.iter().map(|c| c.norm()).collect())
+            .collect()
+    }
+}
+
+/// Reads `signal[index]`, filling positions outside the signal according to `padding`.
+pub fn padded_sample(signal: &[f32], index: isize, padding: Padding) -> f32 {
+    padded_index(signal.len(), index, padding).map(|i| signal[i]).unwrap_or(0.0)
+}
+
+/// The index of the sample `padded_sample` reads for `index` in a signal of `len` samples,
+/// or `None` where it reads zero.
+pub fn padded_index(len: usize, index: isize, padding: Padding) -> Option<usize> {
+    let len = len as isize;
+    if index >= 0 && index < len {
+        return Some(index as usize);
+    }
+    if len == 0 {
+        return None;
+    }
+    match padding {
+        Padding::Zero => None,
+        Padding::Edge => Some(if index < 0 { 0 } else { len as usize - 1 }),
+        Padding::Reflect => {
+            if len == 1 {
+                return Some(0);
+            }
+            // Reflection without repeating the edge sample has period 2 * (len - 1).
+            let period = 2 * (len - 1);
+            let mut folded = index % period;
+            if folded < 0 {
+                folded += period;
+            }
+            if folded >= len {
+                folded = period - folded;
+            }
+            Some(folded as usize)
+        }
+    }
+}
+
+/// Short-time Fourier transform of `signal`.
+pub fn stft(signal: &[f32], config: &StftConfig) -> Result<ComplexSpectrogram, String> {
+    stft_with(signal, config, &DefaultBackend::default())
+}
+
+pub fn stft_with<B>(signal: &[f32],
+                    config: &StftConfig,
+                    backend: &B)
+                    -> Result<ComplexSpectrogram, String>
+    where B: FftBackend<f32>
+{
+    config.validate()?;
+    let window = config.window.coefficients(config.window_length);
+    let padding = if config.center {
+        config.padding
+    } else {
+        Padding::Zero
+    };
+    let mut fft = backend.plan(config.fft_size)?;
+    let mut input = vec![0.0; config.fft_size];
+    let mut frames = Vec::with_capacity(config.frame_count(signal.len()));
+    for frame in 0..config.frame_count(signal.len()) {
+        let start = config.frame_start(frame);
+        for i in 0..config.window_length {
+            input[i] = padded_sample(signal, start + i as isize, padding) * window[i];
+        }
+        let mut coefficients = vec![Complex::new(0.0, 0.0); config.bins()];
+        fft.forward(&input, &mut coefficients);
+        frames.push(coefficients);
+    }
+    Ok(ComplexSpectrogram {
+           frames,
+           config: config.clone(),
+           signal_length: signal.len(),
+       })
+}
+
+/// Sum of `f(window)` at each of the `hop` phases of a steady-state overlap.
+fn overlap_sums<F: Fn(f32) -> f64>(window: &[f32], hop: usize, f: F) -> Vec<f64> {
+    let mut sums = vec![0.0; hop];
+    for (i, &w) in window.iter().enumerate() {
+        sums[i % hop] += f(w);
+    }
+    sums
+}
+
+/// Inverse STFT by weighted overlap-add: each frame is inverse transformed, windowed again
+/// and summed, then divided by the summed squared window. Recovers the original signal
+/// exactly when the frames are unmodified and the configuration `is_nola`. Samples of a
+/// non-centred transform not covered by any frame come back as zero.
+pub fn istft(spectrum: &ComplexSpectrogram) -> Result<Vec<f32>, String> {
+    istft_with(spectrum, &DefaultBackend::default())
+}
+
+pub fn istft_with<B>(spectrum: &ComplexSpectrogram, backend: &B) -> Result<Vec<f32>, String>
+    where B: FftBackend<f32>
+{
+    let config = &spectrum.config;
+    config.validate()?;
+    if !config.is_nola() {
+        return Err(format!("{:?} window of {} samples at hop {} cannot be inverted",
+                           config.window,
+                           config.window_length,
+                           config.hop_size));
+    }
+    let window = config.window.coefficients(config.window_length);
+    let offset = if config.center {
+        config.window_length / 2
+    } else {
+        0
+    };
+    let length = (spectrum.frames.len() * config.hop_size + config.window_length)
+        .max(offset + spectrum.signal_length);
+    let mut output = vec![0.0f64; length];
+    let mut weights = vec![0.0f64; length];
+
+    let mut fft = backend.plan(config.fft_size)?;
+    let mut frame_samples = vec![0.0; config.fft_size];
+    let bins = config.bins();
+    for (frame, coefficients) in spectrum.frames.iter().enumerate() {
+        if coefficients.len() != bins {
+            return Err(format!("frame {} has {} bins, expected {}",
+                               frame,
+                               coefficients.len(),
+                               bins));
+        }
+        fft.inverse(coefficients, &mut frame_samples);
+        let start = frame * config.hop_size;
+        for i in 0..config.window_length {
+            let sample = frame_samples[i] as f64;
+            output[start + i] += sample * window[i] as f64;
+            weights[start + i] += window[i] as f64 * window[i] as f64;
+        }
+    }
+
+    let peak = weights.iter().cloned().fold(0.0, f64::max);
+    Ok((offset..offset + spectrum.signal_length)
+           .map(|i| if weights[i] > peak * NOLA_TOLERANCE {
+                    (output[i] / weights[i]) as f32
+                } else {
+                    0.0
+                })
+           .collect())
+}
@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+
+use futures::{Async, Poll, Stream};
+use num_complex::Complex;
+
+use fft::{DefaultBackend, FftBackend, RealFft};
+use stft::{padded_index, Padding, StftConfig};
+
+/// Computes STFT frames incrementally from chunks of signal of any size.
+///
+/// Frames are emitted as soon as every sample they cover has arrived; `finish` emits the
+/// frames that overlap the end of the signal. The frames match those of `stft` on the
+/// concatenated chunks exactly.
+pub struct StreamingStft<B: FftBackend<f32> = DefaultBackend> {
+    config: StftConfig,
+    window: Vec<f32>,
+    fft: B::Plan,
+    /// Retained signal, starting at sample `buffer_start`.
+    buffer: Vec<f32>,
+    buffer_start: usize,
+    received: usize,
+    next_frame: usize,
+    input: Vec<f32>,
+}
+
+impl StreamingStft<DefaultBackend> {
+    pub fn new(config: &StftConfig) -> Result<StreamingStft<DefaultBackend>, String> {
+        StreamingStft::with_backend(config, &DefaultBackend::default())
+    }
+}
+
+impl<B: FftBackend<f32>> StreamingStft<B> {
+    pub fn with_backend(config: &StftConfig, backend: &B) -> Result<StreamingStft<B>, String> {
+        config.validate()?;
+        Ok(StreamingStft {
+               config: config.clone(),
+               window: config.window.coefficients(config.window_length),
+               fft: backend.plan(config.fft_size)?,
+               buffer: Vec::new(),
+               buffer_start: 0,
+               received: 0,
+               next_frame: 0,
+               input: vec![0.0; config.fft_size],
+           })
+    }
+
+    pub fn config(&self) -> &StftConfig {
+        &self.config
+    }
+
+    /// Number of frames emitted since the start of the stream.
+    pub fn frames_emitted(&self) -> usize {
+        self.next_frame
+    }
+
+    /// Appends `chunk` to the signal and returns every frame it completes.
+    pub fn push(&mut self, chunk: &[f32]) -> Vec<Vec<Complex<f32>>> {
+        self.buffer.extend_from_slice(chunk);
+        self.received += chunk.len();
+        let mut frames = Vec::new();
+        while self.frame_ready(self.next_frame) {
+            let frame = self.next_frame;
+            frames.push(self.compute(frame, false));
+            self.next_frame += 1;
+        }
+        self.discard();
+        frames
+    }
+
+    /// Ends the signal, returns the remaining frames and resets for a new stream.
+    pub fn finish(&mut self) -> Vec<Vec<Complex<f32>>> {
+        let total = self.config.frame_count(self.received);
+        let frames = (self.next_frame..total).map(|frame| self.compute(frame, true)).collect();
+        self.reset();
+        frames
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.buffer_start = 0;
+        self.received = 0;
+        self.next_frame = 0;
+    }
+
+    fn frame_ready(&self, frame: usize) -> bool {
+        let start = self.config.frame_start(frame);
+        let end = start + self.config.window_length as isize;
+        // Padding before the signal only depends on samples that have arrived once the
+        // signal is known to be longer than the padding.
+        end <= self.received as isize && (start >= 0 || (-start) < self.received as isize)
+    }
+
+    fn compute(&mut self, frame: usize, finished: bool) -> Vec<Complex<f32>> {
+        let start = self.config.frame_start(frame);
+        let padding = if self.config.center {
+            self.config.padding
+        } else {
+            Padding::Zero
+        };
+        for i in 0..self.config.window_length {
+            let index = padded_index(self.received, start + i as isize, padding);
+            debug_assert!(finished || index.is_none_or(|index| index < self.received));
+            self.input[i] = match index {
+                Some(index) => self.buffer[index - self.buffer_start] * self.window[i],
+                None => 0.0,
+            };
+        }
+        let mut coefficients = vec![Complex::new(0.0, 0.0); self.config.bins()];
+        self.fft.forward(&self.input, &mut coefficients);
+        coefficients
+    }
+
+    /// Drops samples no later frame can read. The last window's worth is always kept, since
+    /// reflection at the end of the signal reads back from it.
+    fn discard(&mut self) {
+        let next_start = self.config.frame_start(self.next_frame).max(0) as usize;
+        let keep_from = next_start.min(self.received.saturating_sub(self.config.window_length + 1));
+        if keep_from > self.buffer_start {
+            self.buffer.drain(..keep_from - self.buffer_start);
+            self.buffer_start = keep_from;
+        }
+    }
+}
+
+/// Frames computed from an iterator of sample chunks.
+pub struct StftFrames<I> {
+    chunks: I,
+    stft: StreamingStft,
+    pending: VecDeque<Vec<Complex<f32>>>,
+    finished: bool,
+}
+
+pub fn stft_frames<I>(chunks: I, config: &StftConfig) -> Result<StftFrames<I::IntoIter>, String>
+    where I: IntoIterator<Item = Vec<f32>>
+{
+    Ok(StftFrames {
+           chunks: chunks.into_iter(),
+           stft: StreamingStft::new(config)?,
+           pending: VecDeque::new(),
+           finished: false,
+       })
+}
+
+impl<I> Iterator for StftFrames<I>
+    where I: Iterator<Item = Vec<f32>>
+{
+    type Item = Vec<Complex<f32>>;
+
+    fn next(&mut self) -> Option<Vec<Complex<f32>>> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Some(frame);
+            }
+            if self.finished {
+                return None;
+            }
+            match self.chunks.next() {
+                Some(chunk) => self.pending.extend(self.stft.push(&chunk)),
+                None => {
+                    self.pending.extend(self.stft.finish());
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+/// Frames computed from a stream of sample chunks.
+pub struct StftStream<S> {
+    source: S,
+    stft: StreamingStft,
+    pending: VecDeque<Vec<Complex<f32>>>,
+    finished: bool,
+}
+
+pub fn stft_stream<S>(source: S, config: &StftConfig) -> Result<StftStream<S>, String>
+    where S: Stream<Item = Vec<f32>>
+{
+    Ok(StftStream {
+           source,
+           stft: StreamingStft::new(config)?,
+           pending: VecDeque::new(),
+           finished: false,
+       })
+}
+
+impl<S> StftStream<S> {
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> Stream for StftStream<S>
+    where S: Stream<Item = Vec<f32>>
+{
+    type Item = Vec<Complex<f32>>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Vec<Complex<f32>>>, S::Error> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Ok(Async::Ready(Some(frame)));
+            }
+            if self.finished {
+                return Ok(Async::Ready(None));
+            }
+            match self.source.poll()? {
+                Async::Ready(Some(chunk)) => self.pending.extend(self.stft.push(&chunk)),
+                Async::Ready(None) => {
+                    self.pending.extend(self.stft.finish());
+                    self.finished = true;
+                }
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use futures::{Async, Poll, Stream};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NoteName {
+    C,
+    CSharp,
+    D,
+    DSharp,
+    E,
+    F,
+    FSharp,
+    G,
+    GSharp,
+    A,
+    ASharp,
+    B,
+}
+
+static NOTE_NAMES: [NoteName; 12] = [NoteName::C,
+                                     NoteName::CSharp,
+                                     NoteName::D,
+                                     NoteName::DSharp,
+                                     NoteName::E,
+                                     NoteName::F,
+                                     NoteName::FSharp,
+                                     NoteName::G,
+                                     NoteName::GSharp,
+                                     NoteName::A,
+                                     NoteName::ASharp,
+                                     NoteName::B];
+
+impl NoteName {
+    pub fn from_pitch_class(pitch_class: usize) -> NoteName {
+        NOTE_NAMES[pitch_class % 12]
+    }
+
+    pub fn pitch_class(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            NoteName::C => "C",
+            NoteName::CSharp => "C#",
+            NoteName::D => "D",
+            NoteName::DSharp => "D#",
+            NoteName::E => "E",
+            NoteName::F => "F",
+            NoteName::FSharp => "F#",
+            NoteName::G => "G",
+            NoteName::GSharp => "G#",
+            NoteName::A => "A",
+            NoteName::ASharp => "A#",
+            NoteName::B => "B",
+        }
+    }
+}
+
+impl fmt::Display for NoteName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Note {
+    pub name: NoteName,
+    pub octave: i32,
+}
+
+impl Note {
+    /// MIDI note numbering: A4 is 69, C4 is 60.
+    pub fn from_midi(midi: i32) -> Note {
+        let pitch_class = midi.rem_euclid(12);
+        Note {
+            name: NoteName::from_pitch_class(pitch_class as usize),
+            octave: (midi - pitch_class) / 12 - 1,
+        }
+    }
+
+    pub fn midi(&self) -> i32 {
+        (self.octave + 1) * 12 + self.name.pitch_class() as i32
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.name, self.octave)
+    }
+}
+
+static JUST_RATIOS: [f64; 12] = [1.0,
+                                 16.0 / 15.0,
+                                 9.0 / 8.0,
+                                 6.0 / 5.0,
+                                 5.0 / 4.0,
+                                 4.0 / 3.0,
+                                 45.0 / 32.0,
+                                 3.0 / 2.0,
+                                 8.0 / 5.0,
+                                 5.0 / 3.0,
+                                 9.0 / 5.0,
+                                 15.0 / 8.0];
+
+static PYTHAGOREAN_RATIOS: [f64; 12] = [1.0,
+                                        256.0 / 243.0,
+                                        9.0 / 8.0,
+                                        32.0 / 27.0,
+                                        81.0 / 64.0,
+                                        4.0 / 3.0,
+                                        729.0 / 512.0,
+                                        3.0 / 2.0,
+                                        128.0 / 81.0,
+                                        27.0 / 16.0,
+                                        16.0 / 9.0,
+                                        243.0 / 128.0];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Temperament {
+    Equal,
+    /// Five-limit just intonation built on the given tonic.
+    Just(NoteName),
+    /// Pythagorean tuning built on the given tonic.
+    Pythagorean(NoteName),
+    /// Deviation in cents from equal temperament for each pitch class, starting at C.
+    Custom([f64; 12]),
+}
+
+impl Temperament {
+    /// Per pitch class deviation from equal temperament in cents, shifted so that A is
+    /// always in tune with the reference frequency.
+    pub fn offsets_cents(&self) -> [f64; 12] {
+        let mut offsets = [0.0; 12];
+        match *self {
+            Temperament::Equal => (),
+            Temperament::Just(tonic) => rotate_ratios(&JUST_RATIOS, tonic, &mut offsets),
+            Temperament::Pythagorean(tonic) => {
+                rotate_ratios(&PYTHAGOREAN_RATIOS, tonic, &mut offsets)
+            }
+            Temperament::Custom(custom) => offsets = custom,
+        }
+        let a_offset = offsets[NoteName::A.pitch_class()];
+        for offset in offsets.iter_mut() {
+            *offset -= a_offset;
+        }
+        offsets
+    }
+}
+
+fn rotate_ratios(ratios: &[f64; 12], tonic: NoteName, out: &mut [f64; 12]) {
+    for (degree, ratio) in ratios.iter().enumerate() {
+        let pitch_class = (tonic.pitch_class() + degree) % 12;
+        out[pitch_class] = 1200.0 * ratio.log2() - 100.0 * degree as f64;
+    }
+}
+
+/// Finds the note closest to `frequency`, returning it along with the deviation in cents
+/// and the frequency the note should sound at. Returns `None` unless both frequencies are
+/// positive and finite.
+pub fn nearest_note(frequency: f64,
+                    reference_a4: f64,
+                    temperament: &Temperament)
+                    -> Option<(Note, f64, f64)> {
+    if !(frequency > 0.0 && frequency.is_finite() && reference_a4 > 0.0 &&
+         reference_a4.is_finite()) {
+        return None;
+    }
+    let offsets = temperament.offsets_cents();
+    let equal_midi = 69.0 + 12.0 * (frequency / reference_a4).log2();
+    let rounded = equal_midi.round() as i32;
+    let mut best = (Note::from_midi(rounded), f64::INFINITY, 0.0);
+    for midi in (rounded - 1)..(rounded + 2) {
+        let note = Note::from_midi(midi);
+        let semitones = (midi - 69) as f64 + offsets[note.name.pitch_class()] / 100.0;
+        let target = reference_a4 * (semitones / 12.0).exp2();
+        let cents = 1200.0 * (frequency / target).log2();
+        if cents.abs() < best.1.abs() {
+            best = (note, cents, target);
+        }
+    }
+    Some(best)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Yin,
+    Mpm,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PitchEstimate {
+    pub frequency: f64,
+    /// How periodic the frame is, from 0 (noise) to 1 (perfectly periodic).
+    pub confidence: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PitchReading {
+    pub frequency: f64,
+    pub confidence: f64,
+    pub note: Note,
+    pub cents: f64,
+    pub target_frequency: f64,
+    /// Index of the first sample of the analysed frame.
+    pub sample_offset: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TunerConfig {
+    pub sample_rate: f64,
+    pub algorithm: Algorithm,
+    pub window_size: usize,
+    pub hop_size: usize,
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    pub reference_a4: f64,
+    pub temperament: Temperament,
+    /// YIN dip threshold; ignored by MPM.
+    pub yin_threshold: f64,
+    /// Readings below this confidence are not reported.
+    pub min_confidence: f64,
+    /// Frames quieter than this RMS level are treated as silence.
+    pub silence_rms: f32,
+}
+
+impl TunerConfig {
+    pub fn new(sample_rate: f64) -> TunerConfig {
+        TunerConfig {
+            sample_rate,
+            algorithm: Algorithm::Yin,
+            window_size: 2048,
+            hop_size: 512,
+            min_frequency: 40.0,
+            max_frequency: 2000.0,
+            reference_a4: 440.0,
+            temperament: Temperament::Equal,
+            yin_threshold: 0.15,
+            min_confidence: 0.8,
+            silence_rms: 1e-3,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.sample_rate > 0.0 && self.sample_rate.is_finite()) {
+            return Err(format!("invalid sample rate {}", self.sample_rate));
+        }
+        if !(self.min_frequency > 0.0 && self.min_frequency < self.max_frequency &&
+             self.max_frequency.is_finite()) {
+            return Err(format!("invalid frequency range {}..{}",
+                               self.min_frequency,
+                               self.max_frequency));
+        }
+        if self.window_size == 0 || self.hop_size == 0 {
+            return Err("window and hop sizes must be non-zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn detect_pitch(frame: &[f32], config: &TunerConfig) -> Result<Option<PitchEstimate>, String> {
+    config.validate()?;
+    Ok(estimate_pitch(frame, config))
+}
+
+fn estimate_pitch(frame: &[f32], config: &TunerConfig) -> Option<PitchEstimate> {
+    let energy: f64 = frame.iter().map(|&s| s as f64 * s as f64).sum();
+    if frame.is_empty() || (energy / frame.len() as f64).sqrt() < config.silence_rms as f64 {
+        return None;
+    }
+    let min_lag = (config.sample_rate / config.max_frequency).floor().max(2.0) as usize;
+    let max_lag = (config.sample_rate / config.min_frequency).ceil() as usize;
+    match config.algorithm {
+        Algorithm::Yin => yin(frame, config.sample_rate, min_lag, max_lag, config.yin_threshold),
+        Algorithm::Mpm => mpm(frame, config.sample_rate, min_lag, max_lag),
+    }
+}
+
+fn parabolic_offset(prev: f64, at: f64, next: f64) -> f64 {
+    let denominator = prev - 2.0 * at + next;
+    if denominator.abs() < 1e-12 {
+        0.0
+    } else {
+        (0.5 * (prev - next) / denominator).clamp(-1.0, 1.0)
+    }
+}
+
+fn yin(frame: &[f32],
+       sample_rate: f64,
+       min_lag: usize,
+       max_lag: usize,
+       threshold: f64)
+       -> Option<PitchEstimate> {
+    let integration = frame.len() / 2;
+    let max_lag = max_lag.min(frame.len() - integration);
+    if min_lag + 1 >= max_lag {
+        return None;
+    }
+
+    let mut cmnd = vec![1.0; max_lag + 1];
+    let mut running_sum = 0.0;
+    for lag in 1..(max_lag + 1) {
+        let mut difference = 0.0;
+        for j in 0..integration {
+            let delta = frame[j] as f64 - frame[j + lag] as f64;
+            difference += delta * delta;
+        }
+        running_sum += difference;
+        cmnd[lag] = if running_sum > 0.0 {
+            difference * lag as f64 / running_sum
+        } else {
+            1.0
+        };
+    }
+
+    let mut best = None;
+    let mut lag = min_lag;
+    while lag < max_lag {
+        if cmnd[lag] < threshold {
+            while lag + 1 < max_lag && cmnd[lag + 1] < cmnd[lag] {
+                lag += 1;
+            }
+            best = Some(lag);
+            break;
+        }
+        lag += 1;
+    }
+    let lag = match best {
+        Some(lag) => lag,
+        None => {
+            (min_lag..max_lag)
+                .fold(min_lag,
+                      |best, lag| if cmnd[lag] < cmnd[best] { lag } else { best })
+        }
+    };
+
+    let offset = parabolic_offset(cmnd[lag - 1], cmnd[lag], cmnd[lag + 1]);
+    Some(PitchEstimate {
+             frequency: sample_rate / (lag as f64 + offset),
+             confidence: (1.0 - cmnd[lag]).clamp(0.0, 1.0),
+         })
+}
+
+fn mpm(frame: &[f32], sample_rate: f64, min_lag: usize, max_lag: usize) -> Option<PitchEstimate> {
+    let max_lag = max_lag.min(frame.len() - 1);
+    if min_lag + 1 >= max_lag {
+        return None;
+    }
+
+    let mut nsdf = vec![0.0; max_lag + 1];
+    for lag in 0..(max_lag + 1) {
+        let (mut acf, mut energy) = (0.0, 0.0);
+        for j in 0..(frame.len() - lag) {
+            let (a, b) = (frame[j] as f64, frame[j + lag] as f64);
+            acf += a * b;
+            energy += a * a + b * b;
+        }
+        nsdf[lag] = if energy > 0.0 { 2.0 * acf / energy } else { 0.0 };
+    }
+
+    // Key maxima are the highest points of each positive lobe after the first
+    // negative-going zero crossing.
+    let mut key_maxima: Vec<usize> = Vec::new();
+    let mut lag = 1;
+    while lag < max_lag && nsdf[lag] > 0.0 {
+        lag += 1;
+    }
+    let mut lobe_max: Option<usize> = None;
+    while lag < max_lag {
+        if nsdf[lag] > 0.0 {
+            if lobe_max.map(|m| nsdf[lag] > nsdf[m]).unwrap_or(true) {
+                lobe_max = Some(lag);
+            }
+        } else if let Some(m) = lobe_max.take() {
+            key_maxima.push(m);
+        }
+        lag += 1;
+    }
+    if let Some(m) = lobe_max {
+        key_maxima.push(m);
+    }
+
+    let key_maxima: Vec<usize> = key_maxima.into_iter().filter(|&m| m >= min_lag).collect();
+    let highest = key_maxima.iter().fold(0.0f64, |acc, &m| acc.max(nsdf[m]));
+    let chosen = match key_maxima.iter().find(|&&m| nsdf[m] >= 0.93 * highest) {
+        Some(&m) => m,
+        None => return None,
+    };
+
+    let offset = parabolic_offset(nsdf[chosen - 1], nsdf[chosen], nsdf[chosen + 1]);
+    Some(PitchEstimate {
+             frequency: sample_rate / (chosen as f64 + offset),
+             confidence: nsdf[chosen].clamp(0.0, 1.0),
+         })
+}
+
+/// Buffers incoming samples and analyses a window every `hop_size` samples.
+pub struct Tuner {
+    config: TunerConfig,
+    buffer: Vec<f32>,
+    position: u64,
+    skip: usize,
+}
+
+impl Tuner {
+    pub fn new(config: TunerConfig) -> Result<Tuner, String> {
+        config.validate()?;
+        Ok(Tuner {
+               config,
+               buffer: Vec::with_capacity(config.window_size * 2),
+               position: 0,
+               skip: 0,
+           })
+    }
+
+    pub fn config(&self) -> &TunerConfig {
+        &self.config
+    }
+
+    /// Analyses a single frame without confidence gating.
+    pub fn analyze(&self, frame: &[f32], sample_offset: u64) -> Option<PitchReading> {
+        estimate_pitch(frame, &self.config).and_then(|estimate| {
+            if estimate.frequency < self.config.min_frequency ||
+               estimate.frequency > self.config.max_frequency {
+                return None;
+            }
+            let (note, cents, target) = match nearest_note(estimate.frequency,
+                                                           self.config.reference_a4,
+                                                           &self.config.temperament) {
+                Some(nearest) => nearest,
+                None => return None,
+            };
+            Some(PitchReading {
+                     frequency: estimate.frequency,
+                     confidence: estimate.confidence,
+                     note,
+                     cents,
+                     target_frequency: target,
+                     sample_offset,
+                 })
+        })
+    }
+
+    /// Feeds mono samples and returns the confident readings of every completed window.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<PitchReading> {
+        // A hop larger than the window skips samples that had not arrived yet.
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.buffer.extend_from_slice(&samples[skipped..]);
+
+        let (window, hop) = (self.config.window_size, self.config.hop_size);
+        let mut readings = Vec::new();
+        while self.buffer.len() >= window {
+            if let Some(reading) = self.analyze(&self.buffer[..window], self.position) {
+                if reading.confidence >= self.config.min_confidence {
+                    readings.push(reading);
+                }
+            }
+            if hop <= self.buffer.len() {
+                self.buffer.drain(..hop);
+            } else {
+                self.skip = hop - self.buffer.len();
+                self.buffer.clear();
+            }
+            self.position += hop as u64;
+        }
+        readings
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+        self.skip = 0;
+    }
+}
+
+/// Turns a stream of mono sample chunks into a stream of confident pitch readings.
+pub struct PitchStream<S> {
+    source: S,
+    tuner: Tuner,
+    pending: VecDeque<PitchReading>,
+}
+
+pub fn pitch_stream<S>(source: S, config: TunerConfig) -> Result<PitchStream<S>, String>
+    where S: Stream<Item = Vec<f32>>
+{
+    Ok(PitchStream {
+           source,
+           tuner: Tuner::new(config)?,
+           pending: VecDeque::new(),
+       })
+}
+
+impl<S> PitchStream<S> {
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S> Stream for PitchStream<S>
+    where S: Stream<Item = Vec<f32>>
+{
+    type Item = PitchReading;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<PitchReading>, S::Error> {
+        loop {
+            if let Some(reading) = self.pending.pop_front() {
+                return Ok(Async::Ready(Some(reading)));
+            }
+            match self.source.poll()? {
+                Async::Ready(Some(chunk)) => self.pending.extend(self.tuner.process(&chunk)),
+                Async::Ready(None) => return Ok(Async::Ready(None)),
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
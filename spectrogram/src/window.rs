@@ -0,0 +1,71 @@
+use std::f64::consts::PI;
+
+/// Analysis windows. All windows are periodic (DFT-even), which is the form that sums to a
+/// constant under overlap-add.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    /// Four-term Blackman-Harris, 92 dB sidelobes.
+    BlackmanHarris,
+    /// Kaiser window with shape parameter beta.
+    Kaiser(f64),
+    /// Gaussian window with standard deviation given as a fraction of half the window length.
+    Gaussian(f64),
+    /// Five-term flat-top window, for accurate amplitude readings.
+    FlatTop,
+}
+
+impl Window {
+    pub fn coefficients(&self, length: usize) -> Vec<f32> {
+        (0..length).map(|n| self.coefficient(n, length) as f32).collect()
+    }
+
+    fn coefficient(&self, n: usize, length: usize) -> f64 {
+        let phase = 2.0 * PI * n as f64 / length as f64;
+        match *self {
+            Window::Rectangular => 1.0,
+            Window::Hann => cosine_sum(&[0.5, 0.5], phase),
+            Window::Hamming => cosine_sum(&[0.54, 0.46], phase),
+            Window::BlackmanHarris => cosine_sum(&[0.35875, 0.48829, 0.14128, 0.01168], phase),
+            Window::FlatTop => {
+                cosine_sum(&[0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368],
+                           phase)
+            }
+            Window::Kaiser(beta) => {
+                let x = 2.0 * n as f64 / length as f64 - 1.0;
+                bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / bessel_i0(beta)
+            }
+            Window::Gaussian(sigma) => {
+                let half = length as f64 / 2.0;
+                let x = (n as f64 - half) / (sigma * half);
+                (-0.5 * x * x).exp()
+            }
+        }
+    }
+}
+
+/// Generalised cosine window with alternating signs, a0 - a1 cos(x) + a2 cos(2x) - ...
+fn cosine_sum(terms: &[f64], phase: f64) -> f64 {
+    terms.iter()
+        .enumerate()
+        .map(|(k, a)| {
+            let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+            sign * a * (k as f64 * phase).cos()
+        })
+        .sum()
+}
+
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x / 2.0;
+    let mut k = 1.0;
+    while term > sum * 1e-16 {
+        term *= (half / k) * (half / k);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
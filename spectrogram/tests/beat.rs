@@ -0,0 +1,109 @@
+extern crate spectrogram;
+
+use std::f64::consts::PI;
+
+use spectrogram::*;
+
+const SAMPLE_RATE: f64 = 22050.0;
+const FUNCTIONS: [OnsetFunction; 3] = [OnsetFunction::SpectralFlux,
+                                       OnsetFunction::HighFrequencyContent,
+                                       OnsetFunction::ComplexDomain];
+
+/// Clicks of decaying 1.5 kHz tone every beat from `offset` seconds, over quiet noise.
+fn click_track(bpm: f64, offset: f64, seconds: f64) -> (Vec<f32>, Vec<f64>) {
+    let length = (seconds * SAMPLE_RATE) as usize;
+    let mut state = 2468u32;
+    let mut signal: Vec<f32> = (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (0.001 * (state as f64 / 4294967296.0 - 0.5)) as f32
+        })
+        .collect();
+    let mut clicks = Vec::new();
+    let mut time = offset;
+    while time < seconds - 0.1 {
+        let start = (time * SAMPLE_RATE) as usize;
+        for i in 0..(0.02 * SAMPLE_RATE) as usize {
+            let t = i as f64 / SAMPLE_RATE;
+            signal[start + i] += (0.8 * (-t / 0.004).exp() * (2.0 * PI * 1500.0 * t).sin()) as f32;
+        }
+        clicks.push(time);
+        time += 60.0 / bpm;
+    }
+    (signal, clicks)
+}
+
+fn config() -> StftConfig {
+    StftConfig::new(SAMPLE_RATE, 1024)
+}
+
+fn nearest(times: &[f64], target: f64) -> f64 {
+    times.iter().map(|t| (t - target).abs()).fold(f64::INFINITY, f64::min)
+}
+
+#[test]
+fn every_function_finds_every_click() {
+    let (signal, clicks) = click_track(100.0, 0.25, 6.0);
+    for &function in FUNCTIONS.iter() {
+        let found = onsets(&signal, &config(), function, &PeakPicking::new()).unwrap();
+        assert_eq!(found.len(), clicks.len(), "{:?} found {:?}", function, found);
+        for &click in &clicks {
+            let error = nearest(&found, click);
+            assert!(error < 0.03, "{:?} missed click at {} by {}", function, click, error);
+        }
+    }
+}
+
+#[test]
+fn tempo_of_click_tracks() {
+    for &bpm in [72.0, 90.0, 120.0, 160.0].iter() {
+        let (signal, _) = click_track(bpm, 0.1, 12.0);
+        for &function in FUNCTIONS.iter() {
+            let envelope = onset_strength(&signal, &config(), function).unwrap();
+            let tempo = estimate_tempo(&envelope, &TempoConfig::new()).unwrap();
+            assert!((tempo - bpm).abs() < bpm * 0.02,
+                    "{:?} estimated {} for {} BPM",
+                    function,
+                    tempo,
+                    bpm);
+        }
+    }
+}
+
+#[test]
+fn prior_resolves_octave() {
+    let (signal, _) = click_track(200.0, 0.1, 12.0);
+    let envelope = onset_strength(&signal, &config(), OnsetFunction::SpectralFlux).unwrap();
+    let mut tempo_config = TempoConfig::new();
+    let slow = estimate_tempo(&envelope, &tempo_config).unwrap();
+    assert!((slow - 100.0).abs() < 2.0, "estimated {}", slow);
+    tempo_config.prior_bpm = Some(180.0);
+    let fast = estimate_tempo(&envelope, &tempo_config).unwrap();
+    assert!((fast - 200.0).abs() < 4.0, "estimated {}", fast);
+}
+
+#[test]
+fn beats_land_on_clicks() {
+    for &bpm in [90.0, 128.0].iter() {
+        let (signal, clicks) = click_track(bpm, 0.3, 10.0);
+        let envelope = onset_strength(&signal, &config(), OnsetFunction::SpectralFlux).unwrap();
+        let beats = track_beats(&envelope, &BeatConfig::new()).unwrap();
+        assert!((beats.bpm - bpm).abs() < bpm * 0.02);
+        assert!(beats.times.len() + 1 >= clicks.len(),
+                "{} beats for {} clicks",
+                beats.times.len(),
+                clicks.len());
+        for &beat in &beats.times {
+            let error = nearest(&clicks, beat);
+            assert!(error < 0.05, "beat at {} is {} from a click", beat, error);
+        }
+    }
+}
+
+#[test]
+fn silence_has_no_onsets_or_tempo() {
+    let signal = vec![0.0; 44100];
+    let envelope = onset_strength(&signal, &config(), OnsetFunction::SpectralFlux).unwrap();
+    assert!(envelope.peaks(&PeakPicking::new()).is_empty());
+    assert_eq!(estimate_tempo(&envelope, &TempoConfig::new()), None);
+}
@@ -0,0 +1,29 @@
+// Signal generators shared by the integration tests. Each test crate declares this module
+// `pub` so helpers it does not use are not reported as dead code.
+
+use std::f64::consts::PI;
+
+pub fn sine(frequency: f64, amplitude: f64, rate: f64, length: usize) -> Vec<f32> {
+    (0..length)
+        .map(|i| (amplitude * (2.0 * PI * frequency * i as f64 / rate).sin()) as f32)
+        .collect()
+}
+
+/// Uniform white noise in [-1, 1), whose variance is 1/3.
+pub fn noise(length: usize) -> Vec<f32> {
+    let mut state = 12345u32;
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// Tones at 440 Hz and 3150 Hz over a little noise.
+pub fn test_signal(rate: f64, length: usize) -> Vec<f32> {
+    let low = sine(440.0, 0.5, rate, length);
+    let high = sine(3150.0, 0.2, rate, length);
+    let noise = noise(length);
+    (0..length).map(|i| low[i] + high[i] + 0.05 * noise[i]).collect()
+}
@@ -0,0 +1,78 @@
+extern crate spectrogram;
+
+use spectrogram::*;
+
+pub mod common;
+
+use common::sine;
+
+const SAMPLE_RATE: f64 = 22050.0;
+
+/// Band values of the frame in the middle of the signal.
+fn middle(bands: &BandSpectrogram) -> &[f32] {
+    &bands.values[bands.values.len() / 2]
+}
+
+#[test]
+fn centre_frequencies_are_geometric() {
+    let config = CqtConfig::new(SAMPLE_RATE);
+    // A semitone Q: each bin's bandwidth reaches the next bin's centre.
+    assert!((config.q() - 16.817).abs() < 1e-3);
+    assert!((config.frequency(0) - 32.703).abs() < 1e-9);
+    // A4 is 45 semitones above C1.
+    assert!((config.frequency(45) - 440.0).abs() < 0.01);
+    assert!((config.frequency(83) - 3951.07).abs() < 0.05);
+    for bin in 0..config.bins {
+        let length = config.window_length(bin) as f64;
+        let q = length * config.frequency(bin) / SAMPLE_RATE;
+        assert!(q >= config.q() && q < config.q() + config.frequency(bin) / SAMPLE_RATE,
+                "bin {}: {}",
+                bin,
+                q);
+    }
+
+    let mut thirds = CqtConfig::new(SAMPLE_RATE);
+    thirds.bins_per_octave = 36;
+    thirds.bins = 3 * 84;
+    assert!((thirds.q() - 51.44).abs() < 0.01);
+    assert!((thirds.frequency(36) - 2.0 * 32.703).abs() < 1e-9);
+
+    let bands = cqt(&vec![0.0; 1024], &config).unwrap();
+    assert_eq!(bands.frequencies.len(), 84);
+    for (bin, &hz) in bands.frequencies.iter().enumerate() {
+        assert_eq!(hz, config.frequency(bin));
+    }
+    assert_eq!(bands.times[1], 512.0 / SAMPLE_RATE);
+}
+
+#[test]
+fn tones_read_their_amplitude_in_their_own_bin() {
+    let config = CqtConfig::new(SAMPLE_RATE);
+    let mut transform = Cqt::new(&config).unwrap();
+    for &bin in &[12, 40, 57, 80] {
+        let frequency = config.frequency(bin);
+        let bands = transform.process(&sine(frequency, 0.5, SAMPLE_RATE, 44100));
+        let values = middle(&bands);
+        let loudest = (0..values.len()).max_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap());
+        assert_eq!(loudest, Some(bin));
+        assert!((values[bin] - 0.5).abs() < 0.01, "bin {}: {}", bin, values[bin]);
+        // A semitone away the Hann window has fallen to half; an octave away, to nothing.
+        assert!(values[bin - 1] < 0.3 && values[bin + 1] < 0.3, "bin {}: {:?}", bin, values);
+        assert!(values[bin - 12] < 0.01 && values.get(bin + 12).is_none_or(|&v| v < 0.01),
+                "bin {}",
+                bin);
+    }
+}
+
+#[test]
+fn rejects_bins_beyond_nyquist() {
+    let mut config = CqtConfig::new(8000.0);
+    assert!(Cqt::new(&config).is_err());
+    config.bins = 60;
+    assert!(Cqt::new(&config).is_ok());
+    config.bins = 0;
+    assert!(Cqt::new(&config).is_err());
+    let mut config = CqtConfig::new(SAMPLE_RATE);
+    config.min_frequency = 0.0;
+    assert!(Cqt::new(&config).is_err());
+}
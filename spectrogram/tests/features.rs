@@ -0,0 +1,89 @@
+extern crate spectrogram;
+
+use spectrogram::*;
+
+pub mod common;
+
+use common::{noise, sine};
+
+const SAMPLE_RATE: f64 = 16000.0;
+
+fn middle(values: &[f32]) -> f32 {
+    values[values.len() / 2]
+}
+
+#[test]
+fn sine_features() {
+    let config = StftConfig::new(SAMPLE_RATE, 1024);
+    let signal = sine(1000.0, 0.5, SAMPLE_RATE, 16000);
+    let table = features(&signal, &config, &FeatureConfig::new()).unwrap();
+    assert_eq!(table.rows.len(), config.frame_count(16000));
+    assert_eq!(table.times.len(), table.rows.len());
+
+    let centroid = middle(&table.column("centroid").unwrap());
+    assert!((centroid - 1000.0).abs() < 20.0, "centroid {}", centroid);
+    let rolloff = middle(&table.column("rolloff").unwrap());
+    assert!((rolloff - 1000.0).abs() < 40.0, "rolloff {}", rolloff);
+    let bandwidth = middle(&table.column("bandwidth").unwrap());
+    assert!(bandwidth < 100.0, "bandwidth {}", bandwidth);
+    let rms = middle(&table.column("rms").unwrap());
+    assert!((rms - 0.5 / 2f32.sqrt()).abs() < 0.01, "rms {}", rms);
+    let zcr = middle(&table.column("zcr").unwrap());
+    assert!((zcr - 2000.0 / SAMPLE_RATE as f32).abs() < 0.01, "zcr {}", zcr);
+    assert!(middle(&table.column("flatness").unwrap()) < 0.01);
+    assert!(middle(&table.column("flux").unwrap()) < 1.0);
+    // The tone dominates the 800 Hz to 1.6 kHz band.
+    assert!(middle(&table.column("contrast_3").unwrap()) > 40.0);
+}
+
+#[test]
+fn noise_is_flat() {
+    let config = StftConfig::new(SAMPLE_RATE, 1024);
+    let table = features(&noise(16000), &config, &FeatureConfig::new()).unwrap();
+    let flatness = middle(&table.column("flatness").unwrap());
+    assert!(flatness > 0.3, "flatness {}", flatness);
+    let centroid = middle(&table.column("centroid").unwrap());
+    assert!((centroid - 4000.0).abs() < 500.0, "centroid {}", centroid);
+}
+
+#[test]
+fn flux_marks_onset() {
+    let mut signal = vec![0.0; 8000];
+    signal.extend(sine(440.0, 0.5, SAMPLE_RATE, 8000));
+    let config = StftConfig::new(SAMPLE_RATE, 512);
+    let table = features(&signal, &config, &FeatureConfig::new()).unwrap();
+    let flux = table.column("flux").unwrap();
+    let peak = (0..flux.len()).max_by(|&a, &b| flux[a].partial_cmp(&flux[b]).unwrap()).unwrap();
+    assert!((table.times[peak] - 0.5).abs() < 0.05, "peak at {}", table.times[peak]);
+}
+
+#[test]
+fn spectrogram_result_features_match() {
+    let signal = sine(2000.0, 0.25, SAMPLE_RATE, 4000);
+    let config = StftConfig::new(SAMPLE_RATE, 512);
+    let scaling = Scaling::new(Scale::Magnitude);
+    let result = spectrogram::spectrogram(&signal, &config, &scaling).unwrap();
+    let from_result = spectrogram_features(&result, &FeatureConfig::new()).unwrap();
+    let full = features(&signal, &config, &FeatureConfig::new()).unwrap();
+    assert_eq!(from_result.times, full.times);
+    assert_eq!(from_result.column("centroid"), full.column("centroid"));
+    assert_eq!(from_result.column("zcr"), None);
+}
+
+#[test]
+fn csv_and_json_export() {
+    let config = StftConfig::new(SAMPLE_RATE, 256);
+    let signal = sine(500.0, 0.5, SAMPLE_RATE, 1024);
+    let table = features(&signal, &config, &FeatureConfig::new()).unwrap();
+    let csv = table.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), table.rows.len() + 1);
+    assert!(lines[0]
+                .starts_with("time,centroid,bandwidth,rolloff,flatness,flux,zcr,rms,contrast_0"));
+    assert!(lines[1..].iter().all(|line| line.split(',').count() == table.columns.len() + 1));
+
+    let json = table.to_json();
+    assert!(json.starts_with('['));
+    assert!(json.ends_with("]\n"));
+    assert_eq!(json.matches("\"centroid\":").count(), table.rows.len());
+}
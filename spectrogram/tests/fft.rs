@@ -0,0 +1,93 @@
+extern crate num_complex;
+extern crate spectrogram;
+
+use std::f64::consts::PI;
+
+use num_complex::Complex;
+use spectrogram::fft::{FftBackend, RealFft, RustFft};
+
+fn naive_dft(input: &[f64]) -> Vec<Complex<f64>> {
+    let n = input.len();
+    (0..n / 2 + 1)
+        .map(|k| {
+            input.iter()
+                .enumerate()
+                .map(|(t, &x)| {
+                    let angle = -2.0 * PI * ((k * t) % n) as f64 / n as f64;
+                    Complex::new(x * angle.cos(), x * angle.sin())
+                })
+                .fold(Complex::new(0.0, 0.0), |a, b| a + b)
+        })
+        .collect()
+}
+
+fn test_input(size: usize) -> Vec<f64> {
+    (0..size).map(|i| ((i * 7919) % 101) as f64 / 50.0 - 1.0).collect()
+}
+
+#[test]
+fn matches_naive_dft_for_all_size_classes() {
+    // Powers of two, other even sizes and odd sizes take different paths.
+    for &size in [1, 2, 3, 8, 12, 100, 127, 256, 1000, 1024].iter() {
+        let input = test_input(size);
+        let expected = naive_dft(&input);
+        let mut fft = FftBackend::<f64>::plan(&RustFft, size).unwrap();
+        let mut output = vec![Complex::new(0.0, 0.0); size / 2 + 1];
+        fft.forward(&input, &mut output);
+        for (k, (a, b)) in output.iter().zip(&expected).enumerate() {
+            assert!((a - b).norm() < 1e-9 * size as f64,
+                    "size {} bin {}: {} != {}",
+                    size,
+                    k,
+                    a,
+                    b);
+        }
+    }
+}
+
+#[test]
+fn inverse_undoes_forward() {
+    for &size in [2, 9, 64, 200, 999, 2048].iter() {
+        let input = test_input(size);
+        let mut fft = FftBackend::<f64>::plan(&RustFft, size).unwrap();
+        let mut spectrum = vec![Complex::new(0.0, 0.0); size / 2 + 1];
+        let mut output = vec![0.0; size];
+        fft.forward(&input, &mut spectrum);
+        fft.inverse(&spectrum, &mut output);
+        for (a, b) in input.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-12, "size {}: {} != {}", size, a, b);
+        }
+
+        let input: Vec<f32> = input.iter().map(|&x| x as f32).collect();
+        let mut fft = FftBackend::<f32>::plan(&RustFft, size).unwrap();
+        let mut spectrum = vec![Complex::new(0.0, 0.0); size / 2 + 1];
+        let mut output = vec![0.0; size];
+        fft.forward(&input, &mut spectrum);
+        fft.inverse(&spectrum, &mut output);
+        for (a, b) in input.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-4, "size {}: {} != {}", size, a, b);
+        }
+    }
+}
+
+#[cfg(feature = "fftw")]
+#[test]
+fn fftw_matches_naive_dft_and_inverts() {
+    use spectrogram::fft::Fftw;
+
+    for &size in [1, 2, 3, 12, 127, 256].iter() {
+        let input = test_input(size);
+        let expected = naive_dft(&input);
+        let mut fft = FftBackend::<f64>::plan(&Fftw, size).unwrap();
+        let mut spectrum = vec![Complex::new(0.0, 0.0); size / 2 + 1];
+        fft.forward(&input, &mut spectrum);
+        for (a, b) in spectrum.iter().zip(&expected) {
+            assert!((a - b).norm() < 1e-9 * size as f64, "size {}: {} != {}", size, a, b);
+        }
+        let mut output = vec![0.0; size];
+        fft.inverse(&spectrum, &mut output);
+        for (a, b) in input.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-12, "size {}: {} != {}", size, a, b);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+extern crate spectrogram;
+
+use std::f64::consts::PI;
+
+use spectrogram::*;
+
+pub mod common;
+
+use common::sine;
+
+const SAMPLE_RATE: f64 = 16000.0;
+
+const SCALES: [FrequencyScale; 5] = [FrequencyScale::Linear,
+                                     FrequencyScale::Mel(MelScale::Htk),
+                                     FrequencyScale::Mel(MelScale::Slaney),
+                                     FrequencyScale::Bark,
+                                     FrequencyScale::Erb];
+
+#[test]
+fn scales_match_published_values() {
+    let cases = [(FrequencyScale::Mel(MelScale::Htk), 1000.0, 999.986),
+                 (FrequencyScale::Mel(MelScale::Htk), 440.0, 549.639),
+                 (FrequencyScale::Mel(MelScale::Slaney), 440.0, 6.6),
+                 (FrequencyScale::Mel(MelScale::Slaney), 1000.0, 15.0),
+                 (FrequencyScale::Mel(MelScale::Slaney), 2000.0, 25.082),
+                 (FrequencyScale::Bark, 1000.0, 8.527),
+                 (FrequencyScale::Erb, 1000.0, 15.621)];
+    for &(scale, hz, expected) in &cases {
+        let value = scale.from_hz(hz);
+        assert!((value - expected).abs() < 1e-3, "{:?} {} Hz: {}", scale, hz, value);
+    }
+    for &scale in &SCALES {
+        for &hz in &[0.0, 20.0, 440.0, 999.0, 1000.0, 1001.0, 8000.0] {
+            let back = scale.to_hz(scale.from_hz(hz));
+            assert!((back - hz).abs() < 1e-6, "{:?} {} Hz: {}", scale, hz, back);
+        }
+    }
+}
+
+#[test]
+fn band_edges_are_evenly_spaced_on_the_scale() {
+    let mut config = StftConfig::new(SAMPLE_RATE, 1024);
+    config.fft_size = 4096;
+    let bin_hz = SAMPLE_RATE / 4096.0;
+    for &scale in &SCALES {
+        let filterbank = Filterbank::new(scale, 24, 100.0, 7000.0, FilterNorm::Peak, &config)
+            .unwrap();
+        assert_eq!(filterbank.bands(), 24);
+        let step = (scale.from_hz(7000.0) - scale.from_hz(100.0)) / 25.0;
+        // The edges of band i are the centres of bands i - 1 and i + 1.
+        let mut edges = vec![100.0];
+        edges.extend_from_slice(&filterbank.frequencies);
+        edges.push(7000.0);
+        for (i, &hz) in edges.iter().enumerate() {
+            let expected = scale.from_hz(100.0) + step * i as f64;
+            assert!((scale.from_hz(hz) - expected).abs() < 1e-9, "{:?} edge {}", scale, i);
+        }
+        for (band, weights) in filterbank.weights.iter().enumerate() {
+            let (lower, center, upper) = (edges[band], edges[band + 1], edges[band + 2]);
+            for (bin, &w) in weights.iter().enumerate() {
+                let hz = bin as f64 * bin_hz;
+                if hz <= lower || hz >= upper {
+                    // Edges computed back from the scale may be off by a rounding error.
+                    assert!(w < 1e-6, "{:?} band {} bin {}", scale, band, bin);
+                } else {
+                    assert!(w > 0.0 && w <= 1.0, "{:?} band {} bin {}", scale, band, bin);
+                }
+            }
+            // The bin nearest the centre is near the top of the triangle.
+            let nearest = (center / bin_hz).round() as usize;
+            let slope = bin_hz / (center - lower).min(upper - center);
+            assert!(weights[nearest] as f64 >= 1.0 - slope, "{:?} band {}", scale, band);
+        }
+    }
+}
+
+#[test]
+fn slaney_is_linear_below_1_khz_and_htk_is_not() {
+    let mut config = StftConfig::new(SAMPLE_RATE, 1024);
+    config.fft_size = 4096;
+    let centres = |mel: MelScale| {
+        Filterbank::new(FrequencyScale::Mel(mel), 40, 0.0, 8000.0, FilterNorm::Peak, &config)
+            .unwrap()
+            .frequencies
+    };
+    let spacing = |centres: &[f64]| -> Vec<f64> {
+        centres.windows(2).map(|pair| pair[1] - pair[0]).collect()
+    };
+
+    let slaney = centres(MelScale::Slaney);
+    let below: Vec<f64> = slaney.iter().cloned().filter(|&hz| hz < 1000.0).collect();
+    let steps = spacing(&below);
+    assert!(steps.len() > 5);
+    assert!(steps.iter().all(|step| (step - steps[0]).abs() < 1e-9), "{:?}", steps);
+
+    let htk = centres(MelScale::Htk);
+    let below: Vec<f64> = htk.iter().cloned().filter(|&hz| hz < 1000.0).collect();
+    let steps = spacing(&below);
+    assert!(steps.windows(2).all(|pair| pair[1] > pair[0] + 1.0), "{:?}", steps);
+
+    // Both are logarithmic well above the break, so the bands widen in proportion.
+    for centres in &[slaney, htk] {
+        let ratios: Vec<f64> = centres.windows(2).map(|pair| pair[1] / pair[0]).collect();
+        let top = &ratios[ratios.len() - 5..];
+        assert!(top.windows(2).all(|pair| (pair[1] - pair[0]).abs() < 0.01), "{:?}", top);
+    }
+}
+
+#[test]
+fn area_norm_gives_every_band_unit_area() {
+    let mut config = StftConfig::new(SAMPLE_RATE, 1024);
+    config.fft_size = 4096;
+    let bin_hz = SAMPLE_RATE / 4096.0;
+    let filterbank = Filterbank::mel(40, &config).unwrap();
+    for (band, weights) in filterbank.weights.iter().enumerate() {
+        let area = weights.iter().map(|&w| w as f64).sum::<f64>() * bin_hz;
+        assert!((area - 1.0).abs() < 0.01, "band {}: {}", band, area);
+    }
+}
+
+#[test]
+fn rejects_invalid_filterbanks() {
+    let config = StftConfig::new(SAMPLE_RATE, 1024);
+    let mel = FrequencyScale::Mel(MelScale::Slaney);
+    assert!(Filterbank::new(mel, 0, 0.0, 8000.0, FilterNorm::Peak, &config).is_err());
+    assert!(Filterbank::new(mel, 40, 0.0, 9000.0, FilterNorm::Peak, &config).is_err());
+    assert!(Filterbank::new(mel, 40, 500.0, 400.0, FilterNorm::Peak, &config).is_err());
+    let spectrum = stft(&sine(440.0, 1.0, SAMPLE_RATE, 4096), &config).unwrap();
+    let mut mfcc_config = MfccConfig::new();
+    mfcc_config.coefficients = 200;
+    assert!(mfcc(&spectrum, &mfcc_config).is_err());
+}
+
+#[test]
+fn mfcc_of_silence_is_the_floor_in_the_first_coefficient() {
+    let config = StftConfig::new(SAMPLE_RATE, 512);
+    let spectrum = stft(&vec![0.0; 4096], &config).unwrap();
+    let mut mfcc_config = MfccConfig::new();
+    mfcc_config.bands = 64;
+    mfcc_config.coefficients = 13;
+    mfcc_config.lifter = 22.0;
+    // Every band sits at the -100 dB floor; an orthonormal DCT of a constant puts
+    // sqrt(bands) times it in c0 and nothing elsewhere, and liftering leaves c0 alone.
+    let mut expected = vec![0.0; 13];
+    expected[0] = -800.0;
+    for frame in mfcc(&spectrum, &mfcc_config).unwrap() {
+        assert!(frame.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-3),
+                "{:?}",
+                frame);
+    }
+}
+
+#[test]
+fn mfcc_inverts_to_the_log_mel_spectrum() {
+    let config = StftConfig::new(SAMPLE_RATE, 512);
+    let signal: Vec<f32> = sine(440.0, 1.0, SAMPLE_RATE, 8192)
+        .iter()
+        .zip(sine(2500.0, 1.0, SAMPLE_RATE, 8192))
+        .map(|(a, b)| 0.5 * a + 0.1 * b)
+        .collect();
+    let spectrum = stft(&signal, &config).unwrap();
+    let mut mfcc_config = MfccConfig::new();
+    mfcc_config.bands = 40;
+    mfcc_config.coefficients = 40;
+    let coefficients = mfcc(&spectrum, &mfcc_config).unwrap();
+
+    let filterbank = Filterbank::mel(40, &config).unwrap();
+    let scaling = Scaling::new(Scale::PowerDb {
+                                   reference: Reference::Fixed(1.0),
+                                   floor: 1e-10,
+                                   top_db: Some(80.0),
+                               });
+    let log_mel = band_spectrogram(&spectrum, &filterbank, &scaling).values;
+    for (frame, bands) in coefficients.iter().zip(&log_mel) {
+        // DCT-III, the inverse of the orthonormal DCT-II.
+        for (i, &band) in bands.iter().enumerate() {
+            let value: f64 = frame.iter()
+                .enumerate()
+                .map(|(k, &c)| {
+                    let norm = if k == 0 { (1.0f64 / 40.0).sqrt() } else { (2.0f64 / 40.0).sqrt() };
+                    norm * c as f64 * (PI * k as f64 * (i as f64 + 0.5) / 40.0).cos()
+                })
+                .sum();
+            assert!((value - band as f64).abs() < 0.01, "band {}: {} {}", i, value, band);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+extern crate spectrogram;
+
+use std::f64::consts::PI;
+
+use spectrogram::*;
+use spectrogram::tuner::NoteName;
+
+const SAMPLE_RATE: f64 = 22050.0;
+
+fn midi_frequency(midi: i32, reference_a4: f64) -> f64 {
+    reference_a4 * 2f64.powf((midi - 69) as f64 / 12.0)
+}
+
+/// Notes with three decaying harmonics, each held for `seconds`, with the notes of each
+/// inner slice sounding together.
+fn play(chords: &[&[i32]], seconds: f64, reference_a4: f64) -> Vec<f32> {
+    let length = (seconds * SAMPLE_RATE) as usize;
+    let mut signal = Vec::with_capacity(length * chords.len());
+    for chord in chords {
+        for i in 0..length {
+            let t = i as f64 / SAMPLE_RATE;
+            let mut sample = 0.0;
+            for &midi in chord.iter() {
+                let f = midi_frequency(midi, reference_a4);
+                for harmonic in 1..4 {
+                    let weight = 1.0 / (harmonic * harmonic) as f64;
+                    sample += weight * (2.0 * PI * f * harmonic as f64 * t).sin();
+                }
+            }
+            signal.push((0.1 * sample) as f32);
+        }
+    }
+    signal
+}
+
+fn chromagram(signal: &[f32], config: &ChromaConfig) -> Chromagram {
+    let spectrum = stft(signal, &StftConfig::new(SAMPLE_RATE, 4096)).unwrap();
+    Chromagram::from_stft(&spectrum, config)
+}
+
+fn strongest(chroma: &[f32; 12]) -> usize {
+    (0..12).fold(0, |best, i| if chroma[i] > chroma[best] { i } else { best })
+}
+
+#[test]
+fn sine_lands_on_its_pitch_class() {
+    let config = ChromaConfig::new();
+    for &(midi, class) in [(69, 9), (60, 0), (66, 6), (83, 11)].iter() {
+        let total = chromagram(&play(&[&[midi]], 1.0, 440.0), &config).total();
+        assert_eq!(strongest(&total), class, "{:?}", total);
+        assert_eq!(total[class], 1.0);
+    }
+}
+
+#[test]
+fn tuning_reference_shifts_classes() {
+    // A at baroque pitch sits between G# and A at 440 Hz.
+    let signal = play(&[&[69]], 1.0, 415.0);
+    let mut config = ChromaConfig::new();
+    config.reference_a4 = 415.0;
+    assert_eq!(strongest(&chromagram(&signal, &config).total()), 9);
+}
+
+#[test]
+fn constant_q_chroma_matches() {
+    let signal = play(&[&[64, 67, 71]], 1.0, 440.0);
+    let bands = cqt(&signal, &CqtConfig::new(SAMPLE_RATE)).unwrap();
+    let total = Chromagram::from_bands(&bands, &ChromaConfig::new()).total();
+    let mut top: Vec<usize> = (0..12).collect();
+    top.sort_by(|&a, &b| total[b].partial_cmp(&total[a]).unwrap());
+    top.truncate(3);
+    top.sort();
+    assert_eq!(top, vec![4, 7, 11]);
+}
+
+#[test]
+fn cadences_give_their_keys() {
+    let config = ChromaConfig::new();
+    // I IV V I in C major.
+    let c_major = play(&[&[60, 64, 67], &[65, 69, 72], &[67, 71, 74], &[60, 64, 67]], 0.5, 440.0);
+    let key = key::key(&chromagram(&c_major, &config)).unwrap();
+    assert_eq!((key.tonic, key.mode), (NoteName::C, Mode::Major));
+    assert!(key.confidence > 0.0);
+    assert_eq!(key.to_string(), "C major");
+
+    // i iv V i in A minor.
+    let a_minor = play(&[&[57, 60, 64], &[62, 65, 69], &[64, 68, 71], &[57, 60, 64]], 0.5, 440.0);
+    let key = key::key(&chromagram(&a_minor, &config)).unwrap();
+    assert_eq!((key.tonic, key.mode), (NoteName::A, Mode::Minor));
+}
+
+#[test]
+fn sliding_windows_follow_modulation() {
+    let config = ChromaConfig::new();
+    let c_major: [&[i32]; 4] = [&[60, 64, 67], &[65, 69, 72], &[67, 71, 74], &[60, 64, 67]];
+    let f_sharp_major: [&[i32]; 4] = [&[66, 70, 73], &[71, 75, 78], &[73, 77, 80], &[66, 70, 73]];
+    let mut signal = play(&c_major, 0.5, 440.0);
+    signal.extend(play(&f_sharp_major, 0.5, 440.0));
+    let chroma = chromagram(&signal, &config);
+    let window = chroma.values.len() / 2;
+    let keys = key::keys(&chroma, window, window);
+    assert_eq!(keys.len(), 2);
+    assert_eq!((keys[0].1.tonic, keys[0].1.mode), (NoteName::C, Mode::Major));
+    assert_eq!((keys[1].1.tonic, keys[1].1.mode), (NoteName::FSharp, Mode::Major));
+    assert!(keys[0].0 < keys[1].0);
+}
+
+#[test]
+fn silence_has_no_key() {
+    let chroma = chromagram(&vec![0.0; 22050], &ChromaConfig::new());
+    assert_eq!(key::key(&chroma), None);
+}
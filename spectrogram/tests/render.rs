@@ -0,0 +1,161 @@
+extern crate image;
+extern crate spectrogram;
+
+use image::{Rgb, RgbImage};
+use spectrogram::*;
+
+/// `frames` frames of bands every 100 Hz up to 8 kHz, zero except for a line at `hz`.
+fn line(hz: f64, frames: usize) -> BandSpectrogram {
+    let frequencies: Vec<f64> = (0..81).map(|band| band as f64 * 100.0).collect();
+    let frame: Vec<f32> = frequencies.iter().map(|&f| if f == hz { 1.0 } else { 0.0 }).collect();
+    BandSpectrogram {
+        values: vec![frame; frames],
+        frequencies,
+        times: (0..frames).map(|i| i as f64 * 0.5).collect(),
+    }
+}
+
+fn grayscale() -> RenderConfig {
+    let mut config = RenderConfig::new();
+    config.color_map = ColorMap::Grayscale;
+    config
+}
+
+/// Row of the brightest pixel in column `x`.
+fn brightest_row(image: &RgbImage, x: u32) -> u32 {
+    (0..image.height()).max_by_key(|&y| image.get_pixel(x, y)[0]).unwrap()
+}
+
+#[test]
+fn image_size_follows_the_data_and_config() {
+    let spectrogram = line(2000.0, 10);
+    let image = render(&spectrogram, &grayscale()).unwrap();
+    assert_eq!(image.dimensions(), (10, 81));
+
+    let mut config = grayscale();
+    config.width = Some(300);
+    config.height = Some(200);
+    let image = render(&spectrogram, &config).unwrap();
+    assert_eq!(image.dimensions(), (300, 200));
+
+    // Labels add margins around the plot but leave its size alone.
+    config.labels = true;
+    let labelled = render(&spectrogram, &config).unwrap();
+    assert!(labelled.width() > 300 && labelled.height() > 200);
+    let left = labelled.width() - 300 - 12;
+    let plot = (0..200).map(|y| labelled.get_pixel(left + 150, 5 + y)).collect::<Vec<_>>();
+    let plain = (0..200).map(|y| image.get_pixel(150, y)).collect::<Vec<_>>();
+    assert_eq!(plot, plain);
+}
+
+#[test]
+fn rows_follow_the_frequency_axis() {
+    let spectrogram = line(2000.0, 4);
+    let mut config = grayscale();
+    config.height = Some(200);
+    // Row y shows the frequency at (y + 0.5) / height down from the top of the axis.
+    let expected_row = |position: f64| (1.0 - position) * 200.0 - 0.5;
+
+    let image = render(&spectrogram, &config).unwrap();
+    let row = brightest_row(&image, 0) as f64;
+    assert!((row - expected_row(0.25)).abs() <= 1.0, "{}", row);
+
+    config.frequency_axis = FrequencyAxis::Log;
+    let image = render(&spectrogram, &config).unwrap();
+    let row = brightest_row(&image, 0) as f64;
+    // The log axis starts at the lowest band above 0 Hz.
+    let position = (2000.0f64 / 100.0).ln() / (8000.0f64 / 100.0).ln();
+    assert!((row - expected_row(position)).abs() <= 1.0, "{}", row);
+
+    config.frequency_axis = FrequencyAxis::Mel;
+    let image = render(&spectrogram, &config).unwrap();
+    let row = brightest_row(&image, 0) as f64;
+    let mel = FrequencyScale::Mel(MelScale::Slaney);
+    let position = mel.from_hz(2000.0) / mel.from_hz(8000.0);
+    assert!((row - expected_row(position)).abs() <= 1.0, "{}", row);
+
+    config.frequency_axis = FrequencyAxis::Linear;
+    config.min_frequency = Some(1000.0);
+    config.max_frequency = Some(3000.0);
+    let image = render(&spectrogram, &config).unwrap();
+    let row = brightest_row(&image, 0) as f64;
+    assert!((row - expected_row(0.5)).abs() <= 1.0, "{}", row);
+}
+
+#[test]
+fn dynamic_range_sets_the_colour_scale() {
+    let mut spectrogram = line(2000.0, 2);
+    for frame in spectrogram.values.iter_mut() {
+        for v in frame.iter_mut() {
+            *v = *v * 60.0 - 60.0;
+        }
+    }
+    spectrogram.values[1][10] = -30.0;
+    // Half a band beyond each end puts row y on the band at 8000 - 100 y Hz.
+    let mut config = grayscale();
+    config.min_frequency = Some(-50.0);
+    config.max_frequency = Some(8050.0);
+    let image = render(&spectrogram, &config).unwrap();
+    assert_eq!(*image.get_pixel(0, 60), Rgb([255, 255, 255]));
+    assert_eq!(*image.get_pixel(0, 0), Rgb([0, 0, 0]));
+    assert_eq!(*image.get_pixel(1, 70), Rgb([128, 128, 128]));
+
+    config.dynamic_range = Some(20.0);
+    let image = render(&spectrogram, &config).unwrap();
+    assert_eq!(*image.get_pixel(1, 70), Rgb([0, 0, 0]));
+
+    assert_eq!(ColorMap::Viridis.color(0.0), Rgb([68, 1, 84]));
+    assert_eq!(ColorMap::Viridis.color(1.0), Rgb([253, 231, 37]));
+    assert_eq!(ColorMap::Magma.color(2.0), ColorMap::Magma.color(1.0));
+    assert_eq!(ColorMap::Inferno.color(f32::NAN), Rgb([0, 0, 4]));
+}
+
+#[test]
+fn labels_fit_a_narrow_plot() {
+    // Time labels wider than the whole image are left out rather than overflowing it.
+    let mut spectrogram = line(2000.0, 2);
+    spectrogram.times = vec![0.0, 1234.5];
+    let mut config = grayscale();
+    config.labels = true;
+    for &width in &[1, 2, 5, 40] {
+        config.width = Some(width);
+        for &axis in &[FrequencyAxis::Linear, FrequencyAxis::Log, FrequencyAxis::Mel] {
+            config.frequency_axis = axis;
+            let image = render(&spectrogram, &config).unwrap();
+            assert!(image.width() > width);
+        }
+    }
+    config.width = Some(1);
+    config.height = Some(1);
+    assert!(render(&spectrogram, &config).is_ok());
+}
+
+#[test]
+fn rejects_what_it_cannot_draw() {
+    let config = RenderConfig::new();
+    let mut empty = line(2000.0, 1);
+    empty.values.clear();
+    assert!(render(&empty, &config).is_err());
+
+    let mut ragged = line(2000.0, 2);
+    ragged.values[1].pop();
+    assert!(render(&ragged, &config).is_err());
+
+    let spectrogram = line(2000.0, 2);
+    let mut inverted = RenderConfig::new();
+    inverted.min_frequency = Some(3000.0);
+    inverted.max_frequency = Some(1000.0);
+    assert!(render(&spectrogram, &inverted).is_err());
+
+    let mut log = RenderConfig::new();
+    log.frequency_axis = FrequencyAxis::Log;
+    log.min_frequency = Some(0.0);
+    assert!(render(&spectrogram, &log).is_err());
+    let mut dc = line(0.0, 2);
+    dc.frequencies.truncate(1);
+    for frame in dc.values.iter_mut() {
+        frame.truncate(1);
+    }
+    log.min_frequency = None;
+    assert!(render(&dc, &log).is_err());
+}
@@ -0,0 +1,138 @@
+extern crate spectrogram;
+
+use spectrogram::*;
+
+pub mod common;
+
+use common::{noise, sine};
+
+const SAMPLE_RATE: f64 = 16000.0;
+
+const WINDOWS: [Window; 7] = [Window::Rectangular,
+                              Window::Hann,
+                              Window::Hamming,
+                              Window::BlackmanHarris,
+                              Window::Kaiser(8.0),
+                              Window::Gaussian(0.4),
+                              Window::FlatTop];
+
+const WINDOW_LENGTHS: [usize; 4] = [256, 512, 1024, 2048];
+
+fn scaled(signal: &[f32],
+          window: Window,
+          window_length: usize,
+          scaling: &Scaling)
+          -> Vec<Vec<f32>> {
+    let mut config = StftConfig::new(SAMPLE_RATE, window_length);
+    config.window = window;
+    config.center = false;
+    scaling.apply(&stft(signal, &config).unwrap())
+}
+
+/// Largest value of the frames, in dB relative to full scale.
+fn peak_db(signal: &[f32], window: Window, window_length: usize) -> f32 {
+    let scaling = Scaling {
+        scale: Scale::AmplitudeDb {
+            reference: Reference::Fixed(1.0),
+            floor: 1e-10,
+            top_db: None,
+        },
+        normalization: Normalization::None,
+        window_compensation: WindowCompensation::Amplitude,
+    };
+    let frames = scaled(signal, window, window_length, &scaling);
+    let peaks: Vec<f32> = frames.iter()
+        .map(|frame| frame.iter().cloned().fold(f32::NEG_INFINITY, f32::max))
+        .collect();
+    // Every frame holds the same steady tone.
+    let spread = peaks.iter().cloned().fold(0.0, |d: f32, p| d.max((p - peaks[0]).abs()));
+    assert!(spread < 0.01, "{:?} {}: {:?}", window, window_length, peaks);
+    peaks[0]
+}
+
+#[test]
+fn amplitude_compensation_reads_a_sine_the_same_for_every_window() {
+    // 1 kHz is a bin centre for every length, so no window loses anything to scalloping.
+    let signal = sine(1000.0, 1.0, SAMPLE_RATE, 8192);
+    let quiet = sine(1000.0, 0.1, SAMPLE_RATE, 8192);
+    for &window in &WINDOWS {
+        for &length in &WINDOW_LENGTHS {
+            let db = peak_db(&signal, window, length);
+            assert!(db.abs() < 0.01, "{:?} {}: {} dB", window, length, db);
+            let db = peak_db(&quiet, window, length);
+            assert!((db + 20.0).abs() < 0.01, "{:?} {}: {} dB", window, length, db);
+        }
+    }
+}
+
+#[test]
+fn flat_top_reads_a_sine_between_bins() {
+    for &length in &WINDOW_LENGTHS {
+        // Half way between two bins, the worst case for scalloping.
+        let frequency = 1000.0 + 0.5 * SAMPLE_RATE / length as f64;
+        let db = peak_db(&sine(frequency, 1.0, SAMPLE_RATE, 8192), Window::FlatTop, length);
+        assert!(db.abs() < 0.02, "{}: {} dB", length, db);
+        let db = peak_db(&sine(frequency, 1.0, SAMPLE_RATE, 8192), Window::Hann, length);
+        assert!(db < -1.0, "{}: {} dB", length, db);
+    }
+}
+
+#[test]
+fn density_compensation_reads_noise_the_same_for_every_window() {
+    // White noise of variance 1/3 has a one-sided density of 2/3 per sample rate.
+    let signal = noise(65536);
+    let expected = 10.0 * (2.0 / 3.0 / SAMPLE_RATE).log10();
+    let density = |scale: Scale| {
+        Scaling {
+            scale,
+            normalization: Normalization::None,
+            window_compensation: WindowCompensation::Density,
+        }
+    };
+    let power = density(Scale::Power);
+    let scaling = density(Scale::PowerDb {
+                              reference: Reference::Fixed(1.0),
+                              floor: 1e-20,
+                              top_db: None,
+                          });
+    for &window in &WINDOWS {
+        for &length in &WINDOW_LENGTHS {
+            // Average the density over frames and bins, leaving out DC and Nyquist.
+            let frames = scaled(&signal, window, length, &power);
+            let bins = length / 2;
+            let total: f64 = frames.iter()
+                .flat_map(|frame| frame[1..bins].iter())
+                .map(|&p| p as f64)
+                .sum();
+            let mean = total / (frames.len() * (bins - 1)) as f64;
+            let db = 10.0 * mean.log10();
+            assert!((db - expected).abs() < 0.2,
+                    "{:?} {}: {} dB, expected {}",
+                    window,
+                    length,
+                    db,
+                    expected);
+
+            // Decibels are the same values on another scale.
+            let decibels = scaled(&signal, window, length, &scaling);
+            let p = frames[0][bins / 2] as f64;
+            assert!((decibels[0][bins / 2] as f64 - 10.0 * p.log10()).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn uncompensated_levels_depend_on_the_window() {
+    let signal = sine(1000.0, 1.0, SAMPLE_RATE, 8192);
+    let scaling = Scaling::new(Scale::Magnitude);
+    let peak = |window: Window, length: usize| {
+        scaled(&signal, window, length, &scaling)[0]
+            .iter()
+            .cloned()
+            .fold(0.0, f32::max)
+    };
+    // Half the coherent gain of each window.
+    assert!((peak(Window::Rectangular, 1024) - 512.0).abs() < 0.1);
+    assert!((peak(Window::Hann, 1024) - 256.0).abs() < 0.1);
+    assert!((peak(Window::Hann, 2048) - 512.0).abs() < 0.1);
+}
@@ -0,0 +1,119 @@
+extern crate spectrogram;
+
+use spectrogram::*;
+
+pub mod common;
+
+use common::{sine, test_signal};
+
+const SAMPLE_RATE: f64 = 16000.0;
+
+const WINDOWS: [Window; 7] = [Window::Rectangular,
+                              Window::Hann,
+                              Window::Hamming,
+                              Window::BlackmanHarris,
+                              Window::Kaiser(8.0),
+                              Window::Gaussian(0.4),
+                              Window::FlatTop];
+
+fn max_error(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+}
+
+#[test]
+fn round_trip_recovers_signal_for_every_window() {
+    let signal = test_signal(SAMPLE_RATE, 5000);
+    for window in WINDOWS.iter() {
+        for &padding in [Padding::Zero, Padding::Reflect, Padding::Edge].iter() {
+            let mut config = StftConfig::new(SAMPLE_RATE, 256);
+            config.window = *window;
+            config.padding = padding;
+            assert!(config.is_nola(), "{:?}", window);
+            let spectrum = stft(&signal, &config).unwrap();
+            let recovered = istft(&spectrum).unwrap();
+            let error = max_error(&signal, &recovered);
+            assert!(error < 1e-4, "{:?} {:?}: error {}", window, padding, error);
+        }
+    }
+}
+
+#[test]
+fn round_trip_with_zero_padding_and_uneven_hop() {
+    let signal = test_signal(SAMPLE_RATE, 3001);
+    for window in WINDOWS.iter() {
+        let mut config = StftConfig::new(SAMPLE_RATE, 200);
+        config.window = *window;
+        config.fft_size = 512;
+        config.hop_size = 70;
+        let spectrum = stft(&signal, &config).unwrap();
+        assert_eq!(spectrum.bins(), 257);
+        let recovered = istft(&spectrum).unwrap();
+        let error = max_error(&signal, &recovered);
+        assert!(error < 1e-4, "{:?}: error {}", window, error);
+    }
+}
+
+#[test]
+fn round_trip_without_centring() {
+    let signal = test_signal(SAMPLE_RATE, 4096);
+    let mut config = StftConfig::new(SAMPLE_RATE, 512);
+    config.window = Window::Hamming;
+    config.center = false;
+    config.hop_size = 128;
+    let recovered = istft(&stft(&signal, &config).unwrap()).unwrap();
+    // 4096 samples fill exactly 29 frames, so every sample is covered.
+    let error = max_error(&signal, &recovered);
+    assert!(error < 1e-4, "error {}", error);
+}
+
+#[test]
+fn cola_and_nola_checks() {
+    let config = |window: Window, length: usize, hop: usize| {
+        let mut config = StftConfig::new(SAMPLE_RATE, length);
+        config.window = window;
+        config.hop_size = hop;
+        config
+    };
+    assert!(config(Window::Hann, 1024, 512).is_cola());
+    assert!(config(Window::Hann, 1024, 256).is_cola());
+    assert!(config(Window::Hamming, 1024, 512).is_cola());
+    assert!(config(Window::Rectangular, 1024, 1024).is_cola());
+    assert!(config(Window::BlackmanHarris, 1024, 256).is_cola());
+    assert!(!config(Window::Hann, 1024, 768).is_cola());
+    assert!(!config(Window::Kaiser(8.0), 1024, 256).is_cola());
+
+    assert!(config(Window::Hann, 1024, 768).is_nola());
+    assert!(config(Window::Kaiser(8.0), 1024, 256).is_nola());
+    // Periodic Hann is zero at its first sample, so without overlap that sample is lost.
+    assert!(!config(Window::Hann, 1024, 1024).is_nola());
+    assert!(!config(Window::Rectangular, 1024, 1500).is_nola());
+
+    let signal = test_signal(SAMPLE_RATE, 4000);
+    let spectrum = stft(&signal, &config(Window::Hann, 1024, 1024)).unwrap();
+    assert!(istft(&spectrum).is_err());
+}
+
+#[test]
+fn modified_frames_are_resynthesised() {
+    // Zeroing every bin above 1 kHz should remove the 3150 Hz component.
+    let signal = test_signal(SAMPLE_RATE, 8000);
+    let config = StftConfig::new(SAMPLE_RATE, 1024);
+    let mut spectrum = stft(&signal, &config).unwrap();
+    let cutoff = (1000.0 * config.fft_size as f64 / SAMPLE_RATE) as usize;
+    for frame in spectrum.frames.iter_mut() {
+        for bin in frame.iter_mut().skip(cutoff) {
+            *bin = bin.scale(0.0);
+        }
+    }
+    let filtered = istft(&spectrum).unwrap();
+    let tone = sine(440.0, 0.5, SAMPLE_RATE, 8000);
+    let residual = filtered.iter()
+        .zip(&tone)
+        .skip(1024)
+        .take(6000)
+        .map(|(a, b)| ((a - b) * (a - b)) as f64)
+        .sum::<f64>() / 6000.0;
+    // What remains is the low-passed noise, far below the removed tone's power of 0.02.
+    assert!(residual < 2e-3, "residual power {}", residual);
+}
@@ -0,0 +1,90 @@
+extern crate futures;
+extern crate spectrogram;
+
+use futures::{stream, Future, Stream};
+use spectrogram::*;
+
+pub mod common;
+
+use common::test_signal;
+
+/// Splits `signal` into chunks whose sizes cycle through `sizes`.
+fn chunks(signal: &[f32], sizes: &[usize]) -> Vec<Vec<f32>> {
+    let mut chunks = Vec::new();
+    let mut position = 0;
+    let mut i = 0;
+    while position < signal.len() {
+        let end = (position + sizes[i % sizes.len()]).min(signal.len());
+        chunks.push(signal[position..end].to_vec());
+        position = end;
+        i += 1;
+    }
+    chunks
+}
+
+fn configs() -> Vec<StftConfig> {
+    let mut configs = Vec::new();
+    for &center in [true, false].iter() {
+        for &padding in [Padding::Zero, Padding::Reflect, Padding::Edge].iter() {
+            let mut config = StftConfig::new(8000.0, 256);
+            config.center = center;
+            config.padding = padding;
+            configs.push(config.clone());
+            config.fft_size = 400;
+            config.hop_size = 100;
+            config.window = Window::Kaiser(6.0);
+            configs.push(config);
+        }
+    }
+    configs
+}
+
+#[test]
+fn pushes_of_any_size_match_batch() {
+    for &length in [0, 1, 100, 129, 256, 257, 3000, 4097].iter() {
+        let signal = test_signal(8000.0, length);
+        for config in configs() {
+            let batch = stft(&signal, &config).unwrap().frames;
+            for sizes in [vec![1], vec![7, 300, 2], vec![256], vec![10000]].iter() {
+                let mut streaming = StreamingStft::new(&config).unwrap();
+                let mut frames = Vec::new();
+                for chunk in chunks(&signal, sizes) {
+                    frames.extend(streaming.push(&chunk));
+                }
+                frames.extend(streaming.finish());
+                assert!(frames == batch,
+                        "length {} chunks {:?} {:?}: {} frames, batch {}",
+                        length,
+                        sizes,
+                        config,
+                        frames.len(),
+                        batch.len());
+            }
+        }
+    }
+}
+
+#[test]
+fn frames_are_emitted_before_the_end() {
+    let config = StftConfig::new(8000.0, 256);
+    let mut streaming = StreamingStft::new(&config).unwrap();
+    let signal = test_signal(8000.0, 8000);
+    let emitted: usize = chunks(&signal, &[512]).iter().map(|c| streaming.push(c).len()).sum();
+    assert!(emitted >= config.frame_count(signal.len()) - 3);
+    assert_eq!(emitted + streaming.finish().len(),
+               config.frame_count(signal.len()));
+}
+
+#[test]
+fn iterator_and_stream_match_batch() {
+    let signal = test_signal(8000.0, 5000);
+    let config = StftConfig::new(8000.0, 512);
+    let batch = stft(&signal, &config).unwrap().frames;
+
+    let from_iterator: Vec<_> = stft_frames(chunks(&signal, &[333]), &config).unwrap().collect();
+    assert!(from_iterator == batch);
+
+    let source = stream::iter_ok::<_, ()>(chunks(&signal, &[1000, 17]));
+    let from_stream = stft_stream(source, &config).unwrap().collect().wait().unwrap();
+    assert!(from_stream == batch);
+}
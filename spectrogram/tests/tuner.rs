@@ -0,0 +1,141 @@
+extern crate futures;
+extern crate spectrogram;
+
+use std::f64::consts::PI;
+
+use futures::{stream, Future, Stream};
+use spectrogram::tuner::*;
+
+pub mod common;
+
+const SAMPLE_RATE: f64 = 44100.0;
+
+fn harmonic_tone(frequency: f64, seconds: f64) -> Vec<f32> {
+    let samples = (SAMPLE_RATE * seconds) as usize;
+    (0..samples)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE;
+            (0.5 * (2.0 * PI * frequency * t).sin() + 0.3 * (4.0 * PI * frequency * t).sin() +
+             0.2 * (6.0 * PI * frequency * t).sin()) as f32
+        })
+        .collect()
+}
+
+#[test]
+fn detects_fundamental_of_harmonic_tones() {
+    for &algorithm in &[Algorithm::Yin, Algorithm::Mpm] {
+        let mut config = TunerConfig::new(SAMPLE_RATE);
+        config.algorithm = algorithm;
+        for &frequency in &[82.41, 110.0, 196.0, 329.63, 440.0, 987.77] {
+            let signal = harmonic_tone(frequency, 0.1);
+            let estimate = detect_pitch(&signal[..config.window_size], &config)
+                .unwrap()
+                .expect("no pitch detected");
+            let error_cents = 1200.0 * (estimate.frequency / frequency).log2();
+            assert!(error_cents.abs() < 5.0,
+                    "{:?} estimated {} for {}",
+                    algorithm,
+                    estimate.frequency,
+                    frequency);
+            assert!(estimate.confidence > 0.9);
+        }
+    }
+}
+
+#[test]
+fn silence_and_noise_are_rejected() {
+    let mut tuner = Tuner::new(TunerConfig::new(SAMPLE_RATE)).unwrap();
+    assert!(tuner.process(&vec![0.0; 8192]).is_empty());
+    assert!(tuner.process(&common::noise(8192)).is_empty());
+}
+
+#[test]
+fn names_notes_with_cents_deviation() {
+    let (note, cents, target) = nearest_note(440.0, 440.0, &Temperament::Equal).unwrap();
+    assert_eq!(note, Note { name: NoteName::A, octave: 4 });
+    assert!(cents.abs() < 1e-9);
+    assert!((target - 440.0).abs() < 1e-9);
+
+    let (note, cents, _) = nearest_note(261.63 * (10.0f64 / 1200.0).exp2(),
+                                        440.0,
+                                        &Temperament::Equal).unwrap();
+    assert_eq!(note.to_string(), "C4");
+    assert!((cents - 10.0).abs() < 0.1);
+
+    let (note, cents, _) = nearest_note(440.0, 442.0, &Temperament::Equal).unwrap();
+    assert_eq!(note.to_string(), "A4");
+    assert!((cents + 7.85).abs() < 0.01);
+
+    assert_eq!(Note::from_midi(60).to_string(), "C4");
+    assert_eq!(Note::from_midi(-1).to_string(), "B-2");
+    assert_eq!(Note::from_midi(61).midi(), 61);
+}
+
+#[test]
+fn rejects_frequencies_without_a_note() {
+    for &frequency in &[0.0, -440.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(nearest_note(frequency, 440.0, &Temperament::Equal), None);
+    }
+    assert_eq!(nearest_note(440.0, 0.0, &Temperament::Equal), None);
+    assert_eq!(nearest_note(440.0, f64::NAN, &Temperament::Equal), None);
+}
+
+#[test]
+fn temperaments_shift_targets() {
+    // A just major third above F lies 13.7 cents below its equal tempered counterpart.
+    let just = Temperament::Just(NoteName::F);
+    let (_, _, f_target) = nearest_note(349.23, 440.0, &just).unwrap();
+    let (note, cents, a_target) = nearest_note(440.0, 440.0, &just).unwrap();
+    assert_eq!(note.name, NoteName::A);
+    assert!(cents.abs() < 1e-9);
+    assert!((1200.0 * (a_target / f_target).log2() - 386.31).abs() < 0.01);
+
+    let offsets = Temperament::Pythagorean(NoteName::C).offsets_cents();
+    assert!(offsets[NoteName::A.pitch_class()].abs() < 1e-9);
+    assert!((offsets[NoteName::E.pitch_class()] - offsets[NoteName::C.pitch_class()] - 7.82)
+                .abs() < 0.01);
+}
+
+#[test]
+fn stream_emits_readings_from_chunks() {
+    let mut config = TunerConfig::new(SAMPLE_RATE);
+    config.reference_a4 = 442.0;
+    let signal = harmonic_tone(442.0, 0.5);
+    let chunks: Vec<Vec<f32>> = signal.chunks(300).map(|c| c.to_vec()).collect();
+    let readings = pitch_stream(stream::iter_ok::<_, ()>(chunks), config)
+        .unwrap()
+        .collect()
+        .wait()
+        .unwrap();
+
+    let expected_frames = (signal.len() - config.window_size) / config.hop_size + 1;
+    assert_eq!(readings.len(), expected_frames);
+    for (i, reading) in readings.iter().enumerate() {
+        assert_eq!(reading.sample_offset, (i * config.hop_size) as u64);
+        assert_eq!(reading.note.to_string(), "A4");
+        assert!(reading.cents.abs() < 2.0);
+    }
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    let signal = harmonic_tone(440.0, 0.1);
+    let mut configs = Vec::new();
+    for &sample_rate in &[0.0, -44100.0, f64::NAN] {
+        configs.push(TunerConfig { sample_rate, ..TunerConfig::new(SAMPLE_RATE) });
+    }
+    for &(min_frequency, max_frequency) in
+        &[(0.0, 2000.0), (40.0, 0.0), (440.0, 440.0), (2000.0, 40.0)] {
+        configs.push(TunerConfig {
+                         min_frequency,
+                         max_frequency,
+                         ..TunerConfig::new(SAMPLE_RATE)
+                     });
+    }
+    configs.push(TunerConfig { window_size: 0, ..TunerConfig::new(SAMPLE_RATE) });
+    configs.push(TunerConfig { hop_size: 0, ..TunerConfig::new(SAMPLE_RATE) });
+    for config in configs {
+        assert!(Tuner::new(config).is_err(), "{:?}", config);
+        assert!(detect_pitch(&signal[..2048], &config).is_err(), "{:?}", config);
+    }
+}